@@ -1,5 +1,11 @@
 use std::collections::{
-    HashMap
+    HashMap,
+    HashSet
+};
+
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering
 };
 
 use std::sync::{
@@ -19,13 +25,31 @@ use chrono::{
     Utc,
 };
 
+use opencv::core::Mat;
+use uuid::Uuid;
+
 use crate::lib::zones::{
-    Zone
+    Zone,
+    ZoneGrid
 };
+use crate::lib::metrics::{PipelineMetrics, PipelineControl, PublisherStatus};
+use crate::lib::draw::palette_color;
+use crate::lib::tracker::TrackedObjectSnapshot;
+use crate::lib::detection::DetectionsSnapshot;
+use crate::lib::incidents::IncidentLog;
+use crate::lib::lane_changes::{LaneChanges, DEFAULT_MAX_LANE_CHANGES};
+use crate::lib::lifetime_counts::LifetimeCounts;
+use crate::lib::od::ODMatrix;
+use crate::lib::data_storage::{HourlyTotals, HourlyTimezone};
+
+// Number of the latest incidents to be kept in memory by default
+pub const DEFAULT_MAX_INCIDENTS: usize = 100;
 
 #[derive(Debug)]
 pub enum DataStorageError {
-    Poison
+    Poison,
+    ZoneNotFound(String),
+    ZoneAlreadyExists(String),
 }
 
 impl<T> From<PoisonError<T>> for DataStorageError {
@@ -36,7 +60,9 @@ impl<T> From<PoisonError<T>> for DataStorageError {
 impl std::fmt::Display for DataStorageError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            DataStorageError::Poison => write!(f, "PoisonError")
+            DataStorageError::Poison => write!(f, "PoisonError"),
+            DataStorageError::ZoneNotFound(zone_id) => write!(f, "No zone with ID '{}'", zone_id),
+            DataStorageError::ZoneAlreadyExists(zone_id) => write!(f, "Zone with ID '{}' already exists", zone_id),
         }
     }
 }
@@ -44,33 +70,98 @@ impl std::fmt::Display for DataStorageError {
 #[derive(Clone)]
 pub struct DataStorage {
     pub zones: Arc<RwLock<HashMap<String, Mutex<Zone>>>>,
+    // Spatial index over zones. It is rebuilt on zone insertion/deletion (see rebuild_zone_grid)
+    // Lock order: 'zones' first, 'zone_grid' second
+    pub zone_grid: Arc<RwLock<ZoneGrid>>,
     pub period_start: DateTime<Utc>,
     pub period_end: DateTime<Utc>,
     pub id: String,
-    pub verbose: bool
+    // Optional site metadata (see 'equipment_info' settings). It is included into exported payloads next to the identifier
+    pub location_name: Option<String>,
+    // [lon, lat]
+    pub location_wgs84: Option<[f32; 2]>,
+    pub verbose: bool,
+    pub metrics: Arc<PipelineMetrics>,
+    pub control: Arc<PipelineControl>,
+    // Connection state of Redis publisher (see RedisConnection)
+    pub redis_status: Arc<PublisherStatus>,
+    // Number of registered vehicles for each hour of the current day. Key: zone identifier
+    pub hourly_totals: HashMap<String, HourlyTotals>,
+    pub hourly_timezone: HourlyTimezone,
+    // Objects slower than this (km/h) are considered to be stationary and excluded from average speed
+    pub min_moving_speed: f32,
+    // Average speed is undefined for the period when fewer vehicles with defined speed have been registered. Zero means no limit
+    pub min_samples_for_speed: u32,
+    // Keep registrations of the still tracked objects (see tracked_objects) across periods, so they are not counted twice
+    pub dedup_across_periods: bool,
+    // The latest captured frame (without any drawings). It is used for zones preview
+    pub latest_frame: Arc<Mutex<Option<Mat>>>,
+    // Snapshot of the currently tracked objects. It is updated by the detection thread on every frame
+    pub tracked_objects: Arc<Mutex<Vec<TrackedObjectSnapshot>>>,
+    // Detector output (before tracking) of the latest processed frame. None until the first frame is processed
+    pub latest_detections: Arc<Mutex<Option<DetectionsSnapshot>>>,
+    // The latest incidents (e.g. stopped vehicles) detected by the detection thread
+    pub incidents: Arc<Mutex<IncidentLog>>,
+    // Lane changes between the zones of the same direction detected by the detection thread
+    pub lane_changes: Arc<Mutex<LaneChanges>>,
+    // Origin-destination transitions between the zones by virtual line crossings
+    pub od_matrix: Arc<Mutex<ODMatrix>>,
+    // Number of counted vehicles per zone and class since start. It is not reset at the end of the period
+    pub lifetime_counts: Arc<Mutex<LifetimeCounts>>,
+    // Number of connected MJPEG clients. It is maintained by MJPEG broadcaster and the detection thread skips frames preparation when it is zero
+    pub mjpeg_clients: Arc<AtomicUsize>,
+    // Index of the next palette color for zones created without explicit color
+    palette_index: Arc<AtomicUsize>,
 }
 
 impl DataStorage {
     pub fn new_with_id(_id: String, _verbose: bool) -> Self {
         return DataStorage {
             zones: Arc::new(RwLock::new(HashMap::<String, Mutex<Zone>>::new())),
+            zone_grid: Arc::new(RwLock::new(ZoneGrid::default())),
             period_start: TimeZone::with_ymd_and_hms(&Utc, 1970, 1, 1, 0, 0, 0).unwrap(),
             period_end: TimeZone::with_ymd_and_hms(&Utc, 1970, 1, 1, 0, 0, 0).unwrap(),
             id: _id,
-            verbose: _verbose
+            location_name: None,
+            location_wgs84: None,
+            verbose: _verbose,
+            metrics: Arc::new(PipelineMetrics::default()),
+            control: Arc::new(PipelineControl::default()),
+            redis_status: Arc::new(PublisherStatus::default()),
+            hourly_totals: HashMap::new(),
+            hourly_timezone: HourlyTimezone::default(),
+            min_moving_speed: 0.0,
+            min_samples_for_speed: 0,
+            dedup_across_periods: false,
+            latest_frame: Arc::new(Mutex::new(None)),
+            tracked_objects: Arc::new(Mutex::new(vec![])),
+            latest_detections: Arc::new(Mutex::new(None)),
+            incidents: Arc::new(Mutex::new(IncidentLog::new(DEFAULT_MAX_INCIDENTS))),
+            lane_changes: Arc::new(Mutex::new(LaneChanges::new(DEFAULT_MAX_LANE_CHANGES))),
+            od_matrix: Arc::new(Mutex::new(ODMatrix::default())),
+            lifetime_counts: Arc::new(Mutex::new(LifetimeCounts::default())),
+            mjpeg_clients: Arc::new(AtomicUsize::new(0)),
+            palette_index: Arc::new(AtomicUsize::new(0)),
         };
     }
     pub fn insert_zone(&self, zone: Zone) -> Result<(), DataStorageError> {
         let zones = Arc::clone(&self.zones);
         match zones.write() {
             Ok(mut mutex) => {
+                // Zone with the identifier of the renamed one should not be redirected anymore
+                self.od_matrix.lock()?.forget_alias(&zone.get_id());
                 mutex.insert(zone.get_id(), Mutex::new(zone));
             },
             Err(_) => {
                 return Err(DataStorageError::Poison);
             }
         };
-        Ok(())
+        self.rebuild_zone_grid()
+    }
+    // Picks the next distinct color (RGB) for the new zone
+    pub fn next_zone_color(&self) -> [i16; 3] {
+        let index = self.palette_index.fetch_add(1, Ordering::Relaxed);
+        palette_color(index)
     }
     pub fn delete_zone(&self, zone_id: &String) -> Result<(), DataStorageError> {
         let zones = Arc::clone(&self.zones);
@@ -82,21 +173,65 @@ impl DataStorage {
                 return Err(DataStorageError::Poison);
             }
         };
+        self.rebuild_zone_grid()
+    }
+    // Changes identifier of the zone and every reference to it: OD transitions, lifetime and hourly counts
+    // Objects which are being tracked could still refer to the old identifier (previous crossing), so OD matrix keeps it as an alias
+    pub fn rename_zone(&mut self, zone_id: &str, new_zone_id: &str) -> Result<(), DataStorageError> {
+        let mut zones = self.zones.write()?;
+        if zones.contains_key(new_zone_id) {
+            return Err(DataStorageError::ZoneAlreadyExists(new_zone_id.to_string()));
+        }
+        let zone_guarded = match zones.remove(zone_id) {
+            Some(zone_guarded) => zone_guarded,
+            None => {
+                return Err(DataStorageError::ZoneNotFound(zone_id.to_string()));
+            }
+        };
+        let mut zone = zone_guarded.into_inner()?;
+        zone.set_id(new_zone_id.to_string());
+        zones.insert(new_zone_id.to_string(), Mutex::new(zone));
+        drop(zones);
+        if let Some(hourly) = self.hourly_totals.remove(zone_id) {
+            self.hourly_totals.insert(new_zone_id.to_string(), hourly);
+        }
+        self.od_matrix.lock()?.rename_zone(zone_id, new_zone_id);
+        self.lifetime_counts.lock()?.rename_zone(zone_id, new_zone_id);
+        self.rebuild_zone_grid()
+    }
+    // Should be called after any change of zones geometry. Zones should not be locked by the caller
+    pub fn rebuild_zone_grid(&self) -> Result<(), DataStorageError> {
+        let zones = self.zones.read()?;
+        let grid = ZoneGrid::build(&zones, self.zone_grid.read()?.get_cell_size());
+        *self.zone_grid.write()? = grid;
+        drop(zones);
         Ok(())
     }
     pub fn update_statistics(&mut self) -> Result<(), DataStorageError> {
+        let alive_objects = match self.dedup_across_periods {
+            true => Some(self.tracked_objects.lock()?.iter().filter_map(|object| Uuid::parse_str(&object.id).ok()).collect::<HashSet<Uuid>>()),
+            false => None,
+        };
         let zones = Arc::clone(&self.zones);
         match zones.read() {
             Ok(mutex) => {
-                for (_zone_id, zone) in mutex.iter() {
+                for (zone_id, zone) in mutex.iter() {
                     let mut zone = zone.lock()?;
-                    zone.update_statistics(self.period_start, self.period_end);
+                    zone.update_statistics(self.period_start, self.period_end, self.min_moving_speed, self.min_samples_for_speed, alive_objects.as_ref());
+                    self.hourly_totals
+                        .entry(zone_id.clone())
+                        .or_insert_with(HourlyTotals::new)
+                        .add(self.period_end, self.hourly_timezone, zone.statistics.traffic_flow_parameters.sum_intensity);
                 }
+                // Forget about deleted zones
+                self.hourly_totals.retain(|zone_id, _| mutex.contains_key(zone_id));
             },
             Err(_) => {
                 return Err(DataStorageError::Poison);
             }
         };
+        self.lane_changes.lock()?.close_period();
+        self.od_matrix.lock()?.close_period();
         Ok(())
     }
 }
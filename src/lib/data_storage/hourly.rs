@@ -0,0 +1,91 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
+
+// Timezone which is used to split aggregation periods into the hours of the day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HourlyTimezone {
+    // System local timezone
+    Local,
+    UTC,
+}
+
+impl fmt::Display for HourlyTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HourlyTimezone::Local => write!(f, "local"),
+            HourlyTimezone::UTC => write!(f, "utc"),
+        }
+    }
+}
+
+impl Default for HourlyTimezone {
+    fn default() -> Self {
+        HourlyTimezone::Local
+    }
+}
+
+impl FromStr for HourlyTimezone {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(HourlyTimezone::Local),
+            "utc" => Ok(HourlyTimezone::UTC),
+            _ => Err(()),
+        }
+    }
+}
+
+// Number of registered vehicles for each hour of the current day
+#[derive(Debug, Clone)]
+pub struct HourlyTotals {
+    pub day: Option<NaiveDate>,
+    pub totals: [u32; 24],
+}
+
+impl HourlyTotals {
+    pub fn new() -> Self {
+        HourlyTotals {
+            day: None,
+            totals: [0; 24],
+        }
+    }
+    // Adds vehicles to the bucket of the hour which given time belongs to
+    // Buckets are reset when the day changes (midnight in the given timezone)
+    pub fn add(&mut self, at: DateTime<Utc>, tz: HourlyTimezone, count: u32) {
+        let (day, hour) = match tz {
+            HourlyTimezone::Local => {
+                let local_time = at.with_timezone(&Local);
+                (local_time.date_naive(), local_time.hour())
+            },
+            HourlyTimezone::UTC => (at.date_naive(), at.hour())
+        };
+        if self.day != Some(day) {
+            self.totals = [0; 24];
+            self.day = Some(day);
+        }
+        self.totals[hour as usize] += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    #[test]
+    fn test_hourly_totals() {
+        let mut hourly = HourlyTotals::new();
+        hourly.add(Utc.with_ymd_and_hms(2023, 1, 2, 15, 5, 0).unwrap(), HourlyTimezone::UTC, 4);
+        hourly.add(Utc.with_ymd_and_hms(2023, 1, 2, 15, 10, 0).unwrap(), HourlyTimezone::UTC, 3);
+        hourly.add(Utc.with_ymd_and_hms(2023, 1, 2, 23, 55, 0).unwrap(), HourlyTimezone::UTC, 1);
+        assert_eq!(hourly.totals[15], 7);
+        assert_eq!(hourly.totals[23], 1);
+        assert_eq!(hourly.totals.iter().sum::<u32>(), 8);
+        // New day resets totals
+        hourly.add(Utc.with_ymd_and_hms(2023, 1, 3, 0, 5, 0).unwrap(), HourlyTimezone::UTC, 2);
+        assert_eq!(hourly.day, Some(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap()));
+        assert_eq!(hourly.totals[0], 2);
+        assert_eq!(hourly.totals.iter().sum::<u32>(), 2);
+    }
+}
@@ -1,3 +1,4 @@
 mod data_storage;
+mod hourly;
 
-pub use self::{data_storage::*};
\ No newline at end of file
+pub use self::{data_storage::*, hourly::*};
\ No newline at end of file
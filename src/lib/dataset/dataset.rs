@@ -0,0 +1,418 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use opencv::{
+    prelude::*,
+    core::Vector,
+    imgcodecs::imwrite,
+};
+
+use crate::lib::tracker::TrackedObjectSnapshot;
+
+// Name of the single COCO file in the dataset directory
+pub const COCO_ANNOTATIONS_FILE: &str = "annotations.json";
+
+// COCO file is written after this number of new images (and on flush_coco_annotations, e.g. at shutdown)
+pub const COCO_FLUSH_EVERY_IMAGES: usize = 20;
+
+// COCO dataset is kept in memory, so the whole file is not re-read and re-written for every image
+// REST API handles requests concurrently, so access is serialized
+static COCO_WRITER: Mutex<Option<CocoWriter>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelFormat {
+    // '<directory>/labels/<name>.txt' per image
+    Yolo,
+    // Single '<directory>/annotations.json' with images, annotations and categories
+    Coco,
+    // '<directory>/annotations/<name>.xml' per image
+    Voc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetAnnotation {
+    // Index in the list of network classes
+    pub class_id: usize,
+    pub class_name: String,
+    // [x_min, y_min, x_max, y_max] clipped by frame borders
+    pub bbox: [f32; 4],
+}
+
+// Prepares annotations for the tracked objects. Boxes are clipped by frame borders
+// Objects of unknown classes and boxes which are completely outside of the frame are skipped
+pub fn dataset_annotations(objects: &[TrackedObjectSnapshot], net_classes: &[String], frame_width: f32, frame_height: f32) -> Vec<DatasetAnnotation> {
+    let mut annotations = Vec::with_capacity(objects.len());
+    if frame_width <= 0.0 || frame_height <= 0.0 {
+        return annotations;
+    }
+    for object in objects.iter() {
+        let class_id = match net_classes.iter().position(|class_name| class_name == &object.class_name) {
+            Some(class_id) => class_id,
+            None => continue,
+        };
+        let x_min = object.bbox[0].max(0.0);
+        let y_min = object.bbox[1].max(0.0);
+        let x_max = (object.bbox[0] + object.bbox[2]).min(frame_width);
+        let y_max = (object.bbox[1] + object.bbox[3]).min(frame_height);
+        if x_max <= x_min || y_max <= y_min {
+            continue;
+        }
+        annotations.push(DatasetAnnotation {
+            class_id: class_id,
+            class_name: object.class_name.clone(),
+            bbox: [x_min, y_min, x_max, y_max],
+        });
+    }
+    annotations
+}
+
+// YOLO (Darknet) format: '<class_id> <x_center> <y_center> <width> <height>'. Coordinates are normalized to [0; 1]
+pub fn yolo_line(annotation: &DatasetAnnotation, frame_width: f32, frame_height: f32) -> String {
+    let bbox = &annotation.bbox;
+    format!(
+        "{} {:.6} {:.6} {:.6} {:.6}",
+        annotation.class_id,
+        (bbox[0] + bbox[2]) / 2.0 / frame_width,
+        (bbox[1] + bbox[3]) / 2.0 / frame_height,
+        (bbox[2] - bbox[0]) / frame_width,
+        (bbox[3] - bbox[1]) / frame_height,
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CocoDataset {
+    pub images: Vec<CocoImage>,
+    pub annotations: Vec<CocoAnnotation>,
+    pub categories: Vec<CocoCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CocoImage {
+    pub id: u64,
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: u64,
+    pub image_id: u64,
+    pub category_id: u64,
+    // [x, y, width, height]
+    pub bbox: [f32; 4],
+    pub area: f32,
+    pub iscrowd: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CocoCategory {
+    pub id: u64,
+    pub name: String,
+}
+
+impl CocoDataset {
+    // Category identifiers start from 1 (COCO convention), so category_id = class_id + 1
+    pub fn new(net_classes: &[String]) -> Self {
+        CocoDataset {
+            images: vec![],
+            annotations: vec![],
+            categories: net_classes.iter().enumerate().map(|(idx, class_name)| CocoCategory {
+                id: idx as u64 + 1,
+                name: class_name.clone(),
+            }).collect(),
+        }
+    }
+    pub fn add_image(&mut self, file_name: &str, frame_width: f32, frame_height: f32, annotations: &[DatasetAnnotation]) {
+        let image_id = self.images.iter().map(|image| image.id).max().unwrap_or(0) + 1;
+        let mut annotation_id = self.annotations.iter().map(|annotation| annotation.id).max().unwrap_or(0);
+        self.images.push(CocoImage {
+            id: image_id,
+            file_name: file_name.to_string(),
+            width: frame_width as u32,
+            height: frame_height as u32,
+        });
+        for annotation in annotations.iter() {
+            annotation_id += 1;
+            let width = annotation.bbox[2] - annotation.bbox[0];
+            let height = annotation.bbox[3] - annotation.bbox[1];
+            self.annotations.push(CocoAnnotation {
+                id: annotation_id,
+                image_id: image_id,
+                category_id: annotation.class_id as u64 + 1,
+                bbox: [annotation.bbox[0], annotation.bbox[1], width, height],
+                area: width * height,
+                iscrowd: 0,
+            });
+        }
+    }
+}
+
+// COCO dataset of the single directory kept in memory. Existing file is read once when writer is opened
+pub struct CocoWriter {
+    directory: PathBuf,
+    dataset: CocoDataset,
+    // Number of images added since the last flush
+    pending_images: usize,
+    flush_every: usize,
+}
+
+impl CocoWriter {
+    pub fn open(directory: &Path, net_classes: &[String], flush_every: usize) -> Result<Self, Box<dyn Error>> {
+        let dataset = match fs::read_to_string(directory.join(COCO_ANNOTATIONS_FILE)) {
+            Ok(contents) => serde_json::from_str::<CocoDataset>(&contents)?,
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => CocoDataset::new(net_classes),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(CocoWriter {
+            directory: directory.to_path_buf(),
+            dataset: dataset,
+            pending_images: 0,
+            flush_every: flush_every.max(1),
+        })
+    }
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+    // Adds the image and writes the file when enough images are pending
+    pub fn add_image(&mut self, file_name: &str, frame_width: f32, frame_height: f32, annotations: &[DatasetAnnotation]) -> Result<(), Box<dyn Error>> {
+        self.dataset.add_image(file_name, frame_width, frame_height, annotations);
+        self.pending_images += 1;
+        if self.pending_images >= self.flush_every {
+            self.flush()?;
+        }
+        Ok(())
+    }
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.pending_images == 0 {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.directory)?;
+        // Write to the temporary file first: partially written COCO file would break the whole dataset
+        let tmp_path = self.directory.join(format!("{}.tmp", COCO_ANNOTATIONS_FILE));
+        fs::write(&tmp_path, serde_json::to_string(&self.dataset)?)?;
+        fs::rename(&tmp_path, self.directory.join(COCO_ANNOTATIONS_FILE))?;
+        self.pending_images = 0;
+        Ok(())
+    }
+}
+
+// Writes pending COCO annotations. Should be called at shutdown
+pub fn flush_coco_annotations() -> Result<(), Box<dyn Error>> {
+    let mut writer = COCO_WRITER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match writer.as_mut() {
+        Some(writer) => writer.flush(),
+        None => Ok(()),
+    }
+}
+
+// Pascal VOC XML for the single image
+pub fn voc_xml(file_name: &str, frame_width: f32, frame_height: f32, annotations: &[DatasetAnnotation]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<annotation>\n");
+    xml.push_str("    <folder>images</folder>\n");
+    xml.push_str(&format!("    <filename>{}</filename>\n", escape_xml(file_name)));
+    xml.push_str("    <size>\n");
+    xml.push_str(&format!("        <width>{}</width>\n", frame_width as u32));
+    xml.push_str(&format!("        <height>{}</height>\n", frame_height as u32));
+    xml.push_str("        <depth>3</depth>\n");
+    xml.push_str("    </size>\n");
+    for annotation in annotations.iter() {
+        xml.push_str("    <object>\n");
+        xml.push_str(&format!("        <name>{}</name>\n", escape_xml(&annotation.class_name)));
+        xml.push_str("        <difficult>0</difficult>\n");
+        xml.push_str("        <bndbox>\n");
+        xml.push_str(&format!("            <xmin>{}</xmin>\n", annotation.bbox[0].round() as i32));
+        xml.push_str(&format!("            <ymin>{}</ymin>\n", annotation.bbox[1].round() as i32));
+        xml.push_str(&format!("            <xmax>{}</xmax>\n", annotation.bbox[2].round() as i32));
+        xml.push_str(&format!("            <ymax>{}</ymax>\n", annotation.bbox[3].round() as i32));
+        xml.push_str("        </bndbox>\n");
+        xml.push_str("    </object>\n");
+    }
+    xml.push_str("</annotation>\n");
+    xml
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+// Writes annotations of the single image in the given format. Returns number of written annotations
+// COCO annotations are kept in memory and written every COCO_FLUSH_EVERY_IMAGES images (see flush_coco_annotations)
+pub fn write_dataset_labels(directory: &str, name: &str, frame_width: f32, frame_height: f32, annotations: &[DatasetAnnotation], net_classes: &[String], label_format: LabelFormat) -> Result<usize, Box<dyn Error>> {
+    let directory = Path::new(directory);
+    let image_file_name = format!("{}.jpg", name);
+    match label_format {
+        LabelFormat::Yolo => {
+            let labels_dir = directory.join("labels");
+            fs::create_dir_all(&labels_dir)?;
+            let mut content = annotations.iter().map(|annotation| yolo_line(annotation, frame_width, frame_height)).collect::<Vec<String>>().join("\n");
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            fs::write(labels_dir.join(format!("{}.txt", name)), content)?;
+        },
+        LabelFormat::Coco => {
+            fs::create_dir_all(directory)?;
+            let mut writer = COCO_WRITER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if writer.as_ref().map(|writer| writer.get_directory() != directory).unwrap_or(true) {
+                // Pending annotations of another directory should not be lost
+                if let Some(previous) = writer.as_mut() {
+                    previous.flush()?;
+                }
+                *writer = Some(CocoWriter::open(directory, net_classes, COCO_FLUSH_EVERY_IMAGES)?);
+            }
+            if let Some(writer) = writer.as_mut() {
+                writer.add_image(&image_file_name, frame_width, frame_height, annotations)?;
+            }
+        },
+        LabelFormat::Voc => {
+            let annotations_dir = directory.join("annotations");
+            fs::create_dir_all(&annotations_dir)?;
+            fs::write(annotations_dir.join(format!("{}.xml", name)), voc_xml(&image_file_name, frame_width, frame_height, annotations))?;
+        }
+    }
+    Ok(annotations.len())
+}
+
+// Writes '<directory>/images/<name>.jpg' and annotations in the given format (see write_dataset_labels)
+// Returns number of written annotations
+pub fn write_dataset_sample(directory: &str, name: &str, frame: &Mat, annotations: &[DatasetAnnotation], net_classes: &[String], label_format: LabelFormat) -> Result<usize, Box<dyn Error>> {
+    let images_dir = Path::new(directory).join("images");
+    fs::create_dir_all(&images_dir)?;
+    let image_path = images_dir.join(format!("{}.jpg", name));
+    let image_path_str = image_path.to_str().ok_or("Image path is not valid UTF-8")?;
+    if !imwrite(image_path_str, frame, &Vector::<i32>::new())? {
+        return Err(format!("Image '{}' has not been written", image_path_str).into());
+    }
+    write_dataset_labels(directory, name, frame.cols() as f32, frame.rows() as f32, annotations, net_classes, label_format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn snapshot(class_name: &str, bbox: [f32; 4]) -> TrackedObjectSnapshot {
+        TrackedObjectSnapshot {
+            id: "a".to_string(),
+            short_id: 1,
+            class_name: class_name.to_string(),
+            bbox: bbox,
+            centroid: [bbox[0] + bbox[2] / 2.0, bbox[1] + bbox[3] / 2.0],
+            zone_id: None,
+            world_position: None,
+            speed: -1.0,
+            heading: None,
+            first_seen: 0.0,
+            last_seen: 0.0,
+        }
+    }
+    fn test_annotations() -> (Vec<String>, Vec<DatasetAnnotation>) {
+        let net_classes = vec!["car".to_string(), "bus".to_string()];
+        let objects = vec![
+            snapshot("bus", [100.0, 50.0, 200.0, 100.0]),
+            // Unknown class
+            snapshot("person", [10.0, 10.0, 10.0, 10.0]),
+            // Partially outside of the frame
+            snapshot("car", [-50.0, 300.0, 114.0, 200.0]),
+            // Completely outside of the frame
+            snapshot("car", [700.0, 10.0, 20.0, 20.0]),
+        ];
+        let annotations = dataset_annotations(&objects, &net_classes, 640.0, 400.0);
+        (net_classes, annotations)
+    }
+    fn test_dir(format_name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dataset_{}_test_{}", format_name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+    #[test]
+    fn test_dataset_annotations() {
+        let (_, annotations) = test_annotations();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].bbox, [100.0, 50.0, 300.0, 150.0]);
+        assert_eq!(annotations[1].bbox, [0.0, 300.0, 64.0, 400.0]);
+    }
+    #[test]
+    fn test_write_yolo_labels() {
+        let (net_classes, annotations) = test_annotations();
+        let dir = test_dir("yolo");
+        let dir_str = dir.to_str().unwrap();
+        assert_eq!(write_dataset_labels(dir_str, "sample", 640.0, 400.0, &annotations, &net_classes, LabelFormat::Yolo).unwrap(), 2);
+        let contents = fs::read_to_string(dir.join("labels").join("sample.txt")).unwrap();
+        assert_eq!(contents, "1 0.312500 0.250000 0.312500 0.250000\n0 0.050000 0.875000 0.100000 0.250000\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_write_coco_labels() {
+        let (net_classes, annotations) = test_annotations();
+        let dir = test_dir("coco");
+        let dir_str = dir.to_str().unwrap();
+        write_dataset_labels(dir_str, "first", 640.0, 400.0, &annotations, &net_classes, LabelFormat::Coco).unwrap();
+        write_dataset_labels(dir_str, "second", 640.0, 400.0, &annotations[..1], &net_classes, LabelFormat::Coco).unwrap();
+        // Annotations are kept in memory until flush
+        assert!(!dir.join(COCO_ANNOTATIONS_FILE).exists());
+        flush_coco_annotations().unwrap();
+        let coco: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.join(COCO_ANNOTATIONS_FILE)).unwrap()).unwrap();
+        let images = coco["images"].as_array().unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1]["id"], 2);
+        assert_eq!(images[1]["file_name"], "second.jpg");
+        assert_eq!(images[1]["width"], 640);
+        let coco_annotations = coco["annotations"].as_array().unwrap();
+        assert_eq!(coco_annotations.len(), 3);
+        // Identifiers are unique across the images
+        assert_eq!(coco_annotations[2]["id"], 3);
+        assert_eq!(coco_annotations[2]["image_id"], 2);
+        assert_eq!(coco_annotations[0]["category_id"], 2);
+        assert_eq!(coco_annotations[0]["bbox"], serde_json::json!([100.0, 50.0, 200.0, 100.0]));
+        assert_eq!(coco_annotations[0]["area"], 20000.0);
+        let categories = coco["categories"].as_array().unwrap();
+        assert_eq!(categories.len(), 2);
+        assert_eq!(categories[0]["id"], 1);
+        assert_eq!(categories[0]["name"], "car");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_coco_writer_flush() {
+        let (net_classes, annotations) = test_annotations();
+        let dir = test_dir("coco_writer");
+        let coco_path = dir.join(COCO_ANNOTATIONS_FILE);
+        let mut writer = CocoWriter::open(&dir, &net_classes, 2).unwrap();
+        writer.add_image("first.jpg", 640.0, 400.0, &annotations).unwrap();
+        assert!(!coco_path.exists());
+        // File is written periodically
+        writer.add_image("second.jpg", 640.0, 400.0, &annotations).unwrap();
+        assert!(coco_path.exists());
+        writer.add_image("third.jpg", 640.0, 400.0, &annotations).unwrap();
+        writer.flush().unwrap();
+        // Existing file is continued by the new writer
+        let mut writer = CocoWriter::open(&dir, &net_classes, 2).unwrap();
+        writer.add_image("fourth.jpg", 640.0, 400.0, &annotations[..1]).unwrap();
+        writer.flush().unwrap();
+        let coco: CocoDataset = serde_json::from_str(&fs::read_to_string(&coco_path).unwrap()).unwrap();
+        assert_eq!(coco.images.iter().map(|image| image.id).collect::<Vec<u64>>(), vec![1, 2, 3, 4]);
+        assert_eq!(coco.annotations.len(), 7);
+        assert_eq!(coco.annotations[6].id, 7);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_write_voc_labels() {
+        let (net_classes, annotations) = test_annotations();
+        let dir = test_dir("voc");
+        let dir_str = dir.to_str().unwrap();
+        write_dataset_labels(dir_str, "sample", 640.0, 400.0, &annotations, &net_classes, LabelFormat::Voc).unwrap();
+        let xml = fs::read_to_string(dir.join("annotations").join("sample.xml")).unwrap();
+        assert!(xml.starts_with("<annotation>\n"));
+        assert!(xml.contains("<filename>sample.jpg</filename>"));
+        assert!(xml.contains("<width>640</width>"));
+        assert!(xml.contains("<height>400</height>"));
+        assert_eq!(xml.matches("<object>").count(), 2);
+        assert!(xml.contains("<name>bus</name>"));
+        assert!(xml.contains("<xmin>100</xmin>\n            <ymin>50</ymin>\n            <xmax>300</xmax>\n            <ymax>150</ymax>"));
+        assert!(xml.trim_end().ends_with("</annotation>"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,3 @@
+mod dataset;
+
+pub use self::{dataset::*};
@@ -0,0 +1,28 @@
+// Default distance (pixels) to the frame border within which bounding box is considered to be touching the edge
+pub const DEFAULT_EDGE_MARGIN_PIXELS: f32 = 5.0;
+
+// Checks whether bounding box [x, y, width, height] touches the frame border within the margin
+// Such objects are partially visible (entering or leaving the frame), so their tracks and centroids are unreliable
+pub fn touches_frame_edge(bbox: [f32; 4], frame_width: f32, frame_height: f32, margin_pixels: f32) -> bool {
+    let [x, y, width, height] = bbox;
+    x <= margin_pixels
+        || y <= margin_pixels
+        || x + width >= frame_width - margin_pixels
+        || y + height >= frame_height - margin_pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_touches_frame_edge() {
+        let (frame_width, frame_height) = (1280.0, 720.0);
+        assert!(!touches_frame_edge([100.0, 100.0, 50.0, 40.0], frame_width, frame_height, 5.0));
+        assert!(touches_frame_edge([0.0, 100.0, 50.0, 40.0], frame_width, frame_height, 5.0));
+        assert!(touches_frame_edge([4.0, 100.0, 50.0, 40.0], frame_width, frame_height, 5.0));
+        assert!(touches_frame_edge([100.0, 100.0, 1177.0, 40.0], frame_width, frame_height, 5.0));
+        assert!(touches_frame_edge([100.0, 680.0, 50.0, 40.0], frame_width, frame_height, 5.0));
+        // Zero margin: only boxes clipped by the frame itself
+        assert!(!touches_frame_edge([4.0, 100.0, 50.0, 40.0], frame_width, frame_height, 0.0));
+    }
+}
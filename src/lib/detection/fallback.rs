@@ -0,0 +1,65 @@
+// Chooses which of the detection models should be used.
+// When active model returns no detections for given number of consecutive frames
+// the next one is activated (after the last model it goes back to the primary one)
+#[derive(Debug)]
+pub struct ModelFallback {
+    models_count: usize,
+    max_empty_frames: usize,
+    empty_frames: usize,
+    active: usize,
+}
+
+impl ModelFallback {
+    pub fn new(models_count: usize, max_empty_frames: usize) -> Self {
+        ModelFallback {
+            models_count: models_count,
+            max_empty_frames: max_empty_frames,
+            empty_frames: 0,
+            active: 0,
+        }
+    }
+    pub fn get_active(&self) -> usize {
+        self.active
+    }
+    // Registers number of detections of the active model for the current frame
+    // Returns true if the active model has been switched
+    pub fn observe(&mut self, detections_count: usize) -> bool {
+        if detections_count > 0 {
+            self.empty_frames = 0;
+            return false;
+        }
+        self.empty_frames += 1;
+        if self.models_count < 2 || self.empty_frames < self.max_empty_frames {
+            return false;
+        }
+        self.empty_frames = 0;
+        self.active = (self.active + 1) % self.models_count;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_model_fallback() {
+        let mut fallback = ModelFallback::new(2, 3);
+        assert!(!fallback.observe(0));
+        assert!(!fallback.observe(0));
+        // Detections reset the counter
+        assert!(!fallback.observe(5));
+        assert!(!fallback.observe(0));
+        assert!(!fallback.observe(0));
+        assert!(fallback.observe(0));
+        assert_eq!(fallback.get_active(), 1);
+        // Back to the primary one
+        fallback.observe(0);
+        fallback.observe(0);
+        assert!(fallback.observe(0));
+        assert_eq!(fallback.get_active(), 0);
+        // Single model never switches
+        let mut single = ModelFallback::new(1, 1);
+        assert!(!single.observe(0));
+        assert_eq!(single.get_active(), 0);
+    }
+}
@@ -0,0 +1,101 @@
+use opencv::{
+    core::Mat,
+    core::Rect as RectCV,
+    core::Scalar,
+    core::copy_make_border,
+    core::BORDER_CONSTANT,
+};
+
+// Padding which is needed to make frame the same aspect ratio as neural network input
+// Padding is symmetric, so the frame is placed in the center of the padded one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub pad_left: i32,
+    pub pad_top: i32,
+    pub pad_right: i32,
+    pub pad_bottom: i32,
+}
+
+impl Letterbox {
+    // Constructor for Letterbox
+    //
+    // frame_width, frame_height - size of the original frame
+    // net_width, net_height - size of the neural network input
+    //
+    pub fn new(frame_width: i32, frame_height: i32, net_width: i32, net_height: i32) -> Self {
+        let frame_aspect = frame_width as f32 / frame_height as f32;
+        let net_aspect = net_width as f32 / net_height as f32;
+        let (padded_width, padded_height) = if frame_aspect > net_aspect {
+            // Frame is wider than network input: pad top and bottom
+            (frame_width, (frame_width as f32 / net_aspect).round() as i32)
+        } else {
+            // Frame is taller than network input: pad left and right
+            ((frame_height as f32 * net_aspect).round() as i32, frame_height)
+        };
+        let pad_x = (padded_width - frame_width).max(0);
+        let pad_y = (padded_height - frame_height).max(0);
+        Letterbox {
+            pad_left: pad_x / 2,
+            pad_top: pad_y / 2,
+            pad_right: pad_x - pad_x / 2,
+            pad_bottom: pad_y - pad_y / 2,
+        }
+    }
+    // Pads frame with gray borders (as it is done in YOLO preprocessing)
+    pub fn apply(&self, frame: &Mat) -> Result<Mat, opencv::Error> {
+        let mut padded = Mat::default();
+        copy_make_border(frame, &mut padded, self.pad_top, self.pad_bottom, self.pad_left, self.pad_right, BORDER_CONSTANT, Scalar::from((114.0, 114.0, 114.0)))?;
+        Ok(padded)
+    }
+    // Maps bounding box from the padded frame back to the original one
+    //
+    // bbox - bounding box in padded frame coordinates
+    // frame_cols, frame_rows - size of the original frame
+    //
+    pub fn unpad_bbox(&self, bbox: &RectCV, frame_cols: f32, frame_rows: f32) -> RectCV {
+        let x1 = ((bbox.x - self.pad_left) as f32).max(0.0).min(frame_cols);
+        let y1 = ((bbox.y - self.pad_top) as f32).max(0.0).min(frame_rows);
+        let x2 = ((bbox.x + bbox.width - self.pad_left) as f32).max(0.0).min(frame_cols);
+        let y2 = ((bbox.y + bbox.height - self.pad_top) as f32).max(0.0).min(frame_rows);
+        RectCV::new(x1 as i32, y1 as i32, (x2 - x1) as i32, (y2 - y1) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // Simulates the way how network output is scaled back to the input frame: naive resize to network size and back
+    fn through_network(bbox: &RectCV, input_width: f32, input_height: f32, net_width: f32, net_height: f32) -> RectCV {
+        let (sx, sy) = (net_width / input_width, net_height / input_height);
+        let (nx, ny, nw, nh) = (bbox.x as f32 * sx, bbox.y as f32 * sy, bbox.width as f32 * sx, bbox.height as f32 * sy);
+        RectCV::new((nx / sx).round() as i32, (ny / sy).round() as i32, (nw / sx).round() as i32, (nh / sy).round() as i32)
+    }
+    #[test]
+    fn test_letterbox_non_square_frame() {
+        let (frame_width, frame_height) = (640, 360);
+        let (net_width, net_height) = (416, 416);
+        let object = RectCV::new(100, 50, 80, 40);
+
+        // Without letterboxing box is mapped back to the frame coordinates directly
+        let bbox = through_network(&object, frame_width as f32, frame_height as f32, net_width as f32, net_height as f32);
+        assert_eq!(bbox, object);
+
+        // With letterboxing frame is padded to the square one, so box should be shifted back by padding offsets
+        let letterbox = Letterbox::new(frame_width, frame_height, net_width, net_height);
+        assert_eq!(letterbox, Letterbox { pad_left: 0, pad_top: 140, pad_right: 0, pad_bottom: 140 });
+        let object_padded = RectCV::new(object.x + letterbox.pad_left, object.y + letterbox.pad_top, object.width, object.height);
+        let padded_width = frame_width + letterbox.pad_left + letterbox.pad_right;
+        let padded_height = frame_height + letterbox.pad_top + letterbox.pad_bottom;
+        let bbox = through_network(&object_padded, padded_width as f32, padded_height as f32, net_width as f32, net_height as f32);
+        assert_eq!(letterbox.unpad_bbox(&bbox, frame_width as f32, frame_height as f32), object);
+
+        // Box partially placed on padding should be clipped
+        let bbox = RectCV::new(600, 120, 100, 60);
+        assert_eq!(letterbox.unpad_bbox(&bbox, frame_width as f32, frame_height as f32), RectCV::new(600, 0, 40, 40));
+    }
+    #[test]
+    fn test_letterbox_tall_frame() {
+        let letterbox = Letterbox::new(300, 600, 640, 640);
+        assert_eq!(letterbox, Letterbox { pad_left: 150, pad_top: 0, pad_right: 150, pad_bottom: 0 });
+    }
+}
@@ -1,3 +1,6 @@
 mod postprocess;
+mod letterbox;
+mod fallback;
+mod edge_filter;
 
-pub use self::{postprocess::*};
\ No newline at end of file
+pub use self::{postprocess::*, letterbox::*, fallback::*, edge_filter::*};
@@ -18,6 +18,8 @@ use mot_rs::utils::{
 
 use std::collections::HashSet;
 
+use crate::lib::detection::Letterbox;
+
 #[derive(Debug)]
 pub struct Detections {
     pub blobs: Vec<SimpleBlob>,
@@ -25,7 +27,86 @@ pub struct Detections {
     pub confidences: Vec<f32>,
 }
 
-pub fn process_yolo_detections(nms_bboxes: &Vec<RectCV>, nms_classes_ids: Vec<usize>, nms_confidences: Vec<f32>, frame_cols: f32, frame_rows: f32, max_points_in_track: usize, net_classes: &Vec<String>, target_classes: &HashSet<String>, dt: f32) -> Detections {
+impl Detections {
+    // Copy of the detector output for other threads (e.g. REST API). Bounding boxes are in the original frame coordinates
+    pub fn snapshot(&self, frame_width: f32, frame_height: f32) -> DetectionsSnapshot {
+        DetectionsSnapshot {
+            captured_at: Utc::now(),
+            frame_width: frame_width,
+            frame_height: frame_height,
+            objects: self.blobs.iter().zip(self.class_names.iter()).zip(self.confidences.iter()).map(|((blob, class_name), confidence)| {
+                let bbox = blob.get_bbox();
+                DetectedObject {
+                    class_name: class_name.clone(),
+                    confidence: *confidence,
+                    bbox: [bbox.x, bbox.y, bbox.width, bbox.height],
+                }
+            }).collect(),
+        }
+    }
+}
+
+// Detector output of the single frame before tracking
+#[derive(Debug, Clone)]
+pub struct DetectionsSnapshot {
+    pub captured_at: DateTime<Utc>,
+    pub frame_width: f32,
+    pub frame_height: f32,
+    pub objects: Vec<DetectedObject>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectedObject {
+    pub class_name: String,
+    pub confidence: f32,
+    // [x, y, width, height]
+    pub bbox: [f32; 4],
+}
+
+// Shifts bounding boxes by the given offset (e.g. from crop coordinates to the whole frame ones)
+pub fn shift_bboxes(bboxes: &mut Vec<RectCV>, dx: i32, dy: i32) {
+    for bbox in bboxes.iter_mut() {
+        bbox.x += dx;
+        bbox.y += dy;
+    }
+}
+
+// Merges detections from the whole frame with detections from the ROI crops (which should be already shifted to the same coordinates)
+// Same object could be detected both on the whole frame and on the crop, so class-wise NMS is applied to the merged set
+pub fn merge_detections(nms_bboxes: Vec<RectCV>, nms_classes_ids: Vec<usize>, nms_confidences: Vec<f32>, nms_threshold: f32) -> Result<(Vec<RectCV>, Vec<usize>, Vec<f32>), opencv::Error> {
+    suppress_detections(nms_bboxes, nms_classes_ids, nms_confidences, nms_threshold, false)
+}
+
+// Applies NMS to the detections
+// class_agnostic - boxes of different classes suppress each other too (e.g. one vehicle detected both as 'car' and 'truck').
+// Otherwise NMS is applied within each class
+pub fn suppress_detections(nms_bboxes: Vec<RectCV>, nms_classes_ids: Vec<usize>, nms_confidences: Vec<f32>, nms_threshold: f32, class_agnostic: bool) -> Result<(Vec<RectCV>, Vec<usize>, Vec<f32>), opencv::Error> {
+    let mut merged_bboxes = vec![];
+    let mut merged_classes_ids = vec![];
+    let mut merged_confidences = vec![];
+    let groups: Vec<Vec<usize>> = if class_agnostic {
+        vec![(0..nms_classes_ids.len()).collect()]
+    } else {
+        let classes_ids: HashSet<usize> = nms_classes_ids.iter().cloned().collect();
+        classes_ids.into_iter().map(|class_id| (0..nms_classes_ids.len()).filter(|i| nms_classes_ids[*i] == class_id).collect()).collect()
+    };
+    for group_indices in groups {
+        let bboxes: Vector<RectCV> = group_indices.iter().map(|i| nms_bboxes[*i]).collect();
+        let confidences: Vector<f32> = group_indices.iter().map(|i| nms_confidences[*i]).collect();
+        let mut kept_indices: Vector<i32> = Vector::new();
+        nms_boxes(&bboxes, &confidences, 0.0, nms_threshold, &mut kept_indices, 1.0, 0)?;
+        for kept in kept_indices.iter() {
+            let idx = group_indices[kept as usize];
+            merged_bboxes.push(nms_bboxes[idx]);
+            merged_classes_ids.push(nms_classes_ids[idx]);
+            merged_confidences.push(nms_confidences[idx]);
+        }
+    }
+    Ok((merged_bboxes, merged_classes_ids, merged_confidences))
+}
+
+// letterbox - padding applied to the frame before inference (if any). Boxes are mapped back to the original frame using its offsets
+pub fn process_yolo_detections(nms_bboxes: &Vec<RectCV>, nms_classes_ids: Vec<usize>, nms_confidences: Vec<f32>, frame_cols: f32, frame_rows: f32, max_points_in_track: usize, net_classes: &Vec<String>, target_classes: &HashSet<String>, dt: f32, letterbox: Option<&Letterbox>) -> Detections {
     if (nms_bboxes.len() != nms_classes_ids.len()) || (nms_bboxes.len() != nms_confidences.len()) || (nms_classes_ids.len() != nms_confidences.len()) {
         // Something wrong?
         println!("BBoxes len: {}, Classed IDs len: {}, Confidences len: {}", nms_bboxes.len(), nms_classes_ids.len(), nms_confidences.len());
@@ -37,6 +118,8 @@ pub fn process_yolo_detections(nms_bboxes: &Vec<RectCV>, nms_classes_ids: Vec<us
     }
     let mut aggregated_data = vec![];
     let mut class_names: Vec<String> = Vec::with_capacity(nms_classes_ids.len());
+    // Confidences should stay aligned with blobs and class names when some detections are filtered out
+    let mut confidences: Vec<f32> = Vec::with_capacity(nms_confidences.len());
     for (i, bbox) in nms_bboxes.iter().enumerate() {
         let class_id = nms_classes_ids[i];
         if class_id >= net_classes.len() {
@@ -48,15 +131,129 @@ pub fn process_yolo_detections(nms_bboxes: &Vec<RectCV>, nms_classes_ids: Vec<us
             continue;
         }
         class_names.push(classname);
-        let center_x = (bbox.x as f32 + bbox.width as f32 / 2.0);
-        let bottom_center_y = (bbox.y as f32 + bbox.height as f32);
-        let kb: SimpleBlob = SimpleBlob::new_with_center_dt(Point::new(center_x, bottom_center_y), Rect::new(bbox.x as f32, bbox.y as f32, bbox.width as f32, bbox.height as f32), dt);
+        confidences.push(nms_confidences[i]);
+        let bbox = match letterbox {
+            Some(lb) => lb.unpad_bbox(bbox, frame_cols, frame_rows),
+            None => *bbox
+        };
+        let (anchor_x, anchor_y) = bbox_anchor(&bbox);
+        let kb: SimpleBlob = SimpleBlob::new_with_center_dt(Point::new(anchor_x, anchor_y), Rect::new(bbox.x as f32, bbox.y as f32, bbox.width as f32, bbox.height as f32), dt);
         // let mut kb = SimpleBlob::new_with_dt(Rect::new(bbox.x as f32, bbox.y as f32, bbox.width as f32, bbox.height as f32), dt);
         aggregated_data.push(kb);
     }
     return Detections {
         blobs: aggregated_data,
         class_names: class_names,
-        confidences: nms_confidences,
+        confidences: confidences,
     }
 }
+
+// Point of the object used downstream (zone membership, speed, virtual line crossing): bottom center of the box
+// It is derived from the box in the original frame coordinates, so it does not depend on network input size or letterbox
+pub fn bbox_anchor(bbox: &RectCV) -> (f32, f32) {
+    (bbox.x as f32 + bbox.width as f32 / 2.0, bbox.y as f32 + bbox.height as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_merge_detections() {
+        // Car is detected on the whole frame and on the crop, truck overlaps it but has different class
+        let mut crop_bboxes = vec![RectCV::new(12, 8, 50, 30)];
+        shift_bboxes(&mut crop_bboxes, 90, 40);
+        assert_eq!(crop_bboxes[0], RectCV::new(102, 48, 50, 30));
+        let bboxes = vec![RectCV::new(100, 50, 50, 30), RectCV::new(101, 49, 50, 30), crop_bboxes[0], RectCV::new(400, 300, 20, 10)];
+        let classes_ids = vec![2, 7, 2, 2];
+        let confidences = vec![0.6, 0.5, 0.9, 0.7];
+        let (merged_bboxes, merged_classes_ids, merged_confidences) = merge_detections(bboxes, classes_ids, confidences, 0.3).unwrap();
+        assert_eq!(merged_bboxes.len(), 3);
+        // Duplicate with lower confidence is suppressed
+        assert!(!merged_bboxes.contains(&RectCV::new(100, 50, 50, 30)));
+        assert!(merged_bboxes.contains(&RectCV::new(102, 48, 50, 30)));
+        assert!(merged_bboxes.contains(&RectCV::new(400, 300, 20, 10)));
+        let truck_idx = merged_classes_ids.iter().position(|class_id| *class_id == 7).unwrap();
+        assert_eq!(merged_confidences[truck_idx], 0.5);
+    }
+    #[test]
+    fn test_class_agnostic_nms() {
+        // The same vehicle is detected as both car and truck, another car is far away
+        let bboxes = vec![RectCV::new(100, 50, 50, 30), RectCV::new(101, 51, 50, 30), RectCV::new(400, 300, 20, 10)];
+        let classes_ids = vec![2, 7, 2];
+        let confidences = vec![0.9, 0.6, 0.7];
+        let (kept_bboxes, kept_classes_ids, kept_confidences) = suppress_detections(bboxes.clone(), classes_ids.clone(), confidences.clone(), 0.3, true).unwrap();
+        assert_eq!(kept_bboxes.len(), 2);
+        // Truck box has lower confidence, so it is suppressed by the car one
+        assert!(!kept_classes_ids.contains(&7));
+        assert!(kept_bboxes.contains(&RectCV::new(100, 50, 50, 30)));
+        assert!(kept_bboxes.contains(&RectCV::new(400, 300, 20, 10)));
+        assert!(kept_confidences.contains(&0.9) && kept_confidences.contains(&0.7));
+        // Per-class NMS keeps both classes
+        let (kept_bboxes, kept_classes_ids, _) = suppress_detections(bboxes, classes_ids, confidences, 0.3, false).unwrap();
+        assert_eq!(kept_bboxes.len(), 3);
+        assert!(kept_classes_ids.contains(&7));
+    }
+    #[test]
+    fn test_detections_in_original_frame_size() {
+        // Full HD frame is letterboxed to the square network input
+        let (frame_cols, frame_rows) = (1920.0, 1080.0);
+        let letterbox = Letterbox::new(1920, 1080, 640, 640);
+        assert_eq!((letterbox.pad_left, letterbox.pad_top), (0, 420));
+        // Whole frame detection is in the padded frame coordinates
+        let whole_frame_bbox = RectCV::new(1000, 600 + letterbox.pad_top, 200, 120);
+        // ROI crop detection is in the crop coordinates: crop (1400, 300) is shifted the same way as in the main pipeline
+        let mut crop_bboxes = vec![RectCV::new(50, 80, 160, 90)];
+        shift_bboxes(&mut crop_bboxes, 1400 + letterbox.pad_left, 300 + letterbox.pad_top);
+        let bboxes = vec![whole_frame_bbox, crop_bboxes[0]];
+        let net_classes = vec!["car".to_string()];
+        let detections = process_yolo_detections(&bboxes, vec![0, 0], vec![0.9, 0.8], frame_cols, frame_rows, 10, &net_classes, &HashSet::new(), 0.04, Some(&letterbox));
+        assert_eq!(detections.blobs.len(), 2);
+        let bbox = detections.blobs[0].get_bbox();
+        assert_eq!([bbox.x, bbox.y, bbox.width, bbox.height], [1000.0, 600.0, 200.0, 120.0]);
+        let center = detections.blobs[0].get_center();
+        assert_eq!([center.x, center.y], [1100.0, 720.0]);
+        let bbox = detections.blobs[1].get_bbox();
+        assert_eq!([bbox.x, bbox.y, bbox.width, bbox.height], [1450.0, 380.0, 160.0, 90.0]);
+        // Boxes crossing the padding are clipped by the original frame borders
+        let padded_bbox = RectCV::new(1850, 1450, 100, 100);
+        let detections = process_yolo_detections(&vec![padded_bbox], vec![0], vec![0.9], frame_cols, frame_rows, 10, &net_classes, &HashSet::new(), 0.04, Some(&letterbox));
+        let bbox = detections.blobs[0].get_bbox();
+        assert_eq!([bbox.x, bbox.y, bbox.width, bbox.height], [1850.0, 1030.0, 70.0, 50.0]);
+    }
+    #[test]
+    fn test_centroid_matches_anchor() {
+        let (frame_cols, frame_rows) = (1920.0, 1080.0);
+        let letterbox = Letterbox::new(1920, 1080, 640, 640);
+        let net_classes = vec!["car".to_string(), "person".to_string()];
+        let target_classes = vec!["car".to_string()].into_iter().collect::<HashSet<String>>();
+        let bbox = RectCV::new(1001, 603, 201, 121);
+        let padded_bbox = RectCV::new(bbox.x + letterbox.pad_left, bbox.y + letterbox.pad_top, bbox.width, bbox.height);
+        // The same box with and without letterbox. Filtered out detection goes first, so indices are shifted
+        let plain = process_yolo_detections(&vec![RectCV::new(10, 10, 5, 5), bbox], vec![1, 0], vec![0.3, 0.9], frame_cols, frame_rows, 10, &net_classes, &target_classes, 0.04, None);
+        let padded = process_yolo_detections(&vec![RectCV::new(10, 10, 5, 5), padded_bbox], vec![1, 0], vec![0.3, 0.9], frame_cols, frame_rows, 10, &net_classes, &target_classes, 0.04, Some(&letterbox));
+        for detections in [&plain, &padded].iter() {
+            assert_eq!(detections.blobs.len(), 1);
+            assert_eq!(detections.class_names, vec!["car".to_string()]);
+            assert_eq!(detections.confidences, vec![0.9]);
+            let center = detections.blobs[0].get_center();
+            assert_eq!((center.x, center.y), bbox_anchor(&bbox));
+            // Centroid is the bottom center of the tracked box
+            let blob_bbox = detections.blobs[0].get_bbox();
+            assert_eq!((center.x, center.y), (blob_bbox.x + blob_bbox.width / 2.0, blob_bbox.y + blob_bbox.height));
+        }
+        assert_eq!(bbox_anchor(&bbox), (1101.5, 724.0));
+    }
+    #[test]
+    fn test_detections_snapshot() {
+        let net_classes = vec!["car".to_string(), "person".to_string()];
+        let detections = process_yolo_detections(&vec![RectCV::new(10, 20, 30, 40), RectCV::new(100, 100, 50, 60)], vec![1, 0], vec![0.3, 0.9], 640.0, 480.0, 10, &net_classes, &HashSet::new(), 0.04, None);
+        let snapshot = detections.snapshot(640.0, 480.0);
+        assert_eq!((snapshot.frame_width, snapshot.frame_height), (640.0, 480.0));
+        assert_eq!(snapshot.objects.len(), 2);
+        assert_eq!(snapshot.objects[0].class_name, "person");
+        assert_eq!(snapshot.objects[0].confidence, 0.3);
+        assert_eq!(snapshot.objects[0].bbox, [10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(snapshot.objects[1].class_name, "car");
+        assert_eq!(snapshot.objects[1].bbox, [100.0, 100.0, 50.0, 60.0]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use opencv::core::Scalar;
+
+use crate::lib::draw::palette_color;
+
+// Color for every known class. Colors are taken from the palette in order of the classes,
+// so they are stable between runs as long as classes list is the same
+#[derive(Debug, Clone)]
+pub struct ClassColors {
+    colors: HashMap<String, Scalar>,
+}
+
+impl ClassColors {
+    pub fn new(class_names: &[String]) -> Self {
+        let colors = class_names.iter().enumerate().map(|(idx, class_name)| {
+            (class_name.clone(), rgb_to_scalar(palette_color(idx)))
+        }).collect();
+        ClassColors {
+            colors: colors,
+        }
+    }
+    // Unknown classes get color derived from the name
+    pub fn get(&self, class_name: &str) -> Scalar {
+        match self.colors.get(class_name) {
+            Some(color) => *color,
+            None => {
+                let index = class_name.bytes().fold(0usize, |acc, b| acc.wrapping_mul(31).wrapping_add(b as usize));
+                rgb_to_scalar(palette_color(index))
+            }
+        }
+    }
+}
+
+// OpenCV expects BGR order
+fn rgb_to_scalar(rgb: [i16; 3]) -> Scalar {
+    Scalar::from((rgb[2] as f64, rgb[1] as f64, rgb[0] as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_class_colors() {
+        let class_colors = ClassColors::new(&["car".to_string(), "bus".to_string()]);
+        let car = palette_color(0);
+        assert_eq!(class_colors.get("car"), Scalar::from((car[2] as f64, car[1] as f64, car[0] as f64)));
+        assert_ne!(class_colors.get("car"), class_colors.get("bus"));
+        // Unknown class still gets the same color every time
+        assert_eq!(class_colors.get("tram"), class_colors.get("tram"));
+    }
+}
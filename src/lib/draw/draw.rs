@@ -9,17 +9,26 @@ use opencv::{
     imgproc::circle,
     imgproc::rectangle,
     imgproc::put_text,
+    imgproc::get_text_size,
+    imgproc::FILLED,
 };
 
 use crate::lib::tracker::Tracker;
+use crate::lib::draw::{ClassColors, TextStyle};
 
-pub fn draw_trajectories(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar) {
+// max_points - number of the last track points to be drawn. Whole track is drawn if None
+pub fn draw_trajectories(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar, max_points: Option<usize>) {
     for (_, object) in tracker.engine.objects.iter() {
         let mut color_choose = color;
         if object.get_no_match_times() > 1 {
             color_choose = inv_color;
         }
-        for pt in object.get_track().iter() {
+        let track = object.get_track();
+        let start_idx = match max_points {
+            Some(n) => track.len().saturating_sub(n),
+            None => 0
+        };
+        for pt in track[start_idx..].iter() {
             let cv_pt = Point::new(pt.x.floor() as i32, pt.y.floor() as i32);
             match circle(img, cv_pt, 5, color_choose, 2, LINE_8, 0) {
                 Ok(_) => {},
@@ -48,15 +57,71 @@ pub fn draw_bboxes(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: S
     }
 }
 
-pub fn draw_identifiers(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar) {
-    for (_, object) in tracker.engine.objects.iter() {
+// Draws bbox of each object with label "class_name speed km/h" above it. Speed is omitted when it is not estimated yet
+// Lost objects are drawn with inverted color
+pub fn draw_object_boxes(img: &mut Mat, tracker: &Tracker, class_colors: &ClassColors, text_style: &TextStyle) {
+    for (object_id, object_extra) in tracker.objects_extra.iter() {
+        let object = match tracker.engine.objects.get(object_id) {
+            Some(object) => object,
+            None => continue,
+        };
+        let class_name = object_extra.get_classname();
+        let mut color_choose = class_colors.get(&class_name);
+        if object.get_no_match_times() > 1 {
+            color_choose = invert_color(&color_choose);
+        }
+        let bbox = object.get_bbox();
+        let cv_rect = Rect::new(bbox.x.floor() as i32, bbox.y.floor() as i32, bbox.width as i32, bbox.height as i32);
+        match rectangle(img, cv_rect, color_choose, 2, LINE_4, 0) {
+            Ok(_) => {},
+            Err(err) => {
+                panic!("Can't draw rectangle at blob's bbox due the error: {:?}", err)
+            }
+        };
+        let label = match object_extra.spatial_info {
+            Some(ref spatial_info) if spatial_info.speed >= 0.0 => format!("{} {:.0} km/h", class_name, spatial_info.speed),
+            _ => class_name
+        };
+        let mut baseline = 0;
+        let text_size = match get_text_size(&label, FONT_HERSHEY_SIMPLEX, text_style.scale, text_style.thin(), &mut baseline) {
+            Ok(size) => size,
+            Err(err) => {
+                println!("Can't measure label of object due the error {:?}", err);
+                continue;
+            }
+        };
+        // Label is placed above the bbox (or inside of it when bbox touches the top of the frame)
+        let label_y = if cv_rect.y - text_size.height - baseline - 2 >= 0 { cv_rect.y - baseline - 2 } else { cv_rect.y + text_size.height + 2 };
+        let background = Rect::new(cv_rect.x, label_y - text_size.height - 2, text_size.width + 4, text_size.height + baseline + 4);
+        match rectangle(img, background, color_choose, FILLED, LINE_8, 0) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't draw label background of object due the error {:?}", err);
+            }
+        };
+        match put_text(img, &label, Point::new(cv_rect.x + 2, label_y), FONT_HERSHEY_SIMPLEX, text_style.scale, invert_color(&color_choose), text_style.thin(), LINE_8, false) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't display label of object due the error {:?}", err);
+            }
+        };
+    }
+}
+
+// short_ids - draw short integer identifiers instead of UUIDs
+pub fn draw_identifiers(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar, short_ids: bool, text_style: &TextStyle) {
+    for (object_id, object) in tracker.engine.objects.iter() {
         let mut color_choose = color;
         if object.get_no_match_times() > 1 {
             color_choose = inv_color;
         }
         let bbox = object.get_bbox();
-        let anchor = Point::new(bbox.x.floor() as i32 + 2, bbox.y.floor() as i32 + 10);
-        match put_text(img, &object.get_id().to_string(), anchor, FONT_HERSHEY_SIMPLEX, 0.5, color_choose, 2, LINE_8, false) {
+        let anchor = Point::new(bbox.x.floor() as i32 + 2, bbox.y.floor() as i32 + text_style.offset(10));
+        let label = match tracker.objects_extra.get(object_id) {
+            Some(object_extra) if short_ids => object_extra.get_short_id().to_string(),
+            _ => object.get_id().to_string()
+        };
+        match put_text(img, &label, anchor, FONT_HERSHEY_SIMPLEX, text_style.scale, color_choose, text_style.thickness, LINE_8, false) {
             Ok(_) => {},
             Err(err) => {
                 println!("Can't display ID of object due the error {:?}", err);
@@ -65,7 +130,7 @@ pub fn draw_identifiers(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_col
     }
 }
 
-pub fn draw_speeds(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar) {
+pub fn draw_speeds(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: Scalar, text_style: &TextStyle) {
     for (object_id, object_extra) in tracker.objects_extra.iter() {
         let spatial_info = match object_extra.spatial_info {
             Some(ref spatial_info) => spatial_info,
@@ -77,8 +142,8 @@ pub fn draw_speeds(img: &mut Mat, tracker: &Tracker, color: Scalar, inv_color: S
             color_choose = inv_color;
         }
         let bbox = object.get_bbox();
-        let anchor = Point::new(bbox.x.floor() as i32 + 2, bbox.y.floor() as i32 + 20);
-        match put_text(img, &spatial_info.speed.to_string(), anchor, FONT_HERSHEY_SIMPLEX, 0.5, color_choose, 2, LINE_8, false) {
+        let anchor = Point::new(bbox.x.floor() as i32 + 2, bbox.y.floor() as i32 + text_style.offset(20));
+        match put_text(img, &spatial_info.speed.to_string(), anchor, FONT_HERSHEY_SIMPLEX, text_style.scale, color_choose, text_style.thickness, LINE_8, false) {
             Ok(_) => {},
             Err(err) => {
                 println!("Can't display velocity of object due the error {:?}", err);
@@ -1,3 +1,7 @@
 mod draw;
+mod palette;
+mod overlay;
+mod class_colors;
+mod text_style;
 
-pub use self::{draw::*};
\ No newline at end of file
+pub use self::{draw::*, palette::*, overlay::*, class_colors::*, text_style::*};
\ No newline at end of file
@@ -0,0 +1,256 @@
+use opencv::{
+    prelude::*,
+    core::Mat,
+    core::Point,
+    core::Rect,
+    core::Scalar,
+    core::Vector,
+    core::add_weighted,
+    imgproc::fill_poly,
+    imgproc::LINE_8,
+    imgproc::FONT_HERSHEY_SIMPLEX,
+    imgproc::line,
+    imgproc::arrowed_line,
+    imgproc::put_text,
+};
+
+use crate::lib::draw::TextStyle;
+
+// Maximum length of the scale bar in pixels
+const SCALE_BAR_MAX_PIXELS: f32 = 150.0;
+// Length of the north arrow in pixels
+const NORTH_ARROW_PIXELS: f32 = 40.0;
+// Maximum opacity of the zone shading, so the road is still visible under it
+pub const MAX_SHADE_ALPHA: f32 = 0.6;
+// Number of vehicles which gives maximum shading when zone has no spatial calibration (density is unknown)
+const FULL_SHADE_OCCUPANCY: f32 = 5.0;
+// Thickness (pixels) of the arrow for the busiest OD flow
+pub const MAX_OD_FLOW_THICKNESS: i32 = 12;
+
+// Opacity of the zone shading in [0; MAX_SHADE_ALPHA]
+//
+// density - current vehicles per kilometer. Negative value means that it is undefined, so occupancy is used instead
+// jammed_density - density which gives maximum shading
+//
+pub fn shade_alpha(density: f32, occupancy: u16, jammed_density: f32) -> f32 {
+    let ratio = if density >= 0.0 && jammed_density > 0.0 {
+        density / jammed_density
+    } else {
+        occupancy as f32 / FULL_SHADE_OCCUPANCY
+    };
+    if !ratio.is_finite() {
+        return 0.0;
+    }
+    ratio.max(0.0).min(1.0) * MAX_SHADE_ALPHA
+}
+
+// Fills polygon with translucent color. Only bounding box of the polygon is blended, so it is cheap for small zones
+pub fn blend_polygon(img: &mut Mat, points: &[Point], color: Scalar, alpha: f32) -> opencv::Result<()> {
+    if points.len() < 3 || alpha <= 0.0 {
+        return Ok(());
+    }
+    let x_min = points.iter().map(|pt| pt.x).min().unwrap_or(0).max(0);
+    let y_min = points.iter().map(|pt| pt.y).min().unwrap_or(0).max(0);
+    let x_max = points.iter().map(|pt| pt.x).max().unwrap_or(0).min(img.cols() - 1);
+    let y_max = points.iter().map(|pt| pt.y).max().unwrap_or(0).min(img.rows() - 1);
+    if x_max <= x_min || y_max <= y_min {
+        return Ok(());
+    }
+    let bounds = Rect::new(x_min, y_min, x_max - x_min + 1, y_max - y_min + 1);
+    let region = Mat::roi(img, bounds)?.try_clone()?;
+    let mut shaded = region.try_clone()?;
+    let mut polygons = Vector::<Vector<Point>>::new();
+    polygons.push(points.iter().map(|pt| Point::new(pt.x - bounds.x, pt.y - bounds.y)).collect::<Vector<Point>>());
+    fill_poly(&mut shaded, &polygons, color, LINE_8, 0, Point::default())?;
+    let mut blended = Mat::default();
+    add_weighted(&shaded, alpha as f64, &region, 1.0 - alpha as f64, 0.0, &mut blended, -1)?;
+    let mut target = Mat::roi_mut(img, bounds)?;
+    blended.copy_to(&mut *target)?;
+    Ok(())
+}
+
+// Draws grid lines of the zones spatial index over the whole image and shades cells which contain zones
+//
+// cell_size - size of the grid cell in pixels (see ZoneGrid::get_cell_size)
+// occupied_cells - (column, row) of the non-empty cells (see ZoneGrid::get_occupied_cells)
+//
+pub fn draw_zone_grid(img: &mut Mat, cell_size: f32, occupied_cells: &[(i32, i32)], color: Scalar) {
+    if !cell_size.is_finite() || cell_size < 1.0 {
+        return;
+    }
+    for (col, row) in occupied_cells.iter() {
+        let x_min = (*col as f32 * cell_size).round() as i32;
+        let y_min = (*row as f32 * cell_size).round() as i32;
+        let x_max = ((*col + 1) as f32 * cell_size).round() as i32;
+        let y_max = ((*row + 1) as f32 * cell_size).round() as i32;
+        let points = [Point::new(x_min, y_min), Point::new(x_max, y_min), Point::new(x_max, y_max), Point::new(x_min, y_max)];
+        match blend_polygon(img, &points, color, MAX_SHADE_ALPHA / 2.0) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't shade zone grid cell due the error {:?}", err);
+                return;
+            }
+        };
+    }
+    let (cols, rows) = (img.cols(), img.rows());
+    let mut segments = vec![];
+    let mut x = 0.0;
+    while x < cols as f32 {
+        segments.push((Point::new(x.round() as i32, 0), Point::new(x.round() as i32, rows - 1)));
+        x += cell_size;
+    }
+    let mut y = 0.0;
+    while y < rows as f32 {
+        segments.push((Point::new(0, y.round() as i32), Point::new(cols - 1, y.round() as i32)));
+        y += cell_size;
+    }
+    for (a, b) in segments.iter() {
+        match line(img, *a, *b, color, 1, LINE_8, 0) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't draw zone grid due the error {:?}", err);
+                return;
+            }
+        };
+    }
+}
+
+// Returns the largest "round" length (1, 2 or 5 multiplied by power of ten) which does not exceed max_meters
+pub fn nice_scale_length(max_meters: f32) -> f32 {
+    if !max_meters.is_finite() || max_meters <= 0.0 {
+        return 0.0;
+    }
+    let magnitude = 10.0_f32.powf(max_meters.log10().floor());
+    for step in [5.0, 2.0, 1.0].iter() {
+        if step * magnitude <= max_meters {
+            return step * magnitude;
+        }
+    }
+    magnitude
+}
+
+// Draws horizontal scale bar starting at anchor
+//
+// pixels_per_meter - resolution at the place of the bar (e.g. zone's skeleton ppm)
+//
+pub fn draw_scale_bar(img: &mut Mat, anchor: Point, pixels_per_meter: f32, color: Scalar, text_style: &TextStyle) {
+    if !pixels_per_meter.is_finite() || pixels_per_meter <= 0.0 {
+        return;
+    }
+    let meters = nice_scale_length(SCALE_BAR_MAX_PIXELS / pixels_per_meter);
+    let length_pixels = (meters * pixels_per_meter).round() as i32;
+    if length_pixels < 1 {
+        return;
+    }
+    let end = Point::new(anchor.x + length_pixels, anchor.y);
+    let segments = [
+        (anchor, end),
+        (Point::new(anchor.x, anchor.y - 5), Point::new(anchor.x, anchor.y + 5)),
+        (Point::new(end.x, end.y - 5), Point::new(end.x, end.y + 5)),
+    ];
+    for (a, b) in segments.iter() {
+        match line(img, *a, *b, color, 2, LINE_8, 0) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't draw scale bar due the error {:?}", err);
+                return;
+            }
+        };
+    }
+    let label = format!("{} m", meters);
+    match put_text(img, &label, Point::new(end.x + 5, end.y + text_style.offset(5)), FONT_HERSHEY_SIMPLEX, text_style.scale, color, text_style.thickness, LINE_8, false) {
+        Ok(_) => {},
+        Err(err) => {
+            println!("Can't display scale bar label due the error {:?}", err);
+        }
+    };
+}
+
+// Draws arrow pointing to the north
+//
+// direction - unit vector (in pixels) pointing to the north at the anchor
+//
+pub fn draw_north_arrow(img: &mut Mat, anchor: Point, direction: (f32, f32), color: Scalar, text_style: &TextStyle) {
+    let end = Point::new(
+        anchor.x + (direction.0 * NORTH_ARROW_PIXELS).round() as i32,
+        anchor.y + (direction.1 * NORTH_ARROW_PIXELS).round() as i32,
+    );
+    match arrowed_line(img, anchor, end, color, 2, LINE_8, 0, 0.3) {
+        Ok(_) => {},
+        Err(err) => {
+            println!("Can't draw north arrow due the error {:?}", err);
+            return;
+        }
+    };
+    let label_anchor = Point::new(
+        end.x + (direction.0 * 10.0).round() as i32 - 5,
+        end.y + (direction.1 * 10.0).round() as i32 + 5,
+    );
+    match put_text(img, "N", label_anchor, FONT_HERSHEY_SIMPLEX, text_style.scale, color, text_style.thickness, LINE_8, false) {
+        Ok(_) => {},
+        Err(err) => {
+            println!("Can't display north arrow label due the error {:?}", err);
+        }
+    };
+}
+
+// Thickness of the OD flow arrow in [1; MAX_OD_FLOW_THICKNESS] proportional to the count relative to the busiest flow
+pub fn od_flow_thickness(count: u32, max_count: u32) -> i32 {
+    if max_count == 0 {
+        return 1;
+    }
+    let ratio = count.min(max_count) as f32 / max_count as f32;
+    ((ratio * MAX_OD_FLOW_THICKNESS as f32).round() as i32).max(1)
+}
+
+// Draws arrows from origin to destination zones
+//
+// flows - (origin centroid, destination centroid, number of transitions)
+//
+pub fn draw_od_flows(img: &mut Mat, flows: &[((f32, f32), (f32, f32), u32)], color: Scalar) {
+    let max_count = flows.iter().map(|flow| flow.2).max().unwrap_or(0);
+    for (from, to, count) in flows.iter() {
+        if *count == 0 {
+            continue;
+        }
+        let start = Point::new(from.0.round() as i32, from.1.round() as i32);
+        let end = Point::new(to.0.round() as i32, to.1.round() as i32);
+        match arrowed_line(img, start, end, color, od_flow_thickness(*count, max_count), LINE_8, 0, 0.05) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't draw OD flow arrow due the error {:?}", err);
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_od_flow_thickness() {
+        assert_eq!(od_flow_thickness(10, 10), MAX_OD_FLOW_THICKNESS);
+        assert_eq!(od_flow_thickness(5, 10), MAX_OD_FLOW_THICKNESS / 2);
+        // Thin flows are still visible
+        assert_eq!(od_flow_thickness(1, 1000), 1);
+        assert_eq!(od_flow_thickness(0, 0), 1);
+    }
+    #[test]
+    fn test_shade_alpha() {
+        assert_eq!(shade_alpha(0.0, 3, 50.0), 0.0);
+        assert!((shade_alpha(25.0, 3, 50.0) - MAX_SHADE_ALPHA / 2.0).abs() < 0.0001);
+        assert_eq!(shade_alpha(120.0, 3, 50.0), MAX_SHADE_ALPHA);
+        // Undefined density: occupancy is used
+        assert!((shade_alpha(-1.0, 1, 50.0) - MAX_SHADE_ALPHA / FULL_SHADE_OCCUPANCY).abs() < 0.0001);
+        assert_eq!(shade_alpha(-1.0, 10, 50.0), MAX_SHADE_ALPHA);
+    }
+    #[test]
+    fn test_nice_scale_length() {
+        assert_eq!(nice_scale_length(30.0), 20.0);
+        assert_eq!(nice_scale_length(7.5), 5.0);
+        assert_eq!(nice_scale_length(10.0), 10.0);
+        assert!((nice_scale_length(0.3) - 0.2).abs() < 0.0001);
+        assert_eq!(nice_scale_length(0.0), 0.0);
+        assert_eq!(nice_scale_length(f32::INFINITY), 0.0);
+    }
+}
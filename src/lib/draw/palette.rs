@@ -0,0 +1,55 @@
+// Distinct colors for zones which have been created without explicit color
+// Hue is rotated by golden angle, so neighbouring indices are far from each other on the color wheel
+const GOLDEN_ANGLE_DEGREES: f32 = 137.50776;
+const PALETTE_SATURATION: f32 = 0.85;
+const PALETTE_VALUE: f32 = 0.95;
+
+// Returns RGB color for the given palette index
+pub fn palette_color(index: usize) -> [i16; 3] {
+    let hue = (index as f32 * GOLDEN_ANGLE_DEGREES) % 360.0;
+    hsv_to_rgb(hue, PALETTE_SATURATION, PALETTE_VALUE)
+}
+
+// h is in [0; 360), s and v are in [0; 1]
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [i16; 3] {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    [
+        ((r + m) * 255.0).round() as i16,
+        ((g + m) * 255.0).round() as i16,
+        ((b + m) * 255.0).round() as i16,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_hsv_to_rgb() {
+        assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), [255, 0, 0]);
+        assert_eq!(hsv_to_rgb(120.0, 1.0, 1.0), [0, 255, 0]);
+        assert_eq!(hsv_to_rgb(240.0, 1.0, 1.0), [0, 0, 255]);
+        assert_eq!(hsv_to_rgb(60.0, 0.0, 1.0), [255, 255, 255]);
+    }
+    #[test]
+    fn test_palette_color() {
+        let colors = (0..10).map(palette_color).collect::<Vec<[i16; 3]>>();
+        for (i, a) in colors.iter().enumerate() {
+            assert!(a.iter().all(|c| *c >= 0 && *c <= 255));
+            for b in colors.iter().skip(i + 1) {
+                assert_ne!(a, b);
+            }
+        }
+        // Not white (which is default color of the zone)
+        assert_ne!(colors[0], [255, 255, 255]);
+    }
+}
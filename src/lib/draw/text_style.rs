@@ -0,0 +1,68 @@
+// Font scale and thickness which are tuned for this frame height (pixels)
+pub const REFERENCE_FRAME_HEIGHT: f32 = 720.0;
+pub const DEFAULT_FONT_SCALE: f64 = 0.5;
+pub const DEFAULT_FONT_THICKNESS: i32 = 2;
+
+// Font parameters for the text drawn over the frame (labels, identifiers, counters)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub scale: f64,
+    pub thickness: i32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            scale: DEFAULT_FONT_SCALE,
+            thickness: DEFAULT_FONT_THICKNESS,
+        }
+    }
+}
+
+impl TextStyle {
+    // Default font scaled proportionally to the frame height, so text is legible both on small frames and on 4K ones
+    pub fn auto(frame_height: f32) -> Self {
+        let factor = if frame_height > 0.0 { frame_height / REFERENCE_FRAME_HEIGHT } else { 1.0 };
+        TextStyle {
+            scale: DEFAULT_FONT_SCALE * factor as f64,
+            thickness: ((DEFAULT_FONT_THICKNESS as f32 * factor).round() as i32).max(1),
+        }
+    }
+    // Configured values take precedence over the auto-scaled ones
+    pub fn new(scale: Option<f64>, thickness: Option<i32>, frame_height: f32) -> Self {
+        let auto = TextStyle::auto(frame_height);
+        TextStyle {
+            scale: scale.unwrap_or(auto.scale),
+            thickness: thickness.unwrap_or(auto.thickness),
+        }
+    }
+    // Thickness for the text on filled background (e.g. bbox labels): it is half of the regular one
+    pub fn thin(&self) -> i32 {
+        (self.thickness / 2).max(1)
+    }
+    // Scales offset (pixels) tuned for the default font, so text placement follows the font size
+    pub fn offset(&self, pixels: i32) -> i32 {
+        (pixels as f64 * self.scale / DEFAULT_FONT_SCALE).round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_text_style_auto() {
+        assert_eq!(TextStyle::auto(720.0), TextStyle::default());
+        let uhd = TextStyle::auto(2160.0);
+        assert!((uhd.scale - 1.5).abs() < 1e-6);
+        assert_eq!(uhd.thickness, 6);
+        assert_eq!(uhd.thin(), 3);
+        assert_eq!(uhd.offset(10), 30);
+        let small = TextStyle::auto(240.0);
+        assert_eq!(small.thickness, 1);
+        assert_eq!(small.thin(), 1);
+        // Configured values are kept as is
+        let configured = TextStyle::new(Some(0.8), None, 2160.0);
+        assert_eq!(configured.scale, 0.8);
+        assert_eq!(configured.thickness, 6);
+    }
+}
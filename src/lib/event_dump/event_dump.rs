@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use opencv::{
+    prelude::*,
+    core::Vector,
+    imgcodecs::imwrite,
+};
+
+// Kind of the event for virtual line crossing (incidents have their own kinds, e.g. 'stopped_vehicle')
+pub const LINE_CROSSING: &str = "line_crossing";
+
+// Number of files to keep when 'max_files' is not set
+pub const DEFAULT_MAX_EVENT_FILES: usize = 1000;
+
+// Extensions of the files produced by the dump (images and tracks). Only such files are taken into account when pruning
+const EVENT_IMAGE_EXTENSION: &str = "jpg";
+const EVENT_TRACK_EXTENSION: &str = "json";
+
+// '<timestamp>_<event_id>_<kind>_<suffix>.jpg'. Timestamp goes first, so files are ordered by name
+pub fn event_file_name(timestamp: &DateTime<Utc>, event_id: &str, kind: &str, suffix: &str) -> String {
+    format!("{}_{}_{}_{}.{}", timestamp.format("%Y%m%dT%H%M%S%3f"), event_id, kind, suffix, EVENT_IMAGE_EXTENSION)
+}
+
+// '<timestamp>_<event_id>_<kind>_track.json'
+pub fn event_track_file_name(timestamp: &DateTime<Utc>, event_id: &str, kind: &str) -> String {
+    format!("{}_{}_{}_track.{}", timestamp.format("%Y%m%dT%H%M%S%3f"), event_id, kind, EVENT_TRACK_EXTENSION)
+}
+
+// Removes the oldest event files of the directory keeping only 'keep' most recent ones. Returns number of removed files
+pub fn prune_event_images(directory: &Path, keep: usize) -> io::Result<usize> {
+    let mut files: Vec<PathBuf> = vec![];
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let is_image = path.is_file() && (extension == Some(EVENT_IMAGE_EXTENSION) || extension == Some(EVENT_TRACK_EXTENSION));
+        if is_image {
+            files.push(path);
+        }
+    }
+    if files.len() <= keep {
+        return Ok(0);
+    }
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    let excess = files.len() - keep;
+    for path in files.iter().take(excess) {
+        fs::remove_file(path)?;
+    }
+    Ok(excess)
+}
+
+// Track of the object at the moment of the event. Both the smoothed track (as tracker keeps it) and centroids
+// of the raw detections are given, so consumers could do their own filtering
+#[derive(Debug, Serialize)]
+pub struct EventTrack {
+    pub object_id: String,
+    pub short_id: u64,
+    pub class_name: String,
+    // [x, y] in pixels from the oldest to the newest
+    pub smoothed_track: Vec<[f32; 2]>,
+    pub raw_track: Vec<[f32; 2]>,
+}
+
+// Saves frames (raw and annotated ones) for the events like virtual line crossings and incidents
+pub struct EventImageDump {
+    directory: PathBuf,
+    max_files: usize,
+}
+
+impl EventImageDump {
+    pub fn new(directory: &str, max_files: usize) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        Ok(EventImageDump {
+            directory: PathBuf::from(directory),
+            max_files: max_files,
+        })
+    }
+    pub fn save(&self, event_id: &str, kind: &str, raw: &Mat, annotated: &Mat) -> Result<(), Box<dyn Error>> {
+        let timestamp = Utc::now();
+        let params = Vector::<i32>::new();
+        let raw_path = self.directory.join(event_file_name(&timestamp, event_id, kind, "raw"));
+        if !imwrite(&raw_path.to_string_lossy(), raw, &params)? {
+            return Err(format!("Can't write image '{}'", raw_path.display()).into());
+        }
+        let annotated_path = self.directory.join(event_file_name(&timestamp, event_id, kind, "annotated"));
+        if !imwrite(&annotated_path.to_string_lossy(), annotated, &params)? {
+            return Err(format!("Can't write image '{}'", annotated_path.display()).into());
+        }
+        prune_event_images(&self.directory, self.max_files)?;
+        Ok(())
+    }
+    pub fn save_track(&self, event_id: &str, kind: &str, track: &EventTrack) -> Result<(), Box<dyn Error>> {
+        let path = self.directory.join(event_track_file_name(&Utc::now(), event_id, kind));
+        fs::write(&path, serde_json::to_vec(track)?)?;
+        prune_event_images(&self.directory, self.max_files)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    #[test]
+    fn test_event_file_name() {
+        let timestamp = Utc.with_ymd_and_hms(2023, 4, 19, 11, 32, 45).unwrap();
+        let name = event_file_name(&timestamp, "9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21", LINE_CROSSING, "raw");
+        assert_eq!(name, "20230419T113245000_9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21_line_crossing_raw.jpg");
+        let name = event_track_file_name(&timestamp, "9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21", LINE_CROSSING);
+        assert_eq!(name, "20230419T113245000_9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21_line_crossing_track.json");
+    }
+    #[test]
+    fn test_prune_event_images() {
+        let directory = std::env::temp_dir().join(format!("rust_road_traffic_events_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for name in &["20230419T113247000_c_line_crossing_raw.jpg", "20230419T113245000_a_line_crossing_raw.jpg", "20230419T113246000_b_stopped_vehicle_raw.jpg", "20230419T113244000_d_line_crossing_track.json", "notes.txt"] {
+            fs::write(directory.join(name), b"").unwrap();
+        }
+        let removed = prune_event_images(&directory, 2).unwrap();
+        let mut names = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        names.sort();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(names, vec!["20230419T113246000_b_stopped_vehicle_raw.jpg", "20230419T113247000_c_line_crossing_raw.jpg", "notes.txt"]);
+    }
+}
@@ -0,0 +1,3 @@
+mod event_dump;
+
+pub use self::{event_dump::*};
\ No newline at end of file
@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+use crate::lib::tracker::Tracker;
+
+#[derive(Debug, Serialize)]
+pub struct FrameObject {
+    pub id: String,
+    pub short_id: u64,
+    pub class_name: String,
+    // [x, y, width, height]
+    pub bbox: [f32; 4],
+    pub centroid: [f32; 2],
+    // Value "-1" means speed has not been estimated yet
+    pub speed: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrameRecord {
+    pub relative_time: f32,
+    pub objects: Vec<FrameObject>,
+}
+
+impl FrameRecord {
+    pub fn from_tracker(relative_time: f32, tracker: &Tracker) -> Self {
+        let mut objects = Vec::with_capacity(tracker.objects_extra.len());
+        for (object_id, object_extra) in tracker.objects_extra.iter() {
+            let object = match tracker.engine.objects.get(object_id) {
+                Some(object) => object,
+                None => continue,
+            };
+            let bbox = object.get_bbox();
+            let center = object.get_center();
+            objects.push(FrameObject {
+                id: object_id.to_string(),
+                short_id: object_extra.get_short_id(),
+                class_name: object_extra.get_classname(),
+                bbox: [bbox.x, bbox.y, bbox.width, bbox.height],
+                centroid: [center.x, center.y],
+                speed: match object_extra.spatial_info {
+                    Some(ref spatial_info) => spatial_info.speed,
+                    None => -1.0,
+                },
+            });
+        }
+        FrameRecord {
+            relative_time: relative_time,
+            objects: objects,
+        }
+    }
+}
+
+// Writes one JSON line per processed frame. It is heavy, so it is meant for debugging only
+// When file grows beyond max_size_bytes it is renamed to '<path>.1' (previous one is overwritten) and new file is started
+pub struct FrameDumpWriter {
+    path: PathBuf,
+    max_size_bytes: u64,
+    writer: BufWriter<File>,
+    written_bytes: u64,
+}
+
+impl FrameDumpWriter {
+    // max_size_bytes - zero means no rotation
+    pub fn new(path: &str, max_size_bytes: u64) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let (writer, written_bytes) = open_append(&path)?;
+        Ok(FrameDumpWriter {
+            path: path,
+            max_size_bytes: max_size_bytes,
+            writer: writer,
+            written_bytes: written_bytes,
+        })
+    }
+    pub fn write_record(&mut self, record: &FrameRecord) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        if self.max_size_bytes > 0 && self.written_bytes > 0 && self.written_bytes + line.len() as u64 > self.max_size_bytes {
+            self.rotate()?;
+        }
+        self.writer.write_all(line.as_bytes())?;
+        self.written_bytes += line.len() as u64;
+        Ok(())
+    }
+    fn rotate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, &rotated)?;
+        let (writer, written_bytes) = open_append(&self.path)?;
+        self.writer = writer;
+        self.written_bytes = written_bytes;
+        Ok(())
+    }
+}
+
+impl Drop for FrameDumpWriter {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+fn open_append(path: &Path) -> Result<(BufWriter<File>, u64), Box<dyn Error>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((BufWriter::new(file), size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_frame_dump_rotation() {
+        let dir = std::env::temp_dir().join(format!("frame_dump_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("frames.jsonl");
+        let path_str = path.to_str().unwrap();
+        let record = FrameRecord {
+            relative_time: 1.5,
+            objects: vec![FrameObject {
+                id: "a".to_string(),
+                short_id: 1,
+                class_name: "car".to_string(),
+                bbox: [1.0, 2.0, 3.0, 4.0],
+                centroid: [2.5, 4.0],
+                speed: -1.0,
+            }],
+        };
+        let line_len = serde_json::to_string(&record).unwrap().len() as u64 + 1;
+        {
+            // Room for two lines only
+            let mut writer = FrameDumpWriter::new(path_str, line_len * 2).unwrap();
+            for _ in 0..3 {
+                writer.write_record(&record).unwrap();
+            }
+        }
+        let current = fs::read_to_string(&path).unwrap();
+        let rotated = fs::read_to_string(dir.join("frames.jsonl.1")).unwrap();
+        assert_eq!(current.lines().count(), 1);
+        assert_eq!(rotated.lines().count(), 2);
+        assert!(current.starts_with("{\"relative_time\":1.5,\"objects\":[{\"id\":\"a\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
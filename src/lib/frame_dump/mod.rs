@@ -0,0 +1,3 @@
+mod frame_dump;
+
+pub use self::{frame_dump::*};
\ No newline at end of file
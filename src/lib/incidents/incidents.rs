@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use chrono::{DateTime, Utc};
+
+pub const STOPPED_VEHICLE: &str = "stopped_vehicle";
+
+// Result of the low-speed dwell update
+#[derive(Debug, PartialEq)]
+pub enum StopTransition {
+    Unchanged,
+    // Object has been slow for longer than allowed. Contains dwell time in seconds
+    Stopped(f32),
+    // Previously stopped object moves again
+    Moving,
+}
+
+// Tracks how long the object has been moving slower than the threshold
+#[derive(Debug, Default)]
+pub struct StopDwell {
+    low_speed_since: Option<f32>,
+    reported: bool,
+}
+
+impl StopDwell {
+    // time - seconds, speed - km/h
+    // max_speed - object is considered to be slow below this speed (km/h)
+    // min_duration - slow object is considered to be stopped after this number of seconds
+    pub fn update(&mut self, time: f32, speed: f32, max_speed: f32, min_duration: f32) -> StopTransition {
+        if speed >= max_speed {
+            self.low_speed_since = None;
+            if self.reported {
+                self.reported = false;
+                return StopTransition::Moving;
+            }
+            return StopTransition::Unchanged;
+        }
+        let since = *self.low_speed_since.get_or_insert(time);
+        let dwell = time - since;
+        if !self.reported && dwell >= min_duration {
+            self.reported = true;
+            return StopTransition::Stopped(dwell);
+        }
+        StopTransition::Unchanged
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.reported
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IncidentEvent {
+    // Unique identifier of the event (e.g. to match it with the saved images)
+    pub event_id: String,
+    // E.g. "stopped_vehicle"
+    pub kind: String,
+    pub timestamp: DateTime<Utc>,
+    pub zone_id: String,
+    pub object_id: String,
+    pub short_id: u64,
+    pub class_name: String,
+    // Seconds the object has been slow for when the incident has been detected
+    pub dwell_seconds: f32,
+    // Bounding box [x, y, width, height] of the object in the original frame coordinates (before any network resize or padding)
+    pub bbox: [f32; 4],
+    // Point of the object [x, y] in the original frame coordinates
+    pub centroid: [f32; 2],
+}
+
+// Ring buffer of the latest incidents: the oldest events are dropped when capacity is reached
+#[derive(Debug)]
+pub struct IncidentLog {
+    events: VecDeque<IncidentEvent>,
+    capacity: usize,
+}
+
+impl IncidentLog {
+    pub fn new(capacity: usize) -> Self {
+        IncidentLog {
+            events: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+    pub fn push(&mut self, event: IncidentEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    // Events from the oldest to the newest
+    pub fn get_events(&self) -> Vec<IncidentEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_stop_dwell() {
+        let mut dwell = StopDwell::default();
+        assert_eq!(dwell.update(0.0, 50.0, 5.0, 10.0), StopTransition::Unchanged);
+        assert_eq!(dwell.update(1.0, 2.0, 5.0, 10.0), StopTransition::Unchanged);
+        assert_eq!(dwell.update(10.0, 1.0, 5.0, 10.0), StopTransition::Unchanged);
+        assert_eq!(dwell.update(11.0, 0.0, 5.0, 10.0), StopTransition::Stopped(10.0));
+        // Reported once only
+        assert_eq!(dwell.update(12.0, 0.0, 5.0, 10.0), StopTransition::Unchanged);
+        assert!(dwell.is_stopped());
+        assert_eq!(dwell.update(13.0, 20.0, 5.0, 10.0), StopTransition::Moving);
+        assert!(!dwell.is_stopped());
+        // Dwell time starts over
+        assert_eq!(dwell.update(14.0, 0.0, 5.0, 10.0), StopTransition::Unchanged);
+        assert_eq!(dwell.update(20.0, 0.0, 5.0, 10.0), StopTransition::Unchanged);
+    }
+    #[test]
+    fn test_incident_log() {
+        let mut log = IncidentLog::new(2);
+        for i in 0..3 {
+            log.push(IncidentEvent {
+                event_id: format!("event_{}", i),
+                kind: STOPPED_VEHICLE.to_string(),
+                timestamp: Utc::now(),
+                zone_id: "dir_0_lane_0".to_string(),
+                object_id: i.to_string(),
+                short_id: i,
+                class_name: "car".to_string(),
+                dwell_seconds: 10.0,
+                bbox: [100.0, 200.0, 50.0, 30.0],
+                centroid: [125.0, 230.0],
+            });
+        }
+        let events = log.get_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].short_id, 1);
+        assert_eq!(events[1].short_id, 2);
+    }
+}
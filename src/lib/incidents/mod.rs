@@ -0,0 +1,3 @@
+mod incidents;
+
+pub use self::{incidents::*};
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+use chrono::{DateTime, Utc};
+
+use crate::lib::od::TransitionCounts;
+
+// Number of the latest lane change events to keep
+pub const DEFAULT_MAX_LANE_CHANGES: usize = 100;
+
+// Lanes are identified by (lane_direction, lane_number) of the zones
+// Moving between the lanes of the same direction is a lane change. Moving to the zone of another direction
+// (e.g. from approach to exit lane in the intersection) is not
+pub fn is_lane_change(from_lane: (u8, u16), to_lane: (u8, u16)) -> bool {
+    from_lane.0 == to_lane.0 && from_lane.1 != to_lane.1
+}
+
+// Number of consecutive observations of the object in another zone before it is considered to be moved there.
+// It filters out jitter of the object on the border between lanes (A -> B -> A is not a lane change)
+pub const LANE_CHANGE_MIN_FRAMES: usize = 3;
+
+// Zone membership of the object which is used for lane change detection (see follow_lane)
+#[derive(Debug, Default, Clone)]
+pub struct LaneState {
+    // The zone where object has settled
+    zone_id: Option<String>,
+    // Another zone where object is observed now and the number of consecutive observations in it
+    candidate: Option<(String, usize)>,
+}
+
+impl LaneState {
+    pub fn get_zone_id(&self) -> Option<&String> {
+        self.zone_id.as_ref()
+    }
+}
+
+// Moves the object to the zone it is currently in. Object settles in the new zone only after min_frames consecutive observations
+// in it, and returning to the settled zone resets the counter. Returns identifier of the zone the object has left when it is a lane
+// change to be registered: lanes are looked up via lane_of (None for unknown zones). Zone membership is followed even when counting
+// is not active, so the lane change happened e.g. outside of the schedule is not registered later
+pub fn follow_lane<F: Fn(&str) -> Option<(u8, u16)>>(state: &mut LaneState, zone_id: &str, lane_of: F, counting_active: bool, min_frames: usize) -> Option<String> {
+    match &state.zone_id {
+        None => {
+            state.zone_id = Some(zone_id.to_string());
+            return None;
+        },
+        Some(settled_zone_id) if settled_zone_id == zone_id => {
+            state.candidate = None;
+            return None;
+        },
+        _ => {}
+    }
+    let observations = match &state.candidate {
+        Some((candidate_zone_id, observations)) if candidate_zone_id == zone_id => observations + 1,
+        _ => 1,
+    };
+    if observations < min_frames.max(1) {
+        state.candidate = Some((zone_id.to_string(), observations));
+        return None;
+    }
+    state.candidate = None;
+    let from_zone_id = state.zone_id.replace(zone_id.to_string())?;
+    if !counting_active {
+        return None;
+    }
+    match (lane_of(&from_zone_id), lane_of(zone_id)) {
+        (Some(from_lane), Some(to_lane)) if is_lane_change(from_lane, to_lane) => Some(from_zone_id),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LaneChangeEvent {
+    pub timestamp: DateTime<Utc>,
+    pub object_id: String,
+    pub short_id: u64,
+    pub class_name: String,
+    pub from_zone_id: String,
+    pub to_zone_id: String,
+}
+
+// Lane changes counted for each pair of zones per statistics period plus ring buffer of the latest events
+#[derive(Debug)]
+pub struct LaneChanges {
+    counts: TransitionCounts,
+    events: VecDeque<LaneChangeEvent>,
+    capacity: usize,
+}
+
+impl LaneChanges {
+    pub fn new(capacity: usize) -> Self {
+        LaneChanges {
+            counts: TransitionCounts::default(),
+            events: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+    pub fn register(&mut self, event: LaneChangeEvent) {
+        self.counts.register(&event.from_zone_id, &event.to_zone_id);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    // Should be called at the end of the statistics period
+    pub fn close_period(&mut self) {
+        self.counts.close_period();
+    }
+    // Counts of the last finished period as (from_zone_id, to_zone_id, count) sorted by zone identifiers
+    pub fn get_period_counts(&self) -> Vec<(String, String, u32)> {
+        self.counts.get_period_counts()
+    }
+    // Events from the oldest to the newest
+    pub fn get_events(&self) -> Vec<LaneChangeEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn event(short_id: u64, from_zone_id: &str, to_zone_id: &str) -> LaneChangeEvent {
+        LaneChangeEvent {
+            timestamp: Utc::now(),
+            object_id: short_id.to_string(),
+            short_id: short_id,
+            class_name: "car".to_string(),
+            from_zone_id: from_zone_id.to_string(),
+            to_zone_id: to_zone_id.to_string(),
+        }
+    }
+    #[test]
+    fn test_is_lane_change() {
+        assert!(is_lane_change((0, 1), (0, 2)));
+        assert!(!is_lane_change((0, 1), (0, 1)));
+        assert!(!is_lane_change((0, 1), (1, 2)));
+    }
+    #[test]
+    fn test_follow_lane_inactive() {
+        let lane_of = |zone_id: &str| match zone_id {
+            "dir_0_lane_1" => Some((0, 1)),
+            "dir_0_lane_2" => Some((0, 2)),
+            _ => None,
+        };
+        let mut lane_changes = LaneChanges::new(10);
+        let mut state = LaneState::default();
+        // Object changes lane in the inactive window, then counting is activated
+        let frames = [("dir_0_lane_1", false), ("dir_0_lane_2", false), ("dir_0_lane_2", true), ("dir_0_lane_2", true)];
+        for (zone_id, counting_active) in frames.iter() {
+            if let Some(from_zone_id) = follow_lane(&mut state, zone_id, lane_of, *counting_active, 1) {
+                lane_changes.register(event(1, &from_zone_id, zone_id));
+            }
+        }
+        lane_changes.close_period();
+        assert!(lane_changes.get_period_counts().is_empty());
+        assert!(lane_changes.get_events().is_empty());
+        assert_eq!(state.get_zone_id(), Some(&"dir_0_lane_2".to_string()));
+        // Lane change in the active window is registered
+        assert_eq!(follow_lane(&mut state, "dir_0_lane_1", lane_of, true, 1), Some("dir_0_lane_2".to_string()));
+        // Unknown zone is not a lane
+        assert_eq!(follow_lane(&mut state, "removed_zone", lane_of, true, 1), None);
+    }
+    #[test]
+    fn test_follow_lane_jitter() {
+        let lane_of = |zone_id: &str| match zone_id {
+            "dir_0_lane_1" => Some((0, 1)),
+            "dir_0_lane_2" => Some((0, 2)),
+            _ => None,
+        };
+        let mut state = LaneState::default();
+        // Object drives on the border between lanes: A -> B -> A -> B -> B -> A
+        let jitter = ["dir_0_lane_1", "dir_0_lane_2", "dir_0_lane_1", "dir_0_lane_2", "dir_0_lane_2", "dir_0_lane_1"];
+        for zone_id in jitter.iter() {
+            assert_eq!(follow_lane(&mut state, zone_id, lane_of, true, 3), None);
+        }
+        assert_eq!(state.get_zone_id(), Some(&"dir_0_lane_1".to_string()));
+        // Real lane change: object stays in the new lane long enough
+        assert_eq!(follow_lane(&mut state, "dir_0_lane_2", lane_of, true, 3), None);
+        assert_eq!(follow_lane(&mut state, "dir_0_lane_2", lane_of, true, 3), None);
+        assert_eq!(follow_lane(&mut state, "dir_0_lane_2", lane_of, true, 3), Some("dir_0_lane_1".to_string()));
+        assert_eq!(follow_lane(&mut state, "dir_0_lane_2", lane_of, true, 3), None);
+        assert_eq!(state.get_zone_id(), Some(&"dir_0_lane_2".to_string()));
+    }
+    #[test]
+    fn test_lane_changes() {
+        let mut lane_changes = LaneChanges::new(2);
+        lane_changes.register(event(1, "dir_0_lane_1", "dir_0_lane_2"));
+        lane_changes.register(event(2, "dir_0_lane_2", "dir_0_lane_1"));
+        lane_changes.register(event(3, "dir_0_lane_1", "dir_0_lane_2"));
+        // Counts are published at the end of the period only
+        assert!(lane_changes.get_period_counts().is_empty());
+        lane_changes.close_period();
+        assert_eq!(lane_changes.get_period_counts(), vec![
+            ("dir_0_lane_1".to_string(), "dir_0_lane_2".to_string(), 2),
+            ("dir_0_lane_2".to_string(), "dir_0_lane_1".to_string(), 1),
+        ]);
+        let events = lane_changes.get_events();
+        assert_eq!(events.iter().map(|event| event.short_id).collect::<Vec<u64>>(), vec![2, 3]);
+        lane_changes.close_period();
+        assert!(lane_changes.get_period_counts().is_empty());
+    }
+}
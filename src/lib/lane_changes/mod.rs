@@ -0,0 +1,3 @@
+mod lane_changes;
+
+pub use self::{lane_changes::*};
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+
+// Cumulative number of counted vehicles per zone and class. Unlike period statistics it is not reset at the end of the period,
+// only on explicit request (or restart of the process)
+#[derive(Debug)]
+pub struct LifetimeCounts {
+    // UTC time when counting has been started (or reset)
+    since: DateTime<Utc>,
+    // Key: (zone_id, class_name)
+    counts: HashMap<(String, String), u64>,
+}
+
+impl Default for LifetimeCounts {
+    fn default() -> Self {
+        LifetimeCounts {
+            since: Utc::now(),
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl LifetimeCounts {
+    pub fn register(&mut self, zone_id: &str, class_name: &str) {
+        *self.counts.entry((zone_id.to_string(), class_name.to_string())).or_insert(0) += 1;
+    }
+    // Moves counts of the zone to the new identifier
+    pub fn rename_zone(&mut self, old_zone_id: &str, new_zone_id: &str) {
+        let renamed = std::mem::take(&mut self.counts);
+        for ((zone_id, class_name), count) in renamed {
+            let zone_id = if zone_id == old_zone_id { new_zone_id.to_string() } else { zone_id };
+            *self.counts.entry((zone_id, class_name)).or_insert(0) += count;
+        }
+    }
+    pub fn reset(&mut self) {
+        self.since = Utc::now();
+        self.counts.clear();
+    }
+    pub fn get_since(&self) -> DateTime<Utc> {
+        self.since
+    }
+    // Counts as (zone_id, class_name, count) sorted by zone identifier and class
+    pub fn get_counts(&self) -> Vec<(String, String, u64)> {
+        let mut counts = self.counts
+            .iter()
+            .map(|((zone_id, class_name), count)| (zone_id.clone(), class_name.clone(), *count))
+            .collect::<Vec<(String, String, u64)>>();
+        counts.sort();
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_lifetime_counts() {
+        let mut lifetime_counts = LifetimeCounts::default();
+        lifetime_counts.register("dir_0_lane_1", "car");
+        lifetime_counts.register("dir_0_lane_0", "truck");
+        lifetime_counts.register("dir_0_lane_1", "car");
+        assert_eq!(lifetime_counts.get_counts(), vec![
+            ("dir_0_lane_0".to_string(), "truck".to_string(), 1),
+            ("dir_0_lane_1".to_string(), "car".to_string(), 2),
+        ]);
+        let since = lifetime_counts.get_since();
+        lifetime_counts.reset();
+        assert!(lifetime_counts.get_counts().is_empty());
+        assert!(lifetime_counts.get_since() >= since);
+    }
+}
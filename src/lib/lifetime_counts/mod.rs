@@ -0,0 +1,3 @@
+mod lifetime_counts;
+
+pub use self::{lifetime_counts::*};
\ No newline at end of file
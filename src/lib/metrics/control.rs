@@ -0,0 +1,70 @@
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering
+};
+
+// Shared switches of the capture -> detection pipeline
+// Flag is checked in detection thread on every frame and toggled via REST API
+#[derive(Debug)]
+pub struct PipelineControl {
+    // When set frames are still read (to keep stream alive), but detection/tracking/counting is skipped
+    paused: AtomicBool,
+    // Whether current time is inside of the schedule windows (always true when there is no schedule)
+    schedule_active: AtomicBool,
+}
+
+impl Default for PipelineControl {
+    fn default() -> Self {
+        PipelineControl {
+            paused: AtomicBool::new(false),
+            schedule_active: AtomicBool::new(true),
+        }
+    }
+}
+
+impl PipelineControl {
+    // Returns true if state has been changed (pipeline was running)
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::SeqCst)
+    }
+    // Returns true if state has been changed (pipeline was paused)
+    pub fn resume(&self) -> bool {
+        self.paused.swap(false, Ordering::SeqCst)
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+    pub fn set_schedule_active(&self, active: bool) {
+        self.schedule_active.store(active, Ordering::SeqCst);
+    }
+    pub fn is_schedule_active(&self) -> bool {
+        self.schedule_active.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_pause_resume() {
+        let control = PipelineControl::default();
+        assert!(!control.is_paused());
+        // Resume of running pipeline changes nothing
+        assert!(!control.resume());
+        assert!(control.pause());
+        assert!(control.is_paused());
+        // Repeated pause changes nothing
+        assert!(!control.pause());
+        assert!(control.is_paused());
+        assert!(control.resume());
+        assert!(!control.is_paused());
+    }
+    #[test]
+    fn test_schedule_active() {
+        let control = PipelineControl::default();
+        // There is no schedule by default
+        assert!(control.is_schedule_active());
+        control.set_schedule_active(false);
+        assert!(!control.is_schedule_active());
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering
+};
+
+// Performance counters of the capture -> detection pipeline
+// Atomics are used since counters are updated from capture thread on every frame
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    // Number of non-empty frames read from video source
+    frames_captured: AtomicU64,
+    // Number of frames which has been passed to detection thread
+    frames_sent: AtomicU64,
+    // Number of frames which could not be passed to detection thread immediately (detection is busy)
+    frames_backpressured: AtomicU64,
+    // Number of frames which has been dropped because of backpressure
+    frames_dropped: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineMetricsSnapshot {
+    pub frames_captured: u64,
+    pub frames_sent: u64,
+    pub frames_backpressured: u64,
+    pub frames_dropped: u64,
+}
+
+impl PipelineMetrics {
+    pub fn inc_captured(&self) {
+        self.frames_captured.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn inc_sent(&self) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn inc_backpressured(&self) {
+        self.frames_backpressured.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn inc_dropped(&self) {
+        self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn snapshot(&self) -> PipelineMetricsSnapshot {
+        PipelineMetricsSnapshot {
+            frames_captured: self.frames_captured.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            frames_backpressured: self.frames_backpressured.load(Ordering::Relaxed),
+            frames_dropped: self.frames_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::fmt::Display for PipelineMetricsSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "captured: {} | sent: {} | backpressured: {} | dropped: {}",
+            self.frames_captured,
+            self.frames_sent,
+            self.frames_backpressured,
+            self.frames_dropped,
+        )
+    }
+}
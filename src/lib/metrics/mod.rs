@@ -0,0 +1,5 @@
+mod metrics;
+mod control;
+mod publisher_status;
+
+pub use self::{metrics::*, control::*, publisher_status::*};
\ No newline at end of file
@@ -0,0 +1,55 @@
+use std::sync::atomic::{
+    AtomicBool,
+    AtomicU64,
+    AtomicUsize,
+    Ordering
+};
+
+// Connection state of the external publisher (Redis). It is updated by the publisher and read via REST API
+#[derive(Debug, Default)]
+pub struct PublisherStatus {
+    enabled: AtomicBool,
+    connected: AtomicBool,
+    // Number of successful reconnects after connection has been lost
+    reconnects: AtomicU64,
+    // Number of snapshots waiting for connection to be restored
+    pending: AtomicUsize,
+    // Number of snapshots dropped since buffer was full (or reconnect is disabled)
+    dropped: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PublisherStatusSnapshot {
+    pub enabled: bool,
+    pub connected: bool,
+    pub reconnects: u64,
+    pub pending: usize,
+    pub dropped: u64,
+}
+
+impl PublisherStatus {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+    }
+    pub fn inc_reconnects(&self) -> u64 {
+        self.reconnects.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    pub fn set_pending(&self, pending: usize) {
+        self.pending.store(pending, Ordering::Relaxed);
+    }
+    pub fn inc_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn snapshot(&self) -> PublisherStatusSnapshot {
+        PublisherStatusSnapshot {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            connected: self.connected.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            pending: self.pending.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
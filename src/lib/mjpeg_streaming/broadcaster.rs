@@ -1,13 +1,22 @@
 // Strictly taken from https://github.com/LdDl/mjpeg-rs/blob/master/src/mjpeg_streaming/broadcaster.rs
 
 use opencv::{
+    core::Mat,
     core::Vector,
+    imgcodecs::imencode,
+    imgcodecs::IMWRITE_JPEG_QUALITY,
 };
 
 use std::{
+    collections::HashMap,
     thread,
     sync::{
+        Arc,
         Mutex,
+        atomic::{
+            AtomicUsize,
+            Ordering
+        },
         mpsc::{
             Receiver as STDReceiver
         }
@@ -31,19 +40,66 @@ use tokio::sync::mpsc::{
     Sender
 };
 
+// JPEG quality used when neither configuration nor client asks for specific one (the same as OpenCV's default)
+pub const DEFAULT_MJPEG_QUALITY: i32 = 95;
+
+// Quality requested by the client (or the default one) clamped to the valid JPEG range [1; 100]
+pub fn resolve_quality(requested: Option<i32>, default_quality: i32) -> i32 {
+    requested.unwrap_or(default_quality).max(1).min(100)
+}
+
+pub fn encode_jpeg(frame: &Mat, quality: i32) -> opencv::Result<Option<Vector<u8>>> {
+    let mut buffer = Vector::<u8>::new();
+    let params = vec![IMWRITE_JPEG_QUALITY, quality].into_iter().collect::<Vector<i32>>();
+    if !imencode(".jpg", frame, &mut buffer, &params)? {
+        return Ok(None);
+    }
+    Ok(Some(buffer))
+}
+
+struct ClientSender {
+    tx: Sender<web::Bytes>,
+    quality: i32,
+}
+
+// Frames are encoded here rather than in the detection loop, so every client could get its own JPEG quality
+// Each distinct quality is encoded once per frame: CPU cost grows with the number of distinct qualities, not with the number of clients
+// Nothing is encoded when there are no clients
 pub struct Broadcaster {
-    clients: Vec<Sender<web::Bytes>>,
+    clients: Vec<ClientSender>,
+    default_quality: i32,
+    // Number of connected clients. It is shared with the detection thread, so frames are neither annotated nor sent when nobody watches
+    clients_count: Arc<AtomicUsize>,
+}
+
+// Frames should be prepared for MJPEG only when streaming is enabled and at least one client is connected
+pub fn has_mjpeg_viewers(enable_mjpeg: bool, clients_count: &AtomicUsize) -> bool {
+    enable_mjpeg && clients_count.load(Ordering::Relaxed) > 0
 }
 
 impl Broadcaster {
     pub fn default() -> Self {
+        Broadcaster::new(DEFAULT_MJPEG_QUALITY, Arc::new(AtomicUsize::new(0)))
+    }
+    pub fn new(default_quality: i32, clients_count: Arc<AtomicUsize>) -> Self {
+        clients_count.store(0, Ordering::Relaxed);
         Broadcaster {
             clients: Vec::new(),
+            default_quality: resolve_quality(None, default_quality),
+            clients_count: clients_count,
         }
     }
-    pub fn add_client(&mut self) -> Client {
+    pub fn clients_count(&self) -> usize {
+        self.clients_count.load(Ordering::Relaxed)
+    }
+    // quality - JPEG quality for this client. Configured one is used when None
+    pub fn add_client(&mut self, quality: Option<i32>) -> Client {
         let (tx, rx) = channel(1);
-        self.clients.push(tx);
+        self.clients.push(ClientSender {
+            tx: tx,
+            quality: resolve_quality(quality, self.default_quality),
+        });
+        self.clients_count.store(self.clients.len(), Ordering::Relaxed);
         return Client(rx);
     }
     pub fn make_message_block(buffer: &Vector<u8>) -> Vec<u8> {
@@ -52,22 +108,46 @@ impl Broadcaster {
         msg.extend(bfu8);
         msg
     }
-    fn send_image(&mut self, msg: &[u8]) {
+    fn send_frame(&mut self, frame: &Mat) {
+        if self.clients.is_empty() {
+            return;
+        }
+        let mut messages: HashMap<i32, Option<web::Bytes>> = HashMap::new();
         let mut ok_clients = Vec::new();
-        let msg = web::Bytes::from([msg].concat());
-        for client in self.clients.iter() {
-            let result = client.clone().try_send(msg.clone());
-            if let Ok(()) = result {
-                ok_clients.push(client.clone());
+        for client in self.clients.drain(..) {
+            let msg = messages.entry(client.quality).or_insert_with(|| {
+                match encode_jpeg(frame, client.quality) {
+                    Ok(Some(buffer)) => Some(web::Bytes::from(Broadcaster::make_message_block(&buffer))),
+                    Ok(None) => {
+                        println!("image has not been encoded");
+                        None
+                    },
+                    Err(err) => {
+                        println!("Can't encode image for MJPEG due the error: {}", err);
+                        None
+                    }
+                }
+            });
+            let msg = match msg {
+                Some(msg) => msg,
+                // Keep the client: next frame could be encoded
+                None => {
+                    ok_clients.push(client);
+                    continue;
+                }
+            };
+            if let Ok(()) = client.tx.try_send(msg.clone()) {
+                ok_clients.push(client);
             }
         }
         self.clients = ok_clients;
+        // Disconnected clients are dropped only here. It is fine, since frames keep coming while the counter is not zero
+        self.clients_count.store(self.clients.len(), Ordering::Relaxed);
     }
-    pub fn spawn_reciever(_self: web::Data<Mutex<Self>>, rx_frames_data: STDReceiver<Vector<u8>>) {
+    pub fn spawn_reciever(_self: web::Data<Mutex<Self>>, rx_frames_data: STDReceiver<Mat>) {
         thread::spawn(move || {
             for received in rx_frames_data {
-                let msg = Broadcaster::make_message_block(&received);
-                _self.lock().unwrap().send_image(&msg);
+                _self.lock().unwrap().send_frame(&received);
             }
         });
     }
@@ -86,4 +166,38 @@ impl Stream for Client {
             Poll::Pending => Poll::Pending
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_resolve_quality() {
+        assert_eq!(resolve_quality(None, 70), 70);
+        assert_eq!(resolve_quality(Some(30), 70), 30);
+        assert_eq!(resolve_quality(Some(0), 70), 1);
+        assert_eq!(resolve_quality(Some(250), 70), 100);
+        assert_eq!(resolve_quality(None, -5), 1);
+    }
+    #[test]
+    fn test_pause_on_no_clients() {
+        use opencv::core::{Scalar, CV_8UC3};
+        let clients_count = Arc::new(AtomicUsize::new(0));
+        let mut broadcaster = Broadcaster::new(70, clients_count.clone());
+        assert!(!has_mjpeg_viewers(true, &clients_count));
+        let first = broadcaster.add_client(None);
+        let second = broadcaster.add_client(Some(30));
+        assert_eq!(broadcaster.clients_count(), 2);
+        assert!(has_mjpeg_viewers(true, &clients_count));
+        // Disabled streaming never needs frames
+        assert!(!has_mjpeg_viewers(false, &clients_count));
+        let frame = Mat::new_rows_cols_with_default(8, 8, CV_8UC3, Scalar::all(0.0)).unwrap();
+        drop(first);
+        broadcaster.send_frame(&frame);
+        assert_eq!(clients_count.load(Ordering::Relaxed), 1);
+        drop(second);
+        broadcaster.send_frame(&frame);
+        assert_eq!(clients_count.load(Ordering::Relaxed), 0);
+        assert!(!has_mjpeg_viewers(true, &clients_count));
+    }
 }
\ No newline at end of file
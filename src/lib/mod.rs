@@ -5,4 +5,13 @@ pub mod tracker;
 pub mod draw;
 pub mod data_storage;
 pub mod mjpeg_streaming;
-pub mod publisher;
\ No newline at end of file
+pub mod publisher;
+pub mod metrics;
+pub mod frame_dump;
+pub mod schedule;
+pub mod incidents;
+pub mod lane_changes;
+pub mod od;
+pub mod lifetime_counts;
+pub mod dataset;
+pub mod event_dump;
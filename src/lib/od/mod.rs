@@ -0,0 +1,3 @@
+mod od_matrix;
+
+pub use self::{od_matrix::*};
\ No newline at end of file
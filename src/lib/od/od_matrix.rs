@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+// Origin of the vehicle which has not crossed any zone before (or crossed it too long ago)
+pub const EXTERNAL_ZONE: &str = "external";
+
+// Number of transitions for each pair of zones per statistics period
+#[derive(Debug, Default)]
+pub struct TransitionCounts {
+    // Key: (from_zone_id, to_zone_id)
+    current: HashMap<(String, String), u32>,
+    // Counts of the last finished period
+    last_period: HashMap<(String, String), u32>,
+    // Renamed zones: old identifier -> current one. Tracked objects could still refer to the old identifiers (e.g. as the origin)
+    aliases: HashMap<String, String>,
+}
+
+impl TransitionCounts {
+    pub fn register(&mut self, from_zone_id: &str, to_zone_id: &str) {
+        let key = (self.resolve_alias(from_zone_id), self.resolve_alias(to_zone_id));
+        *self.current.entry(key).or_insert(0) += 1;
+    }
+    fn resolve_alias(&self, zone_id: &str) -> String {
+        self.aliases.get(zone_id).cloned().unwrap_or_else(|| zone_id.to_string())
+    }
+    // Re-keys transitions of both current and last periods, so they refer to the new zone identifier
+    // Later transitions referring to the old identifier are attributed to the new one too
+    pub fn rename_zone(&mut self, old_zone_id: &str, new_zone_id: &str) {
+        let rename = |zone_id: String| if zone_id == old_zone_id { new_zone_id.to_string() } else { zone_id };
+        for counts in [&mut self.current, &mut self.last_period].iter_mut() {
+            let renamed = std::mem::take(&mut **counts);
+            for ((from_zone_id, to_zone_id), count) in renamed {
+                *counts.entry((rename(from_zone_id), rename(to_zone_id))).or_insert(0) += count;
+            }
+        }
+        // Keep aliases flat, so every old identifier points to the current one
+        for (_, current_zone_id) in self.aliases.iter_mut() {
+            if current_zone_id == old_zone_id {
+                *current_zone_id = new_zone_id.to_string();
+            }
+        }
+        self.aliases.remove(new_zone_id);
+        self.aliases.insert(old_zone_id.to_string(), new_zone_id.to_string());
+    }
+    // Should be called when zone with the identifier is created, so it is not redirected to the renamed zone anymore
+    pub fn forget_alias(&mut self, zone_id: &str) {
+        self.aliases.remove(zone_id);
+    }
+    // Should be called at the end of the statistics period
+    pub fn close_period(&mut self) {
+        self.last_period = std::mem::take(&mut self.current);
+    }
+    // Counts of the last finished period as (from_zone_id, to_zone_id, count) sorted by zone identifiers
+    pub fn get_period_counts(&self) -> Vec<(String, String, u32)> {
+        let mut counts = self.last_period
+            .iter()
+            .map(|((from_zone_id, to_zone_id), count)| (from_zone_id.clone(), to_zone_id.clone(), *count))
+            .collect::<Vec<(String, String, u32)>>();
+        counts.sort();
+        counts
+    }
+}
+
+// Origin-destination matrix: every virtual line crossing is a transition from the zone crossed previously (origin) to the current one
+pub type ODMatrix = TransitionCounts;
+
+// Resolves origin zone of the crossing
+//
+// previous_crossing - zone identifier and time (seconds) of the previous crossing of the object
+// max_transition_seconds - previous crossing older than this is considered to be stale (e.g. object has been lost and re-acquired),
+// so origin is external. No limit if None
+//
+pub fn resolve_origin(previous_crossing: Option<&(String, f32)>, crossing_time: f32, max_transition_seconds: Option<f32>) -> String {
+    match previous_crossing {
+        Some((zone_id, time)) if max_transition_seconds.map(|max_seconds| crossing_time - time <= max_seconds).unwrap_or(true) => zone_id.clone(),
+        _ => EXTERNAL_ZONE.to_string(),
+    }
+}
+
+// Counts crossing of the zone as a transition from the origin and remembers it for the next crossing of the object
+pub fn register_crossing(od_matrix: &mut ODMatrix, last_crossing: &mut Option<(String, f32)>, zone_id: &str, crossing_time: f32, max_transition_seconds: Option<f32>) {
+    let origin = resolve_origin(last_crossing.as_ref(), crossing_time, max_transition_seconds);
+    od_matrix.register(&origin, zone_id);
+    *last_crossing = Some((zone_id.to_string(), crossing_time));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_resolve_origin() {
+        let previous = ("dir_0_lane_0".to_string(), 10.0);
+        assert_eq!(resolve_origin(None, 12.0, Some(30.0)), EXTERNAL_ZONE);
+        assert_eq!(resolve_origin(Some(&previous), 12.0, Some(30.0)), "dir_0_lane_0");
+        assert_eq!(resolve_origin(Some(&previous), 40.0, Some(30.0)), "dir_0_lane_0");
+        // Stale crossing is not attributed to the origin
+        assert_eq!(resolve_origin(Some(&previous), 40.5, Some(30.0)), EXTERNAL_ZONE);
+        assert_eq!(resolve_origin(Some(&previous), 1000.0, None), "dir_0_lane_0");
+    }
+    #[test]
+    fn test_register_crossing() {
+        let mut od = ODMatrix::default();
+        let mut last_crossing = None;
+        register_crossing(&mut od, &mut last_crossing, "dir_0_lane_0", 5.0, Some(30.0));
+        register_crossing(&mut od, &mut last_crossing, "dir_1_lane_0", 20.0, Some(30.0));
+        // Object has been lost for a long time before the next crossing
+        register_crossing(&mut od, &mut last_crossing, "dir_0_lane_0", 120.0, Some(30.0));
+        assert_eq!(last_crossing, Some(("dir_0_lane_0".to_string(), 120.0)));
+        od.close_period();
+        assert_eq!(od.get_period_counts(), vec![
+            ("dir_0_lane_0".to_string(), "dir_1_lane_0".to_string(), 1),
+            (EXTERNAL_ZONE.to_string(), "dir_0_lane_0".to_string(), 2),
+        ]);
+    }
+    #[test]
+    fn test_rename_zone() {
+        let mut od = ODMatrix::default();
+        let mut last_crossing = None;
+        register_crossing(&mut od, &mut last_crossing, "dir_0_lane_0", 5.0, None);
+        register_crossing(&mut od, &mut last_crossing, "dir_1_lane_0", 10.0, None);
+        od.close_period();
+        register_crossing(&mut od, &mut last_crossing, "dir_0_lane_0", 15.0, None);
+        od.rename_zone("dir_0_lane_0", "north");
+        // Object still remembers the old identifier as its origin
+        assert_eq!(last_crossing, Some(("dir_0_lane_0".to_string(), 15.0)));
+        register_crossing(&mut od, &mut last_crossing, "dir_1_lane_0", 20.0, None);
+        od.rename_zone("north", "north_entry");
+        register_crossing(&mut od, &mut last_crossing, "dir_0_lane_0", 25.0, None);
+        // Last period is re-keyed too
+        assert_eq!(od.get_period_counts(), vec![
+            (EXTERNAL_ZONE.to_string(), "north_entry".to_string(), 1),
+            ("north_entry".to_string(), "dir_1_lane_0".to_string(), 1),
+        ]);
+        od.close_period();
+        // Transitions from the old identifiers (both before and after renaming) are attributed to the new one
+        assert_eq!(od.get_period_counts(), vec![
+            ("dir_1_lane_0".to_string(), "north_entry".to_string(), 2),
+            ("north_entry".to_string(), "dir_1_lane_0".to_string(), 1),
+        ]);
+        // Zone with the old identifier has been created again
+        od.forget_alias("dir_0_lane_0");
+        od.register("dir_0_lane_0", "dir_1_lane_0");
+        od.close_period();
+        assert_eq!(od.get_period_counts(), vec![
+            ("dir_0_lane_0".to_string(), "dir_1_lane_0".to_string(), 1),
+        ]);
+    }
+    #[test]
+    fn test_transition_counts() {
+        let mut od = ODMatrix::default();
+        od.register(EXTERNAL_ZONE, "dir_0_lane_0");
+        od.register("dir_0_lane_0", "dir_1_lane_0");
+        od.register(EXTERNAL_ZONE, "dir_0_lane_0");
+        assert!(od.get_period_counts().is_empty());
+        od.close_period();
+        assert_eq!(od.get_period_counts(), vec![
+            ("dir_0_lane_0".to_string(), "dir_1_lane_0".to_string(), 1),
+            (EXTERNAL_ZONE.to_string(), "dir_0_lane_0".to_string(), 2),
+        ]);
+        od.close_period();
+        assert!(od.get_period_counts().is_empty());
+    }
+}
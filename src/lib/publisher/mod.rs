@@ -1,4 +1,9 @@
 mod redis_message;
 mod redis_publisher;
+#[cfg(feature = "sqlite")]
+mod sqlite_logger;
+mod statistics_proto;
 
-pub use self::{redis_message::*, redis_publisher::*};
\ No newline at end of file
+pub use self::{redis_message::*, redis_publisher::*, statistics_proto::*};
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_logger::*;
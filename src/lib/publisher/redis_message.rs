@@ -1,5 +1,42 @@
 use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 pub trait RedisMessage {
     fn prepare_string(&self) -> Result<String, Box<dyn Error>>;
-}
\ No newline at end of file
+    fn prepare_protobuf(&self) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+// Serialization format of the messages published to Redis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    Json,
+    // See 'proto/statistics.proto'
+    Protobuf,
+}
+
+impl fmt::Display for MessageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageFormat::Json => write!(f, "json"),
+            MessageFormat::Protobuf => write!(f, "protobuf"),
+        }
+    }
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat::Json
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(MessageFormat::Json),
+            "protobuf" => Ok(MessageFormat::Protobuf),
+            _ => Err(()),
+        }
+    }
+}
@@ -1,17 +1,56 @@
 extern crate redis;
 
-use crate::{lib::data_storage::ThreadedDataStorage, rest_api::zones_stats::TrafficFlowInfo};
-use crate::lib::publisher::RedisMessage;
-use crate::rest_api::zones_stats::{AllZonesStats, VehicleTypeParameters, ZoneStats};
-use redis::{Client, Commands};
-use std::collections::HashMap;
+use crate::lib::data_storage::ThreadedDataStorage;
+use crate::lib::publisher::{RedisMessage, MessageFormat, AllZonesStatsProto};
+use crate::rest_api::zones_stats::{AllZonesStats, ZoneStats};
+use crate::lib::metrics::PublisherStatus;
+use redis::{Client, Commands, Connection};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::error::Error;
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use prost::Message;
+
+// Maximum number of snapshots to be kept while Redis is unreachable. The oldest ones are dropped first
+pub const MAX_PENDING_SNAPSHOTS: usize = 60;
+// Delay before the first connection retry. It is doubled for every next attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// Delay before the given retry attempt (starting from zero): exponential backoff with upper bound
+pub fn retry_backoff(attempt: u32) -> Duration {
+    let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+    match RETRY_BASE_DELAY.checked_mul(factor) {
+        Some(delay) if delay < RETRY_MAX_DELAY => delay,
+        _ => RETRY_MAX_DELAY,
+    }
+}
 
 pub struct RedisConnection {
     pub channel_name: String,
     pub client: Arc<Client>,
     pub data_storage: ThreadedDataStorage,
+    // Minimum time between two publishes. Snapshots inside of this window are dropped
+    min_publish_interval: Duration,
+    last_publish: Cell<Option<Instant>>,
+    coalesced: Cell<u64>,
+    // Key to store the latest snapshot at (in addition to PUBLISH). None means that SET is not used
+    latest_key: Option<String>,
+    // Zero means that key never expires
+    latest_key_ttl_seconds: u64,
+    format: MessageFormat,
+    // Connection is kept alive between publishes. None means that it is not established (or has been lost)
+    connection: RefCell<Option<Connection>>,
+    // Serialized snapshots which have not been sent yet due connection problems
+    pending: RefCell<VecDeque<Vec<u8>>>,
+    // Number of extra connection attempts on startup
+    connect_retries: u32,
+    // Whether connection should be restored when it has been lost
+    reconnect: bool,
+    was_connected: Cell<bool>,
+    status: Arc<PublisherStatus>,
 }
 
 impl RedisConnection {
@@ -22,10 +61,24 @@ impl RedisConnection {
         data_storage: ThreadedDataStorage,
     ) -> RedisConnection {
         let client = Client::open(format!("redis://{}:{}/{}", host, port, db_index)).unwrap();
+        let status = data_storage.read().expect("DataStorage is poisoned [RWLock]").redis_status.clone();
+        status.set_enabled(true);
         return RedisConnection {
             channel_name: "DETECTORS_STATISTICS".to_string(),
             client: Arc::new(client),
             data_storage,
+            min_publish_interval: Duration::from_millis(0),
+            last_publish: Cell::new(None),
+            coalesced: Cell::new(0),
+            latest_key: None,
+            latest_key_ttl_seconds: 0,
+            format: MessageFormat::default(),
+            connection: RefCell::new(None),
+            pending: RefCell::new(VecDeque::new()),
+            connect_retries: 0,
+            reconnect: true,
+            was_connected: Cell::new(false),
+            status: status,
         };
     }
     pub fn new_with_password(
@@ -40,29 +93,177 @@ impl RedisConnection {
             password, host, port, db_index
         ))
         .unwrap();
+        let status = data_storage.read().expect("DataStorage is poisoned [RWLock]").redis_status.clone();
+        status.set_enabled(true);
         return RedisConnection {
             channel_name: "DETECTORS_STATISTICS".to_string(),
             client: Arc::new(client),
             data_storage,
+            min_publish_interval: Duration::from_millis(0),
+            last_publish: Cell::new(None),
+            coalesced: Cell::new(0),
+            latest_key: None,
+            latest_key_ttl_seconds: 0,
+            format: MessageFormat::default(),
+            connection: RefCell::new(None),
+            pending: RefCell::new(VecDeque::new()),
+            connect_retries: 0,
+            reconnect: true,
+            was_connected: Cell::new(false),
+            status: status,
         };
     }
     pub fn set_channel(&mut self, _channel_name: String) {
         self.channel_name = _channel_name.clone();
     }
+    // Enables storing of the latest snapshot at given key
+    pub fn set_latest_key(&mut self, key: String, ttl_seconds: u64) {
+        self.latest_key = Some(key);
+        self.latest_key_ttl_seconds = ttl_seconds;
+    }
+    pub fn set_format(&mut self, format: MessageFormat) {
+        self.format = format;
+    }
+    pub fn set_min_publish_interval(&mut self, milliseconds: u64) {
+        self.min_publish_interval = Duration::from_millis(milliseconds);
+    }
+    // Checks if enough time has passed since last publish
+    fn publish_allowed(&self, now: Instant) -> bool {
+        match self.last_publish.get() {
+            Some(last_publish) => now.duration_since(last_publish) >= self.min_publish_interval,
+            None => true
+        }
+    }
+    pub fn set_connect_retries(&mut self, retries: u32) {
+        self.connect_retries = retries;
+    }
+    pub fn set_reconnect(&mut self, reconnect: bool) {
+        self.reconnect = reconnect;
+    }
+    // Establishes connection retrying with backoff. Returns false when all attempts have failed
+    // It is blocking, so it is intended to be called on startup only
+    pub fn connect(&self) -> bool {
+        for attempt in 0..=self.connect_retries {
+            match self.client.get_connection() {
+                Ok(conn) => {
+                    println!("Connected to Redis");
+                    self.set_connection(conn);
+                    return true;
+                },
+                Err(err) => {
+                    if attempt == self.connect_retries {
+                        println!("[WARNING]: Can't connect to Redis due the error: {}. Attempts: {}", err, attempt + 1);
+                        break;
+                    }
+                    let delay = retry_backoff(attempt);
+                    println!("Can't connect to Redis due the error: {}. Next attempt in {:?}", err, delay);
+                    thread::sleep(delay);
+                }
+            }
+        }
+        false
+    }
+    fn set_connection(&self, conn: Connection) {
+        *self.connection.borrow_mut() = Some(conn);
+        self.status.set_connected(true);
+        if self.was_connected.replace(true) {
+            let reconnects = self.status.inc_reconnects();
+            println!("Reconnected to Redis. Reconnects so far: {}", reconnects);
+        }
+    }
+    fn drop_connection(&self) {
+        *self.connection.borrow_mut() = None;
+        self.status.set_connected(false);
+    }
+    // Lost connection is restored only when reconnect is enabled (or connection has never been established)
+    fn ensure_connection(&self) -> Result<(), Box<dyn Error>> {
+        if self.connection.borrow().is_some() {
+            return Ok(());
+        }
+        if self.was_connected.get() && !self.reconnect {
+            return Err("connection has been lost and reconnect is disabled".into());
+        }
+        let conn = self.client.get_connection()?;
+        self.set_connection(conn);
+        Ok(())
+    }
+    fn send_payload(&self, redis_conn: &mut Connection, payload: &[u8]) -> Result<(), Box<dyn Error>> {
+        // PUBLISH gives streaming semantics: only currently connected subscribers receive the message
+        // SET gives last-value semantics: late readers still can get the latest snapshot
+        if let Some(latest_key) = &self.latest_key {
+            if self.latest_key_ttl_seconds > 0 {
+                let _: () = redis_conn.set_ex(latest_key, payload, self.latest_key_ttl_seconds)?;
+            } else {
+                let _: () = redis_conn.set(latest_key, payload)?;
+            }
+        }
+        let _: () = redis_conn.publish(self.channel_name.to_owned(), payload)?;
+        Ok(())
+    }
+    // Snapshot is queued first, so it won't be lost on connection problems (until the queue overflows)
+    fn enqueue(&self, payload: Vec<u8>) {
+        let mut pending = self.pending.borrow_mut();
+        if !self.reconnect && self.was_connected.get() && self.connection.borrow().is_none() {
+            // Nobody is going to send it
+            self.status.inc_dropped();
+            return;
+        }
+        if pending.len() >= MAX_PENDING_SNAPSHOTS {
+            pending.pop_front();
+            self.status.inc_dropped();
+        }
+        pending.push_back(payload);
+        self.status.set_pending(pending.len());
+    }
+    // Sends queued snapshots in order. Connection is dropped on the first error, remaining snapshots stay in queue
+    fn flush_pending(&self) -> Result<usize, Box<dyn Error>> {
+        self.ensure_connection()?;
+        let mut sent = 0;
+        let mut pending = self.pending.borrow_mut();
+        while let Some(payload) = pending.front() {
+            let result = match self.connection.borrow_mut().as_mut() {
+                Some(redis_conn) => self.send_payload(redis_conn, payload),
+                None => break,
+            };
+            if let Err(err) = result {
+                self.status.set_pending(pending.len());
+                self.drop_connection();
+                return Err(err);
+            }
+            pending.pop_front();
+            sent += 1;
+        }
+        self.status.set_pending(pending.len());
+        Ok(sent)
+    }
     pub fn publish(&self, msg: &dyn RedisMessage) -> Result<(), Box<dyn Error>> {
         println!("Trying to send data...");
-        let mut redis_conn = match self.client.get_connection() {
-            Ok(_conn) => _conn,
-            Err(_err) => {
-                return Err(_err.into());
-            }
+        let payload = match self.format {
+            MessageFormat::Json => msg.prepare_string()?.into_bytes(),
+            MessageFormat::Protobuf => msg.prepare_protobuf()?,
         };
-        let msg_string = msg.prepare_string()?;
-        redis_conn.publish(self.channel_name.to_owned(), msg_string)?;
-        println!("...Success");
+        self.enqueue(payload);
+        let sent = self.flush_pending()?;
+        if sent > 1 {
+            println!("...Success. Sent snapshots (including delayed ones): {}", sent);
+        } else {
+            println!("...Success");
+        }
         Ok(())
     }
     pub fn push_statistics(&self) {
+        let now = Instant::now();
+        if !self.publish_allowed(now) {
+            // Do not queue snapshot: next publish after the window will contain the latest data anyway
+            self.coalesced.set(self.coalesced.get() + 1);
+            println!("Redis publish is coalesced due min publish interval. Skipped snapshots in current window: {}", self.coalesced.get());
+            return;
+        }
+        if self.coalesced.get() > 0 {
+            println!("Publishing the latest snapshot. Snapshots coalesced since last publish: {}", self.coalesced.get());
+            self.coalesced.set(0);
+        }
+        self.last_publish.set(Some(now));
         let ds_guard = self
             .data_storage
             .read()
@@ -73,33 +274,13 @@ impl RedisConnection {
             .expect("Spatial data is poisoned [RWLock]");
         let mut prepared_message = AllZonesStats {
             equipment_id: ds_guard.id.clone(),
+            location_name: ds_guard.location_name.clone(),
+            location_wgs84: ds_guard.location_wgs84,
             data: vec![],
         };
         for (_, v) in zones.iter() {
             let element = v.lock().expect("Mutex poisoned");
-            let mut stats = ZoneStats {
-                lane_number: element.road_lane_num,
-                lane_direction: element.road_lane_direction,
-                period_start: element.statistics.period_start,
-                period_end: element.statistics.period_end,
-                statistics: HashMap::new(),
-                traffic_flow_parameters: TrafficFlowInfo{
-                    avg_speed: element.statistics.traffic_flow_parameters.avg_speed,
-                    sum_intensity: element.statistics.traffic_flow_parameters.sum_intensity,
-                    defined_sum_intensity: element.statistics.traffic_flow_parameters.defined_sum_intensity,
-                    avg_headway: element.statistics.traffic_flow_parameters.avg_headway
-                }
-            };
-            for (vehicle_type, statistics) in element.statistics.vehicles_data.iter() {
-                stats.statistics.insert(
-                    vehicle_type.to_string(),
-                    VehicleTypeParameters {
-                        estimated_avg_speed: statistics.avg_speed,
-                        estimated_sum_intensity: statistics.sum_intensity,
-                        estimated_defined_sum_intensity: statistics.defined_sum_intensity
-                    },
-                );
-            }
+            let stats = ZoneStats::from(&*element);
             drop(element);
             prepared_message.data.push(stats);
         }
@@ -119,4 +300,20 @@ impl RedisMessage for AllZonesStats {
         let json = serde_json::to_string(self)?;
         Ok(json)
     }
+    fn prepare_protobuf(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(AllZonesStatsProto::from(self).encode_to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(retry_backoff(0), Duration::from_millis(500));
+        assert_eq!(retry_backoff(1), Duration::from_millis(1000));
+        assert_eq!(retry_backoff(3), Duration::from_millis(4000));
+        assert_eq!(retry_backoff(6), RETRY_MAX_DELAY);
+        assert_eq!(retry_backoff(100), RETRY_MAX_DELAY);
+    }
 }
@@ -0,0 +1,143 @@
+extern crate rusqlite;
+
+use crate::lib::data_storage::ThreadedDataStorage;
+use rusqlite::{params, Connection};
+use std::error::Error;
+
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS statistics (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    equipment_id TEXT NOT NULL,
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    zone_id TEXT NOT NULL,
+    class TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    avg_speed REAL NOT NULL,
+    avg_headway REAL NOT NULL,
+    occupancy REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS statistics_period_start_idx ON statistics (period_start);
+";
+
+// Row of the 'statistics' table
+struct StatisticsRow {
+    period_start: String,
+    period_end: String,
+    zone_id: String,
+    class: String,
+    count: u32,
+    avg_speed: f32,
+    avg_headway: f32,
+    occupancy: f32,
+}
+
+// Stores statistics in SQLite database (durable history, unlike Redis)
+pub struct SqliteLogger {
+    conn: Connection,
+    pub data_storage: ThreadedDataStorage,
+}
+
+impl SqliteLogger {
+    // Opens (or creates) database file and prepares tables if they do not exist yet
+    pub fn new(path: &str, data_storage: ThreadedDataStorage) -> Result<SqliteLogger, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(CREATE_TABLES)?;
+        Ok(SqliteLogger {
+            conn: conn,
+            data_storage: data_storage,
+        })
+    }
+    // Inserts one row per zone and vehicle class for the latest period. Rows are inserted in single transaction
+    // Returns number of inserted rows
+    pub fn log_statistics(&mut self) -> Result<usize, Box<dyn Error>> {
+        let ds_guard = self
+            .data_storage
+            .read()
+            .expect("DataStorage is poisoned [RWLock]");
+        let equipment_id = ds_guard.id.clone();
+        let zones = ds_guard
+            .zones
+            .read()
+            .expect("Spatial data is poisoned [RWLock]");
+        let mut rows = vec![];
+        for (zone_id, zone_guarded) in zones.iter() {
+            let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            for (class, vehicle_type_parameters) in zone.statistics.vehicles_data.iter() {
+                rows.push(StatisticsRow {
+                    period_start: zone.statistics.period_start.to_rfc3339(),
+                    period_end: zone.statistics.period_end.to_rfc3339(),
+                    zone_id: zone_id.clone(),
+                    class: class.clone(),
+                    count: vehicle_type_parameters.sum_intensity,
+                    avg_speed: vehicle_type_parameters.avg_speed,
+                    avg_headway: zone.statistics.traffic_flow_parameters.avg_headway,
+                    occupancy: zone.statistics.traffic_flow_parameters.avg_occupancy,
+                });
+            }
+            drop(zone);
+        }
+        // Do not keep data storage locked while writing to the disk
+        drop(zones);
+        drop(ds_guard);
+
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO statistics (equipment_id, period_start, period_end, zone_id, class, count, avg_speed, avg_headway, occupancy) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            )?;
+            for row in rows.iter() {
+                stmt.execute(params![
+                    equipment_id,
+                    row.period_start,
+                    row.period_end,
+                    row.zone_id,
+                    row.class,
+                    row.count,
+                    row.avg_speed,
+                    row.avg_headway,
+                    row.occupancy,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(rows.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::data_storage::new_datastorage;
+    use crate::lib::zones::Zone;
+    use chrono::Utc;
+    use opencv::core::Point2f;
+    use uuid::Uuid;
+    #[test]
+    fn test_log_statistics() {
+        let path = std::env::temp_dir().join(format!("rust_road_traffic_sqlite_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let data_storage = new_datastorage("test_equipment".to_string(), false);
+        let mut zone = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        zone.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        zone.register_or_update_object(Uuid::new_v4(), 2.0, 2.0, 20.0, 1.0, None, "bus".to_string(), false, (50.0, 50.0));
+        zone.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        data_storage.read().unwrap().insert_zone(zone).unwrap();
+
+        let mut logger = SqliteLogger::new(path.to_str().unwrap(), data_storage.clone()).unwrap();
+        assert_eq!(logger.log_statistics().unwrap(), 2);
+        assert_eq!(logger.log_statistics().unwrap(), 2);
+        // Tables are not re-created on the next run
+        drop(logger);
+        let logger = SqliteLogger::new(path.to_str().unwrap(), data_storage).unwrap();
+        let rows: i64 = logger.conn.query_row("SELECT COUNT(*) FROM statistics WHERE class = 'car'", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 2);
+        drop(logger);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,234 @@
+// Protobuf messages for the statistics snapshot. Keep in sync with 'proto/statistics.proto'
+// Messages are derived by hand instead of code generation, so protoc is not needed for the build
+use std::collections::HashMap;
+use prost::Message;
+
+use crate::rest_api::zones_stats;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AllZonesStatsProto {
+    #[prost(string, tag = "1")]
+    pub equipment_id: String,
+    #[prost(message, repeated, tag = "2")]
+    pub data: Vec<ZoneStatsProto>,
+    // Empty when it is not configured
+    #[prost(string, tag = "3")]
+    pub location_name: String,
+    // [lon, lat] or empty when it is not configured
+    #[prost(float, repeated, tag = "4")]
+    pub location_wgs84: Vec<f32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ZoneStatsProto {
+    #[prost(uint32, tag = "1")]
+    pub lane_number: u32,
+    #[prost(uint32, tag = "2")]
+    pub lane_direction: u32,
+    // Unix time in milliseconds (UTC)
+    #[prost(int64, tag = "3")]
+    pub period_start: i64,
+    #[prost(int64, tag = "4")]
+    pub period_end: i64,
+    #[prost(map = "string, message", tag = "5")]
+    pub statistics: HashMap<String, VehicleTypeParametersProto>,
+    #[prost(message, optional, tag = "6")]
+    pub traffic_flow_parameters: Option<TrafficFlowInfoProto>,
+    #[prost(string, tag = "7")]
+    pub kind: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct VehicleTypeParametersProto {
+    #[prost(float, tag = "1")]
+    pub estimated_avg_speed: f32,
+    #[prost(uint32, tag = "2")]
+    pub estimated_sum_intensity: u32,
+    #[prost(uint32, tag = "3")]
+    pub estimated_defined_sum_intensity: u32,
+    #[prost(float, tag = "4")]
+    pub avg_speed_confidence: f32,
+    #[prost(uint32, tag = "5")]
+    pub over_speed_count: u32,
+    #[prost(uint32, tag = "6")]
+    pub present_intensity: u32,
+    #[prost(uint32, tag = "7")]
+    pub crossed_intensity: u32,
+    #[prost(float, tag = "8")]
+    pub avg_bbox_area: f32,
+    #[prost(float, tag = "9")]
+    pub avg_headway: f32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TrafficFlowInfoProto {
+    #[prost(float, tag = "1")]
+    pub avg_speed: f32,
+    #[prost(uint32, tag = "2")]
+    pub sum_intensity: u32,
+    #[prost(uint32, tag = "3")]
+    pub defined_sum_intensity: u32,
+    #[prost(float, tag = "4")]
+    pub avg_headway: f32,
+    #[prost(float, tag = "5")]
+    pub avg_density: f32,
+    #[prost(string, tag = "6")]
+    pub congestion_level: String,
+    #[prost(uint32, repeated, tag = "7")]
+    pub speed_histogram: Vec<u32>,
+    #[prost(float, tag = "8")]
+    pub speed_histogram_bin_width: f32,
+    #[prost(uint32, tag = "9")]
+    pub stationary_count: u32,
+    #[prost(uint32, tag = "10")]
+    pub harsh_braking_events: u32,
+    #[prost(uint32, tag = "11")]
+    pub hard_acceleration_events: u32,
+    #[prost(float, tag = "12")]
+    pub avg_occupancy: f32,
+    #[prost(float, tag = "13")]
+    pub speed_p50: f32,
+    #[prost(float, tag = "14")]
+    pub speed_p85: f32,
+    #[prost(float, tag = "15")]
+    pub speed_p95: f32,
+    #[prost(uint32, tag = "16")]
+    pub over_speed_count: u32,
+    #[prost(uint32, tag = "17")]
+    pub present_intensity: u32,
+    #[prost(uint32, tag = "18")]
+    pub crossed_intensity: u32,
+    #[prost(float, tag = "19")]
+    pub dominant_heading: f32,
+}
+
+impl From<&zones_stats::AllZonesStats> for AllZonesStatsProto {
+    fn from(stats: &zones_stats::AllZonesStats) -> Self {
+        AllZonesStatsProto {
+            equipment_id: stats.equipment_id.clone(),
+            location_name: stats.location_name.clone().unwrap_or_default(),
+            location_wgs84: stats.location_wgs84.map(|pt| pt.to_vec()).unwrap_or_default(),
+            data: stats.data.iter().map(|zone| ZoneStatsProto {
+                kind: zone.kind.clone(),
+                lane_number: zone.lane_number as u32,
+                lane_direction: zone.lane_direction as u32,
+                period_start: zone.period_start.timestamp_millis(),
+                period_end: zone.period_end.timestamp_millis(),
+                statistics: zone.statistics.iter().map(|(vehicle_type, params)| (vehicle_type.clone(), VehicleTypeParametersProto {
+                    estimated_avg_speed: params.estimated_avg_speed,
+                    estimated_sum_intensity: params.estimated_sum_intensity,
+                    estimated_defined_sum_intensity: params.estimated_defined_sum_intensity,
+                    avg_speed_confidence: params.avg_speed_confidence,
+                    over_speed_count: params.over_speed_count,
+                    present_intensity: params.present_intensity,
+                    crossed_intensity: params.crossed_intensity,
+                    avg_bbox_area: params.avg_bbox_area,
+                    avg_headway: params.avg_headway,
+                })).collect(),
+                traffic_flow_parameters: Some(TrafficFlowInfoProto {
+                    avg_speed: zone.traffic_flow_parameters.avg_speed,
+                    sum_intensity: zone.traffic_flow_parameters.sum_intensity,
+                    defined_sum_intensity: zone.traffic_flow_parameters.defined_sum_intensity,
+                    avg_headway: zone.traffic_flow_parameters.avg_headway,
+                    avg_density: zone.traffic_flow_parameters.avg_density,
+                    congestion_level: zone.traffic_flow_parameters.congestion_level.clone(),
+                    speed_histogram: zone.traffic_flow_parameters.speed_histogram.clone(),
+                    speed_histogram_bin_width: zone.traffic_flow_parameters.speed_histogram_bin_width,
+                    stationary_count: zone.traffic_flow_parameters.stationary_count,
+                    harsh_braking_events: zone.traffic_flow_parameters.harsh_braking_events,
+                    hard_acceleration_events: zone.traffic_flow_parameters.hard_acceleration_events,
+                    avg_occupancy: zone.traffic_flow_parameters.avg_occupancy,
+                    speed_p50: zone.traffic_flow_parameters.speed_p50,
+                    speed_p85: zone.traffic_flow_parameters.speed_p85,
+                    speed_p95: zone.traffic_flow_parameters.speed_p95,
+                    over_speed_count: zone.traffic_flow_parameters.over_speed_count,
+                    present_intensity: zone.traffic_flow_parameters.present_intensity,
+                    crossed_intensity: zone.traffic_flow_parameters.crossed_intensity,
+                    dominant_heading: zone.traffic_flow_parameters.dominant_heading,
+                }),
+            }).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use crate::lib::publisher::RedisMessage;
+    #[test]
+    fn test_protobuf_roundtrip() {
+        let mut statistics = HashMap::new();
+        statistics.insert("car".to_string(), zones_stats::VehicleTypeParameters {
+            estimated_avg_speed: 42.5,
+            estimated_sum_intensity: 10,
+            estimated_defined_sum_intensity: 8,
+            avg_speed_confidence: 0.75,
+            over_speed_count: 2,
+            present_intensity: 12,
+            crossed_intensity: 10,
+            avg_bbox_area: 5400.0,
+            avg_headway: 12.5,
+        });
+        let stats = zones_stats::AllZonesStats {
+            equipment_id: "1e23985f-1fa3-45d0-a365-2d8525a23ddd".to_string(),
+            location_name: Some("Main St. / 5th Ave.".to_string()),
+            location_wgs84: Some([-3.70588, 40.39306]),
+            data: vec![zones_stats::ZoneStats {
+                kind: "vehicle".to_string(),
+                lane_number: 2,
+                lane_direction: 1,
+                period_start: Utc.with_ymd_and_hms(2023, 1, 2, 15, 0, 0).unwrap(),
+                period_end: Utc.with_ymd_and_hms(2023, 1, 2, 15, 5, 0).unwrap(),
+                statistics: statistics,
+                traffic_flow_parameters: zones_stats::TrafficFlowInfo {
+                    avg_speed: 42.5,
+                    sum_intensity: 10,
+                    defined_sum_intensity: 8,
+                    avg_headway: 2.5,
+                    avg_density: 18.4,
+                    avg_occupancy: 1.7,
+                    congestion_level: "moderate".to_string(),
+                    speed_histogram: vec![0, 2, 6],
+                    speed_histogram_bin_width: 10.0,
+                    speed_p50: 40.0,
+                    speed_p85: 48.5,
+                    speed_p95: 55.0,
+                    stationary_count: 1,
+                    harsh_braking_events: 0,
+                    hard_acceleration_events: 1,
+                    over_speed_count: 2,
+                    present_intensity: 12,
+                    crossed_intensity: 10,
+                    dominant_heading: 172.5,
+                },
+            }],
+        };
+        let bytes = stats.prepare_protobuf().unwrap();
+        // Protobuf is expected to be much more compact than JSON
+        assert!(bytes.len() < stats.prepare_string().unwrap().len() / 2);
+        let decoded = AllZonesStatsProto::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.equipment_id, "1e23985f-1fa3-45d0-a365-2d8525a23ddd");
+        assert_eq!(decoded.location_name, "Main St. / 5th Ave.");
+        assert_eq!(decoded.location_wgs84, vec![-3.70588, 40.39306]);
+        assert_eq!(decoded.data.len(), 1);
+        let zone = &decoded.data[0];
+        assert_eq!(zone.lane_number, 2);
+        assert_eq!(zone.kind, "vehicle");
+        assert_eq!(zone.period_start, 1672671600000);
+        assert_eq!(zone.period_end - zone.period_start, 300000);
+        assert_eq!(zone.statistics["car"].estimated_defined_sum_intensity, 8);
+        assert_eq!(zone.statistics["car"].over_speed_count, 2);
+        assert_eq!(zone.statistics["car"].crossed_intensity, 10);
+        assert_eq!(zone.statistics["car"].avg_bbox_area, 5400.0);
+        assert_eq!(zone.statistics["car"].avg_headway, 12.5);
+        let flow = zone.traffic_flow_parameters.as_ref().unwrap();
+        assert_eq!(flow.congestion_level, "moderate");
+        assert_eq!(flow.speed_histogram, vec![0, 2, 6]);
+        assert_eq!(flow.hard_acceleration_events, 1);
+        assert_eq!(flow.speed_p85, 48.5);
+        assert_eq!(flow.over_speed_count, 2);
+        assert_eq!(flow.present_intensity, 12);
+        assert_eq!(flow.dominant_heading, 172.5);
+    }
+}
@@ -0,0 +1,4 @@
+mod schedule;
+mod warmup;
+
+pub use self::{schedule::*, warmup::*};
\ No newline at end of file
@@ -0,0 +1,103 @@
+use chrono::{Datelike, Local, NaiveTime, Utc, Weekday};
+
+use crate::lib::data_storage::HourlyTimezone;
+
+// Time window when statistics should be accumulated
+// Window could span midnight (e.g. 22:00 - 06:00): then it belongs to the day it starts at
+#[derive(Debug, Clone)]
+pub struct ScheduleWindow {
+    // Empty means every day
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ScheduleWindow {
+    // days - short or full english names of weekdays (e.g. "mon", "Tuesday")
+    // start, end - "HH:MM" or "HH:MM:SS"
+    pub fn new_from(days: &Vec<String>, start: &str, end: &str) -> Result<Self, String> {
+        let mut parsed_days = Vec::with_capacity(days.len());
+        for day in days.iter() {
+            match day.parse::<Weekday>() {
+                Ok(weekday) => parsed_days.push(weekday),
+                Err(_) => return Err(format!("unknown weekday '{}'", day)),
+            }
+        }
+        Ok(ScheduleWindow {
+            days: parsed_days,
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+    pub fn contains(&self, weekday: Weekday, time: NaiveTime) -> bool {
+        let day_matches = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        if self.start <= self.end {
+            return day_matches(weekday) && time >= self.start && time < self.end;
+        }
+        // Window spans midnight
+        (day_matches(weekday) && time >= self.start) || (day_matches(weekday.pred()) && time < self.end)
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .map_err(|err| format!("can't parse time '{}': {}", value, err))
+}
+
+// Set of time windows when statistics should be accumulated
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub windows: Vec<ScheduleWindow>,
+    pub timezone: HourlyTimezone,
+}
+
+impl Schedule {
+    pub fn is_active_at(&self, weekday: Weekday, time: NaiveTime) -> bool {
+        self.windows.iter().any(|window| window.contains(weekday, time))
+    }
+    pub fn is_active_now(&self) -> bool {
+        match self.timezone {
+            HourlyTimezone::Local => {
+                let now = Local::now();
+                self.is_active_at(now.weekday(), now.time())
+            },
+            HourlyTimezone::UTC => {
+                let now = Utc::now();
+                self.is_active_at(now.weekday(), now.time())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn hm(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+    #[test]
+    fn test_schedule() {
+        let weekdays = vec!["mon".to_string(), "tue".to_string(), "wed".to_string(), "thu".to_string(), "fri".to_string()];
+        let schedule = Schedule {
+            windows: vec![
+                ScheduleWindow::new_from(&weekdays, "07:00", "10:00").unwrap(),
+                ScheduleWindow::new_from(&vec!["Saturday".to_string()], "22:00", "02:30").unwrap(),
+            ],
+            timezone: HourlyTimezone::UTC,
+        };
+        assert!(schedule.is_active_at(Weekday::Mon, hm(7, 0)));
+        assert!(schedule.is_active_at(Weekday::Fri, hm(9, 59)));
+        assert!(!schedule.is_active_at(Weekday::Fri, hm(10, 0)));
+        assert!(!schedule.is_active_at(Weekday::Sun, hm(8, 0)));
+        // Overnight window belongs to the day it starts at
+        assert!(schedule.is_active_at(Weekday::Sat, hm(23, 0)));
+        assert!(schedule.is_active_at(Weekday::Sun, hm(1, 0)));
+        assert!(!schedule.is_active_at(Weekday::Sun, hm(23, 0)));
+        assert!(!schedule.is_active_at(Weekday::Sat, hm(1, 0)));
+
+        assert!(ScheduleWindow::new_from(&vec!["someday".to_string()], "07:00", "10:00").is_err());
+        assert!(ScheduleWindow::new_from(&vec![], "7am", "10:00").is_err());
+        assert_eq!(ScheduleWindow::new_from(&vec![], "07:00:30", "10:00").unwrap().start, NaiveTime::from_hms_opt(7, 0, 30).unwrap());
+    }
+}
@@ -0,0 +1,88 @@
+// Period after start when objects are tracked (and drawn), but not counted: tracker needs a few frames to stabilize
+// and spurious crossings occur meanwhile
+#[derive(Debug, Clone)]
+pub struct Warmup {
+    seconds: f32,
+    finished: bool,
+}
+
+impl Warmup {
+    // Zero (or negative) duration means that there is no warmup at all
+    pub fn new(seconds: f32) -> Self {
+        Warmup {
+            seconds: seconds,
+            finished: !(seconds > 0.0),
+        }
+    }
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    // elapsed - seconds since start of the video (see ThreadedFrame::overall_seconds)
+    // Returns true once: on the first call when warmup period has passed (strictly)
+    pub fn finish_if_elapsed(&mut self, elapsed: f32) -> bool {
+        if self.finished || !(elapsed > self.seconds) {
+            return false;
+        }
+        self.finished = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::Point2f;
+    use uuid::Uuid;
+    use crate::lib::tracker::SpatialInfo;
+    use crate::lib::zones::Zone;
+    #[test]
+    fn test_warmup() {
+        let mut warmup = Warmup::new(5.0);
+        assert!(!warmup.is_finished());
+        assert!(!warmup.finish_if_elapsed(0.0));
+        // Boundary itself belongs to the warmup
+        assert!(!warmup.finish_if_elapsed(5.0));
+        assert!(!warmup.is_finished());
+        assert!(warmup.finish_if_elapsed(6.0));
+        assert!(warmup.is_finished());
+        // Reported once only
+        assert!(!warmup.finish_if_elapsed(7.0));
+        assert!(warmup.is_finished());
+
+        let mut no_warmup = Warmup::new(0.0);
+        assert!(no_warmup.is_finished());
+        assert!(!no_warmup.finish_if_elapsed(0.0));
+    }
+    #[test]
+    fn test_warmup_gates_registration_only() {
+        let mut warmup = Warmup::new(2.0);
+        let mut zone = Zone::default_from_cv(vec![Point2f::new(0.0, 0.0), Point2f::new(100.0, 0.0), Point2f::new(100.0, 100.0), Point2f::new(0.0, 100.0)]);
+        let object_id = Uuid::new_v4();
+        let mut spatial_info: Option<SpatialInfo> = None;
+        let mut counted = vec![];
+        // Object moves 10 pixels per second, 1 pixel per meter: 36 km/h
+        for step in 0..5 {
+            let time = step as f32;
+            let x = 10.0 * time;
+            warmup.finish_if_elapsed(time);
+            // Mimics the detection loop: speed is estimated during warmup, registration is gated
+            let speed = match spatial_info.as_mut() {
+                Some(info) => {
+                    info.update_avg(time, x, 50.0, x, 50.0, 1.0);
+                    info.speed
+                },
+                None => {
+                    spatial_info = Some(SpatialInfo::new(time, x, 50.0, x, 50.0));
+                    -1.0
+                }
+            };
+            if warmup.is_finished() && zone.register_or_update_object(object_id, time, time, speed, 1.0, None, "car".to_string(), false, (x, 50.0)) {
+                counted.push((time, speed));
+            }
+        }
+        // Object is counted once right after warmup and it gets the speed estimated along the whole track
+        assert_eq!(counted.len(), 1);
+        assert_eq!(counted[0].0, 3.0);
+        assert!((counted[0].1 - 36.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Coordinate reference system of the spatial coordinates provided for zones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpatialCRS {
+    // Longitude/latitude. Coordinates are projected into EPSG:3857 before usage
+    WGS84,
+    // Local metric coordinates (e.g. survey data). Coordinates are used as-is
+    LocalMeters,
+}
+
+impl fmt::Display for SpatialCRS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpatialCRS::WGS84 => write!(f, "wgs84"),
+            SpatialCRS::LocalMeters => write!(f, "local_meters"),
+        }
+    }
+}
+
+impl Default for SpatialCRS {
+    fn default() -> Self {
+        SpatialCRS::WGS84
+    }
+}
+
+impl FromStr for SpatialCRS {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wgs84" => Ok(SpatialCRS::WGS84),
+            "local_meters" => Ok(SpatialCRS::LocalMeters),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Headings are measured in degrees clockwise in range [0; 360):
+// - in pixel space 0 means "up" (towards the top of the frame), 90 means "right";
+// - in spatial space (EPSG:3857 or local meters) it is geographic bearing: 0 means north, 90 means east.
+
+// Wraps angle into [0; 360)
+pub fn normalize_degrees(degrees: f32) -> f32 {
+    let wrapped = degrees % 360.0;
+    if wrapped < 0.0 {
+        wrapped + 360.0
+    } else {
+        wrapped
+    }
+}
+
+// Heading of the movement (dx, dy) in pixels. Y axis of the image points down. None is returned when there is no movement
+pub fn pixel_heading(dx: f32, dy: f32) -> Option<f32> {
+    if !(dx.is_finite() && dy.is_finite()) || (dx == 0.0 && dy == 0.0) {
+        return None;
+    }
+    Some(normalize_degrees(dx.atan2(-dy).to_degrees()))
+}
+
+// Bearing of the movement (dx, dy) in metric CRS where easting grows along X and northing grows along Y
+pub fn geographic_bearing(dx: f32, dy: f32) -> Option<f32> {
+    pixel_heading(dx, -dy)
+}
+
+// Mean of the angles taking into account wrap-around (e.g. mean of 350 and 10 is 0, not 180)
+// None is returned when there are no angles or they cancel each other out
+pub fn circular_mean_degrees(angles: &[f32]) -> Option<f32> {
+    if angles.is_empty() {
+        return None;
+    }
+    let (sum_sin, sum_cos) = angles.iter().fold((0.0_f32, 0.0_f32), |(sum_sin, sum_cos), angle| {
+        let radians = angle.to_radians();
+        (sum_sin + radians.sin(), sum_cos + radians.cos())
+    });
+    let n = angles.len() as f32;
+    if (sum_sin / n).hypot(sum_cos / n) < 1e-3 {
+        return None;
+    }
+    Some(normalize_degrees(sum_sin.atan2(sum_cos).to_degrees()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_pixel_heading() {
+        assert_eq!(pixel_heading(0.0, 0.0), None);
+        assert!((pixel_heading(0.0, -5.0).unwrap() - 0.0).abs() < 0.001);
+        assert!((pixel_heading(5.0, 0.0).unwrap() - 90.0).abs() < 0.001);
+        assert!((pixel_heading(0.0, 5.0).unwrap() - 180.0).abs() < 0.001);
+        assert!((pixel_heading(-5.0, 0.0).unwrap() - 270.0).abs() < 0.001);
+        assert!((geographic_bearing(0.0, 5.0).unwrap() - 0.0).abs() < 0.001);
+        assert!((geographic_bearing(3.0, -3.0).unwrap() - 135.0).abs() < 0.001);
+    }
+    #[test]
+    fn test_circular_mean_degrees() {
+        assert_eq!(circular_mean_degrees(&[]), None);
+        assert_eq!(circular_mean_degrees(&[90.0, 270.0]), None);
+        let mean = circular_mean_degrees(&[350.0, 10.0, 0.0]).unwrap();
+        assert!(mean < 0.001 || mean > 359.999);
+        assert!((circular_mean_degrees(&[80.0, 100.0]).unwrap() - 90.0).abs() < 0.001);
+        assert!((normalize_degrees(-30.0) - 330.0).abs() < 0.001);
+        assert!((normalize_degrees(725.0) - 5.0).abs() < 0.001);
+    }
+}
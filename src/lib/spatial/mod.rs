@@ -2,5 +2,8 @@ mod spatial;
 pub mod point;
 pub mod epsg;
 pub mod haversine;
+pub mod crs;
+pub mod heading;
+pub mod precision;
 
-pub use self::{spatial::*, point::*, epsg::*, haversine::*};
\ No newline at end of file
+pub use self::{spatial::*, point::*, epsg::*, haversine::*, crs::*, heading::*, precision::*};
\ No newline at end of file
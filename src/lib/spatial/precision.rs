@@ -0,0 +1,31 @@
+// Number of decimal places of the exported coordinates by default. 7 decimal places of degree is about 1 cm
+pub const DEFAULT_COORDINATE_PRECISION: u32 = 7;
+// f32 keeps about 7 significant digits, so more decimal places do not make sense
+const MAX_COORDINATE_PRECISION: u32 = 9;
+
+// Rounds coordinate (degrees or meters) to the given number of decimal places, so serialized value has no false precision
+pub fn round_coordinate(value: f32, precision: u32) -> f32 {
+    if !value.is_finite() {
+        return value;
+    }
+    let scale = 10_f64.powi(precision.min(MAX_COORDINATE_PRECISION) as i32);
+    ((value as f64 * scale).round() / scale) as f32
+}
+
+pub fn round_point(pt: [f32; 2], precision: u32) -> [f32; 2] {
+    [round_coordinate(pt[0], precision), round_coordinate(pt[1], precision)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_round_coordinate() {
+        assert_eq!(round_point([37.61734567, 55.75197612], 4), [37.6173, 55.752]);
+        assert_eq!(round_coordinate(-122.4194155, 0), -122.0);
+        assert_eq!(round_coordinate(-1.0, DEFAULT_COORDINATE_PRECISION), -1.0);
+        assert!(round_coordinate(f32::NAN, 3).is_nan());
+        // Serialized value has no more decimal places than requested
+        assert_eq!(serde_json::to_string(&round_point([37.61734567, 55.75197612], 5)).unwrap(), "[37.61734,55.75198]");
+    }
+}
@@ -5,6 +5,7 @@ use opencv::{
     core::Vector,
     core::DECOMP_LU,
     core::mul_mat_mat,
+    core::invert,
     core::CV_32F,
     imgproc::get_perspective_transform
 };
@@ -79,6 +80,31 @@ impl SpatialConverter {
         let yattr = answ_ptr[1];
         return Point2f::new(xattr / scale, yattr / scale);
     }
+    // Returns coefficients of perspective transform matrix (pixels -> EPSG:3857)
+    // None is returned when converter has not been calibrated
+    pub fn get_matrix(&self) -> Option<[[f32; 3]; 3]> {
+        mat_to_array(&self.transform_mat)
+    }
+    // Returns coefficients of inverse perspective transform matrix (EPSG:3857 -> pixels)
+    // None is returned when converter has not been calibrated or matrix is singular
+    pub fn get_inverse_matrix(&self) -> Option<[[f32; 3]; 3]> {
+        if self.transform_mat.empty() {
+            return None;
+        }
+        let mut inverse_mat = Mat::default();
+        match invert(&self.transform_mat, &mut inverse_mat, DECOMP_LU) {
+            Ok(det) => {
+                // OpenCV returns zero when matrix is singular
+                if det == 0.0 {
+                    return None;
+                }
+            },
+            Err(_) => {
+                return None;
+            }
+        };
+        mat_to_array(&inverse_mat)
+    }
     pub fn transform_to_epsg(&self, src_x: f32, src_y: f32) -> (f32, f32) {
         let pmat_data = vec![
             vec![src_x],
@@ -95,6 +121,19 @@ impl SpatialConverter {
     }
 }
 
+fn mat_to_array(mat: &Mat) -> Option<[[f32; 3]; 3]> {
+    if mat.empty() || mat.rows() != 3 || mat.cols() != 3 {
+        return None;
+    }
+    let mut coefficients = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            coefficients[i][j] = *mat.at_2d::<f32>(i as i32, j as i32).ok()?;
+        }
+    }
+    Some(coefficients)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,6 +199,23 @@ mod tests {
         assert!((distance - coorrect_dist).abs() < eps);
     }
     #[test]
+    fn test_homography_matrix() {
+        let converter = SpatialConverter::default();
+        assert!(converter.get_matrix().is_none());
+        assert!(converter.get_inverse_matrix().is_none());
+
+        let src = vec![Point2f::new(0.0, 0.0), Point2f::new(10.0, 0.0), Point2f::new(10.0, 10.0), Point2f::new(0.0, 10.0)];
+        let dst = vec![Point2f::new(0.0, 0.0), Point2f::new(20.0, 0.0), Point2f::new(20.0, 20.0), Point2f::new(0.0, 20.0)];
+        let converter = SpatialConverter::new_from(src, dst);
+        let forward = converter.get_matrix().unwrap();
+        let inverse = converter.get_inverse_matrix().unwrap();
+        let eps = 0.0001;
+        assert!((forward[0][0] - 2.0).abs() < eps);
+        assert!((forward[1][1] - 2.0).abs() < eps);
+        assert!((inverse[0][0] - 0.5).abs() < eps);
+        assert!((inverse[1][1] - 0.5).abs() < eps);
+    }
+    #[test]
     fn test_skeleton() {
         let mut src: Vector<opencv::core::Point_<f32>> = Vector::<Point2f>::new();
         src.push(Point2f::new(51.0, 266.0));
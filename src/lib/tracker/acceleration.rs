@@ -0,0 +1,87 @@
+// Acceleration (m/s^2) below this value is considered as harsh braking
+pub const HARSH_BRAKING_THRESHOLD: f32 = -3.5;
+// Acceleration (m/s^2) above this value is considered as hard acceleration
+pub const HARD_ACCELERATION_THRESHOLD: f32 = 3.0;
+
+// Exponential smoothing factor in (0; 1] for speed and acceleration samples
+// Lower values give smoother estimates, but with bigger lag
+const ACCELERATION_SMOOTHING: f32 = 0.3;
+
+// Estimates acceleration from the series of speed samples
+// Keep in mind: frame-to-frame displacement is just a few pixels, so detection jitter
+// makes raw speed derivative very noisy. Both speed and acceleration are smoothed therefore,
+// but the estimate is still rough for the far-away objects (low pixels per meter)
+#[derive(Debug, Clone)]
+pub struct AccelerationEstimator {
+    // Smoothed speed (m/s)
+    speed: Option<f32>,
+    time: f32,
+    acceleration: Option<f32>,
+}
+
+impl AccelerationEstimator {
+    pub fn new() -> Self {
+        AccelerationEstimator {
+            speed: None,
+            time: 0.0,
+            acceleration: None,
+        }
+    }
+    // time - seconds, speed - m/s
+    // Returns smoothed acceleration (m/s^2). None for the first sample
+    pub fn update(&mut self, time: f32, speed: f32) -> Option<f32> {
+        let prev_speed = match self.speed {
+            Some(prev_speed) => prev_speed,
+            None => {
+                self.speed = Some(speed);
+                self.time = time;
+                return None;
+            }
+        };
+        let time_diff = time - self.time;
+        if time_diff <= 0.0 || !speed.is_finite() {
+            return self.acceleration;
+        }
+        let smoothed_speed = prev_speed + ACCELERATION_SMOOTHING * (speed - prev_speed);
+        let raw_acceleration = (smoothed_speed - prev_speed) / time_diff;
+        self.acceleration = Some(match self.acceleration {
+            Some(prev_acceleration) => prev_acceleration + ACCELERATION_SMOOTHING * (raw_acceleration - prev_acceleration),
+            None => raw_acceleration,
+        });
+        self.speed = Some(smoothed_speed);
+        self.time = time;
+        self.acceleration
+    }
+    pub fn get(&self) -> Option<f32> {
+        self.acceleration
+    }
+    // Smoothed speed (m/s)
+    pub fn get_speed(&self) -> Option<f32> {
+        self.speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_acceleration_estimator() {
+        let mut estimator = AccelerationEstimator::new();
+        assert_eq!(estimator.update(0.0, 10.0), None);
+        // Constant speed
+        for i in 1..10 {
+            estimator.update(i as f32 * 0.1, 10.0);
+        }
+        assert!(estimator.get().unwrap().abs() < 1e-6);
+        // Braking with -5 m/s^2 should be detected eventually
+        let mut speed = 10.0;
+        for i in 10..29 {
+            speed -= 0.5;
+            estimator.update(i as f32 * 0.1, speed);
+        }
+        assert!(estimator.get().unwrap() < HARSH_BRAKING_THRESHOLD);
+        // Same timestamp should not break the estimate
+        let before = estimator.get();
+        assert_eq!(estimator.update(2.8, 0.0), before);
+    }
+}
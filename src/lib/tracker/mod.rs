@@ -1,3 +1,8 @@
 mod tracker;
+mod speed_confidence;
+mod acceleration;
+mod snapshot;
+mod time_base;
+mod speed_method;
 
-pub use self::{tracker::*};
\ No newline at end of file
+pub use self::{tracker::*, speed_confidence::*, acceleration::*, snapshot::*, time_base::*, speed_method::*};
\ No newline at end of file
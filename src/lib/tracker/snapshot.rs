@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::lib::tracker::Tracker;
+use crate::lib::zones::{Zone, ZoneGrid, find_zone_for_point};
+
+// Read-only copy of the tracked object's state. Tracker itself lives in the detection thread only,
+// so this snapshot is the way to share objects with other threads (e.g. REST API)
+#[derive(Debug, Clone)]
+pub struct TrackedObjectSnapshot {
+    pub id: String,
+    pub short_id: u64,
+    pub class_name: String,
+    // [x, y, width, height]
+    pub bbox: [f32; 4],
+    pub centroid: [f32; 2],
+    // Zone which contains the centroid (see find_zone_for_point)
+    pub zone_id: Option<String>,
+    // Lon/lat (or local meters, depending on zone's CRS). None when there is no zone or zone has no spatial calibration
+    pub world_position: Option<[f32; 2]>,
+    // Value "-1" means speed has not been estimated yet
+    pub speed: f32,
+    // Degrees, see Zone::heading_between()
+    pub heading: Option<f32>,
+    // Relative timestamps (seconds) of the first and the latest detections
+    pub first_seen: f32,
+    pub last_seen: f32,
+}
+
+// Collects currently tracked objects. Objects which are lost for a while are skipped
+// Zones should not be locked by the caller
+pub fn snapshot_tracked_objects(tracker: &Tracker, zones: &HashMap<String, Mutex<Zone>>, zone_grid: &ZoneGrid) -> Vec<TrackedObjectSnapshot> {
+    let mut objects = Vec::with_capacity(tracker.objects_extra.len());
+    for (object_id, object_extra) in tracker.objects_extra.iter() {
+        let object = match tracker.engine.objects.get(object_id) {
+            Some(object) => object,
+            None => continue,
+        };
+        if object.get_no_match_times() > 1 {
+            continue;
+        }
+        let bbox = object.get_bbox();
+        let center = object.get_center();
+        let zone_id = find_zone_for_point(zones, zone_grid.get_candidate_zones(center.x, center.y), center.x, center.y);
+        let world_position = zone_id.as_ref().and_then(|zone_id| zones.get(zone_id)).and_then(|zone_guarded| {
+            let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.pixel_to_world(center.x, center.y)
+        });
+        objects.push(TrackedObjectSnapshot {
+            id: object_id.to_string(),
+            short_id: object_extra.get_short_id(),
+            class_name: object_extra.get_classname(),
+            bbox: [bbox.x, bbox.y, bbox.width, bbox.height],
+            centroid: [center.x, center.y],
+            zone_id: zone_id,
+            world_position: world_position,
+            speed: match object_extra.spatial_info {
+                Some(ref spatial_info) => spatial_info.speed,
+                None => -1.0,
+            },
+            heading: object_extra.spatial_info.as_ref().and_then(|spatial_info| spatial_info.heading),
+            first_seen: object_extra.get_first_seen(),
+            last_seen: object_extra.get_last_seen(),
+        });
+    }
+    objects
+}
@@ -0,0 +1,35 @@
+// Pixels per meter at which single pixel error is small enough (10 cm)
+const GOOD_PIXELS_PER_METER: f32 = 10.0;
+// Distance traveled (in meters) which is enough to smooth out detection jitter
+const GOOD_TRACK_LENGTH_METERS: f32 = 10.0;
+
+// Estimates confidence of the speed in [0; 1]
+// Speed accuracy degrades with distance from camera (low pixels per meter) and with short tracks
+//
+// pixels_per_meter - resolution of the zone (or of the measurement base)
+// track_length_meters - distance which has been used for speed estimation
+//
+pub fn speed_confidence(pixels_per_meter: f32, track_length_meters: f32) -> f32 {
+    if !pixels_per_meter.is_finite() || !track_length_meters.is_finite() || pixels_per_meter <= 0.0 || track_length_meters <= 0.0 {
+        return 0.0;
+    }
+    let resolution_score = (pixels_per_meter / GOOD_PIXELS_PER_METER).min(1.0);
+    let track_score = (track_length_meters / GOOD_TRACK_LENGTH_METERS).min(1.0);
+    resolution_score * track_score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_speed_confidence() {
+        assert_eq!(speed_confidence(20.0, 15.0), 1.0);
+        assert!((speed_confidence(5.0, 15.0) - 0.5).abs() < 0.001);
+        assert!((speed_confidence(20.0, 2.5) - 0.25).abs() < 0.001);
+        assert!((speed_confidence(5.0, 5.0) - 0.25).abs() < 0.001);
+        // Undefined calibration
+        assert_eq!(speed_confidence(-1.0, 15.0), 0.0);
+        assert_eq!(speed_confidence(f32::INFINITY, 15.0), 0.0);
+        assert_eq!(speed_confidence(20.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedMethod {
+    // Points are projected onto the zone's skeleton and distance is measured along it via pixels per meter
+    // Robust to jitter across the lane, but under-estimates distance for movement which is not parallel to the skeleton
+    Skeleton,
+    // Distance between consecutive points is measured on the ground plane via spatial converter (homography) and accumulated
+    // Follows turns and lane changes, but accumulates centroid jitter too (consider 'speed_from_kalman')
+    GroundPlane,
+}
+
+impl fmt::Display for SpeedMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpeedMethod::Skeleton => write!(f, "skeleton"),
+            SpeedMethod::GroundPlane => write!(f, "ground_plane"),
+        }
+    }
+}
+
+impl Default for SpeedMethod {
+    fn default() -> Self {
+        SpeedMethod::Skeleton
+    }
+}
+
+impl FromStr for SpeedMethod {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skeleton" => Ok(SpeedMethod::Skeleton),
+            "ground_plane" => Ok(SpeedMethod::GroundPlane),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeBase {
+    // Time is derived from the number of captured frames and FPS of the source
+    // Exact for the video files, but drifts from real time when detection thread lags behind capture
+    Frame,
+    // Time is wall-clock time elapsed since the first processed frame
+    // Keeps speed consistent when frames are skipped, but includes processing jitter (and makes video files replay in real time only)
+    ProcessingWallclock,
+}
+
+impl fmt::Display for TimeBase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeBase::Frame => write!(f, "frame"),
+            TimeBase::ProcessingWallclock => write!(f, "processing_wallclock"),
+        }
+    }
+}
+
+impl Default for TimeBase {
+    fn default() -> Self {
+        TimeBase::Frame
+    }
+}
+
+impl FromStr for TimeBase {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "frame" => Ok(TimeBase::Frame),
+            "processing_wallclock" => Ok(TimeBase::ProcessingWallclock),
+            _ => Err(()),
+        }
+    }
+}
+
+// Provides relative time (seconds) of the processed frame for the detection thread
+pub struct DetectionClock {
+    time_base: TimeBase,
+    started_at: Option<Instant>,
+}
+
+impl DetectionClock {
+    pub fn new(time_base: TimeBase) -> Self {
+        DetectionClock {
+            time_base: time_base,
+            started_at: None,
+        }
+    }
+    // frame_time - time given by capture thread (see TimeBase::Frame)
+    // now - moment when frame is being processed. The first call defines zero time for the wall-clock time base
+    pub fn relative_time(&mut self, frame_time: f32, now: Instant) -> f32 {
+        match self.time_base {
+            TimeBase::Frame => frame_time,
+            TimeBase::ProcessingWallclock => {
+                let started_at = *self.started_at.get_or_insert(now);
+                now.saturating_duration_since(started_at).as_secs_f32()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    #[test]
+    fn test_time_base_parsing() {
+        assert_eq!(TimeBase::from_str("frame"), Ok(TimeBase::Frame));
+        assert_eq!(TimeBase::from_str("Processing_Wallclock"), Ok(TimeBase::ProcessingWallclock));
+        assert!(TimeBase::from_str("wallclock").is_err());
+        assert_eq!(TimeBase::ProcessingWallclock.to_string(), "processing_wallclock");
+    }
+    #[test]
+    fn test_detection_clock() {
+        let start = Instant::now();
+        let mut frame_clock = DetectionClock::new(TimeBase::Frame);
+        assert_eq!(frame_clock.relative_time(3.0, start), 3.0);
+        assert_eq!(frame_clock.relative_time(4.0, start + Duration::from_secs(10)), 4.0);
+
+        let mut wallclock = DetectionClock::new(TimeBase::ProcessingWallclock);
+        assert_eq!(wallclock.relative_time(3.0, start), 0.0);
+        // Frame time is ignored: only processing moments matter (e.g. detection thread lagged and frames have been skipped)
+        assert_eq!(wallclock.relative_time(3.5, start + Duration::from_millis(1500)), 1.5);
+        assert_eq!(wallclock.relative_time(4.0, start + Duration::from_millis(4250)), 4.25);
+        // Time never goes backwards
+        assert_eq!(wallclock.relative_time(5.0, start), 0.0);
+    }
+}
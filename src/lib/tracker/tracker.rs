@@ -1,35 +1,112 @@
 use std::error::Error;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::collections::hash_map::Entry::{
     Occupied,
     Vacant
 };
 use uuid::Uuid;
 use mot_rs::mot::{
-    IoUTracker
+    IoUTracker,
+    SimpleBlob
 };
 
 use crate::lib::detection::Detections;
 use crate::lib::spatial::haversine;
+use crate::lib::tracker::speed_confidence;
+use crate::lib::tracker::AccelerationEstimator;
+use crate::lib::incidents::StopDwell;
+use crate::lib::lane_changes::LaneState;
+
+// Number of the latest bounding box sizes kept per object for size estimation
+pub const MAX_BBOX_HISTORY: usize = 30;
 
 pub struct Tracker {
     pub engine: IoUTracker,
     pub objects_extra: HashMap<Uuid, ObjectExtra>,
+    // Next short identifier to be assigned to the new object
+    next_short_id: u64,
+    // Maximum number of objects to keep (see evict_excess_objects). Zero means no limit
+    max_objects: usize,
+    // Store centroids of the raw detections next to the smoothed track
+    keep_raw_centroids: bool,
 }
 
 pub struct ObjectExtra {
+    // Human-readable identifier: it is increasing monotonically for every new object
+    // UUID is still the main key for the object
+    short_id: u64,
     class_name: String,
     confidence: f32,
-    // Timestamps along the whole track
-    pub times: Vec<f32>,
+    // Timestamp of the first detection
+    first_seen: f32,
+    // Timestamp of the previous detection. It matches the penultimate point of the track
+    previous_seen: Option<f32>,
+    // Timestamp of the latest detection. It matches the last point of the track
+    // Only these three are kept: nothing needs the whole timestamps history, so memory does not grow with track length
+    last_seen: f32,
     pub estimated_velocity: f32,
     pub spatial_info: Option<SpatialInfo>,
+    // Centroid of the latest raw detection. Track of the engine holds Kalman-corrected centroids
+    raw_centroid: (f32, f32),
+    // How long object has been moving slowly (for stopped vehicle incidents)
+    pub stop_dwell: StopDwell,
+    // The zone where object has settled (see find_zone_for_point and follow_lane). It is kept while object is outside of any zone
+    pub lane_state: LaneState,
+    // Zone and time (seconds) of the last virtual line crossing. It is the origin for the next crossing (see resolve_origin)
+    pub last_crossing: Option<(String, f32)>,
+    // (width, height) in pixels of the latest MAX_BBOX_HISTORY bounding boxes
+    bbox_sizes: VecDeque<(f32, f32)>,
+    // Centroids of the raw detections (track of the engine is smoothed). Kept only when enabled (see Tracker::set_keep_raw_centroids)
+    raw_centroids: Option<VecDeque<(f32, f32)>>,
 }
 
 impl ObjectExtra {
     pub fn get_classname(&self) -> String {
         self.class_name.clone()
     }
+    pub fn get_short_id(&self) -> u64 {
+        self.short_id
+    }
+    pub fn get_raw_centroid(&self) -> (f32, f32) {
+        self.raw_centroid
+    }
+    pub fn get_first_seen(&self) -> f32 {
+        self.first_seen
+    }
+    pub fn get_previous_seen(&self) -> Option<f32> {
+        self.previous_seen
+    }
+    pub fn get_last_seen(&self) -> f32 {
+        self.last_seen
+    }
+    pub fn push_bbox_size(&mut self, width: f32, height: f32) {
+        if self.bbox_sizes.len() >= MAX_BBOX_HISTORY {
+            self.bbox_sizes.pop_front();
+        }
+        self.bbox_sizes.push_back((width, height));
+    }
+    pub fn get_bbox_sizes(&self) -> &VecDeque<(f32, f32)> {
+        &self.bbox_sizes
+    }
+    // The same length as the track at most. Oldest centroids are dropped first
+    fn push_raw_centroid(&mut self, x: f32, y: f32, max_len: usize) {
+        if let Some(raw_centroids) = self.raw_centroids.as_mut() {
+            if raw_centroids.len() >= max_len.max(1) {
+                raw_centroids.pop_front();
+            }
+            raw_centroids.push_back((x, y));
+        }
+    }
+    pub fn get_raw_centroids(&self) -> Option<&VecDeque<(f32, f32)>> {
+        self.raw_centroids.as_ref()
+    }
+    // Average area (square pixels) of the latest bounding boxes. Averaging smooths out partial occlusions and detection jitter
+    pub fn get_avg_bbox_area(&self) -> Option<f32> {
+        if self.bbox_sizes.is_empty() {
+            return None;
+        }
+        Some(self.bbox_sizes.iter().map(|(width, height)| width * height).sum::<f32>() / self.bbox_sizes.len() as f32)
+    }
 }
 
 pub struct SpatialInfo {
@@ -45,9 +122,20 @@ pub struct SpatialInfo {
     pub last_y_projected: f32,
     pub distance_traveled: f32,
     pub speed: f32,
+    // Confidence of the speed in [0; 1]. Depends on pixels per meter and on track length used for estimation
+    pub speed_confidence: f32,
+    // Smoothed acceleration (m/s^2) estimated from consecutive positions. None until there are enough samples
+    pub acceleration: Option<f32>,
+    acceleration_estimator: AccelerationEstimator,
+    // The latest heading (degrees) of the object, see Zone::heading_between(). None until object moves inside of a zone
+    pub heading: Option<f32>,
 }
 
 impl SpatialInfo {
+    // Smoothed instantaneous speed (km/h). Unlike 'speed' it reacts to stops quickly since it is not averaged along the whole track
+    pub fn get_smoothed_speed(&self) -> Option<f32> {
+        self.acceleration_estimator.get_speed().map(|speed| speed * 3.6)
+    }
     pub fn new(_time: f32,  _x: f32, _y: f32, _x_projected: f32, _y_projected: f32) -> Self {
         Self {
             first_time: _time,
@@ -62,6 +150,10 @@ impl SpatialInfo {
             last_y_projected: _y_projected,
             distance_traveled: -1.0,
             speed: -1.0,
+            speed_confidence: 0.0,
+            acceleration: None,
+            acceleration_estimator: AccelerationEstimator::new(),
+            heading: None,
         }
     }
     pub fn new_wgs84(_time: f32, _lon: f32, _lat: f32, _x: f32, _y: f32) -> Self {
@@ -78,17 +170,32 @@ impl SpatialInfo {
             last_y_projected: -1.0,
             distance_traveled: -1.0,
             speed: -1.0,
+            speed_confidence: 0.0,
+            acceleration: None,
+            acceleration_estimator: AccelerationEstimator::new(),
+            heading: None,
         }
     }
     // Same as update(), but calculations are done between first and last points
     // This approach helps to avoid situation when distance between two points is approx. 0
     pub fn update_avg(&mut self, _time: f32, _x: f32, _y: f32, _x_projected: f32, _y_projected: f32, pixels_per_meter: f32) {
+        if !(pixels_per_meter > 0.0) {
+            self.mark_uncalibrated(_time, _x, _y, _x_projected, _y_projected);
+            return;
+        }
         // It is possible to calculate speed between two points (old and new)
         let distance_pixels = ((_x_projected - self.first_x_projected).powi(2) + (_y_projected - self.first_y_projected).powi(2)).sqrt();
         let distance_meters = distance_pixels / pixels_per_meter;
         let time_diff = (_time - self.first_time).abs();
         let velocity = distance_meters / time_diff; // meters per second
         self.speed = velocity * 3.6; // convert m/s to km/h
+        self.speed_confidence = speed_confidence(pixels_per_meter, distance_meters);
+        // Average speed is not suitable for acceleration, so use the instantaneous one
+        let step_pixels = ((_x_projected - self.last_x_projected).powi(2) + (_y_projected - self.last_y_projected).powi(2)).sqrt();
+        let step_time = _time - self.last_time;
+        if step_time > 0.0 {
+            self.acceleration = self.acceleration_estimator.update(_time, step_pixels / pixels_per_meter / step_time);
+        }
         self.last_time = _time;
         self.last_x = _x;
         self.last_y = _y;
@@ -96,12 +203,20 @@ impl SpatialInfo {
         self.last_y_projected = _y_projected;
     }
     pub fn update(&mut self, _time: f32, _x: f32, _y: f32, _x_projected: f32, _y_projected: f32, pixels_per_meter: f32) {
+        if !(pixels_per_meter > 0.0) {
+            self.mark_uncalibrated(_time, _x, _y, _x_projected, _y_projected);
+            return;
+        }
         // It is possible to calculate speed between two points (old and new)
         let distance_pixels = ((_x_projected - self.last_x_projected).powi(2) + (_y_projected - self.last_y_projected).powi(2)).sqrt();
         let distance_meters = distance_pixels / pixels_per_meter;
         let time_diff = _time - self.last_time;
         let velocity = distance_meters / time_diff; // meters per second
         self.speed = velocity * 3.6; // convert m/s to km/h
+        self.speed_confidence = speed_confidence(pixels_per_meter, distance_meters);
+        if time_diff > 0.0 {
+            self.acceleration = self.acceleration_estimator.update(_time, velocity);
+        }
 
         self.last_time = _time;
         self.last_x = _x;
@@ -109,6 +224,44 @@ impl SpatialInfo {
         self.last_x_projected = _x_projected;
         self.last_y_projected = _y_projected;
     }
+    // Same as update_avg(), but traveled distance is accumulated from ground plane distances between consecutive points (see SpeedMethod::GroundPlane)
+    //
+    // step_meters - ground plane distance from the last point to the current one. None when zone has no spatial calibration
+    // pixels_per_meter - resolution of the zone. It is used for confidence only
+    //
+    pub fn update_ground_plane(&mut self, _time: f32, _x: f32, _y: f32, _x_projected: f32, _y_projected: f32, step_meters: Option<f32>, pixels_per_meter: f32) {
+        let step_meters = match step_meters {
+            Some(step_meters) if step_meters.is_finite() => step_meters,
+            _ => {
+                self.mark_uncalibrated(_time, _x, _y, _x_projected, _y_projected);
+                return;
+            }
+        };
+        self.distance_traveled = self.distance_traveled.max(0.0) + step_meters;
+        let time_diff = (_time - self.first_time).abs();
+        let velocity = self.distance_traveled / time_diff; // meters per second
+        self.speed = velocity * 3.6; // convert m/s to km/h
+        self.speed_confidence = speed_confidence(pixels_per_meter, self.distance_traveled);
+        let step_time = _time - self.last_time;
+        if step_time > 0.0 {
+            self.acceleration = self.acceleration_estimator.update(_time, step_meters / step_time);
+        }
+        self.last_time = _time;
+        self.last_x = _x;
+        self.last_y = _y;
+        self.last_x_projected = _x_projected;
+        self.last_y_projected = _y_projected;
+    }
+    // Zone has no spatial calibration (pixels per meter is undefined): position is tracked, but speed is undefined
+    fn mark_uncalibrated(&mut self, _time: f32, _x: f32, _y: f32, _x_projected: f32, _y_projected: f32) {
+        self.speed = -1.0;
+        self.speed_confidence = 0.0;
+        self.last_time = _time;
+        self.last_x = _x;
+        self.last_y = _y;
+        self.last_x_projected = _x_projected;
+        self.last_y_projected = _y_projected;
+    }
     fn update_by_wgs84(&mut self, _time: f32, _lon: f32, _lat: f32, _x: f32, _y: f32) {
         // It is possible to calculate speed between two points (old and new)
         let distance = haversine(self.last_lon, self.last_lat, _lon, _lat) * 1000.0;
@@ -116,6 +269,9 @@ impl SpatialInfo {
         let velocity = distance / time_diff; // meters per second
         self.distance_traveled = distance;
         self.speed = velocity * 3.6; // convert m/s to km/h
+        if time_diff > 0.0 {
+            self.acceleration = self.acceleration_estimator.update(_time, velocity);
+        }
         
         self.last_time = _time;
         self.last_lon = _lon;
@@ -124,13 +280,57 @@ impl SpatialInfo {
         self.last_y = _y;
     }
 }
+// Point which is used for speed estimation: either Kalman-corrected centroid kept by the blob (see 'speed_from_kalman')
+// or centroid of the latest raw detection
+pub fn speed_point(object: &SimpleBlob, object_extra: &ObjectExtra, from_kalman: bool) -> (f32, f32) {
+    if from_kalman {
+        let center = object.get_center();
+        (center.x, center.y)
+    } else {
+        object_extra.get_raw_centroid()
+    }
+}
+
 impl Tracker {
     pub fn new(_max_no_match: usize, _iou_threshold: f32) -> Self {
         Self {
             engine: IoUTracker::new(_max_no_match, _iou_threshold),
             objects_extra: HashMap::new(),
+            next_short_id: 1,
+            max_objects: 0,
+            keep_raw_centroids: false,
         }
     }
+    pub fn set_max_objects(&mut self, max_objects: usize) {
+        self.max_objects = max_objects;
+    }
+    pub fn set_keep_raw_centroids(&mut self, keep: bool) {
+        self.keep_raw_centroids = keep;
+    }
+    // Removes objects above the limit from both the engine and the extra information. Returns number of evicted objects
+    // Objects which have not been matched for the longest time go first, then the ones seen least recently, then the oldest ones
+    fn evict_excess_objects(&mut self) -> usize {
+        if self.max_objects == 0 || self.engine.objects.len() <= self.max_objects {
+            return 0;
+        }
+        let excess = self.engine.objects.len() - self.max_objects;
+        let mut candidates: Vec<_> = self.engine.objects.iter().map(|(object_id, object)| {
+            match self.objects_extra.get(object_id) {
+                Some(object_extra) => (*object_id, object.get_no_match_times(), object_extra.get_last_seen(), object_extra.get_short_id()),
+                None => (*object_id, object.get_no_match_times(), f32::MIN, 0),
+            }
+        }).collect();
+        candidates.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then(a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+                .then(a.3.cmp(&b.3))
+        });
+        for (object_id, _, _, _) in candidates.iter().take(excess) {
+            self.engine.objects.remove(object_id);
+            self.objects_extra.remove(object_id);
+        }
+        excess
+    }
     pub fn match_objects(&mut self, detections: &mut Detections, current_second: f32) -> Result<(), Box<dyn Error>>{
         match self.engine.match_objects(&mut detections.blobs) {
             Ok(_) => {
@@ -144,6 +344,8 @@ impl Tracker {
         // Update extra information for each object
         for (idx, detection) in detections.blobs.iter().enumerate() {
             let object_id = detection.get_id();
+            let center = detection.get_center();
+            let bbox = detection.get_bbox();
             // self.objects_extra.entry(object_id)
             //     .and_modify(|entry| {
             //     })
@@ -157,11 +359,12 @@ impl Tracker {
             match self.objects_extra.entry(object_id) {
                 Occupied(mut entry) => {
                     // Object exists in both hash maps, so update the extra information
-                    entry.get_mut().times.push(current_second);
-                    // Make sure that the times vector matches track
-                    if entry.get().times.len() > detection.get_max_track_len() {
-                        entry.get_mut().times = entry.get_mut().times[1..].to_vec();
-                    }
+                    let object_extra = entry.get_mut();
+                    object_extra.raw_centroid = (center.x, center.y);
+                    object_extra.previous_seen = Some(object_extra.last_seen);
+                    object_extra.last_seen = current_second;
+                    object_extra.push_bbox_size(bbox.width, bbox.height);
+                    object_extra.push_raw_centroid(center.x, center.y, detection.get_max_track_len());
                     // print!("{}_{}", object_id, detection.get_no_match_times());
                     // let times = entry.get().times.as_slice();
                     // for (idx, val) in times.iter().enumerate() {
@@ -176,13 +379,24 @@ impl Tracker {
                 Vacant(entry) => {
                     // Object is a new one, so add it to the hash map (with extra information)
                     let mut object_extra = ObjectExtra {
+                        short_id: self.next_short_id,
                         class_name: detections.class_names[idx].to_owned(),
                         confidence: detections.confidences[idx],
-                        times:  Vec::with_capacity(detection.get_max_track_len()),
+                        first_seen: current_second,
+                        previous_seen: None,
+                        last_seen: current_second,
                         estimated_velocity: -1.0,
                         spatial_info: None,
+                        raw_centroid: (center.x, center.y),
+                        stop_dwell: StopDwell::default(),
+                        lane_state: LaneState::default(),
+                        last_crossing: None,
+                        bbox_sizes: VecDeque::with_capacity(MAX_BBOX_HISTORY),
+                        raw_centroids: if self.keep_raw_centroids { Some(VecDeque::with_capacity(detection.get_max_track_len())) } else { None },
                     };
-                    object_extra.times.push(current_second);
+                    object_extra.push_bbox_size(bbox.width, bbox.height);
+                    object_extra.push_raw_centroid(center.x, center.y, detection.get_max_track_len());
+                    self.next_short_id += 1;
                     // print!("{}-initial_{}", object_id, detection.get_no_match_times());
                     // let times = object_extra.times.as_slice();
                     // for (idx, val) in times.iter().enumerate() {
@@ -205,6 +419,10 @@ impl Tracker {
             let save = ref_engine_objects.contains_key(object_id);
             save
         });
+        let evicted = self.evict_excess_objects();
+        if evicted > 0 {
+            println!("[WARNING]: Number of tracked objects exceeds {}. {} object(s) have been evicted", self.max_objects, evicted);
+        }
         Ok(())        
     }
 }
@@ -215,3 +433,185 @@ impl fmt::Display for Tracker {
         write!(f, "{}", self.engine)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mot_rs::utils::{Point, Rect};
+    fn detections(bboxes: &[[f32; 4]]) -> Detections {
+        Detections {
+            blobs: bboxes.iter().map(|bbox| SimpleBlob::new_with_center_dt(Point::new(bbox[0] + bbox[2] / 2.0, bbox[1] + bbox[3]), Rect::new(bbox[0], bbox[1], bbox[2], bbox[3]), 1.0)).collect(),
+            class_names: bboxes.iter().map(|_| "car".to_string()).collect(),
+            confidences: bboxes.iter().map(|_| 0.9).collect(),
+        }
+    }
+    #[test]
+    fn test_max_objects_eviction() {
+        let mut tracker = Tracker::new(15, 0.3);
+        tracker.set_max_objects(2);
+        let first = [10.0, 10.0, 50.0, 50.0];
+        let second = [200.0, 10.0, 50.0, 50.0];
+        let third = [400.0, 10.0, 50.0, 50.0];
+        let mut frame = detections(&[first, second]);
+        tracker.match_objects(&mut frame, 0.0).unwrap();
+        assert_eq!(tracker.engine.objects.len(), 2);
+        let (first_id, second_id) = (frame.blobs[0].get_id(), frame.blobs[1].get_id());
+        // Second object is not matched anymore, while the new one appears: limit is exceeded
+        let mut frame = detections(&[first, third]);
+        tracker.match_objects(&mut frame, 1.0).unwrap();
+        let third_id = frame.blobs[1].get_id();
+        assert_eq!(tracker.engine.objects.len(), 2);
+        assert_eq!(tracker.objects_extra.len(), 2);
+        assert!(!tracker.engine.objects.contains_key(&second_id));
+        assert!(!tracker.objects_extra.contains_key(&second_id));
+        assert!(tracker.objects_extra.contains_key(&first_id));
+        assert!(tracker.objects_extra.contains_key(&third_id));
+        // No limit
+        tracker.set_max_objects(0);
+        let mut frame = detections(&[first, third, second]);
+        tracker.match_objects(&mut frame, 2.0).unwrap();
+        assert_eq!(tracker.engine.objects.len(), 3);
+    }
+    #[test]
+    fn test_ground_plane_speed_vs_skeleton() {
+        // Object drives along a quarter of circle (radius is 10 meters) in 2 seconds, while zone's skeleton is horizontal
+        let pixels_per_meter = 10.0;
+        let radius = 10.0;
+        let steps = 20;
+        let points = (0..=steps).map(|i| {
+            let angle = std::f32::consts::FRAC_PI_2 * i as f32 / steps as f32;
+            (radius * angle.cos() * pixels_per_meter, radius * angle.sin() * pixels_per_meter)
+        }).collect::<Vec<(f32, f32)>>();
+        // Projection onto the horizontal skeleton keeps x only
+        let mut skeleton = SpatialInfo::new(0.0, points[0].0, points[0].1, points[0].0, 0.0);
+        let mut ground_plane = SpatialInfo::new(0.0, points[0].0, points[0].1, points[0].0, 0.0);
+        for i in 1..=steps {
+            let time = i as f32 * 0.1;
+            let (x, y) = points[i];
+            let (prev_x, prev_y) = points[i - 1];
+            let step_meters = ((x - prev_x).powi(2) + (y - prev_y).powi(2)).sqrt() / pixels_per_meter;
+            skeleton.update_avg(time, x, y, x, 0.0, pixels_per_meter);
+            ground_plane.update_ground_plane(time, x, y, x, 0.0, Some(step_meters), pixels_per_meter);
+        }
+        // Arc length is ~15.7 meters
+        let expected = radius * std::f32::consts::FRAC_PI_2 / 2.0 * 3.6;
+        assert!((ground_plane.speed - expected).abs() < 0.01 * expected, "ground plane speed: {}, expected: {}", ground_plane.speed, expected);
+        // Skeleton sees only 10 meters along it
+        assert!((skeleton.speed - radius / 2.0 * 3.6).abs() < 0.01, "skeleton speed: {}", skeleton.speed);
+        assert!(skeleton.speed < ground_plane.speed);
+        // Uncalibrated zone gives undefined speed
+        ground_plane.update_ground_plane(2.1, 0.0, 0.0, 0.0, 0.0, None, -1.0);
+        assert_eq!(ground_plane.speed, -1.0);
+    }
+    #[test]
+    fn test_raw_centroids() {
+        let mut tracker = Tracker::new(15, 0.3);
+        tracker.set_keep_raw_centroids(true);
+        let mut object_id = None;
+        for i in 0..3 {
+            let mut frame = detections(&[[10.0 + i as f32, 10.0, 50.0, 50.0]]);
+            tracker.match_objects(&mut frame, i as f32).unwrap();
+            object_id = Some(frame.blobs[0].get_id());
+        }
+        let raw_centroids = tracker.objects_extra[&object_id.unwrap()].get_raw_centroids().unwrap();
+        assert_eq!(raw_centroids.len(), 3);
+        assert_eq!(raw_centroids[0], (35.0, 60.0));
+        // Disabled by default
+        let mut tracker = Tracker::new(15, 0.3);
+        let mut frame = detections(&[[10.0, 10.0, 50.0, 50.0]]);
+        tracker.match_objects(&mut frame, 0.0).unwrap();
+        assert!(tracker.objects_extra[&frame.blobs[0].get_id()].get_raw_centroids().is_none());
+    }
+    #[test]
+    fn test_bbox_history() {
+        let mut tracker = Tracker::new(15, 0.3);
+        let frames = MAX_BBOX_HISTORY + 5;
+        let mut object_id = None;
+        for i in 0..frames {
+            // Box grows by one pixel in width every frame, so IoU with the previous one stays high
+            let mut frame = detections(&[[10.0, 10.0, 40.0 + i as f32, 20.0]]);
+            tracker.match_objects(&mut frame, i as f32).unwrap();
+            object_id = Some(frame.blobs[0].get_id());
+        }
+        let object_extra = &tracker.objects_extra[&object_id.unwrap()];
+        let sizes = object_extra.get_bbox_sizes();
+        // Only the latest boxes are kept
+        assert_eq!(sizes.len(), MAX_BBOX_HISTORY);
+        assert_eq!(sizes.front(), Some(&(45.0, 20.0)));
+        assert_eq!(sizes.back(), Some(&(40.0 + (frames - 1) as f32, 20.0)));
+        // Widths are 45..=74, so average width is 59.5
+        assert!((object_extra.get_avg_bbox_area().unwrap() - 59.5 * 20.0).abs() < 0.001);
+    }
+    #[test]
+    fn test_seen_timestamps() {
+        let mut tracker = Tracker::new(15, 0.3);
+        let mut object_id = None;
+        // Much longer than the track, but only three timestamps are kept anyway
+        for i in 0..200 {
+            let mut frame = detections(&[[10.0 + i as f32 * 0.1, 10.0, 50.0, 50.0]]);
+            tracker.match_objects(&mut frame, i as f32 * 0.5).unwrap();
+            object_id = Some(frame.blobs[0].get_id());
+        }
+        let object_extra = &tracker.objects_extra[&object_id.unwrap()];
+        assert_eq!(object_extra.get_first_seen(), 0.0);
+        assert_eq!(object_extra.get_previous_seen(), Some(99.0));
+        assert_eq!(object_extra.get_last_seen(), 99.5);
+        // New object has no previous detection
+        let mut tracker = Tracker::new(15, 0.3);
+        let mut frame = detections(&[[10.0, 10.0, 50.0, 50.0]]);
+        tracker.match_objects(&mut frame, 3.0).unwrap();
+        let object_extra = &tracker.objects_extra[&frame.blobs[0].get_id()];
+        assert_eq!(object_extra.get_previous_seen(), None);
+        assert_eq!(object_extra.get_last_seen(), 3.0);
+    }
+    fn variance(values: &[f32]) -> f32 {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+    #[test]
+    fn test_speed_from_kalman() {
+        // Object moves along X axis with 100 px/s (1 pixel per meter), frame is captured every 0.1s
+        let dt = 0.1;
+        let true_speed = 100.0 * 3.6;
+        // Deterministic pseudo-random jitter in range [-3; 3] pixels
+        let mut seed: u32 = 42;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % 7) as f32 - 3.0
+        };
+        let mut tracker = Tracker::new(15, 0.3);
+        let mut raw: Option<SpatialInfo> = None;
+        let mut smoothed: Option<SpatialInfo> = None;
+        let (mut raw_steps, mut smoothed_steps) = (vec![], vec![]);
+        let (mut raw_speeds, mut smoothed_speeds) = (vec![], vec![]);
+        for i in 0..60 {
+            let time = i as f32 * dt;
+            let mut frame = detections(&[[time * 100.0 + noise(), 10.0 + noise(), 50.0, 50.0]]);
+            tracker.match_objects(&mut frame, time).unwrap();
+            let object_id = frame.blobs[0].get_id();
+            let object = &tracker.engine.objects[&object_id];
+            let object_extra = &tracker.objects_extra[&object_id];
+            for (from_kalman, spatial_info, steps, speeds) in [(false, &mut raw, &mut raw_steps, &mut raw_speeds), (true, &mut smoothed, &mut smoothed_steps, &mut smoothed_speeds)] {
+                let (x, y) = speed_point(object, object_extra, from_kalman);
+                match spatial_info.as_mut() {
+                    Some(info) => {
+                        steps.push(((x - info.last_x).powi(2) + (y - info.last_y).powi(2)).sqrt());
+                        info.update_avg(time, x, y, x, y, 1.0);
+                        speeds.push(info.speed);
+                    },
+                    None => *spatial_info = Some(SpatialInfo::new(time, x, y, x, y))
+                }
+            }
+        }
+        // Raw point is the detection itself, smoothed one is the state of the blob
+        let object_id = tracker.objects_extra.keys().next().copied().unwrap();
+        let center = tracker.engine.objects[&object_id].get_center();
+        assert_eq!(speed_point(&tracker.engine.objects[&object_id], &tracker.objects_extra[&object_id], true), (center.x, center.y));
+        // Skip first points since filter needs some time to converge
+        assert!(variance(&smoothed_steps[10..]) <= variance(&raw_steps[10..]));
+        let smoothed_speed = *smoothed_speeds.last().unwrap();
+        assert!((smoothed_speed - true_speed).abs() < 0.1 * true_speed, "smoothed speed: {}, expected: {}", smoothed_speed, true_speed);
+        let raw_speed = *raw_speeds.last().unwrap();
+        assert!((raw_speed - true_speed).abs() < 0.1 * true_speed, "raw speed: {}, expected: {}", raw_speed, true_speed);
+    }
+}
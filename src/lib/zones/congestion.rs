@@ -0,0 +1,87 @@
+use std::fmt;
+
+// Default density thresholds (vehicles per kilometer)
+// Rough estimation based on level-of-service ranges for a single lane
+pub const DEFAULT_MODERATE_DENSITY: f32 = 12.0;
+pub const DEFAULT_CONGESTED_DENSITY: f32 = 25.0;
+pub const DEFAULT_JAMMED_DENSITY: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CongestionLevel {
+    // Density can't be estimated (e.g. zone has no spatial calibration)
+    Undefined,
+    Free,
+    Moderate,
+    Congested,
+    Jammed,
+}
+
+impl fmt::Display for CongestionLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CongestionLevel::Undefined => write!(f, "undefined"),
+            CongestionLevel::Free => write!(f, "free"),
+            CongestionLevel::Moderate => write!(f, "moderate"),
+            CongestionLevel::Congested => write!(f, "congested"),
+            CongestionLevel::Jammed => write!(f, "jammed"),
+        }
+    }
+}
+
+impl Default for CongestionLevel {
+    fn default() -> Self {
+        CongestionLevel::Undefined
+    }
+}
+
+// Lower bounds of density (vehicles per kilometer) for each congestion level
+#[derive(Debug, Clone, Copy)]
+pub struct CongestionThresholds {
+    pub moderate: f32,
+    pub congested: f32,
+    pub jammed: f32,
+}
+
+impl Default for CongestionThresholds {
+    fn default() -> Self {
+        CongestionThresholds {
+            moderate: DEFAULT_MODERATE_DENSITY,
+            congested: DEFAULT_CONGESTED_DENSITY,
+            jammed: DEFAULT_JAMMED_DENSITY,
+        }
+    }
+}
+
+impl CongestionThresholds {
+    // Classifies density (vehicles per kilometer). Negative density means that it is undefined
+    pub fn classify(&self, density: f32) -> CongestionLevel {
+        if density < 0.0 || density.is_nan() {
+            return CongestionLevel::Undefined;
+        }
+        if density >= self.jammed {
+            return CongestionLevel::Jammed;
+        }
+        if density >= self.congested {
+            return CongestionLevel::Congested;
+        }
+        if density >= self.moderate {
+            return CongestionLevel::Moderate;
+        }
+        CongestionLevel::Free
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_classify() {
+        let thresholds = CongestionThresholds::default();
+        assert_eq!(thresholds.classify(-1.0), CongestionLevel::Undefined);
+        assert_eq!(thresholds.classify(0.0), CongestionLevel::Free);
+        assert_eq!(thresholds.classify(11.9), CongestionLevel::Free);
+        assert_eq!(thresholds.classify(12.0), CongestionLevel::Moderate);
+        assert_eq!(thresholds.classify(30.0), CongestionLevel::Congested);
+        assert_eq!(thresholds.classify(120.0), CongestionLevel::Jammed);
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountMode {
+    // Objects are registered when they appear in the polygon (or cross virtual line inside of it)
+    Polygon,
+    // Polygon membership is ignored: only virtual line crossings are registered
+    Line,
+}
+
+impl fmt::Display for CountMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountMode::Polygon => write!(f, "polygon"),
+            CountMode::Line => write!(f, "line"),
+        }
+    }
+}
+
+impl Default for CountMode {
+    fn default() -> Self {
+        CountMode::Polygon
+    }
+}
+
+impl FromStr for CountMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "polygon" => Ok(CountMode::Polygon),
+            "line" => Ok(CountMode::Line),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Suppresses repeated crossings near the same place during short time window
+// Tracker could lose an object and re-acquire it with new identifier, so the same vehicle would be counted twice
+#[derive(Debug, Clone)]
+pub struct CrossingCooldown {
+    pub radius_pixels: f32,
+    pub window_seconds: f32,
+    // Accepted crossings: (x, y, time)
+    recent: Vec<(f32, f32, f32)>,
+}
+
+impl CrossingCooldown {
+    pub fn new(radius_pixels: f32, window_seconds: f32) -> Self {
+        CrossingCooldown {
+            radius_pixels: radius_pixels,
+            window_seconds: window_seconds,
+            recent: vec![],
+        }
+    }
+    // Returns true if crossing should be accepted (and remembers it) or false if it is a duplicate of the recent one
+    pub fn accept(&mut self, x: f32, y: f32, time: f32) -> bool {
+        let window = self.window_seconds;
+        self.recent.retain(|(_, _, t)| (time - t).abs() <= window);
+        let radius_sq = self.radius_pixels * self.radius_pixels;
+        let duplicate = self.recent.iter().any(|(rx, ry, _)| (rx - x) * (rx - x) + (ry - y) * (ry - y) <= radius_sq);
+        if duplicate {
+            return false;
+        }
+        self.recent.push((x, y, time));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_crossing_cooldown() {
+        let mut cooldown = CrossingCooldown::new(20.0, 1.5);
+        assert!(cooldown.accept(100.0, 100.0, 10.0));
+        // Same vehicle re-acquired with new identifier right after
+        assert!(!cooldown.accept(110.0, 105.0, 10.5));
+        // Neighbour vehicle at the same moment
+        assert!(cooldown.accept(160.0, 100.0, 10.5));
+        // Same place, but time window has passed
+        assert!(cooldown.accept(100.0, 100.0, 12.0));
+    }
+}
@@ -1,5 +1,12 @@
 pub mod statistics;
 pub mod skeleton;
 pub mod virtual_line;
+pub mod count_mode;
+pub mod zone_kind;
+pub mod congestion;
+pub mod speed_histogram;
+pub mod speed_lines;
+pub mod crossing_cooldown;
 pub mod zones;
-pub use self::{statistics::*, skeleton::*, virtual_line::*, zones::*, zones::geometry::*, zones::geojson::*};
\ No newline at end of file
+pub mod zone_grid;
+pub use self::{statistics::*, skeleton::*, virtual_line::*, count_mode::*, zone_kind::*, congestion::*, speed_histogram::*, speed_lines::*, crossing_cooldown::*, zones::*, zone_grid::*, zones::geometry::*, zones::geojson::*};
\ No newline at end of file
@@ -39,6 +39,14 @@ impl Skeleton {
             pixels_per_meter: -1.0,
         }
     }
+    // Sets real length of the skeleton and resolution derived from it
+    // Degenerate calibration (e.g. coincident spatial points gives zero length) leaves pixels per meter undefined (-1),
+    // so speed is reported as unknown instead of infinite resolution turning every speed into zero
+    pub fn set_length_meters(&mut self, length_meters: f32) {
+        self.length_meters = length_meters;
+        let pixels_per_meter = self.length_pixels / length_meters;
+        self.pixels_per_meter = if pixels_per_meter.is_finite() && pixels_per_meter > 0.0 { pixels_per_meter } else { -1.0 };
+    }
     pub fn project(&self, x: f32, y: f32) -> (f32, f32) {
         let a = self.line_cvf[0];
         let b = self.line_cvf[1];
@@ -76,6 +84,9 @@ impl Skeleton {
             (x_p_prime, y_p_prime)
         }
     }
+    pub fn get_line(&self) -> [Point2f; 2] {
+        self.line_cvf
+    }
     pub fn draw_on_mat(&self, img: &mut Mat) {
         match line(img, self.line_cvi[0], self.line_cvi[1], self.color, 2, LINE_8, 0) {
             Ok(_) => {},
@@ -0,0 +1,65 @@
+// Default histogram bins (km/h)
+pub const DEFAULT_SPEED_HIST_BIN_WIDTH: f32 = 10.0;
+pub const DEFAULT_SPEED_HIST_MAX_SPEED: f32 = 150.0;
+
+// Bins of the speed histogram
+// Bin "i" covers speeds in [i * bin_width; (i + 1) * bin_width)
+// The last bin also accumulates every speed which is greater than max_speed
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedHistogramBins {
+    pub bin_width: f32,
+    pub max_speed: f32,
+}
+
+impl Default for SpeedHistogramBins {
+    fn default() -> Self {
+        SpeedHistogramBins {
+            bin_width: DEFAULT_SPEED_HIST_BIN_WIDTH,
+            max_speed: DEFAULT_SPEED_HIST_MAX_SPEED,
+        }
+    }
+}
+
+impl SpeedHistogramBins {
+    // Number of bins. There is at least one bin always
+    pub fn bins_count(&self) -> usize {
+        if self.bin_width <= 0.0 || self.max_speed <= 0.0 {
+            return 1;
+        }
+        ((self.max_speed / self.bin_width).ceil() as usize).max(1)
+    }
+    // Returns index of the bin for given speed. Negative (undefined) speed has no bin
+    pub fn bin_index(&self, speed: f32) -> Option<usize> {
+        if speed < 0.0 || speed.is_nan() {
+            return None;
+        }
+        let last_bin = self.bins_count() - 1;
+        if self.bin_width <= 0.0 {
+            return Some(last_bin);
+        }
+        Some(((speed / self.bin_width) as usize).min(last_bin))
+    }
+    // Lower bounds of the bins
+    pub fn edges(&self) -> Vec<f32> {
+        (0..self.bins_count()).map(|i| i as f32 * self.bin_width).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_bin_index() {
+        let bins = SpeedHistogramBins::default();
+        assert_eq!(bins.bins_count(), 15);
+        assert_eq!(bins.bin_index(-1.0), None);
+        assert_eq!(bins.bin_index(0.0), Some(0));
+        assert_eq!(bins.bin_index(9.99), Some(0));
+        assert_eq!(bins.bin_index(10.0), Some(1));
+        assert_eq!(bins.bin_index(149.0), Some(14));
+        assert_eq!(bins.bin_index(220.0), Some(14));
+        let bins = SpeedHistogramBins { bin_width: 25.0, max_speed: 60.0 };
+        assert_eq!(bins.bins_count(), 3);
+        assert_eq!(bins.edges(), vec![0.0, 25.0, 50.0]);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use uuid::Uuid;
+use opencv::core::Mat;
+
+use crate::lib::zones::VirtualLine;
+use crate::lib::tracker::speed_confidence;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpeedLineLabel {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SpeedLinesState {
+    // Object has crossed one of lines at given time and the second one is expected
+    Pending(SpeedLineLabel, f32),
+    // Object has crossed both lines. Speed is in km/h
+    Done(f32),
+}
+
+// Two (parallel) lines placed known real distance apart
+// Speed is estimated as distance divided by time between crossings of the both lines
+// It does not need perspective transform and could be more robust than the projective calibration
+#[derive(Debug)]
+pub struct SpeedLines {
+    pub line_a: VirtualLine,
+    pub line_b: VirtualLine,
+    pub known_distance_meters: f32,
+    states: HashMap<Uuid, SpeedLinesState>,
+}
+
+impl SpeedLines {
+    pub fn new(line_a: VirtualLine, line_b: VirtualLine, known_distance_meters: f32) -> Self {
+        SpeedLines {
+            line_a: line_a,
+            line_b: line_b,
+            known_distance_meters: known_distance_meters,
+            states: HashMap::new(),
+        }
+    }
+    // Checks crossings of the lines by the last segment of the track and returns estimated speed (km/h) of the object
+    // Value "-1" means that speed has not been estimated yet
+    //
+    // (x1, y1, t1) - the last point of the track and its timestamp
+    // (x2, y2, t2) - the point before the last one and its timestamp
+    //
+    pub fn track(&mut self, object_id: Uuid, x1: f32, y1: f32, t1: f32, x2: f32, y2: f32, t2: f32) -> f32 {
+        let crossed_a = self.line_a.intersection_fraction(x2, y2, x1, y1).map(|t| t2 + t * (t1 - t2));
+        let crossed_b = self.line_b.intersection_fraction(x2, y2, x1, y1).map(|t| t2 + t * (t1 - t2));
+        let state = self.states.get(&object_id).copied();
+        let new_state = match (state, crossed_a, crossed_b) {
+            (Some(SpeedLinesState::Done(speed)), _, _) => SpeedLinesState::Done(speed),
+            // Both lines are crossed during the single frame
+            (_, Some(time_a), Some(time_b)) => SpeedLinesState::Done(self.speed(time_a, time_b)),
+            (Some(SpeedLinesState::Pending(SpeedLineLabel::A, time_a)), None, Some(time_b)) => SpeedLinesState::Done(self.speed(time_a, time_b)),
+            (Some(SpeedLinesState::Pending(SpeedLineLabel::B, time_b)), Some(time_a), None) => SpeedLinesState::Done(self.speed(time_a, time_b)),
+            (Some(pending), None, None) => pending,
+            // First crossing (or repeated crossing of the same line: then the latest one is used)
+            (_, Some(time_a), None) => SpeedLinesState::Pending(SpeedLineLabel::A, time_a),
+            (_, None, Some(time_b)) => SpeedLinesState::Pending(SpeedLineLabel::B, time_b),
+            (None, None, None) => return -1.0,
+        };
+        self.states.insert(object_id, new_state);
+        match new_state {
+            SpeedLinesState::Done(speed) => speed,
+            SpeedLinesState::Pending(_, _) => -1.0,
+        }
+    }
+    fn speed(&self, time_a: f32, time_b: f32) -> f32 {
+        let time_diff = (time_b - time_a).abs();
+        if time_diff == 0.0 {
+            return -1.0;
+        }
+        self.known_distance_meters / time_diff * 3.6 // convert m/s to km/h
+    }
+    // Confidence of the measured speed: measurement base is the known distance and resolution is given by pixel distance between lines
+    pub fn confidence(&self) -> f32 {
+        let (a, b) = (&self.line_a.line_cvf, &self.line_b.line_cvf);
+        let (ax, ay) = ((a[0].x + a[1].x) / 2.0, (a[0].y + a[1].y) / 2.0);
+        let (bx, by) = ((b[0].x + b[1].x) / 2.0, (b[0].y + b[1].y) / 2.0);
+        let distance_pixels = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+        speed_confidence(distance_pixels / self.known_distance_meters, self.known_distance_meters)
+    }
+    // Forgets about objects which are not tracked anymore
+    pub fn retain_objects(&mut self, object_ids: &HashSet<Uuid>) {
+        self.states.retain(|object_id, _| object_ids.contains(object_id));
+    }
+    pub fn draw_on_mat(&self, img: &mut Mat) {
+        self.line_a.draw_on_mat(img);
+        self.line_b.draw_on_mat(img);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::zones::VirtualLineDirection;
+    #[test]
+    fn test_speed_lines() {
+        // Horizontal lines at Y=100 and Y=200, which are 10 meters apart
+        let line_a = VirtualLine::new_from([[0, 100], [500, 100]], VirtualLineDirection::default());
+        let line_b = VirtualLine::new_from([[0, 200], [500, 200]], VirtualLineDirection::default());
+        let mut speed_lines = SpeedLines::new(line_a, line_b, 10.0);
+        let object_id = Uuid::new_v4();
+        // Object moves down for 50 pixels every 0.5 second. Line A is crossed at 1.0s, line B - at 2.0s
+        let track = vec![(250.0, 75.0, 0.75), (250.0, 125.0, 1.25), (250.0, 175.0, 1.75), (250.0, 225.0, 2.25)];
+        let mut speeds = vec![];
+        for w in track.windows(2) {
+            let (prev, last) = (w[0], w[1]);
+            speeds.push(speed_lines.track(object_id, last.0, last.1, last.2, prev.0, prev.1, prev.2));
+        }
+        assert_eq!(speeds[0], -1.0);
+        assert_eq!(speeds[1], -1.0);
+        // 10 meters per 1 second is 36 km/h
+        assert!((speeds[2] - 36.0).abs() < 0.001);
+        // 100 pixels per 10 meters
+        assert!((speed_lines.confidence() - 1.0).abs() < 0.001);
+
+        // Speed is kept after crossings
+        let speed = speed_lines.track(object_id, 250.0, 275.0, 2.75, 250.0, 225.0, 2.25);
+        assert!((speed - 36.0).abs() < 0.001);
+
+        // Object which has crossed single line only
+        let other_id = Uuid::new_v4();
+        let speed = speed_lines.track(other_id, 250.0, 125.0, 1.0, 250.0, 75.0, 0.5);
+        assert_eq!(speed, -1.0);
+
+        speed_lines.retain_objects(&vec![other_id].into_iter().collect::<HashSet<Uuid>>());
+        let speed = speed_lines.track(object_id, 250.0, 325.0, 3.25, 250.0, 275.0, 2.75);
+        assert_eq!(speed, -1.0);
+    }
+}
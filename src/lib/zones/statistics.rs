@@ -1,22 +1,47 @@
 use chrono::{DateTime, TimeZone, Utc};
 use std::collections::HashMap;
 
+use crate::lib::zones::CongestionLevel;
+
 #[derive(Debug)]
 pub struct VehicleTypeParameters {
     pub avg_speed: f32,
+    // Average confidence [0; 1] of the defined speeds (see speed_confidence())
+    pub avg_speed_confidence: f32,
     pub sum_intensity: u32,
     // The main difference between defined_sum_intensity and sum_intensity is in that fact
     // that sum_intensity does not take into account whether vehicles have estimated speed, when
     // defined_sum_intensity does. Could be less or equal to sum_intensity.
-    pub defined_sum_intensity: u32
+    pub defined_sum_intensity: u32,
+    // Number of vehicles faster than the speed limit of the class (see Zone::set_speed_limits())
+    pub over_speed_count: u32,
+    // Number of vehicles registered in the polygon whether they have crossed the virtual line or not
+    pub present_intensity: u32,
+    // Number of vehicles which have crossed the virtual line. Always zero when zone has no virtual line
+    pub crossed_intensity: u32,
+    // Average bounding box area (square pixels) of the counted vehicles. Value "-1" means it is undefined
+    // It depends on the camera perspective, so it is comparable between classes of the same zone only
+    pub avg_bbox_area: f32,
+    // Number of vehicles with known bounding box area
+    pub bbox_area_samples: u32,
+    // Average headway between consecutive vehicles of this class only (e.g. to analyze truck platooning)
+    // Zero when there are no valid gaps, "-1" for pedestrian zones
+    pub avg_headway: f32,
 }
 
 impl VehicleTypeParameters {
     pub fn default() -> Self {
         VehicleTypeParameters {
             avg_speed: -1.0,
+            avg_speed_confidence: 0.0,
             sum_intensity: 0,
-            defined_sum_intensity: 0
+            defined_sum_intensity: 0,
+            over_speed_count: 0,
+            present_intensity: 0,
+            crossed_intensity: 0,
+            avg_bbox_area: -1.0,
+            bbox_area_samples: 0,
+            avg_headway: 0.0,
         }
     }
 }
@@ -30,6 +55,31 @@ pub struct TrafficFlowParameters {
     // defined_sum_intensity does. Could be less or equal to sum_intensity.
     pub defined_sum_intensity: u32,
     pub avg_headway: f32,
+    // Average number of vehicles per kilometer during the period. Value "-1" means it is undefined
+    pub avg_density: f32,
+    // Average number of vehicles in the zone at once during the period. Value "-1" means it is undefined
+    pub avg_occupancy: f32,
+    pub congestion_level: CongestionLevel,
+    // Number of vehicles with defined speed in each speed bin (see SpeedHistogramBins)
+    pub speed_histogram: Vec<u32>,
+    pub speed_histogram_bin_width: f32,
+    // Percentiles of the defined speeds (see MAX_SPEED_SAMPLES). Value "-1" means they are undefined
+    pub speed_p50: f32,
+    pub speed_p85: f32,
+    pub speed_p95: f32,
+    // Number of vehicles with speed below the minimum moving speed. They are counted in sum_intensity, but not in average speed
+    pub stationary_count: u32,
+    // Number of vehicles which have been braking/accelerating harder than thresholds
+    // (see HARSH_BRAKING_THRESHOLD and HARD_ACCELERATION_THRESHOLD)
+    pub harsh_braking_events: u32,
+    pub hard_acceleration_events: u32,
+    // Number of vehicles faster than the speed limit of their class
+    pub over_speed_count: u32,
+    // Same as for VehicleTypeParameters, but for all classes
+    pub present_intensity: u32,
+    pub crossed_intensity: u32,
+    // Circular mean of the counted vehicles' headings (degrees, see Zone::heading_between()). Value "-1" means it is undefined
+    pub dominant_heading: f32,
 }
 
 impl TrafficFlowParameters {
@@ -38,7 +88,22 @@ impl TrafficFlowParameters {
             avg_speed: -1.0,
             sum_intensity: 0,
             defined_sum_intensity: 0,
-            avg_headway: 0.0
+            avg_headway: 0.0,
+            avg_density: -1.0,
+            avg_occupancy: -1.0,
+            congestion_level: CongestionLevel::default(),
+            speed_histogram: vec![],
+            speed_histogram_bin_width: 0.0,
+            speed_p50: -1.0,
+            speed_p85: -1.0,
+            speed_p95: -1.0,
+            stationary_count: 0,
+            harsh_braking_events: 0,
+            hard_acceleration_events: 0,
+            over_speed_count: 0,
+            present_intensity: 0,
+            crossed_intensity: 0,
+            dominant_heading: -1.0,
         }
     }
 }
@@ -49,6 +49,13 @@ pub struct VirtualLine {
     pub color_cv: Scalar,
     pub color: [i16; 3],
     pub direction: VirtualLineDirection,
+    // When set, line is defined by coordinates in [0; 1] relative to the frame size (see resolve())
+    // and pixel coordinates are derived from them, so line keeps its place when resolution changes
+    pub normalized: bool,
+    pub line_normalized: [[f32; 2]; 2],
+    // Deadband (pixels) around the line: crossing is counted only when object moves from beyond the margin on one side
+    // to beyond the margin on the other side. It prevents spurious crossings by objects hovering on the line. Zero disables it
+    pub margin_px: f32,
 }
 
 impl VirtualLine {
@@ -60,6 +67,9 @@ impl VirtualLine {
             color_cv: Scalar::from((0.0, 0.0, 0.0)),
             color: [0, 0, 0],
             direction: _direction,
+            normalized: false,
+            line_normalized: [[0.0, 0.0], [0.0, 0.0]],
+            margin_px: 0.0,
         }
     }
     pub fn new_from(ab: [[i32; 2]; 2], _direction: VirtualLineDirection) -> Self {
@@ -70,17 +80,81 @@ impl VirtualLine {
             color_cv: Scalar::from((0.0, 0.0, 0.0)),
             color: [0, 0, 0],
             direction: _direction,
+            normalized: false,
+            line_normalized: [[0.0, 0.0], [0.0, 0.0]],
+            margin_px: 0.0,
         }
     }
+    // Pixel coordinates stay undefined until resolve() is called with the actual frame size
+    pub fn new_normalized(ab: [[f32; 2]; 2], _direction: VirtualLineDirection) -> Self {
+        let mut vl = VirtualLine::new_from([[0, 0], [0, 0]], _direction);
+        vl.normalized = true;
+        vl.line_normalized = ab;
+        vl
+    }
+    // Converts normalized coordinates to pixels for the given frame size. Does nothing for the absolute (pixel) line
+    pub fn resolve(&mut self, frame_width: f32, frame_height: f32) {
+        if !self.normalized {
+            return;
+        }
+        let a = Point2f::new(self.line_normalized[0][0] * frame_width, self.line_normalized[0][1] * frame_height);
+        let b = Point2f::new(self.line_normalized[1][0] * frame_width, self.line_normalized[1][1] * frame_height);
+        self.line = [[a.x as i32, a.y as i32], [b.x as i32, b.y as i32]];
+        self.line_cvf = [a, b];
+        self.line_cvi = [Point2i::new(a.x as i32, a.y as i32), Point2i::new(b.x as i32, b.y as i32)];
+    }
     pub fn set_color_rgb(&mut self, r: i16, g: i16, b: i16) {
         self.color_cv = Scalar::from((b as f64, g as f64, r as f64)); // BGR
         self.color = [r, g, b];
     }
     // is_left returns true if the given point is to the left side of the vertical AB or if the given point is above of the horizontal AB
     pub fn is_left(&self, cx: f32, cy: f32) -> bool {
+        self.side(cx, cy) > 0.0
+    }
+    pub fn set_margin_px(&mut self, margin_px: f32) {
+        self.margin_px = margin_px.max(0.0);
+    }
+    // Same as is_left, but respects the deadband: None is returned when the point is within margin_px of the line
+    pub fn clear_side(&self, cx: f32, cy: f32) -> Option<bool> {
+        if self.margin_px <= 0.0 {
+            return Some(self.is_left(cx, cy));
+        }
         let a = self.line_cvf[0];
         let b = self.line_cvf[1];
-        (b.x - a.x)*(cy - a.y) - (b.y - a.y)*(cx - a.x) > 0.0
+        let length = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+        if length == 0.0 {
+            return None;
+        }
+        // Perpendicular distance to the line (positive to the left)
+        let distance = self.side(cx, cy) / length;
+        if distance > self.margin_px {
+            Some(true)
+        } else if distance < -self.margin_px {
+            Some(false)
+        } else {
+            None
+        }
+    }
+    // side returns signed (doubled) area of triangle ABC: positive when C is to the left of AB, negative when to the right
+    fn side(&self, cx: f32, cy: f32) -> f32 {
+        let a = self.line_cvf[0];
+        let b = self.line_cvf[1];
+        (b.x - a.x)*(cy - a.y) - (b.y - a.y)*(cx - a.x)
+    }
+    // intersection_fraction returns parameter t in [0; 1] of the point where segment P1->P2 intersects the line
+    // Intersection point is P1 + t*(P2 - P1). None is returned when segment does not intersect the line
+    pub fn intersection_fraction(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Option<f32> {
+        let d1 = self.side(x1, y1);
+        let d2 = self.side(x2, y2);
+        let denominator = d1 - d2;
+        if denominator == 0.0 {
+            return None;
+        }
+        let t = d1 / denominator;
+        if t < 0.0 || t > 1.0 {
+            return None;
+        }
+        Some(t)
     }
     pub fn clone(&self) -> Self {
         VirtualLine {
@@ -90,6 +164,9 @@ impl VirtualLine {
             color_cv: self.color_cv,
             color: self.color,
             direction: self.direction,
+            normalized: self.normalized,
+            line_normalized: self.line_normalized,
+            margin_px: self.margin_px,
         }
     }
     pub fn draw_on_mat(&self, img: &mut Mat) {
@@ -145,6 +222,26 @@ mod tests {
         assert_eq!(true, is_left);
     }
     #[test]
+    fn test_intersection_fraction() {
+        let horizontal_line = VirtualLine::new_from_cv(Point2f::new(0.0, 10.0), Point2f::new(20.0, 10.0), VirtualLineDirection::LeftToRightTopToBottom);
+        let eps = 0.0001;
+
+        let t = horizontal_line.intersection_fraction(5.0, 5.0, 5.0, 25.0).unwrap();
+        assert!((t - 0.25).abs() < eps);
+
+        let t = horizontal_line.intersection_fraction(5.0, 25.0, 5.0, 5.0).unwrap();
+        assert!((t - 0.75).abs() < eps);
+
+        let t = horizontal_line.intersection_fraction(5.0, 0.0, 15.0, 10.0).unwrap();
+        assert!((t - 1.0).abs() < eps);
+
+        let t = horizontal_line.intersection_fraction(5.0, 0.0, 5.0, 8.0);
+        assert_eq!(t, None);
+
+        let t = horizontal_line.intersection_fraction(0.0, 5.0, 20.0, 5.0);
+        assert_eq!(t, None);
+    }
+    #[test]
     fn test_horizontal_line() {
         let vertical_line = VirtualLine::new_from_cv(Point2f::new(4.0, 6.0), Point2f::new(9.0, 6.4), VirtualLineDirection::LeftToRightTopToBottom);
         let c = Point2f::new(3.0, 8.0);
@@ -183,4 +280,30 @@ mod tests {
         let is_above = vertical_line.is_left(c.x, c.y);
         assert_eq!(true, is_above);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_clear_side() {
+        let mut horizontal_line = VirtualLine::new_from_cv(Point2f::new(0.0, 10.0), Point2f::new(20.0, 10.0), VirtualLineDirection::LeftToRightTopToBottom);
+        // Without margin it is the same as is_left
+        assert_eq!(horizontal_line.clear_side(5.0, 10.5), Some(true));
+        assert_eq!(horizontal_line.clear_side(5.0, 9.5), Some(false));
+        horizontal_line.set_margin_px(2.0);
+        assert_eq!(horizontal_line.clear_side(5.0, 10.5), None);
+        assert_eq!(horizontal_line.clear_side(5.0, 8.5), None);
+        assert_eq!(horizontal_line.clear_side(5.0, 12.5), Some(true));
+        assert_eq!(horizontal_line.clear_side(5.0, 7.5), Some(false));
+    }
+    #[test]
+    fn test_normalized_line() {
+        let mut vl = VirtualLine::new_normalized([[0.25, 0.5], [0.75, 0.5]], VirtualLineDirection::default());
+        vl.resolve(1920.0, 1080.0);
+        assert_eq!(vl.line, [[480, 540], [1440, 540]]);
+        // Same line for the lower resolution
+        vl.resolve(640.0, 360.0);
+        assert_eq!(vl.line, [[160, 180], [480, 180]]);
+        assert!(vl.is_left(300.0, 170.0) != vl.is_left(300.0, 190.0));
+        // Absolute line is kept as is
+        let mut absolute = VirtualLine::new_from([[10, 20], [30, 40]], VirtualLineDirection::default());
+        absolute.resolve(640.0, 360.0);
+        assert_eq!(absolute.line, [[10, 20], [30, 40]]);
+    }
+}
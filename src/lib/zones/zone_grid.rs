@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use opencv::core::Point2f;
+
+use crate::lib::zones::{Zone, CountMode};
+
+// Size (pixels) of the grid cell by default
+pub const DEFAULT_ZONE_GRID_CELL_SIZE: f32 = 64.0;
+
+// Uniform grid over the image: every cell keeps identifiers of the zones whose bounding boxes overlap it
+// It narrows down the zones to be checked via contains_point() for the given point
+// Grid does not track zone changes, so it should be rebuilt after any zone geometry mutation
+#[derive(Debug, Clone)]
+pub struct ZoneGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<String>>,
+}
+
+impl Default for ZoneGrid {
+    fn default() -> Self {
+        ZoneGrid::new(DEFAULT_ZONE_GRID_CELL_SIZE)
+    }
+}
+
+impl ZoneGrid {
+    pub fn new(cell_size: f32) -> Self {
+        ZoneGrid {
+            cell_size: if cell_size > 0.0 { cell_size } else { DEFAULT_ZONE_GRID_CELL_SIZE },
+            cells: HashMap::new(),
+        }
+    }
+    pub fn get_cell_size(&self) -> f32 {
+        self.cell_size
+    }
+    // Zones should not be locked by the caller
+    pub fn build(zones: &HashMap<String, Mutex<Zone>>, cell_size: f32) -> Self {
+        let mut grid = ZoneGrid::new(cell_size);
+        for (zone_id, zone_guarded) in zones.iter() {
+            let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            grid.insert(zone_id, &zone.get_pixel_coordinates());
+            drop(zone);
+        }
+        grid
+    }
+    pub fn insert(&mut self, zone_id: &str, pixel_coordinates: &[Point2f]) {
+        if pixel_coordinates.is_empty() {
+            return;
+        }
+        let (mut x_min, mut y_min) = (f32::MAX, f32::MAX);
+        let (mut x_max, mut y_max) = (f32::MIN, f32::MIN);
+        for pt in pixel_coordinates.iter() {
+            x_min = x_min.min(pt.x);
+            y_min = y_min.min(pt.y);
+            x_max = x_max.max(pt.x);
+            y_max = y_max.max(pt.y);
+        }
+        let (col_min, row_min) = self.cell_of(x_min, y_min);
+        let (col_max, row_max) = self.cell_of(x_max, y_max);
+        for col in col_min..=col_max {
+            for row in row_min..=row_max {
+                let cell = self.cells.entry((col, row)).or_insert_with(Vec::new);
+                if let Err(pos) = cell.binary_search_by(|id| id.as_str().cmp(zone_id)) {
+                    // Keep identifiers sorted, so candidates order is deterministic
+                    cell.insert(pos, zone_id.to_string());
+                }
+            }
+        }
+    }
+    // Zones which could contain the point. Point still should be checked via contains_point()
+    pub fn get_candidate_zones(&self, x: f32, y: f32) -> &[String] {
+        match self.cells.get(&self.cell_of(x, y)) {
+            Some(cell) => cell,
+            None => &[],
+        }
+    }
+    // Zones to be processed for the object at the given point: candidates of the point's cell plus the zones which
+    // should be checked regardless of the position (e.g. 'line' zones and zones with speed lines, see get_position_independent_zones)
+    // Identifiers are sorted and unique
+    pub fn get_zones_to_check(&self, x: f32, y: f32, position_independent: &[String]) -> Vec<String> {
+        let mut zones_ids = self.get_candidate_zones(x, y).iter().chain(position_independent.iter()).cloned().collect::<Vec<String>>();
+        zones_ids.sort();
+        zones_ids.dedup();
+        zones_ids
+    }
+    // Cells (column, row) which contain at least one zone, sorted by column then by row
+    pub fn get_occupied_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells = self.cells.iter()
+            .filter(|(_, zones_ids)| !zones_ids.is_empty())
+            .map(|(cell, _)| *cell)
+            .collect::<Vec<(i32, i32)>>();
+        cells.sort();
+        cells
+    }
+    fn cell_of(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+}
+
+// Zones which are not tied to the polygon membership of the object: virtual lines of 'line' zones are crossed by the track
+// (so the last point could be in any cell), while speed lines keep their own state for every object
+// It should be prepared once per frame. Zones should not be locked by the caller
+pub fn get_position_independent_zones(zones: &HashMap<String, Mutex<Zone>>) -> Vec<String> {
+    let mut zones_ids = zones.iter()
+        .filter(|(_, zone_guarded)| {
+            let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.get_count_mode() == CountMode::Line || zone.get_speed_lines().is_some()
+        })
+        .map(|(zone_id, _)| zone_id.clone())
+        .collect::<Vec<String>>();
+    zones_ids.sort();
+    zones_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::zones::{find_zone_for_point, find_zone_for_point_by};
+    fn square_zone(id: &str, x: f32, y: f32, size: f32) -> Zone {
+        let mut zone = Zone::default_from_cv(vec![Point2f::new(x, y + size), Point2f::new(x + size, y + size), Point2f::new(x + size, y), Point2f::new(x, y)]);
+        zone.set_id(id.to_string());
+        zone
+    }
+    #[test]
+    fn test_zone_grid_candidates() {
+        let mut grid = ZoneGrid::new(100.0);
+        grid.insert("a", &[Point2f::new(10.0, 10.0), Point2f::new(150.0, 10.0), Point2f::new(150.0, 50.0), Point2f::new(10.0, 50.0)]);
+        grid.insert("b", &[Point2f::new(120.0, 20.0), Point2f::new(180.0, 20.0), Point2f::new(180.0, 90.0), Point2f::new(120.0, 90.0)]);
+        assert_eq!(grid.get_candidate_zones(50.0, 50.0), &["a".to_string()]);
+        assert_eq!(grid.get_candidate_zones(130.0, 30.0), &["a".to_string(), "b".to_string()]);
+        assert!(grid.get_candidate_zones(500.0, 500.0).is_empty());
+        // Negative coordinates are handled by floor division
+        assert!(grid.get_candidate_zones(-50.0, 50.0).is_empty());
+        assert_eq!(grid.get_occupied_cells(), vec![(0, 0), (1, 0)]);
+    }
+    #[test]
+    fn test_zone_grid_reduces_checks() {
+        // 20x10 zones 50x50 pixels each
+        let mut zones = HashMap::new();
+        for col in 0..20 {
+            for row in 0..10 {
+                let zone = square_zone(&format!("zone_{}_{}", col, row), col as f32 * 50.0, row as f32 * 50.0, 50.0);
+                zones.insert(zone.get_id(), Mutex::new(zone));
+            }
+        }
+        let all_ids = zones.keys().cloned().collect::<Vec<String>>();
+        let grid = ZoneGrid::build(&zones, DEFAULT_ZONE_GRID_CELL_SIZE);
+        let mut linear_checks = 0;
+        let mut grid_checks = 0;
+        for x in (5..1000).step_by(37) {
+            for y in (5..500).step_by(23) {
+                let (x, y) = (x as f32, y as f32);
+                let candidates = grid.get_candidate_zones(x, y);
+                let by_grid = find_zone_for_point_by(&zones, candidates, x, y, |zone, x, y| {
+                    grid_checks += 1;
+                    zone.contains_point(x, y)
+                });
+                let linear = find_zone_for_point_by(&zones, &all_ids, x, y, |zone, x, y| {
+                    linear_checks += 1;
+                    zone.contains_point(x, y)
+                });
+                assert_eq!(by_grid, linear);
+                assert_eq!(by_grid, find_zone_for_point(&zones, candidates, x, y));
+            }
+        }
+        assert!(grid_checks * 20 < linear_checks, "grid checks: {}, linear checks: {}", grid_checks, linear_checks);
+    }
+    #[test]
+    fn test_zones_to_check() {
+        let mut zones = HashMap::new();
+        let mut line_zone = square_zone("line", 500.0, 500.0, 50.0);
+        line_zone.set_count_mode(CountMode::Line);
+        for zone in vec![square_zone("a", 0.0, 0.0, 50.0), square_zone("b", 200.0, 0.0, 50.0), line_zone] {
+            zones.insert(zone.get_id(), Mutex::new(zone));
+        }
+        let position_independent = get_position_independent_zones(&zones);
+        assert_eq!(position_independent, vec!["line".to_string()]);
+        let grid = ZoneGrid::build(&zones, DEFAULT_ZONE_GRID_CELL_SIZE);
+        // Far zone 'b' is skipped, while 'line' zone is processed everywhere
+        assert_eq!(grid.get_zones_to_check(10.0, 10.0, &position_independent), vec!["a".to_string(), "line".to_string()]);
+        assert_eq!(grid.get_zones_to_check(510.0, 510.0, &position_independent), vec!["line".to_string()]);
+        assert_eq!(grid.get_zones_to_check(900.0, 900.0, &position_independent), vec!["line".to_string()]);
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Class of the detector which is counted by pedestrian zones by default
+pub const PEDESTRIAN_CLASS: &str = "person";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoneKind {
+    // Road lane: every target class is counted by default (use zone's 'target_classes' to exclude pedestrians)
+    Vehicle,
+    // Crosswalk: only pedestrians are counted by default. Speed is the walking speed, headway is not estimated
+    Pedestrian,
+}
+
+impl ZoneKind {
+    // Whether the class is counted when zone has no explicit classes allowlist
+    pub fn is_default_class(&self, classname: &str) -> bool {
+        match self {
+            ZoneKind::Vehicle => true,
+            ZoneKind::Pedestrian => classname == PEDESTRIAN_CLASS,
+        }
+    }
+}
+
+impl fmt::Display for ZoneKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZoneKind::Vehicle => write!(f, "vehicle"),
+            ZoneKind::Pedestrian => write!(f, "pedestrian"),
+        }
+    }
+}
+
+impl Default for ZoneKind {
+    fn default() -> Self {
+        ZoneKind::Vehicle
+    }
+}
+
+impl FromStr for ZoneKind {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "vehicle" => Ok(ZoneKind::Vehicle),
+            "pedestrian" => Ok(ZoneKind::Pedestrian),
+            _ => Err(()),
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub(crate) mod geometry;
 use chrono::{DateTime, Utc};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use uuid::Uuid;
 
 use geometry::PointsOrientation;
@@ -13,33 +14,54 @@ use geometry::{get_orientation, is_intersects, is_on_segment};
 use geojson::{GeoPolygon, VirtualLineFeature, ZoneFeature, ZonePropertiesGeoJSON};
 
 use crate::{lib::{spatial::compute_center}};
-use crate::lib::spatial::epsg::lonlat_to_meters;
+use crate::lib::spatial::epsg::{lonlat_to_meters, meters_to_lonlat};
 use crate::lib::spatial::haversine;
 use crate::lib::spatial::SpatialConverter;
+use crate::lib::spatial::SpatialCRS;
+use crate::lib::spatial::precision::round_coordinate;
+use crate::lib::spatial::heading::{pixel_heading, geographic_bearing, circular_mean_degrees};
+use crate::lib::draw::{draw_scale_bar, draw_north_arrow, shade_alpha, blend_polygon, TextStyle};
+use crate::lib::tracker::{HARSH_BRAKING_THRESHOLD, HARD_ACCELERATION_THRESHOLD};
 use crate::lib::zones::{
-    Skeleton, Statistics, VehicleTypeParameters, TrafficFlowParameters, VirtualLine, VirtualLineDirection,
+    CongestionThresholds, CountMode, ZoneKind, PEDESTRIAN_CLASS, Skeleton, SpeedHistogramBins, SpeedLines, CrossingCooldown, Statistics, VehicleTypeParameters, TrafficFlowParameters, VirtualLine, VirtualLineDirection,
 };
 use opencv::{
     core::Mat, core::Point2f, core::Point2i, core::Scalar, imgproc::line, imgproc::put_text,
-    imgproc::FONT_HERSHEY_SIMPLEX, imgproc::LINE_8,
+    imgproc::FONT_HERSHEY_SIMPLEX, imgproc::LINE_8, imgproc::arrowed_line, imgproc::circle, imgproc::FILLED,
 };
 
 #[derive(Debug, Clone)]
 struct ObjectInfo {
     classname: String,
     speed: f32,
+    speed_confidence: f32,
+    // Extreme acceleration values (m/s^2) observed while object has been in the zone
+    min_acceleration: f32,
+    max_acceleration: f32,
     crossed_virtual_line: bool,
-    timestamp_registration: f32
+    timestamp_registration: f32,
+    // Object has been counted in one of the previous periods and it is kept registered only to prevent recounting
+    counted_before: bool,
+    // The latest heading of the object (see heading_between())
+    heading: Option<f32>,
+    // Average bounding box area (square pixels) of the object, see ObjectExtra::get_avg_bbox_area()
+    bbox_area: Option<f32>,
 }
 
 type Registered = HashMap<Uuid, ObjectInfo>;
 
+// Maximum number of speed samples per period used for percentiles. Extra samples are ignored,
+// so percentiles are estimated on the arbitrary subset of the vehicles for extremely busy zones
+pub const MAX_SPEED_SAMPLES: usize = 10000;
+
 #[derive(Debug)]
 pub struct Zone {
     pub id: String,
     pixel_coordinates: Vec<Point2f>,
     spatial_coordinates_epsg4326: Vec<Point2f>,
+    // Metric coordinates for the spatial converter. For 'local_meters' CRS they are user-provided local coordinates
     spatial_coordinates_epsg3857: Vec<Point2f>,
+    crs: SpatialCRS,
     pub color: Scalar,
     pub road_lane_num: u16,
     pub road_lane_direction: u8,
@@ -49,6 +71,26 @@ pub struct Zone {
     pub current_statistics: RealTimeStatistics,
     skeleton: Skeleton,
     virtual_line: Option<VirtualLine>,
+    count_mode: CountMode,
+    // Road lane or crosswalk. It defines classes counted by default and which statistics are estimated
+    kind: ZoneKind,
+    congestion_thresholds: CongestionThresholds,
+    speed_histogram_bins: SpeedHistogramBins,
+    // Optional pair of lines with known distance between them for speed estimation
+    speed_lines: Option<SpeedLines>,
+    // Optional suppression of repeated crossings (e.g. when tracker re-acquires the same vehicle with new identifier)
+    crossing_cooldown: Option<CrossingCooldown>,
+    // Optional ground sample distance (meters per pixel). When set it defines pixels per meter of the skeleton instead of the spatial coordinates
+    gsd_meters_per_pixel: Option<f32>,
+    // Resolves overlapping zones: object is assigned to the containing zone with the highest priority
+    priority: i32,
+    // Optional classes allowlist (overrides global target classes). Objects of other classes are ignored by the zone
+    allowed_classes: Option<HashSet<String>>,
+    // Speed limits (km/h) per class. Vehicles of the class driving faster are counted as over-speeding
+    speed_limits: HashMap<String, f32>,
+    // Accumulated occupancy over the period (for density estimation)
+    occupancy_sum: u64,
+    occupancy_samples: u64,
 }
 
 #[derive(Debug)]
@@ -66,6 +108,7 @@ impl Zone {
             pixel_coordinates: vec![],
             spatial_coordinates_epsg4326: vec![],
             spatial_coordinates_epsg3857: vec![],
+            crs: SpatialCRS::default(),
             color: Scalar::from((255.0, 255.0, 255.0)),
             road_lane_num: 0,
             road_lane_direction: 0,
@@ -80,6 +123,18 @@ impl Zone {
             },
             skeleton: Skeleton::default(),
             virtual_line: None,
+            count_mode: CountMode::default(),
+            kind: ZoneKind::default(),
+            congestion_thresholds: CongestionThresholds::default(),
+            speed_histogram_bins: SpeedHistogramBins::default(),
+            speed_lines: None,
+            crossing_cooldown: None,
+            gsd_meters_per_pixel: None,
+            priority: 0,
+            allowed_classes: None,
+            speed_limits: HashMap::new(),
+            occupancy_sum: 0,
+            occupancy_samples: 0,
         }
     }
     pub fn new(
@@ -105,8 +160,7 @@ impl Zone {
             let cd_center = compute_center(c.x, c.y, d.x, d.y);
             let length_meters =
                 haversine(ab_center.0, ab_center.1, cd_center.0, cd_center.1) * 1000.0;
-            skeleton.length_meters = length_meters;
-            skeleton.pixels_per_meter = skeleton.length_pixels / skeleton.length_meters;
+            skeleton.set_length_meters(length_meters);
             SpatialConverter::new_from(coordinates.clone(), spatial_coordinates_epsg3857.clone())
         } else {
             SpatialConverter::default()
@@ -116,6 +170,7 @@ impl Zone {
             pixel_coordinates: coordinates,
             spatial_coordinates_epsg4326: spatial_coordinates_epsg4326,
             spatial_coordinates_epsg3857: spatial_coordinates_epsg3857,
+            crs: SpatialCRS::default(),
             color: color,
             road_lane_num: road_lane_num,
             road_lane_direction: road_lane_direction,
@@ -130,6 +185,18 @@ impl Zone {
             },
             skeleton: skeleton,
             virtual_line: _virtual_line,
+            count_mode: CountMode::default(),
+            kind: ZoneKind::default(),
+            congestion_thresholds: CongestionThresholds::default(),
+            speed_histogram_bins: SpeedHistogramBins::default(),
+            speed_lines: None,
+            crossing_cooldown: None,
+            gsd_meters_per_pixel: None,
+            priority: 0,
+            allowed_classes: None,
+            speed_limits: HashMap::new(),
+            occupancy_sum: 0,
+            occupancy_samples: 0,
         }
     }
     pub fn default_from_cv(points: Vec<Point2f>) -> Self {
@@ -181,38 +248,103 @@ impl Zone {
             vline.set_color_rgb(r, g, b);
         };
     }
+    pub fn get_crs(&self) -> SpatialCRS {
+        self.crs
+    }
+    // Spatial map should be updated after CRS change, since stored coordinates are not converted
+    pub fn set_crs(&mut self, crs: SpatialCRS) {
+        self.crs = crs;
+    }
+    // Returns user-provided spatial coordinates: lon/lat for 'wgs84' and local meters for 'local_meters'
+    pub fn get_spatial_coordinates(&self) -> Vec<Point2f> {
+        match self.crs {
+            SpatialCRS::WGS84 => self.spatial_coordinates_epsg4326.clone(),
+            SpatialCRS::LocalMeters => self.spatial_coordinates_epsg3857.clone(),
+        }
+    }
+    pub fn get_gsd(&self) -> Option<f32> {
+        self.gsd_meters_per_pixel
+    }
+    // Calibrates zone via single ground sample distance (meters per pixel) instead of the spatial coordinates
+    // Homography is not used, so this assumes negligible perspective distortion (e.g. nadir camera)
+    pub fn set_gsd(&mut self, gsd_meters_per_pixel: f32) {
+        self.gsd_meters_per_pixel = Some(gsd_meters_per_pixel);
+        self.update_skeleton();
+    }
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
     pub fn update_skeleton(&mut self) {
+        if let Some(gsd) = self.gsd_meters_per_pixel {
+            let skeleton_line = find_skeleton_line(&self.pixel_coordinates, 0, 2);
+            let mut skeleton = Skeleton::new(skeleton_line[0], skeleton_line[1]);
+            skeleton.set_length_meters(skeleton.length_pixels * gsd);
+            self.skeleton = skeleton;
+            return;
+        }
+        let spatial_points = match self.crs {
+            SpatialCRS::WGS84 => self.spatial_coordinates_epsg4326.len(),
+            SpatialCRS::LocalMeters => self.spatial_coordinates_epsg3857.len(),
+        };
+        if spatial_points < 4 {
+            // No spatial calibration: pixels per meter stays undefined, so speed is not estimated
+            let skeleton_line = find_skeleton_line(&self.pixel_coordinates, 0, 2);
+            self.skeleton = Skeleton::new(skeleton_line[0], skeleton_line[1]);
+            return;
+        }
         /* Eval distance between sides */
-        let a = self.spatial_coordinates_epsg4326[0];
-        let b = self.spatial_coordinates_epsg4326[1];
-        let c = self.spatial_coordinates_epsg4326[2];
-        let d = self.spatial_coordinates_epsg4326[3];
-        let ab_center = compute_center(a.x, a.y, b.x, b.y);
-        let cd_center = compute_center(c.x, c.y, d.x, d.y);
-        let length_meters = haversine(ab_center.0, ab_center.1, cd_center.0, cd_center.1) * 1000.0;
+        let length_meters = match self.crs {
+            SpatialCRS::WGS84 => {
+                let a = self.spatial_coordinates_epsg4326[0];
+                let b = self.spatial_coordinates_epsg4326[1];
+                let c = self.spatial_coordinates_epsg4326[2];
+                let d = self.spatial_coordinates_epsg4326[3];
+                let ab_center = compute_center(a.x, a.y, b.x, b.y);
+                let cd_center = compute_center(c.x, c.y, d.x, d.y);
+                haversine(ab_center.0, ab_center.1, cd_center.0, cd_center.1) * 1000.0
+            },
+            SpatialCRS::LocalMeters => {
+                let a = self.spatial_coordinates_epsg3857[0];
+                let b = self.spatial_coordinates_epsg3857[1];
+                let c = self.spatial_coordinates_epsg3857[2];
+                let d = self.spatial_coordinates_epsg3857[3];
+                let ab_center = compute_center(a.x, a.y, b.x, b.y);
+                let cd_center = compute_center(c.x, c.y, d.x, d.y);
+                ((cd_center.0 - ab_center.0).powi(2) + (cd_center.1 - ab_center.1).powi(2)).sqrt()
+            }
+        };
         /* Init skeleton */
         let skeleton_line = find_skeleton_line(&self.pixel_coordinates, 0, 2); // 0-1 is first segment of polygon, 2-3 is second segment
         let mut skeleton = Skeleton::new(skeleton_line[0], skeleton_line[1]);
-        skeleton.length_meters = length_meters;
-        skeleton.pixels_per_meter = skeleton.length_pixels / skeleton.length_meters;
+        skeleton.set_length_meters(length_meters);
         self.skeleton = skeleton;
     }
     pub fn update_pixel_map_cv(&mut self, pixel_src_points: Vec<Point2f>) {
         self.pixel_coordinates = pixel_src_points;
-        if self.spatial_coordinates_epsg4326.len() == 0 {
-            self.spatial_coordinates_epsg4326 = self
-                .pixel_coordinates
-                .iter()
-                .map(|pt| Point2f::new(pt.x as f32, pt.y as f32))
-                .collect();
-            self.spatial_coordinates_epsg3857 = self
-                .spatial_coordinates_epsg4326
-                .iter()
-                .map(|pt| {
-                    let lonlat = lonlat_to_meters(pt.x, pt.y);
-                    Point2f::new(lonlat.0, lonlat.1)
-                })
-                .collect();
+        if self.spatial_coordinates_epsg3857.len() == 0 {
+            match self.crs {
+                SpatialCRS::WGS84 => {
+                    self.spatial_coordinates_epsg4326 = self
+                        .pixel_coordinates
+                        .iter()
+                        .map(|pt| Point2f::new(pt.x as f32, pt.y as f32))
+                        .collect();
+                    self.spatial_coordinates_epsg3857 = self
+                        .spatial_coordinates_epsg4326
+                        .iter()
+                        .map(|pt| {
+                            let lonlat = lonlat_to_meters(pt.x, pt.y);
+                            Point2f::new(lonlat.0, lonlat.1)
+                        })
+                        .collect();
+                },
+                SpatialCRS::LocalMeters => {
+                    self.spatial_coordinates_epsg3857 = self.pixel_coordinates.clone();
+                }
+            }
         }
         self.spatial_converter = SpatialConverter::new_from(
             self.pixel_coordinates.clone(),
@@ -221,15 +353,24 @@ impl Zone {
         self.update_skeleton();
     }
     pub fn update_spatial_map_cv(&mut self, spatial_dest_points: Vec<Point2f>) {
-        self.spatial_coordinates_epsg4326 = spatial_dest_points;
-        self.spatial_coordinates_epsg3857 = self
-            .spatial_coordinates_epsg4326
-            .iter()
-            .map(|pt| {
-                let lonlat = lonlat_to_meters(pt.x, pt.y);
-                Point2f::new(lonlat.0, lonlat.1)
-            })
-            .collect();
+        match self.crs {
+            SpatialCRS::WGS84 => {
+                self.spatial_coordinates_epsg4326 = spatial_dest_points;
+                self.spatial_coordinates_epsg3857 = self
+                    .spatial_coordinates_epsg4326
+                    .iter()
+                    .map(|pt| {
+                        let lonlat = lonlat_to_meters(pt.x, pt.y);
+                        Point2f::new(lonlat.0, lonlat.1)
+                    })
+                    .collect();
+            },
+            SpatialCRS::LocalMeters => {
+                // Local metric coordinates are used as-is (no lon/lat are known)
+                self.spatial_coordinates_epsg4326 = vec![];
+                self.spatial_coordinates_epsg3857 = spatial_dest_points;
+            }
+        }
         if self.pixel_coordinates.len() == 0 {
             self.pixel_coordinates = self
                 .spatial_coordinates_epsg3857
@@ -259,46 +400,167 @@ impl Zone {
     }
     pub fn set_target_classes(&mut self, vehicle_types: &HashSet<String>) {
         for class in vehicle_types.iter() {
+            if !self.is_class_allowed(class) {
+                continue;
+            }
             self.statistics
                 .vehicles_data
                 .insert(class.clone(), VehicleTypeParameters::default());
         }
     }
+    pub fn get_allowed_classes(&self) -> Option<&HashSet<String>> {
+        self.allowed_classes.as_ref()
+    }
+    // None (or empty set) means that every target class is counted
+    pub fn set_allowed_classes(&mut self, allowed_classes: Option<HashSet<String>>) {
+        self.allowed_classes = allowed_classes.filter(|classes| !classes.is_empty());
+        if let Some(classes) = &self.allowed_classes {
+            self.statistics.vehicles_data.retain(|class, _| classes.contains(class));
+            for class in classes.iter() {
+                self.statistics
+                    .vehicles_data
+                    .entry(class.clone())
+                    .or_insert_with(VehicleTypeParameters::default);
+            }
+        }
+    }
+    pub fn get_speed_limits(&self) -> &HashMap<String, f32> {
+        &self.speed_limits
+    }
+    pub fn set_speed_limits(&mut self, speed_limits: HashMap<String, f32>) {
+        self.speed_limits = speed_limits;
+    }
+    pub fn is_class_allowed(&self, classname: &str) -> bool {
+        match &self.allowed_classes {
+            Some(classes) => classes.contains(classname),
+            None => self.kind.is_default_class(classname),
+        }
+    }
+    pub fn get_kind(&self) -> ZoneKind {
+        self.kind
+    }
+    // Classes which are not counted by the new kind are removed from statistics. Pedestrian zone always has statistics for pedestrians
+    // (unless classes allowlist is set), but objects are counted only if the class is in global target classes
+    pub fn set_kind(&mut self, kind: ZoneKind) {
+        self.kind = kind;
+        if self.allowed_classes.is_some() {
+            return;
+        }
+        self.statistics.vehicles_data.retain(|class, _| kind.is_default_class(class));
+        if kind == ZoneKind::Pedestrian {
+            self.statistics
+                .vehicles_data
+                .entry(PEDESTRIAN_CLASS.to_string())
+                .or_insert_with(VehicleTypeParameters::default);
+        }
+    }
+    // Returns true when object becomes counted: it is registered for the first time (zone without virtual line)
+    // or it has crossed the virtual line for the first time (the crossing has not been rejected by cooldown)
     pub fn register_or_update_object(
         &mut self,
         object_id: Uuid,
         _timestamp: f32,
         _relative_time: f32,
         _speed: f32,
+        _speed_confidence: f32,
+        _acceleration: Option<f32>,
         _classname: String,
         _crossed_virtual_line: bool,
-    ) {
-        let register_as_crossed = match &self.virtual_line {
+        _position: (f32, f32),
+    ) -> bool {
+        if !self.is_class_allowed(&_classname) {
+            return false;
+        }
+        let register_via_virtual_line = self.virtual_line.is_some();
+        let mut register_as_crossed = match &self.virtual_line {
             Some(_) => _crossed_virtual_line,
             None => false,
         };
+        if register_as_crossed {
+            let already_crossed = match self.objects_registered.get(&object_id) {
+                Some(object_info) => object_info.crossed_virtual_line,
+                None => false,
+            };
+            if let Some(cooldown) = self.crossing_cooldown.as_mut() {
+                // Crossing near the recent one is considered to be a duplicate
+                if !already_crossed && !cooldown.accept(_position.0, _position.1, _timestamp) {
+                    register_as_crossed = false;
+                }
+            }
+        }
         match self.objects_registered.entry(object_id) {
             Occupied(mut entry) => {
                 entry.get_mut().classname = _classname;
                 entry.get_mut().speed = _speed;
+                entry.get_mut().speed_confidence = _speed_confidence;
+                if let Some(acceleration) = _acceleration {
+                    entry.get_mut().min_acceleration = entry.get().min_acceleration.min(acceleration);
+                    entry.get_mut().max_acceleration = entry.get().max_acceleration.max(acceleration);
+                }
                 // If object crossed virtual line then we should not reset this flag
                 if !entry.get().crossed_virtual_line {
                     entry.get_mut().crossed_virtual_line = register_as_crossed;
+                    // Object is registered by virtual line, so crossing time is the registration time (e.g. for headway)
+                    if register_as_crossed {
+                        entry.get_mut().timestamp_registration = _timestamp;
+                        return !entry.get().counted_before;
+                    }
                 }
+                false
             }
             Vacant(entry) => {
                 self.current_statistics.last_time_registered = _relative_time;
                 entry.insert(ObjectInfo {
                     classname: _classname,
                     speed: _speed,
+                    speed_confidence: _speed_confidence,
+                    min_acceleration: _acceleration.unwrap_or(0.0).min(0.0),
+                    max_acceleration: _acceleration.unwrap_or(0.0).max(0.0),
                     crossed_virtual_line: register_as_crossed,
-                    timestamp_registration: _timestamp
+                    timestamp_registration: _timestamp,
+                    counted_before: false,
+                    heading: None,
+                    bbox_area: None,
                 });
+                !register_via_virtual_line || register_as_crossed
             }
         }
     }
-    pub fn reset_objects_registered(&mut self) {
-        self.objects_registered.clear();
+    // Objects which are not registered in the zone are ignored
+    pub fn update_object_heading(&mut self, object_id: &Uuid, heading: f32) {
+        if let Some(object_info) = self.objects_registered.get_mut(object_id) {
+            object_info.heading = Some(heading);
+        }
+    }
+    // Objects which are not registered in the zone are ignored
+    pub fn update_object_bbox_area(&mut self, object_id: &Uuid, bbox_area: f32) {
+        if let Some(object_info) = self.objects_registered.get_mut(object_id) {
+            object_info.bbox_area = Some(bbox_area);
+        }
+    }
+    // alive_objects - objects which are still tracked. If provided then they stay registered for the next period (marked as counted),
+    // so long dwellers (e.g. vehicles in a jam) are not counted again. Otherwise all registrations are cleared
+    pub fn reset_objects_registered(&mut self, alive_objects: Option<&HashSet<Uuid>>) {
+        if let Some(speed_lines) = self.speed_lines.as_mut() {
+            // Objects which have not been registered during the period are not expected to cross lines anymore
+            let registered = self.objects_registered.keys().cloned().collect::<HashSet<Uuid>>();
+            speed_lines.retain_objects(&registered);
+        }
+        let alive_objects = match alive_objects {
+            Some(alive_objects) => alive_objects,
+            None => {
+                self.objects_registered.clear();
+                return;
+            }
+        };
+        let register_via_virtual_line = self.virtual_line.is_some();
+        self.objects_registered.retain(|object_id, _| alive_objects.contains(object_id));
+        for (_, object_info) in self.objects_registered.iter_mut() {
+            // Object which has not crossed virtual line yet is not counted, so it still could be counted in the next period
+            if !register_via_virtual_line || object_info.crossed_virtual_line {
+                object_info.counted_before = true;
+            }
+        }
     }
     pub fn reset_statistics(&mut self, _period_start: DateTime<Utc>, _period_end: DateTime<Utc>) {
         self.statistics.period_start = _period_start;
@@ -306,24 +568,44 @@ impl Zone {
         for (_, class_stats) in self.statistics.vehicles_data.iter_mut() {
             class_stats.sum_intensity = 0;
             class_stats.avg_speed = -1.0;
+            class_stats.avg_speed_confidence = 0.0;
+            class_stats.over_speed_count = 0;
+            class_stats.present_intensity = 0;
+            class_stats.crossed_intensity = 0;
+            class_stats.avg_bbox_area = -1.0;
+            class_stats.bbox_area_samples = 0;
+            class_stats.avg_headway = 0.0;
         }
         self.statistics.traffic_flow_parameters = TrafficFlowParameters::default()
     }
-    pub fn update_statistics(&mut self, _period_start: DateTime<Utc>, _period_end: DateTime<Utc>) {
+    // _min_moving_speed - objects with lower speed (km/h) are considered to be stationary: they are counted in intensity, but not in average speed
+    // _min_samples_for_speed - average speed is reported as undefined (-1) when fewer vehicles with defined speed have been registered in the period.
+    // It trades timeliness for reliability: short periods with a single vehicle do not produce single-sample averages
+    // _alive_objects - objects which are still tracked (see reset_objects_registered()). None means clearing all registrations
+    pub fn update_statistics(&mut self, _period_start: DateTime<Utc>, _period_end: DateTime<Utc>, _min_moving_speed: f32, _min_samples_for_speed: u32, _alive_objects: Option<&HashSet<Uuid>>) {
         self.reset_statistics(_period_start, _period_end);
         let register_via_virtual_line = self.virtual_line.is_some();
-        // Are there better ways to sort hashmap (or btreemap) and extract just timestamps? 
-        let headway_avg = if self.objects_registered.len() > 1 { // For headway calculation two vehicles are needed at least
-            let mut sorted_by_time = self.objects_registered.values().map(|object_info| object_info.timestamp_registration).collect::<Vec<f32>>();
-            sorted_by_time.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            sorted_by_time.windows(2).map(|w| w[1] - w[0]).sum::<f32>() / (sorted_by_time.len() as f32 - 1.0)
-        } else {
-            0.0
-        };
+        let headway_avg = average_headway(self.objects_registered.values().filter(|object_info| !object_info.counted_before).map(|object_info| object_info.timestamp_registration).collect());
+        let mut class_timestamps: HashMap<String, Vec<f32>> = HashMap::new();
+        for object_info in self.objects_registered.values().filter(|object_info| !object_info.counted_before) {
+            class_timestamps.entry(object_info.classname.clone()).or_insert_with(Vec::new).push(object_info.timestamp_registration);
+        }
         let mut total_avg_speed = 0.0;
         let mut total_sum_intensity = 0;
         let mut total_defined_sum_intensity: u32 = 0;
+        let mut stationary_count: u32 = 0;
+        let mut harsh_braking_events: u32 = 0;
+        let mut hard_acceleration_events: u32 = 0;
+        let mut over_speed_count: u32 = 0;
+        let mut total_present_intensity: u32 = 0;
+        let mut total_crossed_intensity: u32 = 0;
+        let mut headings: Vec<f32> = vec![];
+        let mut speed_histogram = vec![0; self.speed_histogram_bins.bins_count()];
+        let mut speed_samples: Vec<f32> = Vec::with_capacity(self.objects_registered.len().min(MAX_SPEED_SAMPLES));
         for (_, object_info) in self.objects_registered.iter() {
+            if object_info.counted_before {
+                continue;
+            }
             let classname = object_info.classname.to_owned();
             let speed = object_info.speed;
             let vehicle_type_parameters = match self.statistics.vehicles_data.entry(classname.clone()) {
@@ -333,20 +615,67 @@ impl Zone {
                     new_params
                 }
             };
+            // Objects merely present in the polygon and objects crossed the virtual line are reported separately,
+            // since sum_intensity means either of them depending on whether zone has virtual line
+            vehicle_type_parameters.present_intensity += 1;
+            total_present_intensity += 1;
+            if object_info.crossed_virtual_line {
+                vehicle_type_parameters.crossed_intensity += 1;
+                total_crossed_intensity += 1;
+            }
             if register_via_virtual_line && !object_info.crossed_virtual_line {
                 continue;
             }
             vehicle_type_parameters.sum_intensity += 1;
             total_sum_intensity += 1;
+            if let Some(heading) = object_info.heading {
+                headings.push(heading);
+            }
+            if let Some(bbox_area) = object_info.bbox_area {
+                vehicle_type_parameters.bbox_area_samples += 1;
+                if vehicle_type_parameters.bbox_area_samples < 2 {
+                    vehicle_type_parameters.avg_bbox_area = bbox_area;
+                } else {
+                    vehicle_type_parameters.avg_bbox_area = vehicle_type_parameters.avg_bbox_area + (bbox_area - vehicle_type_parameters.avg_bbox_area) / (vehicle_type_parameters.bbox_area_samples as f32);
+                }
+            }
+            if object_info.min_acceleration < HARSH_BRAKING_THRESHOLD {
+                harsh_braking_events += 1;
+            }
+            if object_info.max_acceleration > HARD_ACCELERATION_THRESHOLD {
+                hard_acceleration_events += 1;
+            }
             // Ignore undefined vehicle speed (but keep it as counted in intensity parameter)
             if speed < 0.0 {
                 continue
             }
+            if let Some(speed_limit) = self.speed_limits.get(&classname) {
+                if speed > *speed_limit {
+                    vehicle_type_parameters.over_speed_count += 1;
+                    over_speed_count += 1;
+                }
+            }
+            // Same for stopped or parked vehicles: near-zero speeds should not pull down the average
+            if speed < _min_moving_speed {
+                stationary_count += 1;
+                continue
+            }
             vehicle_type_parameters.defined_sum_intensity += 1;
             total_defined_sum_intensity += 1;
+            if let Some(bin_idx) = self.speed_histogram_bins.bin_index(speed) {
+                speed_histogram[bin_idx] += 1;
+            }
+            if speed_samples.len() < MAX_SPEED_SAMPLES {
+                speed_samples.push(speed);
+            }
             // Iterative average calculation
             // https://math.stackexchange.com/questions/106700/incremental-averageing
             // Start calculate average speed calculation only when there are two vehicles atleast
+            if vehicle_type_parameters.defined_sum_intensity < 2 {
+                vehicle_type_parameters.avg_speed_confidence = object_info.speed_confidence;
+            } else {
+                vehicle_type_parameters.avg_speed_confidence = vehicle_type_parameters.avg_speed_confidence + (object_info.speed_confidence - vehicle_type_parameters.avg_speed_confidence) / (vehicle_type_parameters.defined_sum_intensity as f32);
+            }
             if total_defined_sum_intensity < 2 {
                 vehicle_type_parameters.avg_speed = speed;
                 total_avg_speed = speed;
@@ -355,7 +684,13 @@ impl Zone {
             vehicle_type_parameters.avg_speed = vehicle_type_parameters.avg_speed + (speed - vehicle_type_parameters.avg_speed) / (vehicle_type_parameters.defined_sum_intensity as f32);
             total_avg_speed = total_avg_speed + (speed - total_avg_speed) / (total_defined_sum_intensity as f32);
         }
-        self.statistics.traffic_flow_parameters.avg_speed = if total_sum_intensity > 0 {
+        // Not enough samples for the reliable average: mark speed as undefined
+        for (_, vehicle_type_parameters) in self.statistics.vehicles_data.iter_mut() {
+            if vehicle_type_parameters.defined_sum_intensity < _min_samples_for_speed {
+                vehicle_type_parameters.avg_speed = -1.0;
+            }
+        }
+        self.statistics.traffic_flow_parameters.avg_speed = if total_sum_intensity > 0 && total_defined_sum_intensity >= _min_samples_for_speed {
             // Could have non-estimated speed for some vehicle classes. Therefore it is needed to filter those
             let speeds = self.statistics.vehicles_data.iter().filter(|vt_param| vt_param.1.avg_speed > 0.0).map(|v| v.1.avg_speed).collect::<Vec<f32>>();
             if speeds.is_empty() {
@@ -368,16 +703,50 @@ impl Zone {
         };
         self.statistics.traffic_flow_parameters.sum_intensity = total_sum_intensity;
         self.statistics.traffic_flow_parameters.defined_sum_intensity = total_defined_sum_intensity;
-        self.statistics.traffic_flow_parameters.avg_headway = headway_avg;
+        // Pedestrians cross in groups, so headway is meaningless for them
+        self.statistics.traffic_flow_parameters.avg_headway = match self.kind {
+            ZoneKind::Vehicle => headway_avg,
+            ZoneKind::Pedestrian => -1.0,
+        };
+        for (classname, timestamps) in class_timestamps {
+            if let Some(vehicle_type_parameters) = self.statistics.vehicles_data.get_mut(&classname) {
+                vehicle_type_parameters.avg_headway = match self.kind {
+                    ZoneKind::Vehicle => average_headway(timestamps),
+                    ZoneKind::Pedestrian => -1.0,
+                };
+            }
+        }
+        self.statistics.traffic_flow_parameters.speed_histogram = speed_histogram;
+        self.statistics.traffic_flow_parameters.speed_histogram_bin_width = self.speed_histogram_bins.bin_width;
+        speed_samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        self.statistics.traffic_flow_parameters.speed_p50 = speed_percentile(&speed_samples, 50.0);
+        self.statistics.traffic_flow_parameters.speed_p85 = speed_percentile(&speed_samples, 85.0);
+        self.statistics.traffic_flow_parameters.speed_p95 = speed_percentile(&speed_samples, 95.0);
+        self.statistics.traffic_flow_parameters.stationary_count = stationary_count;
+        self.statistics.traffic_flow_parameters.harsh_braking_events = harsh_braking_events;
+        self.statistics.traffic_flow_parameters.hard_acceleration_events = hard_acceleration_events;
+        self.statistics.traffic_flow_parameters.over_speed_count = over_speed_count;
+        self.statistics.traffic_flow_parameters.present_intensity = total_present_intensity;
+        self.statistics.traffic_flow_parameters.crossed_intensity = total_crossed_intensity;
+        self.statistics.traffic_flow_parameters.dominant_heading = circular_mean_degrees(&headings).unwrap_or(-1.0);
+        let density = self.estimate_density();
+        self.statistics.traffic_flow_parameters.avg_density = density;
+        self.statistics.traffic_flow_parameters.congestion_level = self.congestion_thresholds.classify(density);
+        if self.occupancy_samples > 0 {
+            self.statistics.traffic_flow_parameters.avg_occupancy = self.occupancy_sum as f32 / self.occupancy_samples as f32;
+        }
+        self.occupancy_sum = 0;
+        self.occupancy_samples = 0;
         // self.statistics.traffic_flow_parameters.avg_speed = self.statistics.vehicles_data.values().map(|vt_param| vt_param.sum_intensity).sum::<u32>();
-        self.reset_objects_registered();
+        self.reset_objects_registered(_alive_objects);
     }
     // Checks if given polygon contains a point
     // Code has been taken from: https://github.com/LdDl/odam/blob/master/virtual_polygons.go#L180
     pub fn contains_point(&self, x: f32, y: f32) -> bool {
         let n = self.pixel_coordinates.len();
-        // @todo: math.maxInt could lead to overflow obviously. Need good workaround. PRs are welcome
-        let extreme_point = vec![99999.0, y as f32];
+        // Extreme point should be always beyond the polygon, so take the rightmost polygon's X
+        let max_x = self.pixel_coordinates.iter().fold(f32::MIN, |acc, pt| acc.max(pt.x));
+        let extreme_point = vec![max_x + 1.0, y as f32];
         let mut intersections_cnt = 0;
         let mut previous = 0;
         loop {
@@ -451,6 +820,9 @@ impl Zone {
         }
         false
     }
+    pub fn get_spatial_converter(&self) -> &SpatialConverter {
+        &self.spatial_converter
+    }
     pub fn project_to_skeleton(&self, x: f32, y: f32) -> (f32, f32) {
         self.skeleton.project(x, y)
     }
@@ -460,8 +832,11 @@ impl Zone {
     pub fn crossed_virtual_line(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> bool {
         match &self.virtual_line {
             Some(vl) => {
-                let is_left_before = vl.is_left(x1, y1);
-                let is_left_after = vl.is_left(x2, y2);
+                // Points within the deadband of the line are not on any side, so they can't make a crossing
+                let (is_left_before, is_left_after) = match (vl.clear_side(x1, y1), vl.clear_side(x2, y2)) {
+                    (Some(is_left_before), Some(is_left_after)) => (is_left_before, is_left_after),
+                    _ => return false,
+                };
                 if vl.direction == VirtualLineDirection::LeftToRightTopToBottom {
                     if is_left_before && !is_left_after {
                         return true;
@@ -478,6 +853,37 @@ impl Zone {
             }
         }
     }
+    // Same as crossed_virtual_line, but returns fraction t in [0; 1] along segment (x1, y1) -> (x2, y2)
+    // where crossing happened. It helps to interpolate crossing time between two frames
+    pub fn crossed_virtual_line_at(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Option<f32> {
+        if !self.crossed_virtual_line(x1, y1, x2, y2) {
+            return None;
+        }
+        match &self.virtual_line {
+            Some(vl) => vl.intersection_fraction(x1, y1, x2, y2),
+            None => None,
+        }
+    }
+    // Same as crossed_virtual_line_at for the last track point (x1, y1), but respects deadband of the virtual line:
+    // object's previous side is taken from the latest earlier track point outside of the deadband
+    // previous_points - track points before the last one, from the latest to the oldest
+    // Fraction is along the last segment. It is zero (the last point) when the line has been crossed while object was within the deadband
+    pub fn crossed_virtual_line_on_track<I>(&self, x1: f32, y1: f32, previous_points: I) -> Option<f32>
+    where
+        I: Iterator<Item = (f32, f32)>,
+    {
+        let vl = self.virtual_line.as_ref()?;
+        let mut previous_points = previous_points;
+        let (x2, y2) = previous_points.next()?;
+        if vl.margin_px <= 0.0 {
+            return self.crossed_virtual_line_at(x1, y1, x2, y2);
+        }
+        let origin = std::iter::once((x2, y2)).chain(previous_points).find(|pt| vl.clear_side(pt.0, pt.1).is_some())?;
+        if !self.crossed_virtual_line(x1, y1, origin.0, origin.1) {
+            return None;
+        }
+        Some(vl.intersection_fraction(x1, y1, x2, y2).unwrap_or(0.0))
+    }
     pub fn get_virtual_line(&self) -> Option<VirtualLine> {
         match &self.virtual_line {
             Some(vl) => Some(vl.clone()),
@@ -487,6 +893,91 @@ impl Zone {
     pub fn set_virtual_line(&mut self, _virtual_line: VirtualLine) {
         self.virtual_line = Some(_virtual_line);
     }
+    // Resolves frame-relative geometry (e.g. normalized virtual line) to pixels
+    pub fn set_frame_size(&mut self, frame_width: f32, frame_height: f32) {
+        if let Some(vl) = self.virtual_line.as_mut() {
+            vl.resolve(frame_width, frame_height);
+        }
+    }
+    pub fn get_speed_lines(&self) -> Option<&SpeedLines> {
+        self.speed_lines.as_ref()
+    }
+    pub fn set_speed_lines(&mut self, _speed_lines: SpeedLines) {
+        self.speed_lines = Some(_speed_lines);
+    }
+    // Confidence of the speed measured via speed lines. None is returned when zone has no speed lines
+    pub fn speed_lines_confidence(&self) -> Option<f32> {
+        self.speed_lines.as_ref().map(|sl| sl.confidence())
+    }
+    // Estimates speed (km/h) of the object via speed lines. Arguments are the same as for SpeedLines::track()
+    // None is returned when zone has no speed lines, "-1" - when speed has not been estimated yet
+    pub fn track_speed_lines(&mut self, object_id: Uuid, x1: f32, y1: f32, t1: f32, x2: f32, y2: f32, t2: f32) -> Option<f32> {
+        match self.speed_lines.as_mut() {
+            Some(sl) => Some(sl.track(object_id, x1, y1, t1, x2, y2, t2)),
+            None => None,
+        }
+    }
+    // Generates virtual line perpendicular to the skeleton at its midpoint and spanning the whole polygon width
+    // None is returned when polygon is degenerate (e.g. not enough points)
+    pub fn auto_virtual_line(&self) -> Option<VirtualLine> {
+        auto_virtual_line_for(&self.pixel_coordinates, self.get_color())
+    }
+    pub fn get_count_mode(&self) -> CountMode {
+        self.count_mode
+    }
+    pub fn set_count_mode(&mut self, _count_mode: CountMode) {
+        self.count_mode = _count_mode;
+    }
+    pub fn get_congestion_thresholds(&self) -> CongestionThresholds {
+        self.congestion_thresholds
+    }
+    pub fn set_congestion_thresholds(&mut self, _thresholds: CongestionThresholds) {
+        self.congestion_thresholds = _thresholds;
+    }
+    pub fn get_speed_histogram_bins(&self) -> SpeedHistogramBins {
+        self.speed_histogram_bins
+    }
+    pub fn set_speed_histogram_bins(&mut self, _bins: SpeedHistogramBins) {
+        self.speed_histogram_bins = _bins;
+    }
+    pub fn get_crossing_cooldown(&self) -> Option<&CrossingCooldown> {
+        self.crossing_cooldown.as_ref()
+    }
+    pub fn set_crossing_cooldown(&mut self, _cooldown: CrossingCooldown) {
+        self.crossing_cooldown = Some(_cooldown);
+    }
+    // Should be called once per frame after occupancy has been calculated
+    pub fn accumulate_occupancy(&mut self) {
+        self.occupancy_sum += self.current_statistics.occupancy as u64;
+        self.occupancy_samples += 1;
+    }
+    // Average number of vehicles per kilometer based on occupancy and skeleton length
+    // Returns -1 if there is no spatial calibration or no occupancy samples
+    fn estimate_density(&self) -> f32 {
+        if self.occupancy_samples == 0 || !(self.skeleton.length_meters > 0.0) {
+            return -1.0;
+        }
+        let avg_occupancy = self.occupancy_sum as f32 / self.occupancy_samples as f32;
+        avg_occupancy / (self.skeleton.length_meters / 1000.0)
+    }
+    // Current number of vehicles per kilometer (based on the current occupancy). Returns -1 if there is no spatial calibration
+    pub fn current_density(&self) -> f32 {
+        if !(self.skeleton.length_meters > 0.0) {
+            return -1.0;
+        }
+        self.current_statistics.occupancy as f32 / (self.skeleton.length_meters / 1000.0)
+    }
+    // Fills polygon with zone's color. Opacity grows with current density up to the 'jammed' congestion threshold
+    pub fn draw_occupancy_shade(&self, img: &mut Mat) {
+        let alpha = shade_alpha(self.current_density(), self.current_statistics.occupancy, self.congestion_thresholds.jammed);
+        let points = self.pixel_coordinates.iter().map(|pt| Point2i::new(pt.x as i32, pt.y as i32)).collect::<Vec<Point2i>>();
+        match blend_polygon(img, &points, self.color, alpha) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't shade polygon due the error: {:?}", err);
+            }
+        };
+    }
     pub fn draw_geom(&self, img: &mut Mat) {
         // @todo: proper error handling
         for i in 1..self.pixel_coordinates.len() {
@@ -531,7 +1022,48 @@ impl Zone {
             None => {}
         }
     }
-    pub fn draw_current_intensity(&self, img: &mut Mat) {
+    // Debug visualization: marks every given point inside of the zone by its side relative to the virtual line
+    // (see VirtualLine::is_left). Green "L" is the left (or upper) side, red "R" is the right (or lower) side.
+    // Crossing is registered only when side flips in the configured direction, so miscalibrated line becomes obvious
+    pub fn draw_line_sides(&self, img: &mut Mat, points: &[(f32, f32)], text_style: &TextStyle) {
+        let vl = match &self.virtual_line {
+            Some(vl) => vl,
+            None => return,
+        };
+        for (x, y) in points.iter() {
+            if !self.contains_point(*x, *y) {
+                continue;
+            }
+            let is_left = vl.is_left(*x, *y);
+            let (color, label) = if is_left {
+                (Scalar::from((0.0, 255.0, 0.0)), "L")
+            } else {
+                (Scalar::from((0.0, 0.0, 255.0)), "R")
+            };
+            let center = Point2i::new(*x as i32, *y as i32);
+            match circle(img, center, 6, color, FILLED, LINE_8, 0) {
+                Ok(_) => {},
+                Err(err) => {
+                    println!("Can't draw line side marker due the error: {:?}", err);
+                }
+            };
+            match put_text(img, label, Point2i::new(center.x + 8, center.y + text_style.offset(5)), FONT_HERSHEY_SIMPLEX, text_style.scale, color, text_style.thickness, LINE_8, false) {
+                Ok(_) => {},
+                Err(err) => {
+                    println!("Can't draw line side label due the error: {:?}", err);
+                }
+            };
+        }
+    }
+    pub fn draw_speed_lines(&self, img: &mut Mat) {
+        match &self.speed_lines {
+            Some(sl) => {
+                sl.draw_on_mat(img);
+            }
+            None => {}
+        }
+    }
+    pub fn draw_current_intensity(&self, img: &mut Mat, text_style: &TextStyle) {
         let register_via_virtual_line = match &self.virtual_line {
             Some(_) => true,
             None => false,
@@ -540,9 +1072,9 @@ impl Zone {
             true => self
                 .objects_registered
                 .iter()
-                .filter(|x| x.1.crossed_virtual_line == true)
+                .filter(|x| x.1.crossed_virtual_line == true && !x.1.counted_before)
                 .count(),
-            false => self.objects_registered.len(),
+            false => self.objects_registered.values().filter(|x| !x.counted_before).count(),
         };
         let anchor = Point2i::new(
             self.pixel_coordinates[0].x as i32 + 20,
@@ -553,9 +1085,9 @@ impl Zone {
             &current_intensity.to_string(),
             anchor,
             FONT_HERSHEY_SIMPLEX,
-            0.5,
+            text_style.scale,
             Scalar::from((0.0, 0.0, 0.0)),
-            2,
+            text_style.thickness,
             LINE_8,
             false,
         ) {
@@ -565,20 +1097,137 @@ impl Zone {
             }
         };
     }
-    pub fn to_geojson(&self) -> ZoneFeature {
+    // Converts pixel coordinates to lon/lat for 'wgs84' CRS or to local meters for 'local_meters' CRS
+    // None is returned when zone has no spatial calibration
+    pub fn pixel_to_world(&self, x: f32, y: f32) -> Option<[f32; 2]> {
+        if self.spatial_converter.get_matrix().is_none() {
+            return None;
+        }
+        let (mx, my) = self.spatial_converter.transform_to_epsg(x, y);
+        match self.crs {
+            SpatialCRS::WGS84 => {
+                let (lon, lat) = meters_to_lonlat(mx, my);
+                Some([lon, lat])
+            },
+            SpatialCRS::LocalMeters => Some([mx, my]),
+        }
+    }
+    // Distance (meters) on the ground plane between two points in pixels. None is returned when zone has no spatial calibration
+    pub fn ground_distance_meters(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Option<f32> {
+        if self.spatial_converter.get_matrix().is_none() {
+            return None;
+        }
+        let (mx1, my1) = self.spatial_converter.transform_to_epsg(x1, y1);
+        let (mx2, my2) = self.spatial_converter.transform_to_epsg(x2, y2);
+        match self.crs {
+            // Web Mercator meters are stretched away from the equator, so distance is measured on the sphere
+            SpatialCRS::WGS84 => {
+                let (lon1, lat1) = meters_to_lonlat(mx1, my1);
+                let (lon2, lat2) = meters_to_lonlat(mx2, my2);
+                Some(haversine(lon1, lat1, lon2, lat2) * 1000.0)
+            },
+            SpatialCRS::LocalMeters => Some(((mx2 - mx1).powi(2) + (my2 - my1).powi(2)).sqrt()),
+        }
+    }
+    // Heading (degrees) of the movement from (x1, y1) to (x2, y2) in pixels. It is geographic bearing when zone has spatial calibration,
+    // otherwise it is measured in the image (0 is "up"). None is returned when there is no movement
+    pub fn heading_between(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Option<f32> {
+        if self.spatial_converter.get_matrix().is_none() {
+            return pixel_heading(x2 - x1, y2 - y1);
+        }
+        let (mx1, my1) = self.spatial_converter.transform_to_epsg(x1, y1);
+        let (mx2, my2) = self.spatial_converter.transform_to_epsg(x2, y2);
+        geographic_bearing(mx2 - mx1, my2 - my1)
+    }
+    // Returns unit vector (in pixels) pointing to the north at the zone's center
+    // None is returned when zone has no spatial calibration
+    pub fn get_north_direction(&self) -> Option<(f32, f32)> {
+        let inverse = self.spatial_converter.get_inverse_matrix()?;
+        if self.pixel_coordinates.is_empty() {
+            return None;
+        }
+        let n = self.pixel_coordinates.len() as f32;
+        let cx = self.pixel_coordinates.iter().map(|pt| pt.x).sum::<f32>() / n;
+        let cy = self.pixel_coordinates.iter().map(|pt| pt.y).sum::<f32>() / n;
+        // Northing grows along Y axis both for EPSG:3857 and for local metric CRS
+        let (mx, my) = self.spatial_converter.transform_to_epsg(cx, cy);
+        let (nx, ny) = apply_homography(&inverse, mx, my + 1.0);
+        let (dx, dy) = (nx - cx, ny - cy);
+        let length = (dx * dx + dy * dy).sqrt();
+        if !length.is_finite() || length == 0.0 {
+            return None;
+        }
+        Some((dx / length, dy / length))
+    }
+    pub fn draw_scale_bar(&self, img: &mut Mat, text_style: &TextStyle) {
+        if self.pixel_coordinates.is_empty() {
+            return;
+        }
+        let anchor = Point2i::new(
+            self.pixel_coordinates[0].x as i32 + 20,
+            self.pixel_coordinates[0].y as i32 + 15,
+        );
+        draw_scale_bar(img, anchor, self.skeleton.pixels_per_meter, self.color, text_style);
+    }
+    pub fn draw_north_arrow(&self, img: &mut Mat, text_style: &TextStyle) {
+        let direction = match self.get_north_direction() {
+            Some(direction) => direction,
+            None => return,
+        };
+        let anchor = match self.get_pixel_centroid() {
+            Some(centroid) => Point2i::new(centroid.0 as i32, centroid.1 as i32),
+            None => return,
+        };
+        draw_north_arrow(img, anchor, direction, self.color, text_style);
+    }
+    // Average of the zone's vertices in pixels. None when zone has no pixel coordinates
+    pub fn get_pixel_centroid(&self) -> Option<(f32, f32)> {
+        if self.pixel_coordinates.is_empty() {
+            return None;
+        }
+        let n = self.pixel_coordinates.len() as f32;
+        Some((
+            self.pixel_coordinates.iter().map(|pt| pt.x).sum::<f32>() / n,
+            self.pixel_coordinates.iter().map(|pt| pt.y).sum::<f32>() / n,
+        ))
+    }
+    // Draws arrow along the skeleton in the configured travel direction (see direction_arrow_points())
+    pub fn draw_direction_arrow(&self, img: &mut Mat) {
+        let skeleton_line = self.skeleton.get_line();
+        let (start, end) = match direction_arrow_points(skeleton_line[0], skeleton_line[1], self.road_lane_direction) {
+            Some(points) => points,
+            None => return,
+        };
+        let start = Point2i::new(start.x as i32, start.y as i32);
+        let end = Point2i::new(end.x as i32, end.y as i32);
+        // Magenta (BGR): it should not be confused with zones' colors which are usually primary ones
+        let color = Scalar::from((255.0, 0.0, 255.0));
+        match arrowed_line(img, start, end, color, 3, LINE_8, 0, 0.2) {
+            Ok(_) => {},
+            Err(err) => {
+                println!("Can't draw direction arrow for zone '{}' due the error {:?}", self.id, err);
+            }
+        };
+    }
+    // coordinate_precision - number of decimal places of the spatial coordinates (see round_coordinate())
+    pub fn to_geojson(&self, coordinate_precision: u32) -> ZoneFeature {
         let mut euclidean: Vec<Vec<i32>> = Vec::new();
         for pt in self.pixel_coordinates.iter() {
             euclidean.push(vec![pt.x as i32, pt.y as i32]);
         }
         let mut geojson_poly = vec![];
         let mut poly_element = vec![];
-        for v in self.spatial_coordinates_epsg4326.iter() {
-            poly_element.push(vec![v.x, v.y]);
+        let spatial_coordinates = match self.crs {
+            SpatialCRS::WGS84 => &self.spatial_coordinates_epsg4326,
+            SpatialCRS::LocalMeters => &self.spatial_coordinates_epsg3857,
+        };
+        for v in spatial_coordinates.iter() {
+            poly_element.push(vec![round_coordinate(v.x, coordinate_precision), round_coordinate(v.y, coordinate_precision)]);
+        }
+        // Counting-only zones have no spatial coordinates
+        if let Some(first) = poly_element.first().cloned() {
+            poly_element.push(first);
         }
-        poly_element.push(vec![
-            self.spatial_coordinates_epsg4326[0].x,
-            self.spatial_coordinates_epsg4326[0].y,
-        ]);
         geojson_poly.push(poly_element);
         ZoneFeature {
             typ: "Feature".to_string(),
@@ -586,6 +1235,9 @@ impl Zone {
             properties: ZonePropertiesGeoJSON {
                 road_lane_num: self.road_lane_num,
                 road_lane_direction: self.road_lane_direction,
+                count_mode: self.count_mode.to_string(),
+                kind: self.kind.to_string(),
+                crs: self.crs.to_string(),
                 coordinates: euclidean,
                 color_rgb: [
                     self.color[2] as i16,
@@ -625,6 +1277,133 @@ fn find_skeleton_line(
     [a_b_center, c_d_center]
 }
 
+// Returns start and end of the travel direction arrow (middle half of the skeleton)
+// Skeleton goes from the first edge of the zone (points 0-1) to the second one (points 2-3):
+// direction "0" means travel from the first edge to the second one, "1" means the opposite
+// Other values are just identifiers (not orientations), so None is returned for them
+fn direction_arrow_points(a: Point2f, b: Point2f, direction: u8) -> Option<(Point2f, Point2f)> {
+    let (from, to) = match direction {
+        0 => (a, b),
+        1 => (b, a),
+        _ => return None,
+    };
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    Some((
+        Point2f::new(from.x + 0.25 * dx, from.y + 0.25 * dy),
+        Point2f::new(from.x + 0.75 * dx, from.y + 0.75 * dy),
+    ))
+}
+
+// Same as Zone::auto_virtual_line(), but for the given polygon. It could be used to check new geometry before applying it
+pub fn auto_virtual_line_for(pixel_coordinates: &Vec<Point2f>, color: [i16; 3]) -> Option<VirtualLine> {
+    let n = pixel_coordinates.len();
+    if n < 4 {
+        return None;
+    }
+    let skeleton_line = find_skeleton_line(pixel_coordinates, 0, 2);
+    let (dx, dy) = (skeleton_line[1].x - skeleton_line[0].x, skeleton_line[1].y - skeleton_line[0].y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return None;
+    }
+    let mid = Point2f::new((skeleton_line[0].x + skeleton_line[1].x) / 2.0, (skeleton_line[0].y + skeleton_line[1].y) / 2.0);
+    // Unit normal to the skeleton
+    let (nx, ny) = (-dy / length, dx / length);
+    // Find the farthest intersections of the normal with polygon's edges on both sides of the midpoint
+    let mut s_min: Option<f32> = None;
+    let mut s_max: Option<f32> = None;
+    for i in 0..n {
+        let p = pixel_coordinates[i];
+        let q = pixel_coordinates[(i + 1) % n];
+        let (ex, ey) = (q.x - p.x, q.y - p.y);
+        let denominator = nx * ey - ny * ex;
+        if denominator == 0.0 {
+            continue;
+        }
+        let (wx, wy) = (p.x - mid.x, p.y - mid.y);
+        let s = (wx * ey - wy * ex) / denominator;
+        let u = (wx * ny - wy * nx) / denominator;
+        if u < 0.0 || u > 1.0 {
+            continue;
+        }
+        if s <= 0.0 && s_min.map_or(true, |v| s < v) {
+            s_min = Some(s);
+        }
+        if s >= 0.0 && s_max.map_or(true, |v| s > v) {
+            s_max = Some(s);
+        }
+    }
+    let (s_min, s_max) = (s_min?, s_max?);
+    if s_max - s_min <= 0.0 {
+        return None;
+    }
+    let a = Point2f::new(mid.x + s_min * nx, mid.y + s_min * ny);
+    let b = Point2f::new(mid.x + s_max * nx, mid.y + s_max * ny);
+    let mut vline = VirtualLine::new_from_cv(a, b, VirtualLineDirection::default());
+    vline.set_color_rgb(color[0], color[1], color[2]);
+    Some(vline)
+}
+
+// Picks the zone (identifier) which owns the point when zones overlap: the highest priority wins, ties are resolved by the smallest identifier
+// Only zones in 'polygon' count mode are considered, since 'line' zones do not use polygon membership
+// candidates - identifiers of the zones to be checked (e.g. see ZoneGrid::get_candidate_zones)
+// Zones should not be locked by the caller
+pub fn find_zone_for_point(zones: &HashMap<String, Mutex<Zone>>, candidates: &[String], x: f32, y: f32) -> Option<String> {
+    find_zone_for_point_by(zones, candidates, x, y, |zone, x, y| zone.contains_point(x, y))
+}
+
+// Same as find_zone_for_point(), but point-in-polygon check is provided by the caller (e.g. to count the checks)
+pub fn find_zone_for_point_by<F: FnMut(&Zone, f32, f32) -> bool>(zones: &HashMap<String, Mutex<Zone>>, candidates: &[String], x: f32, y: f32, mut contains: F) -> Option<String> {
+    let mut best: Option<(i32, String)> = None;
+    for zone_guarded in candidates.iter().filter_map(|zone_id| zones.get(zone_id)) {
+        let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+        if zone.get_count_mode() != CountMode::Polygon || !contains(&zone, x, y) {
+            continue;
+        }
+        let is_better = match &best {
+            Some((priority, id)) => zone.priority > *priority || (zone.priority == *priority && zone.id < *id),
+            None => true,
+        };
+        if is_better {
+            best = Some((zone.priority, zone.get_id()));
+        }
+        drop(zone);
+    }
+    best.map(|(_, id)| id)
+}
+
+// Average time gap (seconds) between consecutive registrations
+// NaN timestamps are ignored. Simultaneous registrations (zero gap) are excluded from the average: such vehicles
+// are neighbours (e.g. side by side in the wide lane), not leader and follower
+// Returns 0 when there are no valid gaps
+fn average_headway(mut timestamps: Vec<f32>) -> f32 {
+    timestamps.retain(|timestamp| !timestamp.is_nan());
+    timestamps.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let gaps = timestamps.windows(2).map(|w| w[1] - w[0]).filter(|gap| *gap > 0.0).collect::<Vec<f32>>();
+    if gaps.is_empty() {
+        return 0.0;
+    }
+    gaps.iter().sum::<f32>() / gaps.len() as f32
+}
+
+// Percentile (linear interpolation between the closest ranks) of the sorted speeds
+// Returns -1 when there are no speeds
+fn speed_percentile(sorted_speeds: &[f32], percentile: f32) -> f32 {
+    if sorted_speeds.is_empty() {
+        return -1.0;
+    }
+    let rank = (percentile / 100.0).max(0.0).min(1.0) * (sorted_speeds.len() - 1) as f32;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted_speeds[lower] + (sorted_speeds[upper] - sorted_speeds[lower]) * (rank - lower as f32)
+}
+
+// Applies perspective transform matrix to the point
+fn apply_homography(m: &[[f32; 3]; 3], x: f32, y: f32) -> (f32, f32) {
+    let scale = m[2][0] * x + m[2][1] * y + m[2][2];
+    ((m[0][0] * x + m[0][1] * y + m[0][2]) / scale, (m[1][0] * x + m[1][1] * y + m[1][2]) / scale)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -662,6 +1441,21 @@ mod tests {
         }
     }
     #[test]
+    fn test_contains_point_large_coordinates() {
+        // Polygon is placed around X=100000, so hardcoded extreme point would be inside of it
+        let polygon = Zone::default_from_cv(vec![
+            Point2f::new(99990.0, 100.0),
+            Point2f::new(100010.0, 100.0),
+            Point2f::new(100010.0, 120.0),
+            Point2f::new(99990.0, 120.0),
+        ]);
+        assert_eq!(polygon.contains_point(100005.0, 110.0), true);
+        assert_eq!(polygon.contains_point(99995.0, 110.0), true);
+        assert_eq!(polygon.contains_point(100020.0, 110.0), false);
+        assert_eq!(polygon.contains_point(99980.0, 110.0), false);
+        assert_eq!(polygon.contains_point(100005.0, 130.0), false);
+    }
+    #[test]
     fn test_object_entered_cv() {
         let polygon = Zone::default_from_cv(vec![
             Point2f::new(23.0, 15.0),
@@ -690,6 +1484,35 @@ mod tests {
         assert_eq!(entered, false);
     }
     #[test]
+    fn test_virtual_line_deadband() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        // Centroid is jittering around the line before it really crosses it
+        let track = [(50.0, 40.0), (50.0, 49.0), (50.0, 51.0), (50.0, 49.0), (50.0, 51.0), (50.0, 49.0), (50.0, 60.0), (50.0, 61.0)];
+        let count_crossings = |zone: &Zone| -> Vec<(usize, f32)> {
+            (1..track.len())
+                .filter_map(|i| zone.crossed_virtual_line_on_track(track[i].0, track[i].1, track[..i].iter().rev().cloned()).map(|t| (i, t)))
+                .collect()
+        };
+        let mut vl = VirtualLine::new_from([[0, 50], [100, 50]], VirtualLineDirection::LeftToRightTopToBottom);
+        polygon.set_virtual_line(vl.clone());
+        assert_eq!(count_crossings(&polygon).iter().map(|(i, _)| *i).collect::<Vec<usize>>(), vec![2, 4, 6]);
+        vl.set_margin_px(2.0);
+        polygon.set_virtual_line(vl);
+        let crossings = count_crossings(&polygon);
+        assert_eq!(crossings.len(), 1);
+        // Crossing is registered once the object is clearly on the other side
+        assert_eq!(crossings[0].0, 6);
+        assert!((crossings[0].1 - 10.0 / 11.0).abs() < 0.001);
+        // Object enters the deadband and leaves it on the same side
+        let back = [(50.0, 40.0), (50.0, 49.0), (50.0, 51.0), (50.0, 45.0)];
+        assert_eq!(polygon.crossed_virtual_line_on_track(back[3].0, back[3].1, back[..3].iter().rev().cloned()), None);
+    }
+    #[test]
     fn test_object_left_cv() {
         let polygon = Zone::default_from_cv(vec![
             Point2f::new(23.0, 15.0),
@@ -714,4 +1537,523 @@ mod tests {
         let left = polygon.object_left_cv(d_track_must_not_enter[0], d_track_must_not_enter[1]);
         assert_eq!(left, false);
     }
+    #[test]
+    fn test_local_meters_crs() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.set_crs(SpatialCRS::LocalMeters);
+        polygon.update_spatial_map_cv(vec![
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1004.0, 500.0),
+            Point2f::new(1004.0, 520.0),
+            Point2f::new(1000.0, 520.0),
+        ]);
+        // 100 pixels between sides' centers correspond to 20 meters
+        assert!((polygon.get_skeleton_ppm() - 5.0).abs() < 0.001);
+        assert_eq!(polygon.get_spatial_coordinates_epsg4326().len(), 0);
+        let projected = polygon.get_spatial_converter().transform_to_epsg(100.0, 100.0);
+        assert!((projected.0 - 1004.0).abs() < 0.01);
+        assert!((projected.1 - 520.0).abs() < 0.01);
+    }
+    #[test]
+    fn test_degenerate_calibration() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.set_crs(SpatialCRS::LocalMeters);
+        // Coincident spatial points give zero skeleton length in meters
+        polygon.update_spatial_map_cv(vec![
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1000.0, 500.0),
+        ]);
+        assert_eq!(polygon.get_skeleton_ppm(), -1.0);
+        let mut spatial_info = crate::lib::tracker::SpatialInfo::new(0.0, 50.0, 0.0, 50.0, 0.0);
+        spatial_info.update_avg(1.0, 50.0, 50.0, 50.0, 50.0, polygon.get_skeleton_ppm());
+        // Speed is unknown rather than zero
+        assert_eq!(spatial_info.speed, -1.0);
+        polygon.set_gsd(0.0);
+        assert_eq!(polygon.get_skeleton_ppm(), -1.0);
+    }
+    #[test]
+    fn test_north_direction() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        assert!(polygon.get_north_direction().is_none());
+        polygon.set_crs(SpatialCRS::LocalMeters);
+        // Northing grows together with pixel Y
+        polygon.update_spatial_map_cv(vec![
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1004.0, 500.0),
+            Point2f::new(1004.0, 520.0),
+            Point2f::new(1000.0, 520.0),
+        ]);
+        let direction = polygon.get_north_direction().unwrap();
+        assert!(direction.0.abs() < 0.001);
+        assert!((direction.1 - 1.0).abs() < 0.001);
+    }
+    #[test]
+    fn test_speed_histogram() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.set_speed_histogram_bins(SpeedHistogramBins { bin_width: 10.0, max_speed: 30.0 });
+        let speeds = vec![5.0, 12.0, 18.0, 45.0, -1.0];
+        for (i, speed) in speeds.iter().enumerate() {
+            polygon.register_or_update_object(Uuid::new_v4(), i as f32, i as f32, *speed, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        }
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // Undefined speed is not included, speed above max_speed goes to the last bin
+        assert_eq!(polygon.statistics.traffic_flow_parameters.speed_histogram, vec![1, 2, 1]);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 5);
+    }
+    #[test]
+    fn test_speed_percentiles() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        // Speeds 1..20 km/h in shuffled order plus undefined one
+        let mut speeds = (1..=20).rev().map(|speed| speed as f32).collect::<Vec<f32>>();
+        speeds.swap(3, 15);
+        speeds.push(-1.0);
+        for (i, speed) in speeds.iter().enumerate() {
+            polygon.register_or_update_object(Uuid::new_v4(), i as f32, i as f32, *speed, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        }
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        let flow = &polygon.statistics.traffic_flow_parameters;
+        assert!((flow.speed_p50 - 10.5).abs() < 0.001);
+        assert!((flow.speed_p85 - 17.15).abs() < 0.001);
+        assert!((flow.speed_p95 - 19.05).abs() < 0.001);
+        // Samples are reset with the period
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.speed_p85, -1.0);
+        assert_eq!(speed_percentile(&[42.0], 85.0), 42.0);
+    }
+    #[test]
+    fn test_stationary_objects() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        let speeds = vec![40.0, 60.0, 0.5, 1.5, -1.0];
+        for (i, speed) in speeds.iter().enumerate() {
+            polygon.register_or_update_object(Uuid::new_v4(), i as f32, i as f32, *speed, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        }
+        polygon.update_statistics(Utc::now(), Utc::now(), 3.0, 0, None);
+        let params = &polygon.statistics.traffic_flow_parameters;
+        // Stopped vehicles are still counted, but do not affect average speed
+        assert_eq!(params.sum_intensity, 5);
+        assert_eq!(params.defined_sum_intensity, 2);
+        assert_eq!(params.stationary_count, 2);
+        assert!((params.avg_speed - 50.0).abs() < 0.001);
+    }
+    #[test]
+    fn test_min_samples_for_speed() {
+        let new_polygon = || {
+            let mut polygon = Zone::default_from_cv(vec![
+                Point2f::new(0.0, 0.0),
+                Point2f::new(100.0, 0.0),
+                Point2f::new(100.0, 100.0),
+                Point2f::new(0.0, 100.0),
+            ]);
+            polygon.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+            polygon.register_or_update_object(Uuid::new_v4(), 2.0, 2.0, 60.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+            polygon.register_or_update_object(Uuid::new_v4(), 3.0, 3.0, 20.0, 1.0, None, "bus".to_string(), false, (50.0, 50.0));
+            polygon
+        };
+        // Single bus is not enough for the class average, but zone average is still defined by cars
+        let mut polygon = new_polygon();
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 2, None);
+        assert!((polygon.statistics.vehicles_data["car"].avg_speed - 50.0).abs() < 0.001);
+        assert_eq!(polygon.statistics.vehicles_data["bus"].avg_speed, -1.0);
+        assert_eq!(polygon.statistics.vehicles_data["bus"].sum_intensity, 1);
+        assert!((polygon.statistics.traffic_flow_parameters.avg_speed - 50.0).abs() < 0.001);
+        // Not enough samples in the whole period: speed is undefined, but vehicles are counted
+        let mut polygon = new_polygon();
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 4, None);
+        assert_eq!(polygon.statistics.vehicles_data["car"].avg_speed, -1.0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.avg_speed, -1.0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 3);
+        // No limit
+        let mut polygon = new_polygon();
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.vehicles_data["bus"].avg_speed, 20.0);
+    }
+    #[test]
+    fn test_dedup_across_periods() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        let (stuck_id, gone_id) = (Uuid::new_v4(), Uuid::new_v4());
+        polygon.register_or_update_object(stuck_id, 1.0, 1.0, 5.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.register_or_update_object(gone_id, 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        let alive = vec![stuck_id].into_iter().collect::<HashSet<Uuid>>();
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, Some(&alive));
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 2);
+        // Stuck vehicle is still in the zone during the next period, but it is not counted again
+        let newcomer_id = Uuid::new_v4();
+        polygon.register_or_update_object(stuck_id, 31.0, 31.0, 3.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.register_or_update_object(newcomer_id, 32.0, 32.0, 30.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, Some(&alive));
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 1);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.avg_speed, 30.0);
+        // Vehicle is not tracked anymore, so its registration is dropped at the end of the period
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, Some(&HashSet::new()));
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 0);
+        polygon.register_or_update_object(stuck_id, 91.0, 91.0, 3.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 1);
+    }
+    #[test]
+    fn test_over_speed_count() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.set_speed_limits(vec![("car".to_string(), 60.0), ("truck".to_string(), 40.0)].into_iter().collect());
+        let objects = vec![("car", 55.0), ("car", 72.0), ("car", 60.0), ("car", -1.0), ("truck", 45.0), ("bus", 90.0)];
+        for (i, (classname, speed)) in objects.iter().enumerate() {
+            polygon.register_or_update_object(Uuid::new_v4(), i as f32, i as f32, *speed, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+        }
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // Undefined speed and classes without limit are never counted as over-speeding
+        assert_eq!(polygon.statistics.vehicles_data["car"].over_speed_count, 1);
+        assert_eq!(polygon.statistics.vehicles_data["truck"].over_speed_count, 1);
+        assert_eq!(polygon.statistics.vehicles_data["bus"].over_speed_count, 0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.over_speed_count, 2);
+        // Counts are reset on the next period
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.vehicles_data["car"].over_speed_count, 0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.over_speed_count, 0);
+    }
+    #[test]
+    fn test_present_and_crossed_intensity() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 20.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // No virtual line: every object is just present
+        assert_eq!(polygon.statistics.vehicles_data["car"].present_intensity, 1);
+        assert_eq!(polygon.statistics.vehicles_data["car"].crossed_intensity, 0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 1);
+        polygon.set_virtual_line(VirtualLine::new_from([[0, 50], [100, 50]], VirtualLineDirection::default()));
+        polygon.register_or_update_object(Uuid::new_v4(), 2.0, 2.0, 40.0, 1.0, None, "car".to_string(), true, (20.0, 50.0));
+        polygon.register_or_update_object(Uuid::new_v4(), 2.0, 2.0, 40.0, 1.0, None, "car".to_string(), false, (80.0, 20.0));
+        polygon.register_or_update_object(Uuid::new_v4(), 2.0, 2.0, 30.0, 1.0, None, "truck".to_string(), false, (50.0, 20.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.vehicles_data["car"].present_intensity, 2);
+        assert_eq!(polygon.statistics.vehicles_data["car"].crossed_intensity, 1);
+        assert_eq!(polygon.statistics.vehicles_data["truck"].present_intensity, 1);
+        assert_eq!(polygon.statistics.vehicles_data["truck"].crossed_intensity, 0);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.present_intensity, 3);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.crossed_intensity, 1);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 1);
+    }
+    #[test]
+    fn test_dominant_heading() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        // No spatial calibration: heading is measured in the image, "up" is zero
+        assert!((polygon.heading_between(50.0, 50.0, 50.0, 40.0).unwrap() - 0.0).abs() < 0.001);
+        assert!(polygon.heading_between(50.0, 50.0, 50.0, 50.0).is_none());
+        let (first_id, second_id) = (Uuid::new_v4(), Uuid::new_v4());
+        polygon.register_or_update_object(first_id, 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.register_or_update_object(second_id, 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.update_object_heading(&first_id, 350.0);
+        polygon.update_object_heading(&second_id, 20.0);
+        // Unknown object is ignored
+        polygon.update_object_heading(&Uuid::new_v4(), 180.0);
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert!((polygon.statistics.traffic_flow_parameters.dominant_heading - 5.0).abs() < 0.01);
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.dominant_heading, -1.0);
+        // Northing grows together with pixel Y, so moving down the frame is moving north
+        polygon.set_crs(SpatialCRS::LocalMeters);
+        polygon.update_spatial_map_cv(vec![
+            Point2f::new(1000.0, 500.0),
+            Point2f::new(1004.0, 500.0),
+            Point2f::new(1004.0, 520.0),
+            Point2f::new(1000.0, 520.0),
+        ]);
+        let bearing = polygon.heading_between(50.0, 40.0, 50.0, 50.0).unwrap();
+        assert!(bearing < 0.01 || bearing > 359.99);
+    }
+    #[test]
+    fn test_avg_bbox_area() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        let objects = [("car", Some(2000.0)), ("car", Some(3000.0)), ("car", None), ("truck", Some(9000.0))];
+        for (classname, bbox_area) in objects.iter() {
+            let object_id = Uuid::new_v4();
+            polygon.register_or_update_object(object_id, 1.0, 1.0, 40.0, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+            if let Some(bbox_area) = bbox_area {
+                polygon.update_object_bbox_area(&object_id, *bbox_area);
+            }
+        }
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // Vehicle without known box area is counted, but it does not affect the average
+        assert_eq!(polygon.statistics.vehicles_data["car"].sum_intensity, 3);
+        assert!((polygon.statistics.vehicles_data["car"].avg_bbox_area - 2500.0).abs() < 0.001);
+        assert!((polygon.statistics.vehicles_data["truck"].avg_bbox_area - 9000.0).abs() < 0.001);
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.vehicles_data["car"].avg_bbox_area, -1.0);
+    }
+    #[test]
+    fn test_register_returns_counted() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        let object_id = Uuid::new_v4();
+        // Zone without virtual line counts object once it is registered
+        assert!(polygon.register_or_update_object(object_id, 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 20.0)));
+        assert!(!polygon.register_or_update_object(object_id, 2.0, 2.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 30.0)));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // Zone with virtual line counts object once it crosses the line
+        polygon.set_virtual_line(VirtualLine::new_from([[0, 50], [100, 50]], VirtualLineDirection::default()));
+        assert!(!polygon.register_or_update_object(object_id, 3.0, 3.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 40.0)));
+        assert!(polygon.register_or_update_object(object_id, 4.0, 4.0, 40.0, 1.0, None, "car".to_string(), true, (50.0, 50.0)));
+        assert!(!polygon.register_or_update_object(object_id, 5.0, 5.0, 40.0, 1.0, None, "car".to_string(), true, (50.0, 60.0)));
+        // Not allowed class is never counted
+        polygon.set_allowed_classes(Some(vec!["bus".to_string()].into_iter().collect()));
+        assert!(!polygon.register_or_update_object(Uuid::new_v4(), 6.0, 6.0, 40.0, 1.0, None, "car".to_string(), true, (50.0, 50.0)));
+    }
+    #[test]
+    fn test_crossing_cooldown() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        polygon.set_virtual_line(VirtualLine::new_from([[0, 50], [100, 50]], VirtualLineDirection::default()));
+        polygon.set_crossing_cooldown(CrossingCooldown::new(15.0, 1.0));
+        // Vehicle crosses the line, then it is lost and re-acquired with new identifier
+        polygon.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), true, (50.0, 50.0));
+        polygon.register_or_update_object(Uuid::new_v4(), 1.3, 1.3, 40.0, 1.0, None, "car".to_string(), true, (55.0, 50.0));
+        // Another vehicle next to it
+        polygon.register_or_update_object(Uuid::new_v4(), 1.3, 1.3, 40.0, 1.0, None, "car".to_string(), true, (80.0, 50.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.sum_intensity, 2);
+    }
+    #[test]
+    fn test_direction_arrow_points() {
+        let a = Point2f::new(100.0, 400.0);
+        let b = Point2f::new(100.0, 0.0);
+        let (start, end) = direction_arrow_points(a, b, 0).unwrap();
+        assert_eq!((start.y, end.y), (300.0, 100.0));
+        // Reversed lane
+        let (start, end) = direction_arrow_points(a, b, 1).unwrap();
+        assert_eq!((start.y, end.y), (100.0, 300.0));
+        assert!(direction_arrow_points(a, b, 42).is_none());
+    }
+    #[test]
+    fn test_find_zone_for_point() {
+        let mut low = Zone::default_from_cv(vec![Point2f::new(0.0, 100.0), Point2f::new(100.0, 100.0), Point2f::new(100.0, 0.0), Point2f::new(0.0, 0.0)]);
+        low.set_id("a_low".to_string());
+        let mut high = Zone::default_from_cv(vec![Point2f::new(50.0, 100.0), Point2f::new(150.0, 100.0), Point2f::new(150.0, 0.0), Point2f::new(50.0, 0.0)]);
+        high.set_id("b_high".to_string());
+        high.set_priority(10);
+        let mut zones = HashMap::new();
+        zones.insert(low.get_id(), Mutex::new(low));
+        zones.insert(high.get_id(), Mutex::new(high));
+        let candidates = zones.keys().cloned().collect::<Vec<String>>();
+        // Overlapping area belongs to the zone with higher priority
+        assert_eq!(find_zone_for_point(&zones, &candidates, 75.0, 50.0), Some("b_high".to_string()));
+        // Not overlapping areas
+        assert_eq!(find_zone_for_point(&zones, &candidates, 25.0, 50.0), Some("a_low".to_string()));
+        assert_eq!(find_zone_for_point(&zones, &candidates, 125.0, 50.0), Some("b_high".to_string()));
+        assert_eq!(find_zone_for_point(&zones, &candidates, 500.0, 50.0), None);
+        // Negative priority loses too
+        zones.get("b_high").unwrap().lock().unwrap().set_priority(-1);
+        assert_eq!(find_zone_for_point(&zones, &candidates, 75.0, 50.0), Some("a_low".to_string()));
+        // Equal priorities are resolved by identifier
+        zones.get("b_high").unwrap().lock().unwrap().set_priority(0);
+        assert_eq!(find_zone_for_point(&zones, &candidates, 75.0, 50.0), Some("a_low".to_string()));
+    }
+    #[test]
+    fn test_pedestrian_zone() {
+        let points = vec![Point2f::new(0.0, 100.0), Point2f::new(100.0, 100.0), Point2f::new(100.0, 0.0), Point2f::new(0.0, 0.0)];
+        let target_classes: HashSet<String> = ["car", "person"].iter().map(|class| class.to_string()).collect();
+        let mut crosswalk = Zone::default_from_cv(points.clone());
+        crosswalk.set_target_classes(&target_classes);
+        crosswalk.set_kind(ZoneKind::Pedestrian);
+        let mut road = Zone::default_from_cv(points);
+        road.set_target_classes(&target_classes);
+        assert_eq!(crosswalk.statistics.vehicles_data.keys().collect::<Vec<&String>>(), vec!["person"]);
+        // Vehicle zone has no per-kind filter: every target class is counted
+        let mut road_classes = road.statistics.vehicles_data.keys().collect::<Vec<&String>>();
+        road_classes.sort();
+        assert_eq!(road_classes, vec!["car", "person"]);
+        let objects = [("person", 4.0), ("car", 40.0), ("person", 6.0)];
+        for (idx, (classname, speed)) in objects.iter().enumerate() {
+            let object_id = Uuid::new_v4();
+            crosswalk.register_or_update_object(object_id, idx as f32, idx as f32, *speed, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+            road.register_or_update_object(object_id, idx as f32, idx as f32, *speed, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+        }
+        crosswalk.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        road.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(crosswalk.statistics.traffic_flow_parameters.sum_intensity, 2);
+        assert_eq!(crosswalk.statistics.vehicles_data["person"].sum_intensity, 2);
+        // Walking speed is reported, headway is not
+        assert!((crosswalk.statistics.traffic_flow_parameters.avg_speed - 5.0).abs() < 0.001);
+        assert_eq!(crosswalk.statistics.traffic_flow_parameters.avg_headway, -1.0);
+        assert_eq!(crosswalk.statistics.vehicles_data["person"].avg_headway, -1.0);
+        assert_eq!(road.statistics.traffic_flow_parameters.sum_intensity, 3);
+        assert_eq!(road.statistics.vehicles_data["car"].sum_intensity, 1);
+        assert_eq!(road.statistics.vehicles_data["person"].sum_intensity, 2);
+    }
+    #[test]
+    fn test_class_headway() {
+        let points = vec![Point2f::new(0.0, 100.0), Point2f::new(100.0, 100.0), Point2f::new(100.0, 0.0), Point2f::new(0.0, 0.0)];
+        let target_classes: HashSet<String> = ["car", "truck", "bus"].iter().map(|class| class.to_string()).collect();
+        let mut zone = Zone::default_from_cv(points);
+        zone.set_target_classes(&target_classes);
+        let objects = [("truck", 0.0), ("car", 1.0), ("truck", 4.0), ("car", 2.0), ("truck", 8.0), ("car", 3.0)];
+        for (classname, timestamp) in objects.iter() {
+            zone.register_or_update_object(Uuid::new_v4(), *timestamp, *timestamp, 30.0, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+        }
+        zone.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        // Overall: gaps are 1, 1, 1, 1, 4
+        assert!((zone.statistics.traffic_flow_parameters.avg_headway - 1.6).abs() < 0.001);
+        assert_eq!(zone.statistics.vehicles_data["truck"].avg_headway, 4.0);
+        assert_eq!(zone.statistics.vehicles_data["car"].avg_headway, 1.0);
+        // No vehicles of the class in the period
+        assert_eq!(zone.statistics.vehicles_data["bus"].avg_headway, 0.0);
+    }
+    #[test]
+    fn test_allowed_classes() {
+        let points = vec![Point2f::new(0.0, 100.0), Point2f::new(100.0, 100.0), Point2f::new(100.0, 0.0), Point2f::new(0.0, 0.0)];
+        let target_classes: HashSet<String> = ["car", "bus", "bicycle"].iter().map(|class| class.to_string()).collect();
+        let mut bus_lane = Zone::default_from_cv(points.clone());
+        bus_lane.set_allowed_classes(Some(["bus".to_string()].iter().cloned().collect()));
+        bus_lane.set_target_classes(&target_classes);
+        let mut bike_lane = Zone::default_from_cv(points);
+        bike_lane.set_allowed_classes(Some(["bicycle".to_string()].iter().cloned().collect()));
+        bike_lane.set_target_classes(&target_classes);
+        let objects = [("bus", Uuid::new_v4()), ("car", Uuid::new_v4()), ("bicycle", Uuid::new_v4()), ("bus", Uuid::new_v4())];
+        for (idx, (classname, object_id)) in objects.iter().enumerate() {
+            bus_lane.register_or_update_object(*object_id, idx as f32, idx as f32, 30.0, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+            bike_lane.register_or_update_object(*object_id, idx as f32, idx as f32, 30.0, 1.0, None, classname.to_string(), false, (50.0, 50.0));
+        }
+        bus_lane.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        bike_lane.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(bus_lane.statistics.traffic_flow_parameters.sum_intensity, 2);
+        assert_eq!(bus_lane.statistics.vehicles_data.keys().collect::<Vec<&String>>(), vec!["bus"]);
+        assert_eq!(bike_lane.statistics.traffic_flow_parameters.sum_intensity, 1);
+        assert_eq!(bike_lane.statistics.vehicles_data.keys().collect::<Vec<&String>>(), vec!["bicycle"]);
+        assert_eq!(bike_lane.statistics.vehicles_data["bicycle"].sum_intensity, 1);
+    }
+    #[test]
+    fn test_average_headway() {
+        assert_eq!(average_headway(vec![]), 0.0);
+        assert_eq!(average_headway(vec![5.0]), 0.0);
+        assert_eq!(average_headway(vec![3.0, 1.0, 7.0]), 3.0);
+        // Tied timestamps are neighbours, so gaps are: 2.0 and 4.0
+        assert_eq!(average_headway(vec![3.0, 1.0, 3.0, 7.0]), 3.0);
+        assert_eq!(average_headway(vec![2.0, 2.0]), 0.0);
+        // NaN timestamps are ignored
+        assert_eq!(average_headway(vec![f32::NAN, 3.0, 1.0, f32::NAN, 7.0]), 3.0);
+        assert_eq!(average_headway(vec![f32::NAN, f32::NAN]), 0.0);
+    }
+    #[test]
+    fn test_gsd_calibration() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 200.0),
+            Point2f::new(0.0, 200.0),
+        ]);
+        polygon.set_gsd(0.05);
+        assert!((polygon.get_skeleton_ppm() - 20.0).abs() < 0.001);
+        // Skeleton is 200 pixels long
+        assert!((polygon.skeleton.length_meters - 10.0).abs() < 0.001);
+    }
+    #[test]
+    fn test_acceleration_events() {
+        let mut polygon = Zone::default_from_cv(vec![
+            Point2f::new(0.0, 0.0),
+            Point2f::new(100.0, 0.0),
+            Point2f::new(100.0, 100.0),
+            Point2f::new(0.0, 100.0),
+        ]);
+        let braking_id = Uuid::new_v4();
+        polygon.register_or_update_object(braking_id, 1.0, 1.0, 40.0, 1.0, None, "car".to_string(), false, (50.0, 50.0));
+        polygon.register_or_update_object(braking_id, 1.1, 1.1, 30.0, 1.0, Some(-5.0), "car".to_string(), false, (50.0, 55.0));
+        // Braking is still counted once even if acceleration is back to normal
+        polygon.register_or_update_object(braking_id, 1.2, 1.2, 30.0, 1.0, Some(-0.5), "car".to_string(), false, (50.0, 60.0));
+        polygon.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, Some(4.0), "car".to_string(), false, (20.0, 50.0));
+        polygon.register_or_update_object(Uuid::new_v4(), 1.0, 1.0, 40.0, 1.0, Some(1.0), "car".to_string(), false, (80.0, 50.0));
+        polygon.update_statistics(Utc::now(), Utc::now(), 0.0, 0, None);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.harsh_braking_events, 1);
+        assert_eq!(polygon.statistics.traffic_flow_parameters.hard_acceleration_events, 1);
+    }
+    #[test]
+    fn test_auto_virtual_line() {
+        // Trapezoid: 0-1 is the entry side, 2-3 is the exit side
+        let polygon = Zone::default_from_cv(vec![
+            Point2f::new(10.0, 200.0),
+            Point2f::new(190.0, 200.0),
+            Point2f::new(150.0, 0.0),
+            Point2f::new(50.0, 0.0),
+        ]);
+        let vline = polygon.auto_virtual_line().unwrap();
+        // Line should be placed in the middle and span the whole polygon width
+        assert!((vline.line_cvf[0].y - 100.0).abs() < 0.001);
+        assert!((vline.line_cvf[1].y - 100.0).abs() < 0.001);
+        assert!(((vline.line_cvf[0].x - vline.line_cvf[1].x).abs() - 140.0).abs() < 0.001);
+        // Entry and exit halves are on the different sides of the line
+        let entry_side = vline.is_left(100.0, 190.0);
+        let exit_side = vline.is_left(100.0, 10.0);
+        assert_ne!(entry_side, exit_side);
+        // Object moving from entry side to exit side crosses the line
+        assert!(vline.intersection_fraction(100.0, 190.0, 100.0, 10.0).is_some());
+        // Object moving inside of the entry half does not cross the line
+        assert!(vline.intersection_fraction(60.0, 190.0, 120.0, 150.0).is_none());
+
+        let degenerate = Zone::default();
+        assert!(degenerate.auto_virtual_line().is_none());
+        // New geometry could be checked before it is applied to the zone
+        assert!(auto_virtual_line_for(&vec![Point2f::new(0.0, 0.0); 4], [0, 0, 0]).is_none());
+        assert!(auto_virtual_line_for(&polygon.get_pixel_coordinates(), [0, 0, 0]).is_some());
+    }
 }
@@ -46,6 +46,21 @@ pub struct ZonePropertiesGeoJSON {
     /// Corresponding road lane direction
     #[schema(example = 1)]
     pub road_lane_direction: u8,
+    /// Counting mode. Possible values:
+    /// 'polygon' - objects are registered when they appear in the zone (or cross virtual line inside of it)
+    /// 'line' - polygon membership is ignored and only virtual line crossings are registered
+    #[schema(example = "polygon")]
+    pub count_mode: String,
+    /// Kind of the zone. Possible values:
+    /// 'vehicle' - road lane: every target class is counted by default
+    /// 'pedestrian' - crosswalk: only pedestrians ('person' class) are counted by default, headway is not estimated
+    #[schema(example = "vehicle")]
+    pub kind: String,
+    /// Coordinate reference system of the zone's geometry. Possible values:
+    /// 'wgs84' - geometry is given in longitude/latitude
+    /// 'local_meters' - geometry is given in local metric coordinates
+    #[schema(example = "wgs84")]
+    pub crs: String,
     /// Corresponding zone's coordinates for the video frames
     #[schema(example = json!([[51,266],[281,264],[334,80],[179,68]]))]
     pub coordinates: Vec<Vec<i32>>,
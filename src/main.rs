@@ -4,15 +4,18 @@ use opencv::{
     core::Scalar,
     core::Size,
     core::Mat,
-    core::Vector,
+    core::Rect,
+    core::CV_8UC3,
     core::get_cuda_enabled_device_count,
+    core::get_num_threads,
+    core::set_num_threads,
     highgui::named_window,
     highgui::resize_window,
     highgui::imshow,
     highgui::wait_key,
-    videoio::VideoCapture,
     imgproc::resize,
-    imgcodecs::imencode,
+    imgcodecs::imread,
+    imgcodecs::IMREAD_COLOR,
     dnn::DNN_BACKEND_CUDA,
     dnn::DNN_TARGET_CUDA,
     dnn::DNN_BACKEND_OPENCV,
@@ -28,38 +31,79 @@ use od_opencv::{
 
 mod lib;
 use lib::data_storage::new_datastorage;
+use lib::data_storage::ThreadedDataStorage;
+use lib::data_storage::HourlyTimezone;
+use lib::data_storage::DEFAULT_MAX_INCIDENTS;
+use lib::incidents::{IncidentEvent, IncidentLog, StopTransition, STOPPED_VEHICLE};
+use lib::lane_changes::{LaneChangeEvent, follow_lane, LANE_CHANGE_MIN_FRAMES};
+use lib::od::{register_crossing, EXTERNAL_ZONE};
+use lib::schedule::{Schedule, ScheduleWindow, Warmup};
 use lib::draw;
+use lib::frame_dump::{FrameDumpWriter, FrameRecord};
+use lib::event_dump::{EventImageDump, EventTrack, DEFAULT_MAX_EVENT_FILES, LINE_CROSSING};
+use lib::dataset::flush_coco_annotations;
 use lib::tracker::{
     Tracker,
-    SpatialInfo
+    ObjectExtra,
+    SpatialInfo,
+    speed_point,
+    snapshot_tracked_objects,
+    TimeBase,
+    DetectionClock,
+    SpeedMethod
 };
 use lib::detection::process_yolo_detections;
-use lib::zones::Zone;
+use lib::detection::{merge_detections, suppress_detections, shift_bboxes};
+use lib::detection::Letterbox;
+use lib::detection::ModelFallback;
+use lib::detection::{touches_frame_edge, DEFAULT_EDGE_MARGIN_PIXELS};
+use lib::mjpeg_streaming::has_mjpeg_viewers;
+use lib::zones::{
+    Zone,
+    CountMode,
+    find_zone_for_point,
+    get_position_independent_zones
+};
 
 mod settings;
 use settings::AppSettings;
+use settings::DetectionModelSettings;
 
 mod video_capture;
 use video_capture::{
-    get_video_capture,
+    get_frame_source,
+    FrameSource,
+    FpsThrottle,
+    skip_frame,
+    resolve_fps,
+    DEFAULT_ASSUMED_FPS,
     ThreadedFrame
 };
 
-use lib::publisher::RedisConnection;
+use lib::publisher::{RedisConnection, MAX_PENDING_SNAPSHOTS};
+use lib::publisher::MessageFormat;
+#[cfg(feature = "sqlite")]
+use lib::publisher::SqliteLogger;
 
 mod rest_api;
+use rest_api::{ModelInfo, ModelSelfTest};
 
 use std::env;
 use std::time::Duration as STDDuration;
 use std::time::SystemTime;
+use std::time::Instant;
 use std::process;
 use std::thread;
 use std::sync::mpsc;
 use std::fmt;
 use std::collections::HashSet;
 use std::iter::FromIterator;
+use std::str::FromStr;
+use uuid::Uuid;
 
 const EMPTY_FRAMES_LIMIT: u16 = 60;
+// Number of consecutive frames without detections before switching to the fallback model
+const DEFAULT_FALLBACK_EMPTY_FRAMES: usize = 50;
 
 fn get_sys_time_in_secs() -> u64 {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
@@ -68,6 +112,31 @@ fn get_sys_time_in_secs() -> u64 {
     }
 }
 
+// Interpolates time of virtual line crossing between two last track points
+// t - fraction along segment from the last point to the point before it
+fn interpolate_crossing_time(last_time: f32, before_last_time: Option<f32>, t: f32) -> f32 {
+    match before_last_time {
+        Some(before_last_time) => last_time + t * (before_last_time - last_time),
+        None => last_time
+    }
+}
+
+// Interpolates point of virtual line crossing between two last track points (same t as for interpolate_crossing_time)
+fn interpolate_crossing_point(last_point: &mot_rs::utils::Point, before_last_point: &mot_rs::utils::Point, t: f32) -> (f32, f32) {
+    (last_point.x + t * (before_last_point.x - last_point.x), last_point.y + t * (before_last_point.y - last_point.y))
+}
+
+// Track of the object for the event files. Raw track is empty when raw centroids are not kept by tracker
+fn event_track(object_id: &Uuid, object_extra: &ObjectExtra, track: &[mot_rs::utils::Point]) -> EventTrack {
+    EventTrack {
+        object_id: object_id.to_string(),
+        short_id: object_extra.get_short_id(),
+        class_name: object_extra.get_classname(),
+        smoothed_track: track.iter().map(|pt| [pt.x, pt.y]).collect(),
+        raw_track: object_extra.get_raw_centroids().map(|raw| raw.iter().map(|(x, y)| [*x, *y]).collect()).unwrap_or_default(),
+    }
+}
+
 #[derive(Debug)]
 struct AppVideoError{typ: i16}
 impl fmt::Display for AppVideoError {
@@ -107,8 +176,27 @@ impl From<opencv::Error> for AppError {
     }
 }
 
-fn probe_video(capture: &mut VideoCapture) ->  Result<(f32, f32, f32), AppError> {
-    let fps = capture.get(opencv::videoio::CAP_PROP_FPS)? as f32;
+// assumed_fps - FPS to be used when the probed one is zero or implausible
+// For folder of images it is the only source of FPS (so timestamps are synthetic)
+fn probe_video(source: &mut FrameSource, assumed_fps: f32) ->  Result<(f32, f32, f32), AppError> {
+    let capture = match source {
+        FrameSource::Video(capture) => capture,
+        FrameSource::Images(images) => {
+            let (frame_cols, frame_rows) = match images.probe_size()? {
+                Some(size) => size,
+                None => {
+                    return Err(AppError::VideoError(AppVideoError{typ: 2}));
+                }
+            };
+            let (fps, _) = resolve_fps(0.0, assumed_fps);
+            return Ok((frame_cols, frame_rows, fps));
+        }
+    };
+    let probed_fps = capture.get(opencv::videoio::CAP_PROP_FPS)? as f32;
+    let (fps, fallback) = resolve_fps(probed_fps, assumed_fps);
+    if fallback {
+        println!("[WARNING]: Probed FPS is {}, which is not plausible. Falling back to {} FPS (see 'input.assumed_fps')", probed_fps, fps);
+    }
     let frame_cols = capture.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)? as f32;
     let frame_rows = capture.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)? as f32;
 
@@ -125,7 +213,8 @@ fn probe_video(capture: &mut VideoCapture) ->  Result<(f32, f32, f32), AppError>
     Ok((frame_cols, frame_rows, fps))
 }
 
-fn prepare_neural_net(mf: ModelFormat, mv: ModelVersion, weights: &str, configuration: Option<String>, net_size: (i32, i32)) -> Result<Box<dyn ModelTrait>, AppError> {
+// Returns prepared neural network and flag whether CUDA is used
+fn prepare_neural_net(mf: ModelFormat, mv: ModelVersion, weights: &str, configuration: Option<String>, net_size: (i32, i32)) -> Result<(Box<dyn ModelTrait>, bool), AppError> {
 
     /* Check if CUDA is an option at all */
     let cuda_count = get_cuda_enabled_device_count()?;
@@ -151,10 +240,124 @@ fn prepare_neural_net(mf: ModelFormat, mv: ModelVersion, weights: &str, configur
             panic!("Can't read network '{}' (with cfg '{:?}') due the error: {:?}", weights, configuration, err);
         }
     };
-    Ok(neural_net)
+    Ok((neural_net, cuda_available))
+}
+
+// Converts frame-relative geometry of the zones (e.g. normalized virtual lines) to pixels
+fn resolve_zones_frame_size(data_storage: &ThreadedDataStorage, frame_width: f32, frame_height: f32) {
+    let ds_guard = data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+    for (_, zone_guarded) in zones.iter() {
+        let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+        zone.set_frame_size(frame_width, frame_height);
+        drop(zone);
+    }
+    drop(zones);
+    drop(ds_guard);
+}
+
+// Each model has its own network size, so letterbox is prepared for every model (None when letterbox is disabled)
+fn prepare_letterboxes(neural_nets: &[DetectionModel], enabled: bool, frame_width: f32, frame_height: f32) -> Vec<Option<Letterbox>> {
+    neural_nets.iter().map(|model| {
+        if enabled {
+            let lb = Letterbox::new(frame_width as i32, frame_height as i32, model.net_size.0, model.net_size.1);
+            println!("Letterbox padding: {:?}", lb);
+            Some(lb)
+        } else {
+            None
+        }
+    }).collect()
+}
+
+// Regions of the frame for the additional inference. Regions out of the frame bounds are skipped
+fn prepare_roi_crops(crops: &[[i32; 4]], frame_width: f32, frame_height: f32) -> Vec<Rect> {
+    crops.iter()
+        .filter_map(|crop| {
+            let rect = Rect::new(crop[0], crop[1], crop[2], crop[3]);
+            if rect.x < 0 || rect.y < 0 || rect.width <= 0 || rect.height <= 0 || rect.x + rect.width > frame_width as i32 || rect.y + rect.height > frame_height as i32 {
+                println!("[WARNING]: ROI crop {:?} is out of the frame bounds. It is skipped", crop);
+                return None;
+            }
+            Some(rect)
+        })
+        .collect()
+}
+
+// Neural network with its own input size
+struct DetectionModel {
+    net: Box<dyn ModelTrait>,
+    net_size: (i32, i32),
+    format: String,
+    version: String,
+}
+
+// Returns prepared neural networks (primary one goes first) and flag whether CUDA is used
+// OpenCV treats zero as 'disable threading' and negative value as 'reset to default'. In configuration zero means default
+fn opencv_num_threads(num_threads: u32) -> i32 {
+    if num_threads == 0 {
+        return -1;
+    }
+    num_threads.min(i32::MAX as u32) as i32
 }
 
-fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neural_net: &mut dyn ModelTrait, verbose: bool) -> Result<(), AppError> {
+fn prepare_neural_nets(models: &[DetectionModelSettings]) -> Result<(Vec<DetectionModel>, bool), AppError> {
+    let mut prepared = Vec::with_capacity(models.len());
+    let mut cuda_enabled = false;
+    for model_settings in models.iter() {
+        let model_format = match model_settings.get_nn_format() {
+            Ok(mf) => mf,
+            Err(err) => {
+                panic!("Can't get model format of '{}' due the error: {}", model_settings.network_weights, err);
+            }
+        };
+        let model_version = match model_settings.get_nn_version() {
+            Ok(mv) => mv,
+            Err(err) => {
+                panic!("Can't get model version of '{}' due the error: {}", model_settings.network_weights, err);
+            }
+        };
+        let format = format!("{:?}", model_format);
+        let version = format!("{:?}", model_version);
+        let net_size = (model_settings.net_width, model_settings.net_height);
+        let (neural_net, cuda_available) = prepare_neural_net(model_format, model_version, &model_settings.network_weights, model_settings.network_cfg.clone(), net_size)?;
+        cuda_enabled = cuda_available;
+        prepared.push(DetectionModel {
+            net: neural_net,
+            net_size: net_size,
+            format: format,
+            version: version,
+        });
+    }
+    Ok((prepared, cuda_enabled))
+}
+
+// Runs inference once on the test image (or on the blank frame of the network size), so broken weights / configuration are visible without live video
+fn run_model_selftest(model: &mut DetectionModel, image_path: Option<&str>, conf_threshold: f32, nms_threshold: f32) -> ModelSelfTest {
+    let image = image_path.unwrap_or("blank").to_string();
+    let frame = match image_path {
+        Some(path) => match imread(path, IMREAD_COLOR) {
+            Ok(frame) if !frame.empty() => Ok(frame),
+            Ok(_) => Err(format!("Can't read image '{}'", path)),
+            Err(err) => Err(format!("{:?}", err)),
+        },
+        None => Mat::new_rows_cols_with_default(model.net_size.1, model.net_size.0, CV_8UC3, Scalar::all(114.0)).map_err(|err| format!("{:?}", err)),
+    };
+    let started = Instant::now();
+    let result = match frame {
+        Ok(frame) => model.net.forward(&frame, conf_threshold, nms_threshold).map(|(bboxes, _, _)| bboxes.len()).map_err(|err| format!("{:?}", err)),
+        Err(err) => Err(err),
+    };
+    let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    ModelSelfTest {
+        image: image,
+        detections: *result.as_ref().unwrap_or(&0),
+        elapsed_ms: elapsed_ms,
+        tested_at: Utc::now(),
+        error: result.err(),
+    }
+}
+
+fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neural_nets: &mut Vec<DetectionModel>, model_info: ModelInfo, model_selftest: ModelSelfTest, verbose: bool) -> Result<(), AppError> {
     println!("Verbose is '{}'", verbose);
     println!("REST API is '{}'", settings.rest_api.enable);
     println!("Redis publisher is '{}'", settings.redis_publisher.enable);
@@ -168,6 +371,48 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
 
     /* Preprocess spatial data */
     let data_storage = new_datastorage(settings.equipment_info.id.clone(), verbose);
+    data_storage.write().expect("DataStorage is poisoned [RWLock]").location_name = settings.equipment_info.location_name.clone();
+    data_storage.write().expect("DataStorage is poisoned [RWLock]").location_wgs84 = settings.equipment_info.location_wgs84;
+    if let Some(tz) = &settings.worker.hourly_timezone {
+        let hourly_timezone = match HourlyTimezone::from_str(tz) {
+            Ok(result) => result,
+            Err(_) => {
+                panic!("Can't prepare hourly totals due the unknown timezone: {}", tz);
+            }
+        };
+        data_storage.write().expect("DataStorage is poisoned [RWLock]").hourly_timezone = hourly_timezone;
+    }
+    if let Some(min_moving_speed) = settings.tracking.min_moving_speed {
+        data_storage.write().expect("DataStorage is poisoned [RWLock]").min_moving_speed = min_moving_speed;
+    }
+    if let Some(min_samples_for_speed) = settings.worker.min_samples_for_speed {
+        data_storage.write().expect("DataStorage is poisoned [RWLock]").min_samples_for_speed = min_samples_for_speed;
+    }
+    let dedup_across_periods = settings.worker.dedup_across_periods.unwrap_or(false);
+    data_storage.write().expect("DataStorage is poisoned [RWLock]").dedup_across_periods = dedup_across_periods;
+    let schedule = match &settings.schedule {
+        Some(schedule_settings) => {
+            let timezone = match HourlyTimezone::from_str(schedule_settings.timezone.as_deref().unwrap_or("local")) {
+                Ok(result) => result,
+                Err(_) => {
+                    panic!("Can't prepare schedule due the unknown timezone: {:?}", schedule_settings.timezone);
+                }
+            };
+            let mut windows = vec![];
+            for window_settings in schedule_settings.windows.iter() {
+                let window = match ScheduleWindow::new_from(&window_settings.days.to_owned().unwrap_or(vec![]), &window_settings.start, &window_settings.end) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        panic!("Can't prepare schedule due the error: {}", err);
+                    }
+                };
+                windows.push(window);
+            }
+            println!("Statistics are accumulated only within {} schedule window(s)", windows.len());
+            Some(Schedule { windows: windows, timezone: timezone })
+        },
+        None => None
+    };
     let target_classes = HashSet::from_iter(settings.detection.target_classes.to_owned().unwrap_or(vec![]));
     let net_classes = settings.detection.net_classes.to_owned();
     let net_classes_set = HashSet::from_iter(net_classes.clone());
@@ -192,6 +437,9 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
     println!("Press `Ctrl-C` to stop main programm");
     ctrlc::set_handler(move || {
         println!("Ctrl+C has been pressed! Exit in 2 seconds");
+        if let Err(err) = flush_coco_annotations() {
+            println!("Can't write COCO annotations due the error: {}", err);
+        }
         thread::sleep(STDDuration::from_secs(2));
         process::exit(1);
     }).expect("Error setting `Ctrl-C` handler");
@@ -222,6 +470,25 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
             if redis_channel.chars().count() != 0 {
                 redis_conn.set_channel(redis_channel);
             }
+            redis_conn.set_min_publish_interval(settings.redis_publisher.min_publish_interval_ms.unwrap_or(0));
+            if let Some(format) = &settings.redis_publisher.format {
+                match MessageFormat::from_str(format.as_str()) {
+                    Ok(format) => redis_conn.set_format(format),
+                    Err(_) => {
+                        panic!("Can't prepare Redis publisher due the unknown format: '{}'. Possible values: 'json', 'protobuf'", format);
+                    }
+                }
+            }
+            if settings.redis_publisher.set_latest_key.unwrap_or(false) {
+                let latest_key = format!("traffic:{}:latest", settings.equipment_info.id);
+                println!("Latest snapshot will be stored at Redis key '{}'", latest_key);
+                redis_conn.set_latest_key(latest_key, settings.redis_publisher.latest_key_ttl_seconds.unwrap_or(0));
+            }
+            redis_conn.set_connect_retries(settings.redis_publisher.connect_retries.unwrap_or(0));
+            redis_conn.set_reconnect(settings.redis_publisher.reconnect.unwrap_or(true));
+            if !redis_conn.connect() {
+                println!("[WARNING]: Redis is unreachable. Snapshots will be buffered (up to {}) until connection is established", MAX_PENDING_SNAPSHOTS);
+            }
             Some(redis_conn)
         },
         false => {
@@ -229,14 +496,42 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
         }
     };
 
+    /* SQLite logger */
+    #[cfg(not(feature = "sqlite"))]
+    if settings.sqlite.as_ref().map(|sqlite_settings| sqlite_settings.enable).unwrap_or(false) {
+        panic!("SQLite logger is enabled in configuration, but application is built without it. Rebuild with '--features sqlite'");
+    }
+    #[cfg(feature = "sqlite")]
+    let mut sqlite_logger = match &settings.sqlite {
+        Some(sqlite_settings) if sqlite_settings.enable => {
+            match SqliteLogger::new(&sqlite_settings.path, data_storage.clone()) {
+                Ok(logger) => {
+                    println!("Statistics will be stored in SQLite database '{}'", sqlite_settings.path);
+                    Some(logger)
+                },
+                Err(err) => {
+                    panic!("Can't prepare SQLite database '{}' due the error: {}", sqlite_settings.path, err);
+                }
+            }
+        },
+        _ => None
+    };
+
     /* Start REST API if needed */ 
     let overwrite_file = path_to_config.to_string();
     let (tx_mjpeg, rx_mjpeg) = mpsc::sync_channel(0);
     if settings.rest_api.enable {
+        // Load certificates before spawning API thread to fail fast on bad TLS configuration
+        let tls_config = match rest_api::prepare_tls_config(&settings.rest_api) {
+            Ok(config) => config,
+            Err(err) => {
+                panic!("Can't prepare TLS for REST API due the error: {}", err);
+            }
+        };
         let settings_clone = settings.clone();
         let ds_api = data_storage.clone();
         thread::spawn(move || {
-            match rest_api::start_rest_api(settings_clone.rest_api.host.clone(), settings_clone.rest_api.back_end_port, ds_api, enable_mjpeg, rx_mjpeg, settings_clone, &overwrite_file) {
+            match rest_api::start_rest_api(settings_clone.rest_api.host.clone(), settings_clone.rest_api.back_end_port, ds_api, enable_mjpeg, rx_mjpeg, settings_clone, &overwrite_file, tls_config, model_info, model_selftest) {
                 Ok(_) => {},
                 Err(err) => {
                     println!("Can't start API due the error: {:?}", err)
@@ -246,13 +541,15 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
     }
 
     /* Probe video */
-    let mut video_capture = get_video_capture(&settings.input.video_src, settings.input.typ.clone());
-    let opened = VideoCapture::is_opened(&video_capture).map_err(AppError::from)?;
+    let mut video_capture = get_frame_source(&settings.input.video_src, settings.input.typ.clone());
+    let images_input = matches!(video_capture, FrameSource::Images(_));
+    let opened = video_capture.is_opened().map_err(AppError::from)?;
     if !opened {
         return Err(AppError::VideoError(AppVideoError{typ: 1}))
     }
-    let (width, height, fps) = probe_video(&mut video_capture)?;
+    let (width, height, fps) = probe_video(&mut video_capture, settings.input.assumed_fps.unwrap_or(DEFAULT_ASSUMED_FPS))?;
     println!("Video probe: {{Width: {width}px | Height: {height}px | FPS: {fps}}}");
+    resolve_zones_frame_size(&data_storage, width, height);
     // Create imshow() if needed
     let window = &settings.output.window_name;
     let output_width: i32 = settings.output.width;
@@ -274,25 +571,94 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
 
     /* Start capture loop */
     let (tx_capture, rx_capture): (mpsc::SyncSender<ThreadedFrame>, mpsc::Receiver<ThreadedFrame>) = mpsc::sync_channel(0);
-    thread::spawn(move || {
+    let drop_on_backpressure = settings.input.drop_on_backpressure.unwrap_or(false);
+    println!("Drop frames on backpressure is '{}'", drop_on_backpressure);
+    let mut fps_throttle = settings.input.target_fps.and_then(|target_fps| FpsThrottle::new(target_fps, fps));
+    if let Some(target_fps) = settings.input.target_fps {
+        match fps_throttle {
+            Some(_) => println!("Processing is capped to {} FPS", target_fps),
+            None => println!("[WARNING]: Target FPS {} is not lower than source FPS {}. Processing is not capped", target_fps, fps)
+        }
+    }
+    let loop_input = settings.input.loop_input.unwrap_or(false);
+    if loop_input {
+        println!("[WARNING]: Input is looped. The same traffic is counted on every pass, so long-term aggregates are skewed");
+    }
+    let capture_metrics = data_storage.read().expect("DataStorage is poisoned [RWLock]").metrics.clone();
+    // Detection thread notifies capture thread about the end of processing (by dropping the sender)
+    let (tx_finished, rx_finished) = mpsc::channel::<()>();
+    let capture_handle = thread::spawn(move || {
         let mut frames_counter: f32 = 0.0;
         let mut total_seconds: f32 = 0.0;
         let mut overall_seconds: f32 = 0.0;
         let mut empty_frames_countrer: u16 = 0;
-        // @experimental
-        let skip_every_n_frame = 2;
+        // Time passed since the last statistics reset (seconds)
+        let mut second_fraction: f32 = 0.0;
+        // Closes the current statistics period and publishes it
+        let mut flush_statistics = |period_ms: i64| {
+            let mut ds_writer = ds_worker.write().expect("Bad DS");
+            if ds_writer.period_end == ds_writer.period_start {
+                // First iteration
+                ds_writer.period_end = Utc::now();
+                ds_writer.period_start = ds_writer.period_end - chrono::Duration::milliseconds(period_ms);
+            } else {
+                // Next iterations
+                ds_writer.period_start = ds_writer.period_end;
+                ds_writer.period_end += chrono::Duration::milliseconds(period_ms);
+            }
+            
+            match ds_writer.update_statistics() {
+                Ok(_) => {
+                    // Do not forget to drop mutex explicitly since we possible need to work with DS in REST API and Redis
+                    drop(ds_writer)
+                },
+                Err(err) => {
+                    println!("Can't update statistics due the error: {}", err);
+                }
+            }
+            if redis_enabled {
+                redis_conn.as_ref().unwrap().push_statistics();
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(logger) = sqlite_logger.as_mut() {
+                match logger.log_statistics() {
+                    Ok(rows) => {
+                        if verbose {
+                            println!("Statistics have been stored in SQLite. Rows: {}", rows);
+                        }
+                    },
+                    Err(err) => {
+                        println!("Can't store statistics in SQLite due the error: {}", err);
+                    }
+                }
+            }
+        };
         // @todo: remove hardcode
         // let fps = 18.0;
         loop {
             let mut read_frame = Mat::default();
             match video_capture.read(&mut read_frame) {
-                Ok(_) => {},
+                Ok(true) => {},
+                Ok(false) => {
+                    if loop_input && video_capture.rewind().unwrap_or(false) {
+                        println!("All images have been processed. Starting over");
+                        continue;
+                    }
+                    println!("All images have been processed");
+                    break;
+                },
                 Err(_) => {
                     println!("Can't read next frame");
                     break;
                 }
             };
             if read_frame.empty() {
+                // Video files end with empty frame (for images it is unreadable file). Time counters are not touched, so timestamps keep growing monotonically after the wrap
+                if loop_input && !images_input && video_capture.rewind().unwrap_or(false) {
+                    println!("End of video. Starting over");
+                    empty_frames_countrer = 0;
+                    continue;
+                }
                 if verbose {
                     println!("[WARNING]: Empty frame");
                 }
@@ -303,16 +669,23 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
                 }
                 continue;
             }
+            capture_metrics.inc_captured();
             frames_counter += 1.0;
-            let second_fraction = total_seconds + (frames_counter / fps);
+            second_fraction = total_seconds + (frames_counter / fps);
             if frames_counter >= fps {
                 total_seconds += 1.0;
                 overall_seconds += 1.0;
                 frames_counter = 0.0;
             }
-            if frames_counter as i32 % skip_every_n_frame != 0 {
+            if skip_frame(frames_counter, images_input) {
                 continue;
             }
+            if let Some(throttle) = fps_throttle.as_mut() {
+                if !throttle.admit(overall_seconds + frames_counter / fps) {
+                    continue;
+                }
+                throttle.wait();
+            }
             // println!("Frame {frames_counter} | Second: {total_seconds} | Fraction: {second_fraction}");
 
 
@@ -323,41 +696,50 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
                 current_second: second_fraction,
             };
 
-            match tx_capture.send(frame) {
-                Ok(_)=>{},
-                Err(_err) => {
+            // Channel is a rendezvous one, so 'Full' means that detection thread is still busy with previous frame
+            match tx_capture.try_send(frame) {
+                Ok(_) => {
+                    capture_metrics.inc_sent();
+                },
+                Err(mpsc::TrySendError::Full(frame)) => {
+                    capture_metrics.inc_backpressured();
+                    if drop_on_backpressure {
+                        capture_metrics.inc_dropped();
+                    } else {
+                        match tx_capture.send(frame) {
+                            Ok(_) => {
+                                capture_metrics.inc_sent();
+                            },
+                            Err(_err) => {
+                                // Closed channel?
+                                // println!("Error on send frame to detection thread: {}", _err)
+                            }
+                        };
+                    }
+                },
+                Err(mpsc::TrySendError::Disconnected(_)) => {
                     // Closed channel?
-                    // println!("Error on send frame to detection thread: {}", _err)
                 }
             };
 
             // println!("Total seconds: {}", total_seconds);
             if total_seconds >= next_reset {
                 println!("Reset timer due analytics. Current local time is: {}", second_fraction);
-                total_seconds = 0.0;
-                let mut ds_writer = ds_worker.write().expect("Bad DS");
-                if ds_writer.period_end == ds_writer.period_start {
-                    // First iteration
-                    ds_writer.period_end = Utc::now();
-                    ds_writer.period_start = ds_writer.period_end - chrono::Duration::milliseconds(reset_time);
-                } else {
-                    // Next iterations
-                    ds_writer.period_start = ds_writer.period_end;
-                    ds_writer.period_end += chrono::Duration::milliseconds(reset_time);
-                }
-                
-                match ds_writer.update_statistics() {
-                    Ok(_) => {
-                        // Do not forget to drop mutex explicitly since we possible need to work with DS in REST API and Redis
-                        drop(ds_writer)
-                    },
-                    Err(err) => {
-                        println!("Can't update statistics due the error: {}", err);
-                    }
-                }
-                if redis_enabled {
-                    redis_conn.as_ref().unwrap().push_statistics();
+                if verbose {
+                    println!("Pipeline frames: {}", capture_metrics.snapshot());
                 }
+                total_seconds = 0.0;
+                second_fraction = 0.0;
+                flush_statistics(reset_time);
+            }
+        }
+        if images_input {
+            // Let detection thread process the remaining frame, then finalize partial statistics period
+            drop(tx_capture);
+            let _ = rx_finished.recv();
+            if second_fraction > 0.0 {
+                println!("Finalizing statistics for the last {} seconds", second_fraction);
+                flush_statistics((second_fraction * 1000.0) as i64);
             }
         }
         match video_capture.release() {
@@ -373,12 +755,113 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
     /* Detection thread */
     let conf_threshold: f32 = settings.detection.conf_threshold;
     let nms_threshold: f32 = settings.detection.nms_threshold;
+    let nms_class_agnostic: bool = settings.detection.nms_class_agnostic.unwrap_or(false);
     let max_points_in_track: usize = settings.tracking.max_points_in_track;
+    let speed_from_kalman: bool = settings.tracking.speed_from_kalman.unwrap_or(false);
+    let edge_margin_pixels: Option<f32> = settings.detection.edge_filter.as_ref()
+        .filter(|edge_filter| edge_filter.enable)
+        .map(|edge_filter| edge_filter.edge_margin_pixels.unwrap_or(DEFAULT_EDGE_MARGIN_PIXELS));
+    if let Some(margin) = edge_margin_pixels {
+        println!("Crossings by objects within {} pixels of the frame border are ignored", margin);
+    }
+    let time_base = match &settings.tracking.time_base {
+        Some(val) => match TimeBase::from_str(val.as_str()) {
+            Ok(time_base) => time_base,
+            Err(_) => {
+                panic!("Can't prepare tracking due the unknown time base: '{}'. Possible values: 'frame', 'processing_wallclock'", val);
+            }
+        },
+        None => TimeBase::default()
+    };
+    println!("Time base for tracking is '{}'", time_base);
+    let speed_method = match &settings.tracking.speed_method {
+        Some(val) => match SpeedMethod::from_str(val.as_str()) {
+            Ok(speed_method) => speed_method,
+            Err(_) => {
+                panic!("Can't prepare tracking due the unknown speed method: '{}'. Possible values: 'skeleton', 'ground_plane'", val);
+            }
+        },
+        None => SpeedMethod::default()
+    };
+    println!("Speed method is '{}'", speed_method);
+    let mut detection_clock = DetectionClock::new(time_base);
+    let mut warmup = Warmup::new(settings.worker.warmup_seconds.unwrap_or(0.0));
+    if !warmup.is_finished() {
+        println!("Objects are not counted during the first {} seconds (warmup)", settings.worker.warmup_seconds.unwrap_or(0.0));
+    }
+    let track_draw_points: Option<usize> = settings.output.track_draw_points;
+    let draw_short_ids: bool = settings.output.draw_short_ids.unwrap_or(true);
+    let draw_scale_bar: bool = settings.output.draw_scale_bar.unwrap_or(false);
+    let draw_north_arrow: bool = settings.output.draw_north_arrow.unwrap_or(false);
+    let draw_direction_arrow: bool = settings.output.draw_direction_arrow.unwrap_or(true);
+    let draw_boxes: bool = settings.output.draw_boxes.unwrap_or(false);
+    let shade_by_occupancy: bool = settings.output.shade_by_occupancy.unwrap_or(false);
+    let draw_od_flows: bool = settings.output.draw_od_flows.unwrap_or(false);
+    let class_colors = draw::ClassColors::new(&settings.detection.net_classes);
+    // Text is drawn over the frame of the source resolution, so font is scaled relatively to its height unless it is configured explicitly
+    let mut text_style = draw::TextStyle::new(settings.output.font_scale, settings.output.font_thickness, height);
+    let draw_line_side: bool = settings.debug.as_ref().map(|debug| debug.enable && debug.draw_line_side.unwrap_or(false)).unwrap_or(false);
+    let draw_zone_grid: bool = settings.debug.as_ref().map(|debug| debug.enable && debug.draw_zone_grid.unwrap_or(false)).unwrap_or(false);
     let mut resized_frame = Mat::default();
+    let letterbox_enabled = settings.detection.letterbox.unwrap_or(false);
+    let mut letterboxes = prepare_letterboxes(neural_nets, letterbox_enabled, width, height);
+    let mut model_fallback = ModelFallback::new(neural_nets.len(), settings.detection.fallback_empty_frames.unwrap_or(DEFAULT_FALLBACK_EMPTY_FRAMES));
+    if neural_nets.len() > 1 {
+        println!("Fallback models: {}. Switching after {} frames without detections", neural_nets.len() - 1, settings.detection.fallback_empty_frames.unwrap_or(DEFAULT_FALLBACK_EMPTY_FRAMES));
+    }
+
+    // Additional inference on the regions of the frame. Each crop costs one more forward pass per frame
+    let roi_crops_settings = settings.detection.roi_crops.clone().unwrap_or_default();
+    let mut roi_crops = prepare_roi_crops(&roi_crops_settings, width, height);
+
+    let mut frame_dump = match settings.debug.as_ref().and_then(|debug| debug.frame_dump.as_ref()) {
+        Some(frame_dump_settings) if frame_dump_settings.enable => {
+            match FrameDumpWriter::new(&frame_dump_settings.path, frame_dump_settings.max_size_mb.unwrap_or(0) * 1024 * 1024) {
+                Ok(writer) => {
+                    println!("[WARNING]: Per-frame dump is enabled ('{}'). It is for debugging only and could slow down processing", frame_dump_settings.path);
+                    Some(writer)
+                },
+                Err(err) => {
+                    println!("Can't open frame dump file '{}' due the error: {}", frame_dump_settings.path, err);
+                    None
+                }
+            }
+        },
+        _ => None
+    };
+    let event_image_dump = match settings.events.as_ref().and_then(|events| events.image_dump.as_ref()) {
+        Some(image_dump_settings) if image_dump_settings.enable => {
+            match EventImageDump::new(&image_dump_settings.directory, image_dump_settings.max_files.unwrap_or(DEFAULT_MAX_EVENT_FILES)) {
+                Ok(dump) => Some(dump),
+                Err(err) => {
+                    println!("Can't prepare directory '{}' for event images due the error: {}", image_dump_settings.directory, err);
+                    None
+                }
+            }
+        },
+        _ => None
+    };
+    let include_raw_track = event_image_dump.is_some() && settings.events.as_ref().and_then(|events| events.include_raw_track).unwrap_or(false);
 
     let ds_tracker = data_storage.clone();
+    let latest_frame = data_storage.read().expect("DataStorage is poisoned [RWLock]").latest_frame.clone();
+    let pipeline_control = data_storage.read().expect("DataStorage is poisoned [RWLock]").control.clone();
+    let tracked_objects = data_storage.read().expect("DataStorage is poisoned [RWLock]").tracked_objects.clone();
+    let latest_detections = data_storage.read().expect("DataStorage is poisoned [RWLock]").latest_detections.clone();
+    let incidents = data_storage.read().expect("DataStorage is poisoned [RWLock]").incidents.clone();
+    let lane_changes = data_storage.read().expect("DataStorage is poisoned [RWLock]").lane_changes.clone();
+    let od_matrix = data_storage.read().expect("DataStorage is poisoned [RWLock]").od_matrix.clone();
+    let lifetime_counts = data_storage.read().expect("DataStorage is poisoned [RWLock]").lifetime_counts.clone();
+    let mjpeg_clients = data_storage.read().expect("DataStorage is poisoned [RWLock]").mjpeg_clients.clone();
+    let od_max_transition_seconds = settings.od.as_ref().and_then(|od| od.max_transition_seconds);
+    let incidents_settings = settings.incidents.clone().filter(|incidents_settings| incidents_settings.enable);
+    if let Some(incidents_settings) = &incidents_settings {
+        println!("Stopped vehicles are reported after {} seconds below {} km/h", incidents_settings.stopped_duration_seconds, incidents_settings.stopped_speed);
+        *incidents.lock().expect("Incidents are poisoned [Mutex]") = IncidentLog::new(incidents_settings.max_events.unwrap_or(DEFAULT_MAX_INCIDENTS));
+    }
     
     let tracker_dt = 1.0/fps;
+    let mut frame_size = (width, height);
 
     /* Can't create colors as const/static currently */
     let trajectory_scalar: Scalar = Scalar::from((0.0, 255.0, 0.0));
@@ -390,28 +873,115 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
     for received in rx_capture {
         // println!("Received frame from capture thread: {}", received.current_second);
         let mut frame = received.frame.clone();
-        let (nms_bboxes, nms_classes_ids, nms_confidences) = match neural_net.forward(&frame, conf_threshold, nms_threshold) {
+        let received_size = (frame.cols() as f32, frame.rows() as f32);
+        if received_size != frame_size {
+            // Stream resolution has been changed, so frame-relative geometry (e.g. normalized virtual lines) should be resolved again
+            println!("Frame size has been changed from {:?} to {:?}", frame_size, received_size);
+            resolve_zones_frame_size(&ds_tracker, received_size.0, received_size.1);
+            // Everything which depends on the frame size is prepared again: probed size is not valid anymore
+            letterboxes = prepare_letterboxes(neural_nets, letterbox_enabled, received_size.0, received_size.1);
+            roi_crops = prepare_roi_crops(&roi_crops_settings, received_size.0, received_size.1);
+            text_style = draw::TextStyle::new(settings.output.font_scale, settings.output.font_thickness, received_size.1);
+            frame_size = received_size;
+        }
+        if settings.rest_api.enable {
+            // Keep raw frame for the zones preview
+            *latest_frame.lock().expect("Latest frame is poisoned [Mutex]") = Some(received.frame);
+        }
+        if pipeline_control.is_paused() {
+            // Frame has been read already (stream is kept alive), but nothing should be detected or counted
+            continue;
+        }
+        let active_model = model_fallback.get_active();
+        let neural_net = &mut neural_nets[active_model].net;
+        let letterbox = &letterboxes[active_model];
+        let padded_frame;
+        let net_input = match letterbox {
+            Some(lb) => {
+                padded_frame = match lb.apply(&frame) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        println!("Can't apply letterbox to the frame due the error {:?}", err);
+                        continue;
+                    }
+                };
+                &padded_frame
+            },
+            None => &frame
+        };
+        let (mut nms_bboxes, mut nms_classes_ids, mut nms_confidences) = match neural_net.forward(net_input, conf_threshold, nms_threshold) {
             Ok((a, b, c)) => { (a, b, c) },
             Err(err) => {
                 println!("Can't process input of neural network due the error {:?}", err);
                 continue;
             }
         };
+        if !roi_crops.is_empty() {
+            for roi in roi_crops.iter() {
+                let crop = match Mat::roi(&frame, *roi).and_then(|crop| crop.try_clone()) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        println!("Can't crop ROI {:?} due the error {:?}", roi, err);
+                        continue;
+                    }
+                };
+                let (mut crop_bboxes, crop_classes_ids, crop_confidences) = match neural_net.forward(&crop, conf_threshold, nms_threshold) {
+                    Ok((a, b, c)) => { (a, b, c) },
+                    Err(err) => {
+                        println!("Can't process ROI {:?} by neural network due the error {:?}", roi, err);
+                        continue;
+                    }
+                };
+                // Whole frame boxes are in the padded coordinates when letterbox is used
+                let (pad_left, pad_top) = match letterbox {
+                    Some(lb) => (lb.pad_left, lb.pad_top),
+                    None => (0, 0)
+                };
+                shift_bboxes(&mut crop_bboxes, roi.x + pad_left, roi.y + pad_top);
+                nms_bboxes.extend(crop_bboxes);
+                nms_classes_ids.extend(crop_classes_ids);
+                nms_confidences.extend(crop_confidences);
+            }
+            (nms_bboxes, nms_classes_ids, nms_confidences) = match merge_detections(nms_bboxes, nms_classes_ids, nms_confidences, nms_threshold) {
+                Ok((a, b, c)) => { (a, b, c) },
+                Err(err) => {
+                    println!("Can't merge detections from ROI crops due the error {:?}", err);
+                    continue;
+                }
+            };
+        }
+        if nms_class_agnostic {
+            (nms_bboxes, nms_classes_ids, nms_confidences) = match suppress_detections(nms_bboxes, nms_classes_ids, nms_confidences, nms_threshold, true) {
+                Ok((a, b, c)) => { (a, b, c) },
+                Err(err) => {
+                    println!("Can't apply class-agnostic NMS due the error {:?}", err);
+                    continue;
+                }
+            };
+        }
+        if model_fallback.observe(nms_bboxes.len()) {
+            println!("Model #{} returned no detections for a while. Switching to model #{}", active_model, model_fallback.get_active());
+        }
         
         /* Process detected objects and match them to existing ones */
         let mut tmp_detections = process_yolo_detections(
             &nms_bboxes,
             nms_classes_ids,
             nms_confidences,
-            width,
-            height,
+            frame_size.0,
+            frame_size.1,
             max_points_in_track,
             &net_classes,
             &target_classes,
             tracker_dt,
+            letterbox.as_ref(),
         );
+        if settings.rest_api.enable {
+            // Detections are changed by tracker, so keep their copy before matching
+            *latest_detections.lock().expect("Latest detections are poisoned [Mutex]") = Some(tmp_detections.snapshot(frame_size.0, frame_size.1));
+        }
 
-        let relative_time = received.overall_seconds;
+        let relative_time = detection_clock.relative_time(received.overall_seconds, Instant::now());
         match tracker.match_objects(&mut tmp_detections, relative_time) {
             Ok(_) => {},
             Err(err) => {
@@ -420,8 +990,21 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
             }
         };
 
+        let counting_active = match &schedule {
+            Some(sch) => sch.is_active_now(),
+            None => true
+        };
+        pipeline_control.set_schedule_active(counting_active);
+        if warmup.finish_if_elapsed(received.overall_seconds) {
+            println!("Warmup is over. Counting has been started");
+        }
+        // During warmup objects are tracked (and drawn) and their speeds are estimated, but they are not registered in zones
+        // (just like outside of the schedule windows)
+        let counting_active = counting_active && warmup.is_finished();
+
         let ds_guard = ds_tracker.read().expect("DataStorage is poisoned [RWLock]");
         let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+        let zone_grid = ds_guard.zone_grid.read().expect("Zones index is poisoned [RWLock]");
         
         // Reset current occupancy for zones 
         let current_ut = get_sys_time_in_secs();
@@ -433,6 +1016,12 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
             drop(zone);
         }
 
+        // Events (identifier and kind) fired during the current frame. Collected only when images should be saved for them
+        let mut pending_events: Vec<(String, String)> = vec![];
+        // Zones which are processed for every object regardless of its position (see get_position_independent_zones)
+        let position_independent_zones = get_position_independent_zones(&zones);
+        // Outside of the schedule windows objects are still tracked (and drawn), speeds, occupancy and incidents are updated,
+        // but objects are neither registered in zones nor counted
         for (object_id, object_extra) in tracker.objects_extra.iter_mut() {
             let object = tracker.engine.objects.get(object_id).unwrap();
             if object.get_no_match_times() > 1 {
@@ -441,64 +1030,316 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
                 continue;
             }
 
-            let times = &object_extra.times;
-            let last_time = times[times.len() - 1];
+            // Exact timestamps of the two last track points
+            let last_time = object_extra.get_last_seen();
+            let before_last_time = object_extra.get_previous_seen();
 
             let track: &Vec<mot_rs::utils::Point> = object.get_track();
             let last_point = &track[track.len() - 1];
+            // Partially visible object could trigger false crossing, so it is not allowed to cross virtual lines
+            let on_frame_edge = edge_margin_pixels.map(|margin| {
+                let object_bbox = object.get_bbox();
+                touches_frame_edge([object_bbox.x, object_bbox.y, object_bbox.width, object_bbox.height], frame_size.0, frame_size.1, margin)
+            }).unwrap_or(false);
+            // Point which is used for speed estimation
+            let (speed_x, speed_y) = speed_point(object, object_extra, speed_from_kalman);
 
+            // Overlapping zones are resolved by priority, so object is counted in one polygon zone at most
+            let point_zone_id = find_zone_for_point(&zones, zone_grid.get_candidate_zones(last_point.x, last_point.y), last_point.x, last_point.y);
+            // Moving from one zone to another zone of the same direction is a lane change. Unlike virtual line crossings it is not tied to any line:
+            // zone membership of the object is compared only (object could pass gap between zones)
+            // Lane changes are registered while counting is active only (see follow_lane)
+            if let Some(zone_id) = &point_zone_id {
+                let lane_of = |lane_zone_id: &str| zones.get(lane_zone_id).map(|zone_guarded| {
+                    let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+                    (zone.road_lane_direction, zone.road_lane_num)
+                });
+                if let Some(from_zone_id) = follow_lane(&mut object_extra.lane_state, zone_id, lane_of, counting_active, LANE_CHANGE_MIN_FRAMES) {
+                    if verbose {
+                        println!("Object {} changed lane: '{}' -> '{}'", object_extra.get_short_id(), from_zone_id, zone_id);
+                    }
+                    lane_changes.lock().expect("Lane changes are poisoned [Mutex]").register(LaneChangeEvent {
+                        timestamp: Utc::now(),
+                        object_id: object_id.to_string(),
+                        short_id: object_extra.get_short_id(),
+                        class_name: object_extra.get_classname(),
+                        from_zone_id: from_zone_id,
+                        to_zone_id: zone_id.clone(),
+                    });
+                }
+            }
             // Check if object is inside of any zone (optionally: check if it crossed the virtual line inside of it)
-            for (_, zone_guarded) in zones.iter() {
+            // Zones far from the object are skipped via the grid index
+            let zones_to_check = zone_grid.get_zones_to_check(last_point.x, last_point.y, &position_independent_zones);
+            for zone_guarded in zones_to_check.iter().filter_map(|zone_id| zones.get(zone_id)) {
                 let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
-                if !zone.contains_point(last_point.x, last_point.y) {
+                // Speed estimated via two lines with known distance between them (if zone has ones).
+                // It overrides speed estimated via spatial converter
+                let line_speed = match before_last_time {
+                    Some(before_last_time) if track.len() >= 2 => {
+                        let last_before_point = &track[track.len() - 2];
+                        zone.track_speed_lines(*object_id, last_point.x, last_point.y, last_time, last_before_point.x, last_before_point.y, before_last_time)
+                    },
+                    _ => zone.get_speed_lines().map(|_| -1.0)
+                };
+                if zone.get_count_mode() == CountMode::Line {
+                    // Polygon is just a region of interest: only virtual line crossings are registered
+                    if track.len() < 2 || on_frame_edge || !counting_active {
+                        continue
+                    }
+                    let last_before_point = &track[track.len() - 2];
+                    if let Some(t) = zone.crossed_virtual_line_on_track(last_point.x, last_point.y, track.iter().rev().skip(1).map(|pt| (pt.x, pt.y))) {
+                        let (speed, speed_confidence) = match (line_speed, &object_extra.spatial_info) {
+                            (Some(line_speed), _) => (line_speed, zone.speed_lines_confidence().unwrap_or(0.0)),
+                            (None, Some(spatial_info)) => (spatial_info.speed, spatial_info.speed_confidence),
+                            (None, None) => (-1.0, 0.0)
+                        };
+                        let crossing_time = interpolate_crossing_time(last_time, before_last_time, t);
+                        let crossing_point = interpolate_crossing_point(last_point, last_before_point, t);
+                        let acceleration = object_extra.spatial_info.as_ref().and_then(|spatial_info| spatial_info.acceleration);
+                        let counted = zone.register_or_update_object(*object_id, crossing_time, relative_time, speed, speed_confidence, acceleration, object_extra.get_classname(), true, crossing_point);
+                        if counted {
+                            lifetime_counts.lock().expect("Lifetime counts are poisoned [Mutex]").register(&zone.get_id(), &object_extra.get_classname());
+                        }
+                        if let Some(heading) = zone.heading_between(last_before_point.x, last_before_point.y, last_point.x, last_point.y) {
+                            zone.update_object_heading(object_id, heading);
+                        }
+                        if let Some(bbox_area) = object_extra.get_avg_bbox_area() {
+                            zone.update_object_bbox_area(object_id, bbox_area);
+                        }
+                        if !counted {
+                            // Crossings rejected by cooldown (or repeated ones) are neither transitions nor events
+                            continue
+                        }
+                        register_crossing(&mut od_matrix.lock().expect("OD matrix is poisoned [Mutex]"), &mut object_extra.last_crossing, &zone.get_id(), crossing_time, od_max_transition_seconds);
+                        if let Some(dump) = event_image_dump.as_ref() {
+                            let event_id = Uuid::new_v4().to_string();
+                            if include_raw_track {
+                                if let Err(err) = dump.save_track(&event_id, LINE_CROSSING, &event_track(object_id, object_extra, track)) {
+                                    println!("Can't save track for event '{}' due the error: {}", event_id, err);
+                                }
+                            }
+                            pending_events.push((event_id, LINE_CROSSING.to_string()));
+                        }
+                    }
+                    continue
+                }
+                if point_zone_id.as_ref() != Some(&zone.id) {
+                    continue
+                }
+                if !zone.is_class_allowed(&object_extra.get_classname()) {
+                    // Zone has its own classes allowlist (e.g. bus lane)
                     continue
                 }
                 zone.current_statistics.occupancy += 1; // Increment current load to match number of objects in zone
 
-                let projected_pt = zone.project_to_skeleton(last_point.x, last_point.y);
+                let projected_pt = zone.project_to_skeleton(speed_x, speed_y);
                 let pixels_per_meters = zone.get_skeleton_ppm();
 
-                let crossed = if track.len() >= 2 {
-                    let last_before_point = &track[track.len() - 2];
-                    zone.crossed_virtual_line(last_point.x, last_point.y, last_before_point.x, last_before_point.y)
+                let last_before_point = if track.len() >= 2 { &track[track.len() - 2] } else { last_point };
+                let crossing_fraction = if track.len() >= 2 && !on_frame_edge {
+                    zone.crossed_virtual_line_on_track(last_point.x, last_point.y, track.iter().rev().skip(1).map(|pt| (pt.x, pt.y)))
                 } else {
-                    false
+                    None
                 };
-                match object_extra.spatial_info {
+                let crossed = crossing_fraction.is_some();
+                // Registration time (and point) is either interpolated crossing time or just current time
+                let (registration_time, registration_point) = match crossing_fraction {
+                    Some(t) => (interpolate_crossing_time(last_time, before_last_time, t), interpolate_crossing_point(last_point, last_before_point, t)),
+                    None => (last_time, (last_point.x, last_point.y))
+                };
+                let line_speed_confidence = zone.speed_lines_confidence().unwrap_or(0.0);
+                let counted = match object_extra.spatial_info {
                     Some(ref mut spatial_info) => {
-                        spatial_info.update_avg(last_time, last_point.x, last_point.y, projected_pt.0, projected_pt.1, pixels_per_meters);
-                        zone.register_or_update_object(*object_id, last_time, relative_time, spatial_info.speed, object_extra.get_classname(), crossed);
+                        match speed_method {
+                            SpeedMethod::Skeleton => spatial_info.update_avg(last_time, speed_x, speed_y, projected_pt.0, projected_pt.1, pixels_per_meters),
+                            SpeedMethod::GroundPlane => {
+                                let step_meters = zone.ground_distance_meters(spatial_info.last_x, spatial_info.last_y, speed_x, speed_y);
+                                spatial_info.update_ground_plane(last_time, speed_x, speed_y, projected_pt.0, projected_pt.1, step_meters, pixels_per_meters);
+                            }
+                        };
+                        let (speed, speed_confidence) = match line_speed {
+                            Some(line_speed) => (line_speed, line_speed_confidence),
+                            None => (spatial_info.speed, spatial_info.speed_confidence)
+                        };
+                        counting_active && zone.register_or_update_object(*object_id, registration_time, relative_time, speed, speed_confidence, spatial_info.acceleration, object_extra.get_classname(), crossed, registration_point)
                     },
                     None => {
-                        object_extra.spatial_info = Some(SpatialInfo::new(last_time, last_point.x, last_point.y, projected_pt.0, projected_pt.1));
-                        zone.register_or_update_object(*object_id, last_time, relative_time, -1.0, object_extra.get_classname(), crossed);
+                        object_extra.spatial_info = Some(SpatialInfo::new(last_time, speed_x, speed_y, projected_pt.0, projected_pt.1));
+                        let (speed, speed_confidence) = match line_speed {
+                            Some(line_speed) => (line_speed, line_speed_confidence),
+                            None => (-1.0, 0.0)
+                        };
+                        counting_active && zone.register_or_update_object(*object_id, registration_time, relative_time, speed, speed_confidence, None, object_extra.get_classname(), crossed, registration_point)
+                    }
+                };
+                if counted {
+                    lifetime_counts.lock().expect("Lifetime counts are poisoned [Mutex]").register(&zone.get_id(), &object_extra.get_classname());
+                }
+                if let Some(bbox_area) = object_extra.get_avg_bbox_area() {
+                    zone.update_object_bbox_area(object_id, bbox_area);
+                }
+                if track.len() >= 2 {
+                    if let Some(heading) = zone.heading_between(last_before_point.x, last_before_point.y, last_point.x, last_point.y) {
+                        if let Some(spatial_info) = object_extra.spatial_info.as_mut() {
+                            spatial_info.heading = Some(heading);
+                        }
+                        zone.update_object_heading(object_id, heading);
+                    }
+                }
+                if crossed && counted {
+                    // Crossings rejected by cooldown (or repeated ones) are neither transitions nor events
+                    register_crossing(&mut od_matrix.lock().expect("OD matrix is poisoned [Mutex]"), &mut object_extra.last_crossing, &zone.get_id(), registration_time, od_max_transition_seconds);
+                    if let Some(dump) = event_image_dump.as_ref() {
+                        let event_id = Uuid::new_v4().to_string();
+                        if include_raw_track {
+                            if let Err(err) = dump.save_track(&event_id, LINE_CROSSING, &event_track(object_id, object_extra, track)) {
+                                println!("Can't save track for event '{}' due the error: {}", event_id, err);
+                            }
+                        }
+                        pending_events.push((event_id, LINE_CROSSING.to_string()));
+                    }
+                }
+                if let Some(incidents_settings) = &incidents_settings {
+                    if let Some(smoothed_speed) = object_extra.spatial_info.as_ref().and_then(|spatial_info| spatial_info.get_smoothed_speed()) {
+                        match object_extra.stop_dwell.update(last_time, smoothed_speed, incidents_settings.stopped_speed, incidents_settings.stopped_duration_seconds) {
+                            StopTransition::Stopped(dwell_seconds) => {
+                                println!("Object {} is stopped in zone '{}' for {:.1} seconds", object_extra.get_short_id(), zone.get_id(), dwell_seconds);
+                                let object_bbox = object.get_bbox();
+                                let event_id = Uuid::new_v4().to_string();
+                                if event_image_dump.is_some() && counting_active {
+                                    pending_events.push((event_id.clone(), STOPPED_VEHICLE.to_string()));
+                                }
+                                incidents.lock().expect("Incidents are poisoned [Mutex]").push(IncidentEvent {
+                                    event_id: event_id,
+                                    kind: STOPPED_VEHICLE.to_string(),
+                                    timestamp: Utc::now(),
+                                    zone_id: zone.get_id(),
+                                    object_id: object_id.to_string(),
+                                    short_id: object_extra.get_short_id(),
+                                    class_name: object_extra.get_classname(),
+                                    dwell_seconds: dwell_seconds,
+                                    bbox: [object_bbox.x, object_bbox.y, object_bbox.width, object_bbox.height],
+                                    centroid: [last_point.x, last_point.y],
+                                });
+                            },
+                            StopTransition::Moving => {
+                                println!("Object {} moves again in zone '{}'", object_extra.get_short_id(), zone.get_id());
+                            },
+                            StopTransition::Unchanged => {}
+                        }
                     }
                 }
                 drop(zone);
             }
         }
-        if enable_mjpeg || settings.output.enable {
+        // Occupancy is final for the current frame, so it could be used for density estimation
+        // Realtime occupancy is always updated, but it is accumulated for statistics only within the schedule windows
+        if counting_active {
+            for (_, zone_guarded) in zones.iter() {
+                let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+                zone.accumulate_occupancy();
+                drop(zone);
+            }
+        }
+        if settings.rest_api.enable || dedup_across_periods {
+            // Tracker is not shared with REST API, so it gets read-only copy of the objects. Statistics worker uses it to keep registrations of the alive objects
+            *tracked_objects.lock().expect("Tracked objects are poisoned [Mutex]") = snapshot_tracked_objects(&tracker, &zones, &zone_grid);
+        }
+        if let Some(writer) = frame_dump.as_mut() {
+            if let Err(err) = writer.write_record(&FrameRecord::from_tracker(relative_time, &tracker)) {
+                println!("Can't write frame dump due the error: {}", err);
+            }
+        }
+
+        // Raw frame for the events should be copied before any drawing. Frame is annotated for the events even if output is disabled
+        let raw_event_frame = if pending_events.is_empty() {
+            None
+        } else {
+            match frame.try_clone() {
+                Ok(raw_frame) => Some(raw_frame),
+                Err(err) => {
+                    println!("Can't copy frame for the events due the error: {}. Images for {} event(s) are skipped", err, pending_events.len());
+                    None
+                }
+            }
+        };
+        // No need to annotate and send frames for MJPEG when nobody watches the stream
+        let mjpeg_active = has_mjpeg_viewers(enable_mjpeg, &mjpeg_clients);
+        let annotate_frame = mjpeg_active || settings.output.enable || raw_event_frame.is_some();
+        if annotate_frame {
+            let line_side_points = if draw_line_side {
+                tracker.engine.objects.values()
+                    .filter(|object| object.get_no_match_times() <= 1)
+                    .filter_map(|object| object.get_track().last().map(|pt| (pt.x, pt.y)))
+                    .collect::<Vec<(f32, f32)>>()
+            } else {
+                vec![]
+            };
+            if draw_zone_grid && (mjpeg_active || settings.output.enable) {
+                draw::draw_zone_grid(&mut frame, zone_grid.get_cell_size(), &zone_grid.get_occupied_cells(), id_scalar);
+            }
             for (_, v) in zones.iter() {
                 let zone = v.lock().expect("Mutex poisoned");
+                if shade_by_occupancy {
+                    zone.draw_occupancy_shade(&mut frame);
+                }
                 zone.draw_geom(&mut frame);
                 zone.draw_skeleton(&mut frame);
-                zone.draw_current_intensity(&mut frame);
+                zone.draw_current_intensity(&mut frame, &text_style);
                 zone.draw_virtual_line(&mut frame);
+                if draw_line_side {
+                    zone.draw_line_sides(&mut frame, &line_side_points, &text_style);
+                }
+                zone.draw_speed_lines(&mut frame);
+                if draw_scale_bar {
+                    zone.draw_scale_bar(&mut frame, &text_style);
+                }
+                if draw_north_arrow {
+                    zone.draw_north_arrow(&mut frame, &text_style);
+                }
+                if draw_direction_arrow {
+                    zone.draw_direction_arrow(&mut frame);
+                }
                 drop(zone);
             }
+            if draw_od_flows && (mjpeg_active || settings.output.enable) {
+                let centroid_of = |zone_id: &str| zones.get(zone_id).and_then(|zone_guarded| zone_guarded.lock().expect("Zone is poisoned [Mutex]").get_pixel_centroid());
+                let period_counts = od_matrix.lock().expect("OD matrix is poisoned [Mutex]").get_period_counts();
+                let flows = period_counts.iter()
+                    .filter(|(from_zone_id, to_zone_id, _)| from_zone_id != EXTERNAL_ZONE && from_zone_id != to_zone_id)
+                    .filter_map(|(from_zone_id, to_zone_id, count)| Some((centroid_of(from_zone_id)?, centroid_of(to_zone_id)?, *count)))
+                    .collect::<Vec<((f32, f32), (f32, f32), u32)>>();
+                draw::draw_od_flows(&mut frame, &flows, id_scalar);
+            }
         }
 
         // We need drop here explicitly, since we need to release lock on zones for MJPEG / REST API / Redis publisher and statistics threads
+        drop(zone_grid);
         drop(zones);
         drop(ds_guard);
         
         /* Imshow + re-stream input video as MJPEG */
-        if enable_mjpeg || settings.output.enable {
-            draw::draw_trajectories(&mut frame, tracker, trajectory_scalar, trajectory_scalar_inverse);
-            draw::draw_bboxes(&mut frame, tracker, bbox_scalar, bbox_scalar_inverse);
-            draw::draw_identifiers(&mut frame, tracker, id_scalar, id_scalar_inverse);
-            draw::draw_speeds(&mut frame, tracker, id_scalar, id_scalar_inverse);
+        if annotate_frame {
+            draw::draw_trajectories(&mut frame, tracker, trajectory_scalar, trajectory_scalar_inverse, track_draw_points);
+            if draw_boxes {
+                draw::draw_object_boxes(&mut frame, tracker, &class_colors, &text_style);
+            } else {
+                draw::draw_bboxes(&mut frame, tracker, bbox_scalar, bbox_scalar_inverse);
+            }
+            draw::draw_identifiers(&mut frame, tracker, id_scalar, id_scalar_inverse, draw_short_ids, &text_style);
+            if !draw_boxes {
+                draw::draw_speeds(&mut frame, tracker, id_scalar, id_scalar_inverse, &text_style);
+            }
             draw::draw_projections(&mut frame, tracker, id_scalar, id_scalar_inverse);
+
+            if let (Some(dump), Some(raw_frame)) = (event_image_dump.as_ref(), raw_event_frame.as_ref()) {
+                for (event_id, kind) in pending_events.iter() {
+                    if let Err(err) = dump.save(event_id, kind, raw_frame, &frame) {
+                        println!("Can't save images for event '{}' due the error: {}", event_id, err);
+                    }
+                }
+            }
             
             if settings.output.enable {
                 match resize(&frame, &mut resized_frame, Size::new(output_width, output_height), 1.0, 1.0, 1) {
@@ -516,29 +1357,60 @@ fn run(settings: &AppSettings, path_to_config: &str, tracker: &mut Tracker, neur
                 }
             }
         }
-        if enable_mjpeg {
-            let mut buffer = Vector::<u8>::new();
-            let params = Vector::<i32>::new();
-            let encoded = imencode(".jpg", &frame, &mut buffer, &params).unwrap();
-            if !encoded {
-                println!("image has not been encoded");
-                continue;
-            }
-            match tx_mjpeg.send(buffer) {
-                Ok(_)=>{},
-                Err(_err) => {
-                    println!("Error on send frame to MJPEG thread: {}", _err)
+        if mjpeg_active {
+            // Frame is encoded by MJPEG thread, since every client could ask for its own quality
+            match frame.try_clone() {
+                Ok(mjpeg_frame) => {
+                    match tx_mjpeg.send(mjpeg_frame) {
+                        Ok(_)=>{},
+                        Err(_err) => {
+                            println!("Error on send frame to MJPEG thread: {}", _err)
+                        }
+                    };
+                },
+                Err(err) => {
+                    println!("Can't copy frame for MJPEG due the error: {}", err)
                 }
             };
         }
 
         
+    }
+    if images_input {
+        drop(tx_finished);
+        match capture_handle.join() {
+            Ok(_) => {
+                println!("Processing of images has been finished");
+            },
+            Err(_) => {
+                println!("Capture thread has panicked");
+            }
+        }
     }
     Ok(())
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    if args.len() >= 2 && args[1] == "--dump-openapi" {
+        let path_to_openapi = match args.get(2) {
+            Some(path) => path,
+            None => {
+                println!("Path to the output JSON file should be provided: --dump-openapi path.json");
+                process::exit(1);
+            }
+        };
+        match rest_api::dump_openapi(path_to_openapi) {
+            Ok(_) => {
+                println!("OpenAPI document has been written to '{}'", path_to_openapi);
+                process::exit(0);
+            },
+            Err(err) => {
+                println!("Can't dump OpenAPI document due the error: {}", err);
+                process::exit(1);
+            }
+        }
+    }
     let path_to_config = match args.len() {
         2 => {
             &args[1]
@@ -552,25 +1424,20 @@ fn main() {
     println!("Settings are:\n\t{}", app_settings);
 
     let mut tracker = Tracker::new(15, 0.3);
+    tracker.set_max_objects(app_settings.tracking.max_objects.unwrap_or(0));
+    tracker.set_keep_raw_centroids(app_settings.events.as_ref().and_then(|events| events.include_raw_track).unwrap_or(false));
     println!("Tracker is:\n\t{}", tracker);
 
-    let model_format = match app_settings.detection.get_nn_format() {
-        Ok(mf) => mf,
-        Err(err) => {
-            println!("Can't get model format due the error: {}", err);
-            return
-        }
-    };
-
-    let model_version = match app_settings.detection.get_nn_version() {
-        Ok(mf) => mf,
-        Err(err) => {
-            println!("Can't get model version due the error: {}", err);
-            return
-        }
-    };
+    // Thread count should be set before any network is loaded, since OpenCV picks it up on the first parallel region
+    if let Err(err) = set_num_threads(opencv_num_threads(app_settings.detection.num_threads.unwrap_or(0))) {
+        println!("Can't set number of OpenCV threads due the error: {}", err);
+    }
+    match get_num_threads() {
+        Ok(num_threads) => println!("OpenCV uses {} threads", num_threads),
+        Err(err) => println!("Can't get number of OpenCV threads due the error: {}", err),
+    }
 
-    let mut neural_net = match prepare_neural_net(model_format, model_version, &app_settings.detection.network_weights, app_settings.detection.network_cfg.clone(), (app_settings.detection.net_width, app_settings.detection.net_height)) {
+    let (mut neural_nets, cuda_enabled) = match prepare_neural_nets(&app_settings.detection.get_models()) {
         Ok(nn) => nn,
         Err(err) => {
             println!("Can't prepare neural network due the error: {}", err);
@@ -583,7 +1450,28 @@ fn main() {
         None => { false }
     };
     
-    match run(&app_settings, path_to_config, &mut tracker, &mut *neural_net, verbose) {
+    let target_classes = match &app_settings.detection.target_classes {
+        Some(classes) if !classes.is_empty() => classes.clone(),
+        _ => app_settings.detection.net_classes.clone()
+    };
+    let model_info = ModelInfo {
+        format: neural_nets[0].format.clone(),
+        version: neural_nets[0].version.clone(),
+        net_width: neural_nets[0].net_size.0,
+        net_height: neural_nets[0].net_size.1,
+        fallback_models: neural_nets.len() - 1,
+        cuda_enabled: cuda_enabled,
+        net_classes: app_settings.detection.net_classes.clone(),
+        target_classes: target_classes,
+    };
+
+    let model_selftest = run_model_selftest(&mut neural_nets[0], app_settings.detection.selftest_image.as_deref(), app_settings.detection.conf_threshold, app_settings.detection.nms_threshold);
+    match &model_selftest.error {
+        None => println!("Model self-test on '{}': {} detections in {:.1} ms", model_selftest.image, model_selftest.detections, model_selftest.elapsed_ms),
+        Some(err) => println!("[WARNING]: Model self-test on '{}' failed due the error: {}", model_selftest.image, err),
+    };
+
+    match run(&app_settings, path_to_config, &mut tracker, &mut neural_nets, model_info, model_selftest, verbose) {
         Ok(_) => {},
         Err(_err) => {
             println!("Error in main thread: {}", _err);
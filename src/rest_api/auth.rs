@@ -0,0 +1,222 @@
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+    HttpResponse,
+    web,
+};
+use std::collections::HashMap;
+use futures::future::LocalBoxFuture;
+use crate::rest_api::ErrorResponse;
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+// Query parameter which could carry the key when headers can't be set (e.g. '<img src="/live_streaming?api_key=...">')
+pub const API_KEY_QUERY_PARAM: &str = "api_key";
+
+// Checks if provided key matches expected one
+//
+// expected - configured API key. None means that authorization is not required
+// provided - value of the 'X-API-Key' header (if any)
+//
+pub fn is_authorized(expected: Option<&str>, provided: Option<&str>) -> bool {
+    match expected {
+        Some(key) => provided == Some(key),
+        None => true
+    }
+}
+
+// Value of the 'api_key' query parameter (if any)
+fn query_api_key(query_string: &str) -> Option<String> {
+    web::Query::<HashMap<String, String>>::from_query(query_string)
+        .ok()
+        .and_then(|query| query.get(API_KEY_QUERY_PARAM).cloned())
+}
+
+// Middleware which requires 'X-API-Key' header to match configured key
+// When key is not configured every request passes through
+pub struct ApiKeyAuth {
+    api_key: Option<String>,
+    // Accept the key via 'api_key' query parameter when there is no header
+    allow_query_param: bool,
+}
+
+impl ApiKeyAuth {
+    pub fn new(api_key: Option<String>) -> Self {
+        // Empty key is the same as no key at all
+        ApiKeyAuth {
+            api_key: api_key.filter(|key| !key.is_empty()),
+            allow_query_param: false,
+        }
+    }
+    // For the endpoints which are opened by browser directly (e.g. MJPEG stream in '<img>' tag)
+    pub fn with_query_param(mut self) -> Self {
+        self.allow_query_param = true;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiKeyAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyAuthMiddleware {
+            service,
+            api_key: self.api_key.clone(),
+            allow_query_param: self.allow_query_param,
+        }))
+    }
+}
+
+pub struct ApiKeyAuthMiddleware<S> {
+    service: S,
+    api_key: Option<String>,
+    allow_query_param: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let provided = match req.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok()) {
+            Some(value) => Some(value.to_string()),
+            None if self.allow_query_param => query_api_key(req.query_string()),
+            None => None
+        };
+        if !is_authorized(self.api_key.as_deref(), provided.as_deref()) {
+            let error_text = if self.allow_query_param {
+                format!("Missing or wrong {} header ('{}' query parameter)", API_KEY_HEADER, API_KEY_QUERY_PARAM)
+            } else {
+                format!("Missing or wrong {} header", API_KEY_HEADER)
+            };
+            let response = HttpResponse::Unauthorized()
+                .json(ErrorResponse {
+                    error_text: error_text,
+                })
+                .map_into_right_body();
+            return Box::pin(async { Ok(req.into_response(response)) });
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test as actix_test, web, App};
+
+    #[actix_web::test]
+    async fn test_api_key_auth() {
+        let app = actix_test::init_service(
+            App::new().service(
+                web::scope("/mutations")
+                    .wrap(ApiKeyAuth::new(Some("secret".to_string())))
+                    .route("/zones/create", web::post().to(|| async { HttpResponse::Ok().finish() })),
+            ),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/mutations/zones/create")
+            .insert_header((API_KEY_HEADER, "secret"))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = actix_test::TestRequest::post()
+            .uri("/mutations/zones/create")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = actix_test::TestRequest::post()
+            .uri("/mutations/zones/create")
+            .insert_header((API_KEY_HEADER, "wrong"))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    #[actix_web::test]
+    async fn test_api_key_auth_disabled() {
+        let app = actix_test::init_service(
+            App::new().service(
+                web::scope("/mutations")
+                    .wrap(ApiKeyAuth::new(None))
+                    .route("/zones/create", web::post().to(|| async { HttpResponse::Ok().finish() })),
+            ),
+        )
+        .await;
+        let req = actix_test::TestRequest::post()
+            .uri("/mutations/zones/create")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    #[actix_web::test]
+    async fn test_api_key_auth_query_param() {
+        let app = actix_test::init_service(
+            App::new()
+                .service(
+                    web::resource("/live_streaming")
+                        .wrap(ApiKeyAuth::new(Some("secret".to_string())).with_query_param())
+                        .route(web::get().to(|| async { HttpResponse::Ok().finish() })),
+                )
+                .service(
+                    web::resource("/zones")
+                        .wrap(ApiKeyAuth::new(Some("secret".to_string())))
+                        .route(web::get().to(|| async { HttpResponse::Ok().finish() })),
+                ),
+        )
+        .await;
+        for (uri, expected) in vec![
+            ("/live_streaming?api_key=secret", StatusCode::OK),
+            ("/live_streaming?quality=40&api_key=secret", StatusCode::OK),
+            ("/live_streaming?api_key=wrong", StatusCode::UNAUTHORIZED),
+            ("/live_streaming", StatusCode::UNAUTHORIZED),
+            // Query parameter is not accepted unless it is enabled explicitly
+            ("/zones?api_key=secret", StatusCode::UNAUTHORIZED),
+        ] {
+            let req = actix_test::TestRequest::get().uri(uri).to_request();
+            let resp = actix_test::call_service(&app, req).await;
+            assert_eq!(resp.status(), expected, "uri: {}", uri);
+        }
+        // Header is still accepted
+        let req = actix_test::TestRequest::get()
+            .uri("/live_streaming")
+            .insert_header((API_KEY_HEADER, "secret"))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    #[test]
+    fn test_is_authorized() {
+        assert_eq!(is_authorized(Some("secret"), Some("secret")), true);
+        assert_eq!(is_authorized(Some("secret"), None), false);
+        assert_eq!(is_authorized(Some("secret"), Some("wrong")), false);
+        assert_eq!(is_authorized(None, None), true);
+        assert_eq!(is_authorized(None, Some("any")), true);
+    }
+}
@@ -0,0 +1,36 @@
+use actix_web::{HttpRequest, HttpResponse, http::header, web, Error};
+use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+use crate::rest_api::toml_mutations::effective_settings;
+
+// Clients could ask for TOML (the same format as configuration file) via 'Accept' header. JSON is the default
+fn wants_toml(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("toml"))
+        .unwrap_or(false)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Configuration file mutations",
+    path = "/api/config",
+    responses(
+        (status = 200, description = "Current configuration (including zones mutations) with redacted secrets. JSON by default, TOML when 'Accept' header contains 'application/toml'"),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+pub async fn get_config(req: HttpRequest, data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let mut settings = effective_settings(&data);
+    settings.redact_secrets();
+    if !wants_toml(&req) {
+        return Ok(HttpResponse::Ok().json(settings));
+    }
+    match toml::to_string(&settings) {
+        Ok(docs) => Ok(HttpResponse::Ok().content_type("application/toml").body(docs)),
+        Err(err) => Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+            error_text: format!("Can't prepare TOML due the error: {}", err),
+        })),
+    }
+}
@@ -0,0 +1,84 @@
+use actix_web::{HttpResponse, web, Error, http::StatusCode};
+use chrono::Utc;
+use serde::Serialize;
+use utoipa::ToSchema;
+use opencv::prelude::*;
+use crate::lib::dataset::{dataset_annotations, write_dataset_sample};
+use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+
+/// Information about captured dataset sample
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DatasetCaptureResponse {
+    /// Name of the written files (without extension)
+    #[schema(example = "20230419T113245123")]
+    pub sample_name: String,
+    /// Number of the written annotations
+    #[schema(example = 7)]
+    pub annotations_written: usize,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Dataset",
+    path = "/api/dataset/capture_now",
+    responses(
+        (status = 200, description = "The latest frame and annotations of the currently tracked objects have been written", body = DatasetCaptureResponse),
+        (status = 400, description = "Dataset capture is not configured", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse),
+        (status = 503, description = "No frame available yet", body = ErrorResponse)
+    )
+)]
+pub async fn capture_now(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let (directory, label_format) = match data.app_settings.dataset.as_ref().filter(|dataset| dataset.enable) {
+        Some(dataset) => match dataset.get_label_format() {
+            Ok(label_format) => (dataset.directory.clone(), label_format),
+            Err(err) => {
+                return Ok(HttpResponse::build(StatusCode::BAD_REQUEST).json(ErrorResponse {
+                    error_text: format!("{}", err)
+                }));
+            }
+        },
+        None => {
+            return Ok(HttpResponse::build(StatusCode::BAD_REQUEST).json(ErrorResponse {
+                error_text: "Dataset capture is not enabled. Check [dataset] section of configuration".to_string()
+            }));
+        }
+    };
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let latest_frame = ds_guard.latest_frame.lock().expect("Latest frame is poisoned [Mutex]");
+    let frame = match latest_frame.as_ref().map(|frame| frame.try_clone()) {
+        Some(Ok(frame)) => frame,
+        Some(Err(err)) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: format!("Can't copy the latest frame due the error: {}", err)
+            }));
+        },
+        None => {
+            return Ok(HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE).json(ErrorResponse {
+                error_text: "No frame available yet".to_string()
+            }));
+        }
+    };
+    drop(latest_frame);
+    // Snapshot could be one frame behind the latest frame, since detection thread updates it after tracking
+    let tracked_objects = ds_guard.tracked_objects.lock().expect("Tracked objects are poisoned [Mutex]");
+    let annotations = dataset_annotations(&tracked_objects, &data.app_settings.detection.net_classes, frame.cols() as f32, frame.rows() as f32);
+    drop(tracked_objects);
+    drop(ds_guard);
+
+    let sample_name = Utc::now().format("%Y%m%dT%H%M%S%3f").to_string();
+    match write_dataset_sample(&directory, &sample_name, &frame, &annotations, &data.app_settings.detection.net_classes, label_format) {
+        Ok(annotations_written) => {
+            return Ok(HttpResponse::Ok().json(DatasetCaptureResponse {
+                sample_name: sample_name,
+                annotations_written: annotations_written,
+            }));
+        },
+        Err(err) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: format!("Can't write dataset sample due the error: {}", err)
+            }));
+        }
+    };
+}
@@ -0,0 +1,74 @@
+use actix_web::{HttpResponse, web, Error, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+
+/// Detector output of the latest processed frame
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatestDetections {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// UTC time when the frame has been processed
+    #[schema(value_type = String, example = "2023-01-02T15:00:00.125Z")]
+    pub captured_at: DateTime<Utc>,
+    /// Width of the frame in pixels
+    #[schema(example = 1280.0)]
+    pub frame_width: f32,
+    /// Height of the frame in pixels
+    #[schema(example = 720.0)]
+    pub frame_height: f32,
+    /// Detections after NMS and target classes filtering, but before tracking
+    pub data: Vec<Detection>,
+}
+
+/// Single detection
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Detection {
+    /// Class of the object
+    #[schema(example = "car")]
+    pub class_name: String,
+    /// Confidence of the detector
+    #[schema(example = 0.87)]
+    pub confidence: f32,
+    /// Bounding box [x, y, width, height] in pixels of the original frame
+    #[schema(example = json!([412.0, 290.0, 64.0, 48.0]))]
+    pub bbox: [f32; 4],
+}
+
+#[utoipa::path(
+    get,
+    tag = "Model",
+    path = "/api/detections/latest",
+    responses(
+        (status = 200, description = "Raw detections of the latest processed frame", body = LatestDetections),
+        (status = 503, description = "No frame has been processed yet", body = ErrorResponse)
+    )
+)]
+pub async fn latest_detections(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let latest_detections = ds_guard.latest_detections.lock().expect("Latest detections are poisoned [Mutex]");
+    let ans = match latest_detections.as_ref() {
+        Some(snapshot) => LatestDetections {
+            equipment_id: ds_guard.id.clone(),
+            captured_at: snapshot.captured_at,
+            frame_width: snapshot.frame_width,
+            frame_height: snapshot.frame_height,
+            data: snapshot.objects.iter().map(|object| Detection {
+                class_name: object.class_name.clone(),
+                confidence: object.confidence,
+                bbox: object.bbox,
+            }).collect(),
+        },
+        None => {
+            return Ok(HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE).json(ErrorResponse {
+                error_text: "No frame has been processed yet".to_string()
+            }));
+        }
+    };
+    drop(latest_detections);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
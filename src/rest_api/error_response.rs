@@ -0,0 +1,10 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Error response. It is shared by every endpoint, so OpenAPI components have the single schema for it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Error message
+    #[schema(example = "No such zone. Requested ID: dir_0_lane_1")]
+    pub error_text: String,
+}
@@ -0,0 +1,68 @@
+use actix_web::{HttpResponse, web, Error};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// Performance counters of the capture -> detection pipeline
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PipelineHealth {
+    /// Number of non-empty frames read from video source
+    #[schema(example = 1500)]
+    pub frames_captured: u64,
+    /// Number of frames which has been passed to detection
+    #[schema(example = 700)]
+    pub frames_sent: u64,
+    /// Number of frames which could not be passed to detection immediately since it was busy
+    #[schema(example = 50)]
+    pub frames_backpressured: u64,
+    /// Number of frames which has been dropped because detection could not keep up
+    #[schema(example = 50)]
+    pub frames_dropped: u64,
+    /// Is pipeline paused via REST API (frames are still captured, but not processed)
+    #[schema(example = false)]
+    pub paused: bool,
+    /// Is current time inside of the schedule windows (statistics are accumulated). Always true when there is no schedule
+    #[schema(example = true)]
+    pub schedule_active: bool,
+    /// Is Redis publisher connected. Null when publisher is disabled
+    #[schema(example = true)]
+    pub redis_connected: Option<bool>,
+    /// Number of reconnects to Redis after connection has been lost
+    #[schema(example = 0)]
+    pub redis_reconnects: u64,
+    /// Number of snapshots waiting for Redis connection
+    #[schema(example = 0)]
+    pub redis_pending_snapshots: usize,
+    /// Number of snapshots dropped because Redis was unreachable for too long
+    #[schema(example = 0)]
+    pub redis_dropped_snapshots: u64,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Health",
+    path = "/api/health/pipeline",
+    responses(
+        (status = 200, description = "Pipeline performance counters", body = PipelineHealth)
+    )
+)]
+pub async fn pipeline_health(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let snapshot = ds_guard.metrics.snapshot();
+    let paused = ds_guard.control.is_paused();
+    let schedule_active = ds_guard.control.is_schedule_active();
+    let redis_status = ds_guard.redis_status.snapshot();
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(PipelineHealth {
+        frames_captured: snapshot.frames_captured,
+        frames_sent: snapshot.frames_sent,
+        frames_backpressured: snapshot.frames_backpressured,
+        frames_dropped: snapshot.frames_dropped,
+        paused: paused,
+        schedule_active: schedule_active,
+        redis_connected: if redis_status.enabled { Some(redis_status.connected) } else { None },
+        redis_reconnects: redis_status.reconnects,
+        redis_pending_snapshots: redis_status.pending,
+        redis_dropped_snapshots: redis_status.dropped,
+    }));
+}
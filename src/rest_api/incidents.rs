@@ -0,0 +1,89 @@
+use actix_web::{HttpResponse, web, Error};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// The latest incidents
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AllIncidents {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// Name of the site (see 'equipment_info.location_name' in configuration). Null when it is not configured
+    #[schema(example = "Main St. / 5th Ave.")]
+    pub location_name: Option<String>,
+    /// Position of the site [lon, lat]. Null when it is not configured
+    #[schema(example = json!([-3.70588, 40.39306]))]
+    pub location_wgs84: Option<[f32; 2]>,
+    /// Incidents from the oldest to the newest
+    pub data: Vec<Incident>,
+}
+
+/// Detected incident
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Incident {
+    /// Unique identifier of the event. Images saved for the event (if enabled) contain it in the file names
+    #[schema(example = "5f0c7a52-3d1e-4b8a-9a55-2f9e4c8b1d07")]
+    pub event_id: String,
+    /// Type of the incident. Possible values: 'stopped_vehicle'
+    #[schema(example = "stopped_vehicle")]
+    pub kind: String,
+    /// UTC time when incident has been detected
+    #[schema(value_type = String, example = "2023-04-19T11:32:45.123456789Z")]
+    pub timestamp: DateTime<Utc>,
+    /// Zone where incident has been detected
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    /// Object identifier
+    #[schema(example = "9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21")]
+    pub object_id: String,
+    /// Human-readable object identifier
+    #[schema(example = 42)]
+    pub short_id: u64,
+    /// Class of the object
+    #[schema(example = "car")]
+    pub class_name: String,
+    /// Number of seconds the object has been moving slower than threshold when incident has been detected
+    #[schema(example = 30.2)]
+    pub dwell_seconds: f32,
+    /// Bounding box of the object [x, y, width, height] in pixels of the original (full-resolution) frame
+    #[schema(example = json!([1052.0, 604.0, 181.0, 117.0]))]
+    pub bbox: [f32; 4],
+    /// Bottom center point of the object [x, y] in pixels of the original (full-resolution) frame
+    #[schema(example = json!([1142.5, 721.0]))]
+    pub centroid: [f32; 2],
+}
+
+#[utoipa::path(
+    get,
+    tag = "Incidents",
+    path = "/api/incidents",
+    responses(
+        (status = 200, description = "The latest incidents (e.g. stopped vehicles)", body = AllIncidents)
+    )
+)]
+pub async fn all_incidents(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let incidents = ds_guard.incidents.lock().expect("Incidents are poisoned [Mutex]");
+    let ans = AllIncidents {
+        equipment_id: ds_guard.id.clone(),
+        location_name: ds_guard.location_name.clone(),
+        location_wgs84: ds_guard.location_wgs84,
+        data: incidents.get_events().into_iter().map(|event| Incident {
+            event_id: event.event_id,
+            kind: event.kind,
+            timestamp: event.timestamp,
+            zone_id: event.zone_id,
+            object_id: event.object_id,
+            short_id: event.short_id,
+            class_name: event.class_name,
+            dwell_seconds: event.dwell_seconds,
+            bbox: event.bbox,
+            centroid: event.centroid,
+        }).collect(),
+    };
+    drop(incidents);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
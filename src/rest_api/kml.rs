@@ -0,0 +1,163 @@
+use std::fmt::Write;
+use actix_web::{HttpResponse, web, Error};
+use crate::lib::spatial::{SpatialCRS, DEFAULT_COORDINATE_PRECISION, round_point};
+use crate::rest_api::APIStorage;
+
+// Opacity of the zone polygon fill in KML (0-255). Outline is opaque
+const KML_FILL_ALPHA: u8 = 0x40;
+
+// Geographic geometry of the zone prepared for KML export
+pub struct KmlZone {
+    pub id: String,
+    pub color_rgb: [i16; 3],
+    // Polygon vertices as [lon, lat] (not closed)
+    pub polygon: Vec<[f32; 2]>,
+    // Virtual line as [lon, lat] pair and its color
+    pub virtual_line: Option<([[f32; 2]; 2], [i16; 3])>,
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// KML color is 'aabbggrr' hex string
+fn kml_color(rgb: [i16; 3], alpha: u8) -> String {
+    let channel = |value: i16| value.max(0).min(255) as u8;
+    format!("{:02x}{:02x}{:02x}{:02x}", alpha, channel(rgb[2]), channel(rgb[1]), channel(rgb[0]))
+}
+
+// KML expects longitude first, then latitude (the same order as GeoJSON), so swapped input is rejected by ranges check
+fn is_lon_lat(pt: &[f32; 2]) -> bool {
+    pt[0].is_finite() && pt[1].is_finite() && pt[0].abs() <= 180.0 && pt[1].abs() <= 90.0
+}
+
+// Tuples 'lon,lat,alt' separated by spaces. Altitude is always zero (clamped to ground)
+fn kml_coordinates(points: &[[f32; 2]]) -> String {
+    points.iter()
+        .map(|pt| format!("{},{},0", pt[0], pt[1]))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Document with one polygon placemark per zone and one line placemark per virtual line. Zones are sorted by identifier
+// Zones with less than 3 vertices or with coordinates out of lon/lat ranges are skipped
+pub fn zones_kml(zones: &[KmlZone]) -> String {
+    let mut sorted = zones.iter().collect::<Vec<&KmlZone>>();
+    sorted.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n<name>Detection zones</name>\n");
+    for zone in sorted {
+        if zone.polygon.len() < 3 || !zone.polygon.iter().all(is_lon_lat) {
+            continue;
+        }
+        let id = escape_xml(&zone.id);
+        let mut ring = zone.polygon.clone();
+        ring.push(zone.polygon[0]);
+        let _ = write!(kml,
+            "<Placemark id=\"{id}\">\n<name>{id}</name>\n<Style><LineStyle><color>{line_color}</color><width>2</width></LineStyle><PolyStyle><color>{fill_color}</color></PolyStyle></Style>\n<Polygon><outerBoundaryIs><LinearRing><coordinates>{coordinates}</coordinates></LinearRing></outerBoundaryIs></Polygon>\n</Placemark>\n",
+            id = id,
+            line_color = kml_color(zone.color_rgb, 0xff),
+            fill_color = kml_color(zone.color_rgb, KML_FILL_ALPHA),
+            coordinates = kml_coordinates(&ring),
+        );
+        if let Some((line, color_rgb)) = &zone.virtual_line {
+            if !line.iter().all(is_lon_lat) {
+                continue;
+            }
+            let _ = write!(kml,
+                "<Placemark id=\"{id}_virtual_line\">\n<name>{id} virtual line</name>\n<Style><LineStyle><color>{line_color}</color><width>3</width></LineStyle></Style>\n<LineString><coordinates>{coordinates}</coordinates></LineString>\n</Placemark>\n",
+                id = id,
+                line_color = kml_color(*color_rgb, 0xff),
+                coordinates = kml_coordinates(line),
+            );
+        }
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+#[utoipa::path(
+    get,
+    tag = "Zones",
+    path = "/api/zones/kml",
+    responses(
+        (status = 200, description = "Detections zones and virtual lines as KML placemarks. Only zones with 'wgs84' spatial coordinates are exported", body = String, content_type = "application/vnd.google-earth.kml+xml")
+    )
+)]
+pub async fn all_zones_kml(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let coordinate_precision = data.app_settings.rest_api.coordinate_precision.unwrap_or(DEFAULT_COORDINATE_PRECISION);
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+    let mut kml_zones = vec![];
+    for (zone_id, zone_guarded) in zones.iter() {
+        let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+        if zone.get_crs() != SpatialCRS::WGS84 {
+            drop(zone);
+            continue;
+        }
+        let virtual_line = zone.get_virtual_line().and_then(|vl| {
+            let a = zone.pixel_to_world(vl.line_cvf[0].x, vl.line_cvf[0].y)?;
+            let b = zone.pixel_to_world(vl.line_cvf[1].x, vl.line_cvf[1].y)?;
+            Some(([round_point(a, coordinate_precision), round_point(b, coordinate_precision)], vl.color))
+        });
+        kml_zones.push(KmlZone {
+            id: zone_id.clone(),
+            color_rgb: zone.get_color(),
+            polygon: zone.get_spatial_coordinates_epsg4326().iter().map(|pt| round_point([pt.x, pt.y], coordinate_precision)).collect(),
+            virtual_line: virtual_line,
+        });
+        drop(zone);
+    }
+    let filename = format!("zones_{}.kml", ds_guard.id);
+    drop(zones);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok()
+        .content_type("application/vnd.google-earth.kml+xml")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .body(zones_kml(&kml_zones)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_zones_kml() {
+        let zones = vec![
+            KmlZone {
+                id: "dir_0_lane_1".to_string(),
+                color_rgb: [255, 0, 10],
+                polygon: vec![[37.61, 55.75], [37.62, 55.75], [37.62, 55.76], [37.61, 55.76]],
+                virtual_line: Some(([[37.61, 55.755], [37.62, 55.755]], [0, 0, 255])),
+            },
+            // Swapped lat/lon is out of range and it is skipped
+            KmlZone {
+                id: "swapped".to_string(),
+                color_rgb: [0, 0, 0],
+                polygon: vec![[55.75, 137.61], [55.75, 137.62], [55.76, 137.62]],
+                virtual_line: None,
+            },
+            KmlZone {
+                id: "a<b>".to_string(),
+                color_rgb: [0, 255, 0],
+                polygon: vec![[10.0, 20.0], [11.0, 20.0], [11.0, 21.0]],
+                virtual_line: None,
+            },
+        ];
+        let kml = zones_kml(&zones);
+        assert!(kml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">"));
+        assert!(kml.contains("<coordinates>37.61,55.75,0 37.62,55.75,0 37.62,55.76,0 37.61,55.76,0 37.61,55.75,0</coordinates>"));
+        assert!(kml.contains("<LineString><coordinates>37.61,55.755,0 37.62,55.755,0</coordinates></LineString>"));
+        // RGB (255, 0, 10) is 'aabbggrr'
+        assert!(kml.contains("<LineStyle><color>ff0a00ff</color>"));
+        assert!(kml.contains("<PolyStyle><color>400a00ff</color>"));
+        assert!(kml.contains("<Placemark id=\"a&lt;b&gt;\">"));
+        assert!(!kml.contains("swapped"));
+        // Placemarks are sorted by zone identifier
+        assert!(kml.find("a&lt;b&gt;").unwrap() < kml.find("dir_0_lane_1").unwrap());
+        assert!(kml.ends_with("</Document>\n</kml>\n"));
+    }
+}
@@ -0,0 +1,93 @@
+use actix_web::{HttpResponse, web, Error};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// Lane changes between the zones of the same direction
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AllLaneChanges {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// UTC start of the last finished statistics period
+    #[schema(value_type = String, example = "2023-04-19T11:32:00Z")]
+    pub period_start: DateTime<Utc>,
+    /// UTC end of the last finished statistics period
+    #[schema(value_type = String, example = "2023-04-19T11:32:30Z")]
+    pub period_end: DateTime<Utc>,
+    /// Number of lane changes for each pair of zones during the last finished period
+    pub counts: Vec<LaneChangeCount>,
+    /// The latest lane changes from the oldest to the newest
+    pub events: Vec<LaneChange>,
+}
+
+/// Number of lane changes from one zone to another
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LaneChangeCount {
+    /// Zone which object has left
+    #[schema(example = "dir_0_lane_1")]
+    pub from_zone_id: String,
+    /// Zone which object has entered
+    #[schema(example = "dir_0_lane_2")]
+    pub to_zone_id: String,
+    #[schema(example = 3)]
+    pub count: u32,
+}
+
+/// Detected lane change
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LaneChange {
+    /// UTC time when lane change has been detected
+    #[schema(value_type = String, example = "2023-04-19T11:32:45.123456789Z")]
+    pub timestamp: DateTime<Utc>,
+    /// Object identifier
+    #[schema(example = "9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21")]
+    pub object_id: String,
+    /// Human-readable object identifier
+    #[schema(example = 42)]
+    pub short_id: u64,
+    /// Class of the object
+    #[schema(example = "car")]
+    pub class_name: String,
+    /// Zone which object has left
+    #[schema(example = "dir_0_lane_1")]
+    pub from_zone_id: String,
+    /// Zone which object has entered
+    #[schema(example = "dir_0_lane_2")]
+    pub to_zone_id: String,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Statistics",
+    path = "/api/lane_changes",
+    responses(
+        (status = 200, description = "Lane changes counted for each pair of zones (the last finished period) and the latest lane change events", body = AllLaneChanges)
+    )
+)]
+pub async fn all_lane_changes(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let lane_changes = ds_guard.lane_changes.lock().expect("Lane changes are poisoned [Mutex]");
+    let ans = AllLaneChanges {
+        equipment_id: ds_guard.id.clone(),
+        period_start: ds_guard.period_start,
+        period_end: ds_guard.period_end,
+        counts: lane_changes.get_period_counts().into_iter().map(|(from_zone_id, to_zone_id, count)| LaneChangeCount {
+            from_zone_id: from_zone_id,
+            to_zone_id: to_zone_id,
+            count: count,
+        }).collect(),
+        events: lane_changes.get_events().into_iter().map(|event| LaneChange {
+            timestamp: event.timestamp,
+            object_id: event.object_id,
+            short_id: event.short_id,
+            class_name: event.class_name,
+            from_zone_id: event.from_zone_id,
+            to_zone_id: event.to_zone_id,
+        }).collect(),
+    };
+    drop(lane_changes);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
@@ -0,0 +1,80 @@
+use actix_web::{HttpResponse, web, Error};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+use crate::lib::lifetime_counts::LifetimeCounts;
+
+/// Cumulative number of counted vehicles since start of the process (or since the last reset)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AllLifetimeCounts {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// UTC time when counting has been started or reset
+    #[schema(value_type = String, example = "2023-04-19T08:00:00Z")]
+    pub since: DateTime<Utc>,
+    /// Counts for each zone and vehicle class
+    pub data: Vec<LifetimeCount>,
+}
+
+/// Number of counted vehicles of the class in the zone
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LifetimeCount {
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    #[schema(example = "car")]
+    pub class_name: String,
+    #[schema(example = 15324)]
+    pub count: u64,
+}
+
+impl AllLifetimeCounts {
+    fn new(equipment_id: String, lifetime_counts: &LifetimeCounts) -> Self {
+        AllLifetimeCounts {
+            equipment_id: equipment_id,
+            since: lifetime_counts.get_since(),
+            data: lifetime_counts.get_counts().into_iter().map(|(zone_id, class_name, count)| LifetimeCount {
+                zone_id: zone_id,
+                class_name: class_name,
+                count: count,
+            }).collect(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    tag = "Statistics",
+    path = "/api/zones/lifetime",
+    responses(
+        (status = 200, description = "Number of counted vehicles per zone and class since start (independent of statistics periods)", body = AllLifetimeCounts)
+    )
+)]
+pub async fn zones_lifetime_counts(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let lifetime_counts = ds_guard.lifetime_counts.lock().expect("Lifetime counts are poisoned [Mutex]");
+    let ans = AllLifetimeCounts::new(ds_guard.id.clone(), &lifetime_counts);
+    drop(lifetime_counts);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
+
+#[utoipa::path(
+    post,
+    tag = "Statistics",
+    path = "/api/mutations/zones/lifetime/reset",
+    responses(
+        (status = 200, description = "Lifetime counts have been reset. Response contains counts right before the reset", body = AllLifetimeCounts)
+    )
+)]
+pub async fn reset_zones_lifetime_counts(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let mut lifetime_counts = ds_guard.lifetime_counts.lock().expect("Lifetime counts are poisoned [Mutex]");
+    let ans = AllLifetimeCounts::new(ds_guard.id.clone(), &lifetime_counts);
+    lifetime_counts.reset();
+    drop(lifetime_counts);
+    drop(ds_guard);
+    println!("Lifetime counts have been reset");
+    return Ok(HttpResponse::Ok().json(ans));
+}
@@ -1,8 +1,15 @@
 use actix_web::{HttpResponse, web, Responder};
+use serde::Deserialize;
 use crate::rest_api::APIStorage;
 
-pub async fn add_new_client(ds: web::Data<APIStorage>) -> impl Responder {
-    let rx = ds.mjpeg_broadcaster.lock().unwrap().add_client();
+#[derive(Deserialize)]
+pub struct MJPEGClientQuery {
+    // JPEG quality for this connection in [1; 100]. Out of range values are clamped. Configured quality is used when absent
+    pub quality: Option<i32>,
+}
+
+pub async fn add_new_client(ds: web::Data<APIStorage>, query: web::Query<MJPEGClientQuery>) -> impl Responder {
+    let rx = ds.mjpeg_broadcaster.lock().unwrap().add_client(query.quality);
     HttpResponse::Ok()
         .append_header(("Cache-Control", "no-store, must-revalidate"))
         .append_header(("Pragma", "no-cache"))
@@ -1,10 +1,27 @@
 mod mjpeg_page;
 mod mjpeg_client;
 mod zones_list;
+mod kml;
+mod zones_calibration;
+mod zones_preview;
+mod health;
+mod pipeline_control;
+mod model_info;
+mod tracked_objects;
+mod detections;
+mod incidents;
+mod lane_changes;
+mod od_matrix;
+mod lifetime_counts;
+mod dataset;
 pub mod zones_stats;
 mod zones_mutations;
 mod toml_mutations;
+mod config;
 mod rest_api;
 mod services;
+mod tls;
+mod auth;
+mod error_response;
 
-pub use self::{rest_api::*, services::*, zones_mutations::VirtualLineRequestData};
\ No newline at end of file
+pub use self::{rest_api::*, services::*, tls::*, auth::*, error_response::ErrorResponse, model_info::{ModelInfo, ModelSelfTest}, zones_mutations::VirtualLineRequestData};
\ No newline at end of file
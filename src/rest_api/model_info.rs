@@ -0,0 +1,82 @@
+use actix_web::{HttpResponse, web, Error};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// Information about loaded neural network
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelInfo {
+    /// Model format
+    #[schema(example = "Darknet")]
+    pub format: String,
+    /// Model version
+    #[schema(example = "V4")]
+    pub version: String,
+    /// Network input width
+    #[schema(example = 416)]
+    pub net_width: i32,
+    /// Network input height
+    #[schema(example = 256)]
+    pub net_height: i32,
+    /// Number of fallback models (used when the active one returns no detections for a while)
+    #[schema(example = 0)]
+    pub fallback_models: usize,
+    /// Is CUDA used for inference
+    #[schema(example = false)]
+    pub cuda_enabled: bool,
+    /// All classes which network can detect
+    #[schema(example = json!(["car", "motorbike", "bus", "train", "truck"]))]
+    pub net_classes: Vec<String>,
+    /// Classes which are used for analytics
+    #[schema(example = json!(["car", "bus"]))]
+    pub target_classes: Vec<String>,
+}
+
+/// Result of the inference on the test frame. It is done once at start with the primary model
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelSelfTest {
+    /// Path to the test image or "blank" for the synthetic frame of the network input size
+    #[schema(example = "./data/selftest.jpg")]
+    pub image: String,
+    /// Number of detections (after NMS) on the test frame
+    #[schema(example = 3)]
+    pub detections: usize,
+    /// Inference time in milliseconds (the first inference includes network initialization)
+    #[schema(example = 57.3)]
+    pub elapsed_ms: f64,
+    /// When self-test has been done
+    #[schema(value_type = String, example = "2023-04-19T11:32:45.123456789Z")]
+    pub tested_at: DateTime<Utc>,
+    /// Error text. Not empty when either test image could not be read or inference failed
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Model",
+    path = "/api/model/selftest",
+    responses(
+        (status = 200, description = "Inference on the test frame succeeded", body = ModelSelfTest),
+        (status = 500, description = "Inference on the test frame failed", body = ModelSelfTest)
+    )
+)]
+pub async fn model_selftest(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    if data.model_selftest.error.is_some() {
+        return Ok(HttpResponse::InternalServerError().json(&data.model_selftest));
+    }
+    return Ok(HttpResponse::Ok().json(&data.model_selftest));
+}
+
+#[utoipa::path(
+    get,
+    tag = "Model",
+    path = "/api/model/info",
+    responses(
+        (status = 200, description = "Information about loaded neural network", body = ModelInfo)
+    )
+)]
+pub async fn model_info(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    return Ok(HttpResponse::Ok().json(&data.model_info));
+}
@@ -0,0 +1,68 @@
+use actix_web::{HttpResponse, web, Error};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// Origin-destination matrix by virtual line crossings
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ODMatrixInfo {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// Name of the site (see 'equipment_info.location_name' in configuration). Null when it is not configured
+    #[schema(example = "Main St. / 5th Ave.")]
+    pub location_name: Option<String>,
+    /// Position of the site [lon, lat]. Null when it is not configured
+    #[schema(example = json!([-3.70588, 40.39306]))]
+    pub location_wgs84: Option<[f32; 2]>,
+    /// UTC start of the last finished statistics period
+    #[schema(value_type = String, example = "2023-04-19T11:32:00Z")]
+    pub period_start: DateTime<Utc>,
+    /// UTC end of the last finished statistics period
+    #[schema(value_type = String, example = "2023-04-19T11:32:30Z")]
+    pub period_end: DateTime<Utc>,
+    /// Number of transitions for each pair of zones during the last finished period
+    pub data: Vec<ODTransition>,
+}
+
+/// Number of objects which crossed destination zone after origin one
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ODTransition {
+    /// Zone crossed by the object previously. Value 'external' means that there is no such zone (or crossing has been too long ago)
+    #[schema(example = "dir_0_lane_0")]
+    pub from_zone_id: String,
+    /// Zone crossed by the object
+    #[schema(example = "dir_1_lane_0")]
+    pub to_zone_id: String,
+    #[schema(example = 7)]
+    pub count: u32,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Statistics",
+    path = "/api/od",
+    responses(
+        (status = 200, description = "Origin-destination transitions between zones (by virtual line crossings) for the last finished period", body = ODMatrixInfo)
+    )
+)]
+pub async fn od_matrix(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let od_matrix = ds_guard.od_matrix.lock().expect("OD matrix is poisoned [Mutex]");
+    let ans = ODMatrixInfo {
+        equipment_id: ds_guard.id.clone(),
+        location_name: ds_guard.location_name.clone(),
+        location_wgs84: ds_guard.location_wgs84,
+        period_start: ds_guard.period_start,
+        period_end: ds_guard.period_end,
+        data: od_matrix.get_period_counts().into_iter().map(|(from_zone_id, to_zone_id, count)| ODTransition {
+            from_zone_id: from_zone_id,
+            to_zone_id: to_zone_id,
+            count: count,
+        }).collect(),
+    };
+    drop(od_matrix);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
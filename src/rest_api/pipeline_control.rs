@@ -0,0 +1,51 @@
+use actix_web::{HttpResponse, web, Error};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+
+/// State of the processing pipeline
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PipelineState {
+    /// Is detection/tracking/counting paused
+    #[schema(example = true)]
+    pub paused: bool,
+    /// Has the request changed the state
+    #[schema(example = true)]
+    pub changed: bool,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Pipeline",
+    path = "/api/pipeline/pause",
+    responses(
+        (status = 200, description = "Pipeline has been paused (frames are still read, but nothing is detected or counted)", body = PipelineState)
+    )
+)]
+pub async fn pause_pipeline(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let changed = ds_guard.control.pause();
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(PipelineState {
+        paused: true,
+        changed: changed,
+    }));
+}
+
+#[utoipa::path(
+    post,
+    tag = "Pipeline",
+    path = "/api/pipeline/resume",
+    responses(
+        (status = 200, description = "Pipeline has been resumed", body = PipelineState)
+    )
+)]
+pub async fn resume_pipeline(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let changed = ds_guard.control.resume();
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(PipelineState {
+        paused: false,
+        changed: changed,
+    }));
+}
@@ -1,11 +1,12 @@
 use std::sync::{Arc, RwLock};
-use actix_web::{web, http, App, HttpServer};
+use actix_web::{web, http, http::header::HeaderName, App, HttpServer};
 use actix_cors::Cors;
 
 use crate::settings::AppSettings;
 use crate::rest_api::services;
+use crate::rest_api::{ModelInfo, ModelSelfTest};
 use crate::lib::data_storage::ThreadedDataStorage;
-use crate::lib::mjpeg_streaming::Broadcaster;
+use crate::lib::mjpeg_streaming::{Broadcaster, DEFAULT_MJPEG_QUALITY};
 use std::sync::{
     Mutex,
     mpsc::{
@@ -13,25 +14,32 @@ use std::sync::{
     }
 };
 use opencv::{
-    core::Vector,
+    core::Mat,
 };
+use rustls::ServerConfig;
 
 pub struct APIStorage {
     pub data_storage: ThreadedDataStorage,
     pub app_settings: AppSettings,
     pub settings_filename: String,
-    pub mjpeg_broadcaster: web::Data<Mutex<Broadcaster>>
+    pub mjpeg_broadcaster: web::Data<Mutex<Broadcaster>>,
+    pub model_info: ModelInfo,
+    pub model_selftest: ModelSelfTest,
 }
 
 #[actix_web::main]
-pub async fn start_rest_api(server_host: String, server_port: i32, data_storage: ThreadedDataStorage, enable_mjpeg: bool, rx_frames_data: Receiver<Vector<u8>>, app_settings: AppSettings, settings_filename: &str) -> std::io::Result<()> {
+pub async fn start_rest_api(server_host: String, server_port: i32, data_storage: ThreadedDataStorage, enable_mjpeg: bool, rx_frames_data: Receiver<Mat>, app_settings: AppSettings, settings_filename: &str, tls_config: Option<ServerConfig>, model_info: ModelInfo, model_selftest: ModelSelfTest) -> std::io::Result<()> {
     let bind_address = format!("{}:{}", server_host, server_port);
-    println!("REST API is starting on host:port {}:{}", server_host, server_port);
+    println!("REST API is starting on host:port {}:{} (TLS is '{}')", server_host, server_port, tls_config.is_some());
+    let mjpeg_quality = app_settings.rest_api.mjpeg_streaming.as_ref().and_then(|mjpeg| mjpeg.quality).unwrap_or(DEFAULT_MJPEG_QUALITY);
+    let mjpeg_clients = data_storage.read().expect("DataStorage is poisoned [RWLock]").mjpeg_clients.clone();
     let storage = APIStorage{
         data_storage: data_storage,
         app_settings: app_settings,
         settings_filename: settings_filename.to_string(),
-        mjpeg_broadcaster: web::Data::new(Mutex::new(Broadcaster::default())),
+        mjpeg_broadcaster: web::Data::new(Mutex::new(Broadcaster::new(mjpeg_quality, mjpeg_clients))),
+        model_info: model_info,
+        model_selftest: model_selftest,
     };
 
     /* Enable MJPEG streaming server if needed */
@@ -39,11 +47,18 @@ pub async fn start_rest_api(server_host: String, server_port: i32, data_storage:
         Broadcaster::spawn_reciever(storage.mjpeg_broadcaster.clone(), rx_frames_data);
     }
 
+    /* Optional API key for the mutation endpoints (and for the read-only ones if requested) */
+    let api_key = storage.app_settings.rest_api.api_key.clone();
+    let read_only_api_key = match storage.app_settings.rest_api.protect_read_only {
+        Some(true) => api_key.clone(),
+        _ => None
+    };
+
     let data = web::Data::new(storage);
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
-            .allowed_headers(vec![http::header::ORIGIN, http::header::AUTHORIZATION, http::header::CONTENT_TYPE, http::header::CONTENT_LENGTH, http::header::ACCEPT, http::header::ACCEPT_ENCODING])
+            .allowed_headers(vec![http::header::ORIGIN, http::header::AUTHORIZATION, http::header::CONTENT_TYPE, http::header::CONTENT_LENGTH, http::header::ACCEPT, http::header::ACCEPT_ENCODING, HeaderName::from_static("x-api-key")])
             .allowed_methods(vec!["GET", "POST"])
             .expose_headers(vec![http::header::CONTENT_LENGTH])
             .supports_credentials()
@@ -51,11 +66,16 @@ pub async fn start_rest_api(server_host: String, server_port: i32, data_storage:
         App::new()
             .wrap(cors)
             .app_data(data.clone())
-            .configure(services::init_routes(enable_mjpeg))
-    })
-    .bind(&bind_address)
-    .unwrap_or_else(|_| panic!("Could not bind server to address: {}", &bind_address))
-    .run()
-    .await
+            .configure(services::init_routes(enable_mjpeg, api_key.clone(), read_only_api_key.clone()))
+    });
+    /* MJPEG streaming is served by the same server, so it goes over TLS too */
+    let server = match tls_config {
+        Some(config) => server.bind_rustls(&bind_address, config),
+        None => server.bind(&bind_address)
+    };
+    server
+        .unwrap_or_else(|_| panic!("Could not bind server to address: {}", &bind_address))
+        .run()
+        .await
 }
 
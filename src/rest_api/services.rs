@@ -3,26 +3,52 @@ use actix_web_static_files::ResourceFiles;
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
 use crate::rest_api::{
+    ApiKeyAuth,
     zones_mutations,
     toml_mutations,
+    config,
     mjpeg_page,
     mjpeg_client,
     zones_list,
-    zones_stats
+    kml,
+    zones_calibration,
+    zones_preview,
+    zones_stats,
+    health,
+    pipeline_control,
+    model_info,
+    tracked_objects,
+    detections,
+    incidents,
+    lane_changes,
+    od_matrix,
+    lifetime_counts,
+    dataset
 };
 
 async fn say_ping() -> impl Responder {
     HttpResponse::Ok().body("pong")
 }
 
-pub fn init_routes(enable_mjpeg: bool) -> impl Fn(&mut web::ServiceConfig) {
+// api_key - key required for the mutation endpoints
+// read_only_api_key - key required for the read-only endpoints (zones, statistics, live stream)
+pub fn init_routes(enable_mjpeg: bool, api_key: Option<String>, read_only_api_key: Option<String>) -> impl Fn(&mut web::ServiceConfig) {
     move |cfg| {
         let generated = generate();
 
         if enable_mjpeg {
+            // Browser can't set headers for '<img>' tag, so key could be passed via query parameter too
             cfg
-                .route("/live", web::get().to(mjpeg_page::mjpeg_page))
-                .route("/live_streaming", web::get().to(mjpeg_client::add_new_client));
+                .service(
+                    web::resource("/live")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()).with_query_param())
+                    .route(web::get().to(mjpeg_page::mjpeg_page))
+                )
+                .service(
+                    web::resource("/live_streaming")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()).with_query_param())
+                    .route(web::get().to(mjpeg_client::add_new_client))
+                );
         }
 
         cfg
@@ -31,23 +57,93 @@ pub fn init_routes(enable_mjpeg: bool) -> impl Fn(&mut web::ServiceConfig) {
                 .service(RapiDoc::with_openapi("/docs.json", ApiDoc::openapi()))
                 .service(RapiDoc::new("/api/docs.json").path("/docs"))
                 .route("/ping", web::get().to(say_ping))
+                .service(
+                    web::scope("/model")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("/info", web::get().to(model_info::model_info))
+                    .route("/selftest", web::get().to(model_info::model_selftest))
+                )
+                .service(
+                    web::scope("/health")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("/pipeline", web::get().to(health::pipeline_health))
+                )
+                .service(
+                    web::scope("/pipeline")
+                    .wrap(ApiKeyAuth::new(api_key.clone()))
+                    .route("/pause", web::post().to(pipeline_control::pause_pipeline))
+                    .route("/resume", web::post().to(pipeline_control::resume_pipeline))
+                )
                 .service(
                     web::scope("/polygons")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
                     .route("/geojson", web::get().to(zones_list::all_zones_list))
                 )
+                .service(
+                    web::scope("/zones")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("/preview", web::post().to(zones_preview::zone_preview))
+                    .route("/stats.csv", web::get().to(zones_stats::all_zones_stats_csv))
+                    .route("/kml", web::get().to(kml::all_zones_kml))
+                    .route("/{zone_id}/homography", web::get().to(zones_calibration::zone_homography))
+                    .route("/{zone_id}/hourly", web::get().to(zones_stats::zone_hourly_totals))
+                    .route("/{zone_id}/export", web::get().to(zones_mutations::export_zone))
+                    .route("/lifetime", web::get().to(lifetime_counts::zones_lifetime_counts))
+                )
                 .service(
                     web::scope("/stats")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
                     .route("/all", web::get().to(zones_stats::all_zones_stats))
                 )
+                .service(
+                    web::scope("/tracked_objects")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("", web::get().to(tracked_objects::all_tracked_objects))
+                )
+                .service(
+                    web::scope("/detections")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("/latest", web::get().to(detections::latest_detections))
+                )
+                .service(
+                    web::scope("/incidents")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("", web::get().to(incidents::all_incidents))
+                )
+                .service(
+                    web::scope("/lane_changes")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("", web::get().to(lane_changes::all_lane_changes))
+                )
+                .service(
+                    web::scope("/od")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("", web::get().to(od_matrix::od_matrix))
+                )
+                .service(
+                    web::scope("/dataset")
+                    .wrap(ApiKeyAuth::new(api_key.clone()))
+                    .route("/capture_now", web::post().to(dataset::capture_now))
+                )
                 .service(
                     web::scope("/realtime")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
                     .route("/occupancy", web::get().to(zones_stats::all_zones_occupancy))
                 )
+                .service(
+                    web::scope("/config")
+                    .wrap(ApiKeyAuth::new(read_only_api_key.clone()))
+                    .route("", web::get().to(config::get_config))
+                )
                 .service(
                     web::scope("/mutations")
+                    .wrap(ApiKeyAuth::new(api_key.clone()))
                     .route("/zones/create", web::post().to(zones_mutations::create_zone))
                     .route("/zones/update", web::post().to(zones_mutations::update_zone))
+                    .route("/zones/virtual_line", web::post().to(zones_mutations::update_zone_virtual_line))
                     .route("/zones/delete", web::post().to(zones_mutations::delete_zone))
+                    .route("/zones/rename", web::post().to(zones_mutations::rename_zone))
+                    .route("/zones/lifetime/reset", web::post().to(lifetime_counts::reset_zones_lifetime_counts))
                     .route("/replace_all", web::post().to(zones_mutations::replace_all))
                     .route("/save_toml", web::get().to(toml_mutations::save_toml))
                 )
@@ -64,18 +160,46 @@ use utoipa_rapidoc::RapiDoc;
 #[openapi(
     paths(
         zones_list::all_zones_list,
+        kml::all_zones_kml,
+        zones_calibration::zone_homography,
+        zones_preview::zone_preview,
         zones_stats::all_zones_stats,
+        zones_stats::all_zones_stats_csv,
         zones_stats::all_zones_occupancy,
+        zones_stats::zone_hourly_totals,
+        tracked_objects::all_tracked_objects,
+        detections::latest_detections,
+        incidents::all_incidents,
+        lane_changes::all_lane_changes,
+        od_matrix::od_matrix,
+        lifetime_counts::zones_lifetime_counts,
+        lifetime_counts::reset_zones_lifetime_counts,
+        dataset::capture_now,
+        health::pipeline_health,
+        pipeline_control::pause_pipeline,
+        pipeline_control::resume_pipeline,
+        model_info::model_info,
+        model_info::model_selftest,
+        zones_mutations::export_zone,
         zones_mutations::create_zone,
         zones_mutations::update_zone,
+        zones_mutations::update_zone_virtual_line,
         zones_mutations::delete_zone,
+        zones_mutations::rename_zone,
         zones_mutations::replace_all,
         toml_mutations::save_toml,
+        config::get_config,
     ),
     tags(
         (name = "Zones", description = "Main information about detection zones"),
         (name = "Statistics", description = "Aggregated and real-time statistics in the detections zones"),
         (name = "Zones mutations", description = "A way to mutate information about detection zones"),
+        (name = "Tracking", description = "Currently tracked objects"),
+        (name = "Incidents", description = "Detected incidents (e.g. stopped vehicles)"),
+        (name = "Dataset", description = "Capturing samples for the training dataset"),
+        (name = "Health", description = "Performance information about the processing pipeline"),
+        (name = "Pipeline", description = "Control of the processing pipeline"),
+        (name = "Model", description = "Information about loaded neural network"),
     ),
     components(
         // We need to import all possible schemas since `utopia` can't discover recursive schemas (yet?)
@@ -90,19 +214,50 @@ use utoipa_rapidoc::RapiDoc;
             crate::rest_api::zones_stats::VehicleTypeParameters,
             crate::rest_api::zones_stats::AllZonesRealtimeStatistics,
             crate::rest_api::zones_stats::ZoneRealtime,
+            crate::rest_api::zones_stats::ZoneHourlyTotals,
+            crate::rest_api::ErrorResponse,
+            crate::rest_api::tracked_objects::AllTrackedObjects,
+            crate::rest_api::tracked_objects::TrackedObject,
+            crate::rest_api::incidents::AllIncidents,
+            crate::rest_api::incidents::Incident,
+            crate::rest_api::lane_changes::AllLaneChanges,
+            crate::rest_api::lane_changes::LaneChangeCount,
+            crate::rest_api::lane_changes::LaneChange,
+            crate::rest_api::od_matrix::ODMatrixInfo,
+            crate::rest_api::od_matrix::ODTransition,
+            crate::rest_api::lifetime_counts::AllLifetimeCounts,
+            crate::rest_api::lifetime_counts::LifetimeCount,
+            crate::rest_api::dataset::DatasetCaptureResponse,
             crate::rest_api::zones_mutations::VirtualLineRequestData,
             crate::rest_api::zones_mutations::ZoneCreateRequest,
             crate::rest_api::zones_mutations::ZoneCreateResponse,
             crate::rest_api::zones_mutations::ZoneUpdateRequest,
             crate::rest_api::zones_mutations::ZoneUpdateResponse,
+            crate::rest_api::zones_mutations::ZoneVirtualLineRequest,
             crate::rest_api::zones_mutations::ZoneDeleteRequest,
             crate::rest_api::zones_mutations::ZoneDeleteResponse,
+            crate::rest_api::zones_mutations::ZoneRenameRequest,
+            crate::rest_api::zones_mutations::ZoneRenameResponse,
             crate::rest_api::zones_mutations::ZonesOverwriteAllRequest,
             crate::rest_api::zones_mutations::ZonesOverwriteAllResponse,
-            crate::rest_api::zones_mutations::ErrorResponse,
             crate::rest_api::toml_mutations::UpdateTOMLResponse,
-            crate::rest_api::toml_mutations::ErrorResponse,
+            crate::rest_api::zones_calibration::ZoneHomographyResponse,
+            crate::rest_api::zones_preview::ZonePreviewRequest,
+            crate::rest_api::health::PipelineHealth,
+            crate::rest_api::pipeline_control::PipelineState,
+            crate::rest_api::model_info::ModelInfo,
+            crate::rest_api::model_info::ModelSelfTest,
+            crate::rest_api::detections::LatestDetections,
+            crate::rest_api::detections::Detection,
         ),
     )
 )]
-struct ApiDoc;
\ No newline at end of file
+struct ApiDoc;
+
+// Writes OpenAPI document (the same one which is served at /docs.json) to the given file
+// Could be used for client generation without running the whole application
+pub fn dump_openapi(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let json = ApiDoc::openapi().to_pretty_json()?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+use rustls::{
+    Certificate,
+    PrivateKey,
+    ServerConfig
+};
+use rustls_pemfile::{
+    certs,
+    pkcs8_private_keys,
+    rsa_private_keys
+};
+
+use crate::settings::RestAPISettings;
+
+// Prepares rustls configuration for REST API if TLS is enabled
+//
+// Returns None when TLS section is missing or disabled, so plain HTTP should be used
+//
+pub fn prepare_tls_config(settings: &RestAPISettings) -> Result<Option<ServerConfig>, Box<dyn Error>> {
+    match &settings.tls {
+        Some(tls) if tls.enable => {
+            let config = load_rustls_config(&tls.cert_path, &tls.key_path)?;
+            Ok(Some(config))
+        },
+        _ => Ok(None)
+    }
+}
+
+// Loads PEM-encoded certificate chain and private key
+//
+// cert_path - path to the certificate chain
+// key_path - path to the private key. Both PKCS#8 and RSA keys are supported
+//
+pub fn load_rustls_config(cert_path: &str, key_path: &str) -> Result<ServerConfig, Box<dyn Error>> {
+    let cert_file = File::open(cert_path).map_err(|err| format!("Can't open TLS certificate '{}' due the error: {}", cert_path, err))?;
+    let cert_chain: Vec<Certificate> = certs(&mut BufReader::new(cert_file))
+        .map_err(|err| format!("Can't parse TLS certificate '{}' due the error: {}", cert_path, err))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if cert_chain.is_empty() {
+        return Err(format!("No certificates found in '{}'", cert_path).into());
+    }
+
+    let key_file = File::open(key_path).map_err(|err| format!("Can't open TLS private key '{}' due the error: {}", key_path, err))?;
+    let mut keys: Vec<PrivateKey> = pkcs8_private_keys(&mut BufReader::new(key_file))
+        .map_err(|err| format!("Can't parse TLS private key '{}' due the error: {}", key_path, err))?
+        .into_iter()
+        .map(PrivateKey)
+        .collect();
+    if keys.is_empty() {
+        // Fallback to the RSA (PKCS#1) keys
+        let key_file = File::open(key_path).map_err(|err| format!("Can't open TLS private key '{}' due the error: {}", key_path, err))?;
+        keys = rsa_private_keys(&mut BufReader::new(key_file))
+            .map_err(|err| format!("Can't parse TLS private key '{}' due the error: {}", key_path, err))?
+            .into_iter()
+            .map(PrivateKey)
+            .collect();
+    }
+    if keys.is_empty() {
+        return Err(format!("No private keys found in '{}'", key_path).into());
+    }
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, keys.remove(0))
+        .map_err(|err| format!("Can't use TLS certificate/key pair due the error: {}", err))?;
+    Ok(config)
+}
@@ -2,16 +2,17 @@ use actix_web::{HttpResponse, web, Error};
 use serde::Serialize;
 use utoipa::ToSchema;
 use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+use crate::settings::AppSettings;
 use crate::settings::RoadLanesSettings;
 use crate::settings::VirtualLineSettings;
-
-/// Error response
-#[derive(Debug, Serialize, ToSchema)]
-pub struct ErrorResponse {
-    /// Error message
-    #[schema(example = "Can't save TOML due the error")]
-    pub error_text: String,
-}
+use crate::settings::CongestionSettings;
+use crate::settings::ZoneSpatialSettings;
+use crate::settings::SpeedHistogramSettings;
+use crate::settings::SpeedLinesSettings;
+use crate::settings::CrossingCooldownSettings;
+use crate::lib::spatial::SpatialCRS;
+use crate::lib::zones::ZoneKind;
 
 /// Response for the save configuration file request
 #[derive(Debug, Serialize, ToSchema)]
@@ -32,6 +33,22 @@ pub struct UpdateTOMLResponse<'a> {
 )]
 pub async fn save_toml(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
     println!("Saving TOML configuration");
+    let setting_cloned = effective_settings(&data);
+    match setting_cloned.save(&data.settings_filename) {
+        Ok(_) => {},
+        Err(_err) => {
+            return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error_text: format!("Can't save TOML due the error: {}", _err),
+            }));
+        },
+    };
+    return Ok(HttpResponse::Ok().json(UpdateTOMLResponse{
+        message: "ok"
+    }));
+}
+
+// Settings which software is running with: zones are taken from the storage (so they include all mutations made via API)
+pub fn effective_settings(data: &APIStorage) -> AppSettings {
     let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
     let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
     let mut setting_cloned = data.app_settings.get_copy_no_roads();
@@ -40,13 +57,34 @@ pub async fn save_toml(data: web::Data<APIStorage>) -> Result<HttpResponse, Erro
         setting_cloned.road_lanes.push(RoadLanesSettings{
             color_rgb: [zone.color[2] as i16, zone.color[1] as i16, zone.color[0] as i16], // BGR -> RGB
             geometry: zone.get_pixel_coordinates().iter().map(|pt| [pt.x as i32, pt.y as i32]).collect(),
-            geometry_wgs84: zone.get_spatial_coordinates_epsg4326().iter().map(|pt| [pt.x, pt.y]).collect(),
+            geometry_wgs84: match zone.get_spatial_coordinates_epsg4326() {
+                geom_wgs84 if !geom_wgs84.is_empty() => Some(geom_wgs84.iter().map(|pt| [pt.x, pt.y]).collect()),
+                _ => None
+            },
+            geometry_local_meters: match zone.get_crs() {
+                SpatialCRS::LocalMeters => Some(zone.get_spatial_coordinates().iter().map(|pt| [pt.x, pt.y]).collect()),
+                SpatialCRS::WGS84 => None
+            },
             lane_direction: zone.road_lane_direction,
             lane_number: zone.road_lane_num,
+            count_mode: Some(zone.get_count_mode().to_string()),
+            kind: if zone.get_kind() != ZoneKind::default() { Some(zone.get_kind().to_string()) } else { None },
+            priority: if zone.get_priority() != 0 { Some(zone.get_priority()) } else { None },
+            target_classes: zone.get_allowed_classes().map(|classes| {
+                let mut classes = classes.iter().cloned().collect::<Vec<String>>();
+                classes.sort();
+                classes
+            }),
             virtual_line: match &zone.get_virtual_line() {
                 Some(vl) => {
                     Some(VirtualLineSettings{
-                        geometry: vl.line,
+                        geometry: if vl.normalized {
+                            vl.line_normalized
+                        } else {
+                            [[vl.line[0][0] as f32, vl.line[0][1] as f32], [vl.line[1][0] as f32, vl.line[1][1] as f32]]
+                        },
+                        normalized: if vl.normalized { Some(true) } else { None },
+                        margin_px: if vl.margin_px > 0.0 { Some(vl.margin_px) } else { None },
                         color_rgb: [vl.color[0] as i16, vl.color[1] as i16, vl.color[2] as i16], // BGR -> RGB
                         direction: vl.direction.to_string(),
                     })
@@ -55,6 +93,50 @@ pub async fn save_toml(data: web::Data<APIStorage>) -> Result<HttpResponse, Erro
                     None
                 }
             },
+            congestion: {
+                let thresholds = zone.get_congestion_thresholds();
+                Some(CongestionSettings{
+                    moderate: Some(thresholds.moderate),
+                    congested: Some(thresholds.congested),
+                    jammed: Some(thresholds.jammed),
+                })
+            },
+            spatial: Some(ZoneSpatialSettings{
+                crs: Some(zone.get_crs().to_string()),
+                gsd_meters_per_pixel: zone.get_gsd(),
+            }),
+            speed_hist: {
+                let bins = zone.get_speed_histogram_bins();
+                Some(SpeedHistogramSettings{
+                    bin_width: Some(bins.bin_width),
+                    max_speed: Some(bins.max_speed),
+                })
+            },
+            speed_lines: match zone.get_speed_lines() {
+                Some(sl) => {
+                    Some(SpeedLinesSettings{
+                        geometry_a: sl.line_a.line,
+                        geometry_b: sl.line_b.line,
+                        known_distance_meters: sl.known_distance_meters,
+                        color_rgb: Some([sl.line_a.color[0], sl.line_a.color[1], sl.line_a.color[2]]),
+                    })
+                },
+                None => {
+                    None
+                }
+            },
+            crossing_cooldown: match zone.get_crossing_cooldown() {
+                Some(cooldown) => {
+                    Some(CrossingCooldownSettings{
+                        radius_pixels: cooldown.radius_pixels,
+                        window_seconds: cooldown.window_seconds,
+                    })
+                },
+                None => {
+                    None
+                }
+            },
+            speed_limits: if zone.get_speed_limits().is_empty() { None } else { Some(zone.get_speed_limits().clone()) },
         });
         drop(zone);
     }
@@ -64,16 +146,6 @@ pub async fn save_toml(data: web::Data<APIStorage>) -> Result<HttpResponse, Erro
         // If option is empty, set one
         setting_cloned.detection.target_classes = Some(setting_cloned.detection.net_classes.clone());
     }
-    match setting_cloned.save(&data.settings_filename) {
-        Ok(_) => {},
-        Err(_err) => {
-            return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
-                error_text: format!("Can't save TOML due the error: {}", _err),
-            }));
-        },
-    };
-    return Ok(HttpResponse::Ok().json(UpdateTOMLResponse{
-        message: "ok"
-    }));
+    setting_cloned
 }
 
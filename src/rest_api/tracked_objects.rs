@@ -0,0 +1,86 @@
+use actix_web::{HttpResponse, web, Error};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::lib::spatial::{DEFAULT_COORDINATE_PRECISION, round_point};
+use crate::rest_api::APIStorage;
+
+/// Currently tracked objects
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AllTrackedObjects {
+    /// Equipment identifier. Should match software configuration
+    #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
+    pub equipment_id: String,
+    /// Set of objects
+    pub data: Vec<TrackedObject>,
+}
+
+/// Tracked object's state on the latest processed frame
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrackedObject {
+    /// Object identifier
+    #[schema(example = "9d1a4b4e-0b8c-4a5e-9d5f-0a3a7d1f6c21")]
+    pub id: String,
+    /// Human-readable identifier (increasing monotonically for every new object)
+    #[schema(example = 42)]
+    pub short_id: u64,
+    /// Class of the object
+    #[schema(example = "car")]
+    pub class_name: String,
+    /// Bounding box [x, y, width, height] in pixels
+    #[schema(example = json!([412.0, 290.0, 64.0, 48.0]))]
+    pub bbox: [f32; 4],
+    /// Point of the object (bottom center of the bounding box) in pixels. It is used for zone membership, speed and virtual line crossing
+    #[schema(example = json!([444.0, 314.0]))]
+    pub centroid: [f32; 2],
+    /// Zone which contains the object (if any)
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: Option<String>,
+    /// Longitude and latitude of the object (or local meters when zone's CRS is 'local_meters'). Null when zone has no spatial calibration
+    #[schema(example = json!([37.6185, 54.2071]))]
+    pub world_position: Option<[f32; 2]>,
+    /// Current speed (km/h). Value "-1" indicates that speed has not been estimated yet
+    #[schema(example = 42.5)]
+    pub speed: f32,
+    /// Heading of the movement in degrees clockwise [0; 360). It is geographic bearing (0 is north) when zone has spatial calibration, otherwise 0 is the top of the frame. Null until object moves inside of a zone
+    #[schema(example = 87.5)]
+    pub heading: Option<f32>,
+    /// Time (seconds, relative to the start of processing) when the object has been detected for the first time
+    #[schema(example = 125.4)]
+    pub first_seen: f32,
+    /// Time (seconds, relative to the start of processing) when the object has been detected for the last time
+    #[schema(example = 128.9)]
+    pub last_seen: f32,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Tracking",
+    path = "/api/tracked_objects",
+    responses(
+        (status = 200, description = "Objects tracked on the latest processed frame", body = AllTrackedObjects)
+    )
+)]
+pub async fn all_tracked_objects(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let coordinate_precision = data.app_settings.rest_api.coordinate_precision.unwrap_or(DEFAULT_COORDINATE_PRECISION);
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let tracked_objects = ds_guard.tracked_objects.lock().expect("Tracked objects are poisoned [Mutex]");
+    let ans = AllTrackedObjects {
+        equipment_id: ds_guard.id.clone(),
+        data: tracked_objects.iter().map(|object| TrackedObject {
+            id: object.id.clone(),
+            short_id: object.short_id,
+            class_name: object.class_name.clone(),
+            bbox: object.bbox,
+            centroid: object.centroid,
+            zone_id: object.zone_id.clone(),
+            world_position: object.world_position.map(|pt| round_point(pt, coordinate_precision)),
+            speed: object.speed,
+            heading: object.heading,
+            first_seen: object.first_seen,
+            last_seen: object.last_seen,
+        }).collect(),
+    };
+    drop(tracked_objects);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
@@ -0,0 +1,64 @@
+use actix_web::{HttpResponse, web, Error, http::StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+
+/// Perspective transform matrices of the detection zone
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ZoneHomographyResponse {
+    /// Zone identifier
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    /// 3x3 matrix (row-major) to transform pixel coordinates to EPSG:3857
+    #[schema(example = json!([[-0.0104, -0.0052, 4187869.5], [0.0053, -0.0165, 7209670.0], [0.0, 0.0, 1.0]]))]
+    pub forward: [[f32; 3]; 3],
+    /// 3x3 matrix (row-major) to transform EPSG:3857 coordinates to pixels
+    #[schema(example = json!([[-79.1, 25.0, 150321.2], [-25.5, -49.9, 466812.3], [0.0, 0.0, 1.0]]))]
+    pub inverse: [[f32; 3]; 3],
+}
+
+#[utoipa::path(
+    get,
+    tag = "Zones",
+    path = "/api/zones/{zone_id}/homography",
+    params(
+        ("zone_id" = String, Path, description = "Zone identifier")
+    ),
+    responses(
+        (status = 200, description = "Perspective transform matrices of the zone", body = ZoneHomographyResponse),
+        (status = 404, description = "No such zone", body = ErrorResponse),
+        (status = 424, description = "Zone has no spatial calibration", body = ErrorResponse)
+    )
+)]
+pub async fn zone_homography(data: web::Data<APIStorage>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let zone_id = path.into_inner();
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+    let zone_guarded = match zones.get(&zone_id) {
+        Some(val) => val,
+        None => {
+            return Ok(HttpResponse::build(StatusCode::NOT_FOUND).json(ErrorResponse {
+                error_text: format!("No such zone. Requested ID: {}", zone_id)
+            }));
+        }
+    };
+    let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+    let converter = zone.get_spatial_converter();
+    let (forward, inverse) = match (converter.get_matrix(), converter.get_inverse_matrix()) {
+        (Some(forward), Some(inverse)) => (forward, inverse),
+        _ => {
+            return Ok(HttpResponse::build(StatusCode::FAILED_DEPENDENCY).json(ErrorResponse {
+                error_text: format!("Zone '{}' has no spatial calibration (pixel and WGS84 coordinates are needed)", zone_id)
+            }));
+        }
+    };
+    drop(zone);
+    drop(zones);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ZoneHomographyResponse {
+        zone_id: zone_id,
+        forward: forward,
+        inverse: inverse,
+    }));
+}
@@ -1,5 +1,6 @@
 use actix_web::{HttpResponse, web, Error};
 use crate::lib::zones::geojson::ZonesFeatureCollection;
+use crate::lib::spatial::DEFAULT_COORDINATE_PRECISION;
 use crate::rest_api::APIStorage;
 
 #[utoipa::path(
@@ -11,13 +12,14 @@ use crate::rest_api::APIStorage;
     )
 )]
 pub async fn all_zones_list(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let coordinate_precision = data.app_settings.rest_api.coordinate_precision.unwrap_or(DEFAULT_COORDINATE_PRECISION);
     let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
     let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
     let mut ans = ZonesFeatureCollection::new();
 
     for (_, zone_guarded) in zones.iter() {
         let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
-        let geo_feature = zone.to_geojson();
+        let geo_feature = zone.to_geojson(coordinate_precision);
         ans.features.push(geo_feature);
     }
 
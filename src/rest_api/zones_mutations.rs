@@ -5,20 +5,22 @@ use serde::{
     Serialize
 };
 use utoipa::ToSchema;
+use opencv::core::Point2f;
 use crate::lib::zones::{
     Zone,
     VirtualLineDirection,
-    VirtualLine
+    VirtualLine,
+    CountMode,
+    ZoneKind,
+    auto_virtual_line_for
+};
+use crate::lib::spatial::SpatialCRS;
+use crate::lib::data_storage::{
+    DataStorage,
+    DataStorageError
 };
 use crate::rest_api::APIStorage;
-
-/// Error response
-#[derive(Debug, Serialize, ToSchema)]
-pub struct ErrorResponse {
-    /// Error message
-    #[schema(example = "No such zone. Requested ID: dir_0_lane_1")]
-    pub error_text: String,
-}
+use crate::rest_api::ErrorResponse;
 
 /// The body of the request to update the zone
 #[derive(Debug, Deserialize, ToSchema)]
@@ -29,10 +31,14 @@ pub struct ZoneUpdateRequest {
     /// 4 points represinting zone for the image coordinates
     #[schema(example = json!([[299, 222], [572, 265], [547, 66], [359, 69]]))]
     pub pixel_points: Option<[[u16; 2]; 4]>,
-    /// 4 points represinting zone for the spatial coordinates (WGS84)
+    /// 4 points represinting zone for the spatial coordinates (WGS84 or local meters, see 'crs')
     /// Order of points should be the same as for the pixel_points
     #[schema(example = json!([[37.61896269287956, 54.205680987916566], [37.61892595368445, 54.205685474312446], [37.618908137083054, 54.20564619851147], [37.618944938776394, 54.20563975740504]]))]
     pub spatial_points: Option<[[f32; 2]; 4]>,
+    /// Coordinate reference system of the spatial_points: 'wgs84' or 'local_meters'
+    /// When it is provided, spatial_points should be provided too
+    #[schema(example = "wgs84")]
+    pub crs: Option<String>,
     /// Road lane number
     #[schema(example = 939)]
     pub lane_number: Option<u16>,
@@ -42,8 +48,22 @@ pub struct ZoneUpdateRequest {
     /// Color of the zone
     #[schema(example = json!([130, 0, 100]))]
     pub color_rgb: Option<[i16; 3]>,
+    /// Counting mode: 'polygon' or 'line'
+    #[schema(example = "polygon")]
+    pub count_mode: Option<String>,
+    /// Kind of the zone: 'vehicle' (road lane) or 'pedestrian' (crosswalk, counts 'person' class by default)
+    #[schema(example = "vehicle")]
+    pub kind: Option<String>,
+    /// Classes allowlist for the zone (e.g. bus lane). Objects of other classes are ignored by the zone
+    /// Empty list means that every target class is counted
+    #[schema(example = json!(["bus"]))]
+    pub target_classes: Option<Vec<String>>,
     /// Virtual line
-    pub virtual_line: Option<VirtualLineRequestData>
+    pub virtual_line: Option<VirtualLineRequestData>,
+    /// Generate virtual line perpendicular to the zone's skeleton at its midpoint
+    /// It is ignored when virtual_line is provided explicitly
+    #[schema(example = false)]
+    pub auto_virtual_line: Option<bool>
 }
 
 /// Respone on zone update request
@@ -61,27 +81,101 @@ pub struct ZoneUpdateResponse <'a>{
     request_body = ZoneUpdateRequest,
     responses(
         (status = 200, description = "Specific zone has been updated", body = ZoneUpdateResponse),
-        (status = 424, description = "Failed dependency", body = ErrorResponse)
+        (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 424, description = "Failed dependency", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
 pub async fn update_zone(data: web::Data<APIStorage>, _update_zone: web::Json<ZoneUpdateRequest>) -> Result<HttpResponse, Error> {
-
     let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let result = apply_zone_update(&ds_guard, &_update_zone);
+    drop(ds_guard);
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ZoneUpdateResponse{
+            message: "ok"
+        })),
+        Err((status, error_text)) => Ok(HttpResponse::build(status).json(ErrorResponse {
+            error_text: error_text
+        }))
+    }
+}
+
+// Applies the update to the zone. The whole request is validated before any mutation, so rejected request leaves both
+// the zone and the zones index as is. Error is the status code and the error text
+fn apply_zone_update(ds_guard: &DataStorage, _update_zone: &ZoneUpdateRequest) -> Result<(), (StatusCode, String)> {
     let mut zones = ds_guard.zones.write().expect("Spatial data is poisoned [RWLock]");
 
     let zone_guarded = match zones.get_mut(&_update_zone.zone_id) {
         /* Check if polygon with such identifier exists */
         Some(val) => val,
         None => {
-            return Ok(HttpResponse::build(StatusCode::FAILED_DEPENDENCY).json(ErrorResponse {
-                error_text: format!("No such zone. Requested ID: {}", _update_zone.zone_id)
-            }));
+            return Err((StatusCode::FAILED_DEPENDENCY, format!("No such zone. Requested ID: {}", _update_zone.zone_id)));
         }
     };
 
     // @todo need to deal with those (see main function):
     // polygon.set_target_classes(COCO_FILTERED_CLASSNAMES);
 
+    /* Validate the request before any mutation, so rejected request leaves the zone as is */
+    let count_mode = match &_update_zone.count_mode {
+        Some(val) => match CountMode::from_str(val.as_str()) {
+            Ok(mode) => Some(mode),
+            Err(_) => {
+                return Err((StatusCode::BAD_REQUEST, format!("Unknown count mode: {}", val)));
+            }
+        },
+        None => None
+    };
+    let kind = match &_update_zone.kind {
+        Some(val) => match ZoneKind::from_str(val.as_str()) {
+            Ok(kind) => Some(kind),
+            Err(_) => {
+                return Err((StatusCode::BAD_REQUEST, format!("Unknown zone kind: {}", val)));
+            }
+        },
+        None => None
+    };
+    let crs = match &_update_zone.crs {
+        Some(val) => match SpatialCRS::from_str(val.as_str()) {
+            Ok(crs) => Some(crs),
+            Err(_) => {
+                return Err((StatusCode::BAD_REQUEST, format!("Unknown CRS: {}", val)));
+            }
+        },
+        None => None
+    };
+    if crs.is_some() && _update_zone.spatial_points.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "CRS can't be changed without providing spatial points".to_string()));
+    }
+    // Virtual line is generated for the new geometry (and color), but before anything is applied: it fails for the degenerate polygon
+    let auto_line = match (&_update_zone.virtual_line, _update_zone.auto_virtual_line.unwrap_or(false)) {
+        (None, true) => {
+            let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            let pixel_points = match _update_zone.pixel_points {
+                Some(points) => points.iter().map(|pt| Point2f::new(pt[0] as f32, pt[1] as f32)).collect(),
+                None => zone.get_pixel_coordinates()
+            };
+            let color = _update_zone.color_rgb.unwrap_or(zone.get_color());
+            drop(zone);
+            match auto_virtual_line_for(&pixel_points, color) {
+                Some(vline) => Some(vline),
+                None => {
+                    return Err((StatusCode::FAILED_DEPENDENCY, format!("Can't generate virtual line for zone '{}': zone geometry is degenerate", _update_zone.zone_id)));
+                }
+            }
+        },
+        _ => None
+    };
+
+    match crs {
+        Some(crs) => {
+            let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.set_crs(crs);
+            drop(zone)
+        },
+        _ => {}
+    }
+
     match _update_zone.pixel_points {
         Some(data) => {
             let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
@@ -129,10 +223,40 @@ pub async fn update_zone(data: web::Data<APIStorage>, _update_zone: web::Json<Zo
         _ => {}
     }
 
+    match count_mode {
+        Some(mode) => {
+            let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.set_count_mode(mode);
+            drop(zone)
+        },
+        _ => {}
+    }
+
+    match kind {
+        Some(kind) => {
+            let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.set_kind(kind);
+            drop(zone)
+        },
+        _ => {}
+    }
+
+    match &_update_zone.target_classes {
+        Some(val) => {
+            let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+            zone.set_allowed_classes(Some(val.iter().cloned().collect()));
+            drop(zone)
+        },
+        _ => {}
+    }
+
     match &_update_zone.virtual_line {
         Some(val) => {
             let dir = VirtualLineDirection::from_str(val.direction.as_str()).unwrap_or_default();
             let mut new_line = VirtualLine::new_from(val.geometry, dir);
+            if let Some(margin_px) = val.margin_px {
+                new_line.set_margin_px(margin_px);
+            }
             let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
             if let Some(rgb) = val.color_rgb{
                 new_line.set_color_rgb(rgb[0], rgb[1], rgb[2]);
@@ -143,17 +267,80 @@ pub async fn update_zone(data: web::Data<APIStorage>, _update_zone: web::Json<Zo
             zone.set_virtual_line(new_line);
             drop(zone)
         },
-        _ => {}
+        _ => {
+            if let Some(new_line) = auto_line {
+                let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+                zone.set_virtual_line(new_line);
+                drop(zone)
+            }
+        }
     }
 
     drop(zone_guarded);
+    drop(zones);
+    if _update_zone.pixel_points.is_some() {
+        // Zone geometry has been changed, so spatial index should be updated
+        if let Err(err) = ds_guard.rebuild_zone_grid() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Can't rebuild zones index due the error: {}", err)));
+        }
+    }
+    Ok(())
+}
+
+/// The body of the request to update virtual line of the zone
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ZoneVirtualLineRequest {
+    /// Zone identifier
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    /// Virtual line. Color of the zone is used when color_rgb is omitted
+    pub virtual_line: VirtualLineRequestData,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Zones mutations",
+    path = "/api/mutations/zones/virtual_line",
+    request_body = ZoneVirtualLineRequest,
+    responses(
+        (status = 200, description = "Virtual line of the specific zone has been updated (zone geometry is kept as is)", body = ZoneUpdateResponse),
+        (status = 404, description = "Zone not found", body = ErrorResponse)
+    )
+)]
+pub async fn update_zone_virtual_line(data: web::Data<APIStorage>, _update_line: web::Json<ZoneVirtualLineRequest>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+    let zone_guarded = match zones.get(&_update_line.zone_id) {
+        Some(val) => val,
+        None => {
+            return Ok(HttpResponse::build(StatusCode::NOT_FOUND).json(ErrorResponse {
+                error_text: format!("No such zone. Requested ID: {}", _update_line.zone_id)
+            }));
+        }
+    };
+    let val = &_update_line.virtual_line;
+    let dir = VirtualLineDirection::from_str(val.direction.as_str()).unwrap_or_default();
+    let mut new_line = VirtualLine::new_from(val.geometry, dir);
+    if let Some(margin_px) = val.margin_px {
+        new_line.set_margin_px(margin_px);
+    }
+    let mut zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+    if let Some(rgb) = val.color_rgb {
+        new_line.set_color_rgb(rgb[0], rgb[1], rgb[2]);
+    } else {
+        let zone_color = zone.get_color();
+        new_line.set_color_rgb(zone_color[0], zone_color[1], zone_color[2]);
+    };
+    zone.set_virtual_line(new_line);
+    drop(zone);
+    drop(zones);
+    drop(ds_guard);
 
     return Ok(HttpResponse::Ok().json(ZoneUpdateResponse{
         message: "ok"
     }));
 }
 
-
 /// The body of the request to delete the zone
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct ZoneDeleteRequest {
@@ -196,31 +383,120 @@ pub async fn delete_zone(data: web::Data<APIStorage>, _delete_zone: web::Json<Zo
     }));
 }
 
-/// The body of the request to create new zone
+/// The body of the request to rename the zone
 #[derive(Debug, Deserialize, ToSchema)]
+pub struct ZoneRenameRequest {
+    /// Current zone identifier
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    /// New zone identifier. It should not be used by any other zone
+    #[schema(example = "north_entry")]
+    pub new_zone_id: String,
+}
+
+/// Respone on zone rename request
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ZoneRenameResponse <'a>{
+    /// Message
+    #[schema(example = "ok")]
+    pub message: &'a str,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Zones mutations",
+    path = "/api/mutations/zones/rename",
+    request_body = ZoneRenameRequest,
+    responses(
+        (status = 200, description = "Zone has been renamed. OD matrix, lifetime and hourly counts refer to the new identifier", body = ZoneRenameResponse),
+        (status = 400, description = "Empty identifier", body = ErrorResponse),
+        (status = 404, description = "Zone not found", body = ErrorResponse),
+        (status = 409, description = "Zone with the new identifier already exists", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+pub async fn rename_zone(data: web::Data<APIStorage>, _rename_zone: web::Json<ZoneRenameRequest>) -> Result<HttpResponse, Error> {
+    let new_zone_id = _rename_zone.new_zone_id.trim();
+    if new_zone_id.is_empty() {
+        return Ok(HttpResponse::build(StatusCode::BAD_REQUEST).json(ErrorResponse {
+            error_text: "New zone ID should not be empty".to_string()
+        }));
+    }
+    if new_zone_id == _rename_zone.zone_id {
+        return Ok(HttpResponse::build(StatusCode::CONFLICT).json(ErrorResponse {
+            error_text: format!("Zone ID is already '{}'", new_zone_id)
+        }));
+    }
+    // Hourly totals are re-keyed too, so exclusive access is needed
+    let mut ds_guard = data.data_storage.write().expect("DataStorage is poisoned [RWLock]");
+    let result = ds_guard.rename_zone(&_rename_zone.zone_id, new_zone_id);
+    drop(ds_guard);
+    match result {
+        Ok(_) => {},
+        Err(DataStorageError::ZoneNotFound(zone_id)) => {
+            return Ok(HttpResponse::build(StatusCode::NOT_FOUND).json(ErrorResponse {
+                error_text: format!("No zone with ID: {}", zone_id)
+            }));
+        },
+        Err(DataStorageError::ZoneAlreadyExists(zone_id)) => {
+            return Ok(HttpResponse::build(StatusCode::CONFLICT).json(ErrorResponse {
+                error_text: format!("Zone with ID '{}' already exists", zone_id)
+            }));
+        },
+        Err(err) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: format!("Can't rename zone ID: {}. Error: {}", _rename_zone.zone_id, err)
+            }));
+        }
+    }
+    return Ok(HttpResponse::Ok().json(ZoneRenameResponse{
+        message: "ok"
+    }));
+}
+
+/// The body of the request to create new zone
+/// It is also the format of the zone export (see /api/zones/{zone_id}/export)
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ZoneCreateRequest {
     /// 4 points represinting zone for the image coordinates
     #[schema(example = json!([[230, 200], [550, 235], [512, 40], [359, 69]]))]
     pub pixel_points: Option<[[u16; 2]; 4]>,
-    /// 4 points represinting zone for the spatial coordinates (WGS84)
+    /// 4 points represinting zone for the spatial coordinates (WGS84 or local meters, see 'crs')
     /// Order of points should be the same as for the pixel_points
     #[schema(example = json!([[37.618908137083054, 54.20564619851147], [37.61891517788172, 54.20564502193819], [37.618927247822285, 54.205668749493036], [37.61892020702362, 54.2056701221611]]))]
     pub spatial_points: Option<[[f32; 2]; 4]>,
+    /// Coordinate reference system of the spatial_points: 'wgs84' (default) or 'local_meters'
+    #[schema(example = "wgs84")]
+    pub crs: Option<String>,
     /// Road lane number
     #[schema(example = 939)]
     pub lane_number: Option<u16>,
     /// Road lane direction
     #[schema(example = 33)]
     pub lane_direction: Option<u8>,
-    /// Color of the zone
+    /// Color of the zone. When omitted distinct color from the palette is picked
     #[schema(example = json!([130, 130, 0]))]
     pub color_rgb: Option<[i16; 3]>,
+    /// Counting mode: 'polygon' or 'line'
+    #[schema(example = "polygon")]
+    pub count_mode: Option<String>,
+    /// Kind of the zone: 'vehicle' (road lane) or 'pedestrian' (crosswalk, counts 'person' class by default)
+    #[schema(example = "vehicle")]
+    pub kind: Option<String>,
+    /// Classes allowlist for the zone (e.g. bus lane). Objects of other classes are ignored by the zone
+    /// Empty list means that every target class is counted
+    #[schema(example = json!(["bus"]))]
+    pub target_classes: Option<Vec<String>>,
     /// Virtual line
-    pub virtual_line: Option<VirtualLineRequestData>
+    pub virtual_line: Option<VirtualLineRequestData>,
+    /// Generate virtual line perpendicular to the zone's skeleton at its midpoint
+    /// It is ignored when virtual_line is provided explicitly
+    #[schema(example = false)]
+    pub auto_virtual_line: Option<bool>
 }
 
 /// Information about virtual line
-#[derive(Deserialize, Debug, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct VirtualLineRequestData {
     /// Line geometry. 2 points
     #[schema(example = json!([[365, 177], [540, 185]]))]
@@ -233,6 +509,157 @@ pub struct VirtualLineRequestData {
     /// 'rlbt' stands for "right->left, bottom->top"
     #[schema(example = "lrtb")]
     pub direction: String,
+    /// Deadband (pixels) around the line: object should move from beyond it on one side to beyond it on the other side to cross the line
+    #[schema(example = 3.0)]
+    pub margin_px: Option<f32>,
+}
+
+impl ZoneCreateRequest {
+    // Builds the zone as the create endpoint does. 'fallback_color' is called when color is not provided
+    pub fn to_zone<F: FnOnce() -> [i16; 3]>(&self, fallback_color: F) -> Result<Zone, String> {
+        let mut zone = Zone::default();
+        match &self.crs {
+            Some(val) => {
+                match SpatialCRS::from_str(val.as_str()) {
+                    Ok(crs) => zone.set_crs(crs),
+                    Err(_) => {
+                        return Err(format!("Unknown CRS: {}", val));
+                    }
+                };
+            },
+            _ => {}
+        }
+        match self.pixel_points {
+            Some(data) => {
+                zone.update_pixel_map(data);
+            },
+            _ => {}
+        }
+
+        match self.spatial_points {
+            Some(data) => {
+                zone.update_spatial_map(data);
+            },
+            _ => {}
+        }
+
+        match self.lane_direction {
+            Some(val) => {
+                zone.set_road_lane_direction(val);
+            },
+            _ => {}
+        }
+
+        match self.lane_number {
+            Some(val) => {
+                zone.set_road_lane_num(val);
+            },
+            _ => {}
+        }
+
+        match self.color_rgb {
+            Some(val) => {
+                zone.set_color(val);
+            },
+            _ => {
+                zone.set_color(fallback_color());
+            }
+        }
+
+        match &self.count_mode {
+            Some(val) => {
+                zone.set_count_mode(CountMode::from_str(val.as_str()).unwrap_or_default());
+            },
+            _ => {}
+        }
+
+        match &self.kind {
+            Some(val) => {
+                zone.set_kind(ZoneKind::from_str(val.as_str()).unwrap_or_default());
+            },
+            _ => {}
+        }
+
+        match &self.target_classes {
+            Some(val) => {
+                zone.set_allowed_classes(Some(val.iter().cloned().collect()));
+            },
+            _ => {}
+        }
+
+        match &self.virtual_line {
+            Some(val) => {
+                let dir = VirtualLineDirection::from_str(val.direction.as_str()).unwrap_or_default();
+                let mut new_line = VirtualLine::new_from(val.geometry, dir);
+                if let Some(margin_px) = val.margin_px {
+                    new_line.set_margin_px(margin_px);
+                }
+                if let Some(rgb) = val.color_rgb{
+                    new_line.set_color_rgb(rgb[0], rgb[1], rgb[2]);
+                } else {
+                    let zone_color = zone.get_color();
+                    new_line.set_color_rgb(zone_color[0], zone_color[1], zone_color[2]);
+                };
+                zone.set_virtual_line(new_line);
+            },
+            _ => {
+                if self.auto_virtual_line.unwrap_or(false) {
+                    match zone.auto_virtual_line() {
+                        Some(vline) => zone.set_virtual_line(vline),
+                        None => {
+                            return Err("Can't generate virtual line: pixel_points are missing or degenerate".to_string());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(zone)
+    }
+    // Request which reproduces the zone via the create endpoint (identifier and statistics are not included)
+    // Normalized virtual line is exported in pixels of the current frame size
+    // Returns None when the zone is not a quadrilateral, since the request could hold only 4 points
+    pub fn from_zone(zone: &Zone) -> Option<Self> {
+        let pixel_coordinates = zone.get_pixel_coordinates();
+        if pixel_coordinates.len() != 4 {
+            return None;
+        }
+        let mut pixel_points = [[0_u16; 2]; 4];
+        for (i, pt) in pixel_coordinates.iter().enumerate() {
+            pixel_points[i] = [pt.x.round() as u16, pt.y.round() as u16];
+        }
+        let spatial_coordinates = zone.get_spatial_coordinates();
+        let spatial_points = if spatial_coordinates.len() == 4 {
+            let mut points = [[0.0_f32; 2]; 4];
+            for (i, pt) in spatial_coordinates.iter().enumerate() {
+                points[i] = [pt.x, pt.y];
+            }
+            Some(points)
+        } else {
+            None
+        };
+        Some(ZoneCreateRequest {
+            pixel_points: Some(pixel_points),
+            spatial_points: spatial_points,
+            crs: Some(zone.get_crs().to_string()),
+            lane_number: Some(zone.road_lane_num),
+            lane_direction: Some(zone.road_lane_direction),
+            color_rgb: Some(zone.get_color()),
+            count_mode: Some(zone.get_count_mode().to_string()),
+            kind: Some(zone.get_kind().to_string()),
+            target_classes: zone.get_allowed_classes().map(|classes| {
+                let mut classes = classes.iter().cloned().collect::<Vec<String>>();
+                classes.sort();
+                classes
+            }),
+            virtual_line: zone.get_virtual_line().map(|vl| VirtualLineRequestData {
+                geometry: vl.line,
+                color_rgb: Some(vl.color),
+                direction: vl.direction.to_string(),
+                margin_px: if vl.margin_px > 0.0 { Some(vl.margin_px) } else { None },
+            }),
+            auto_virtual_line: None,
+        })
+    }
 }
 
 /// Respone on zone create request
@@ -250,6 +677,7 @@ pub struct ZoneCreateResponse {
     request_body = ZoneCreateRequest,
     responses(
         (status = 201, description = "Zone has been created", body = ZoneCreateResponse),
+        (status = 400, description = "Bad request", body = ErrorResponse),
         (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
@@ -258,56 +686,20 @@ pub async fn create_zone(data: web::Data<APIStorage>, _new_zone: web::Json<ZoneC
     // @todo need to deal with those (see main function):
     // polygon.set_target_classes(COCO_FILTERED_CLASSNAMES);
 
-    let mut zone = Zone::default();
-    match _new_zone.pixel_points {
-        Some(data) => {
-            zone.update_pixel_map(data);
-        },
-        _ => {}
-    }
-
-    match _new_zone.spatial_points {
-        Some(data) => {
-            zone.update_spatial_map(data);
-        },
-        _ => {}
-    }
-
-    match _new_zone.lane_direction {
-        Some(val) => {
-            zone.set_road_lane_direction(val);
-        },
-        _ => {}
-    }
-
-    match _new_zone.lane_number {
-        Some(val) => {
-            zone.set_road_lane_num(val);
-        },
-        _ => {}
-    }
-
-    match _new_zone.color_rgb {
-        Some(val) => {
-            zone.set_color(val);
-        },
-        _ => {}
-    }
-
-    match &_new_zone.virtual_line {
-        Some(val) => {
-            let dir = VirtualLineDirection::from_str(val.direction.as_str()).unwrap_or_default();
-            let mut new_line = VirtualLine::new_from(val.geometry, dir);
-            if let Some(rgb) = val.color_rgb{
-                new_line.set_color_rgb(rgb[0], rgb[1], rgb[2]);
-            } else {
-                let zone_color = zone.get_color();
-                new_line.set_color_rgb(zone_color[0], zone_color[1], zone_color[2]);
-            };
-            zone.set_virtual_line(new_line);
-        },
-        _ => {}
-    }
+    let zone = match _new_zone.to_zone(|| {
+        // Pick distinct color, so zones do not merge visually
+        let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+        let color = ds_guard.next_zone_color();
+        drop(ds_guard);
+        color
+    }) {
+        Ok(zone) => zone,
+        Err(err) => {
+            return Ok(HttpResponse::build(StatusCode::BAD_REQUEST).json(ErrorResponse {
+                error_text: err
+            }));
+        }
+    };
 
     let new_id = zone.get_id().clone();
 
@@ -329,6 +721,44 @@ pub async fn create_zone(data: web::Data<APIStorage>, _new_zone: web::Json<ZoneC
 }
 
 
+#[utoipa::path(
+    get,
+    tag = "Zones",
+    path = "/api/zones/{zone_id}/export",
+    params(
+        ("zone_id" = String, Path, description = "Zone identifier")
+    ),
+    responses(
+        (status = 200, description = "Configuration of the zone. It could be imported as is via /api/mutations/zones/create", body = ZoneCreateRequest),
+        (status = 404, description = "No such zone", body = ErrorResponse),
+        (status = 422, description = "Zone is not a quadrilateral and can't be expressed as create request", body = ErrorResponse)
+    )
+)]
+pub async fn export_zone(data: web::Data<APIStorage>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let zone_id = path.into_inner();
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
+    let zone_guarded = match zones.get(&zone_id) {
+        Some(val) => val,
+        None => {
+            return Ok(HttpResponse::build(StatusCode::NOT_FOUND).json(ErrorResponse {
+                error_text: format!("No such zone. Requested ID: {}", zone_id)
+            }));
+        }
+    };
+    let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+    let exported = ZoneCreateRequest::from_zone(&zone);
+    drop(zone);
+    drop(zones);
+    drop(ds_guard);
+    match exported {
+        Some(ans) => Ok(HttpResponse::Ok().json(ans)),
+        None => Ok(HttpResponse::build(StatusCode::UNPROCESSABLE_ENTITY).json(ErrorResponse {
+            error_text: format!("Zone '{}' should have exactly 4 pixel points to be exported", zone_id)
+        })),
+    }
+}
+
 /// The body of the request to overwrite all zones
 /// It does delete all existing zones and create new ones
 #[derive(Debug, Deserialize, ToSchema)]
@@ -364,6 +794,16 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
         }));
     }
 
+    for new_zone in _new_zones.data.iter() {
+        if let Some(val) = &new_zone.crs {
+            if SpatialCRS::from_str(val.as_str()).is_err() {
+                return Ok(HttpResponse::build(StatusCode::BAD_REQUEST).json(ErrorResponse {
+                    error_text: format!("Unknown CRS: {}", val)
+                }));
+            }
+        }
+    }
+
     // Mark data for clean
     let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
     let zones = ds_guard.zones.read().expect("Spatial data is poisoned [RWLock]");
@@ -375,6 +815,9 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
     let mut response = vec![];
     for new_zone in _new_zones.data.iter() {
         let mut zone = Zone::default();
+        if let Some(val) = &new_zone.crs {
+            zone.set_crs(SpatialCRS::from_str(val.as_str()).unwrap_or_default());
+        }
         match new_zone.pixel_points {
             Some(data) => {
                 zone.update_pixel_map(data);
@@ -407,6 +850,32 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
             Some(val) => {
                 zone.set_color(val);
             },
+            _ => {
+                // Pick distinct color, so zones do not merge visually
+                let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+                zone.set_color(ds_guard.next_zone_color());
+                drop(ds_guard);
+            }
+        }
+
+        match &new_zone.count_mode {
+            Some(val) => {
+                zone.set_count_mode(CountMode::from_str(val.as_str()).unwrap_or_default());
+            },
+            _ => {}
+        }
+
+        match &new_zone.kind {
+            Some(val) => {
+                zone.set_kind(ZoneKind::from_str(val.as_str()).unwrap_or_default());
+            },
+            _ => {}
+        }
+
+        match &new_zone.target_classes {
+            Some(val) => {
+                zone.set_allowed_classes(Some(val.iter().cloned().collect()));
+            },
             _ => {}
         }
 
@@ -414,6 +883,9 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
             Some(val) => {
                 let dir = VirtualLineDirection::from_str(val.direction.as_str()).unwrap_or_default();
                 let mut new_line = VirtualLine::new_from(val.geometry, dir);
+                if let Some(margin_px) = val.margin_px {
+                    new_line.set_margin_px(margin_px);
+                }
                 if let Some(rgb) = val.color_rgb{  
                     new_line.set_color_rgb(rgb[0], rgb[1], rgb[2]);
                 } else {
@@ -422,7 +894,16 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
                 };
                 zone.set_virtual_line(new_line);
             },
-            _ => {}
+            _ => {
+                if new_zone.auto_virtual_line.unwrap_or(false) {
+                    match zone.auto_virtual_line() {
+                        Some(vline) => zone.set_virtual_line(vline),
+                        None => {
+                            println!("[WARNING]: Can't generate virtual line for zone '{}': pixel_points are missing or degenerate", zone.get_id());
+                        }
+                    }
+                }
+            }
         }
 
         let new_id = zone.get_id().clone();
@@ -459,3 +940,94 @@ pub async fn replace_all(data: web::Data<APIStorage>, _new_zones: web::Json<Zone
         zones_ids: response
     }));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_zone_export_roundtrip() {
+        let request: ZoneCreateRequest = serde_json::from_value(serde_json::json!({
+            "pixel_points": [[230, 200], [550, 235], [512, 40], [359, 69]],
+            "spatial_points": [[37.618908, 54.205646], [37.618915, 54.205645], [37.618927, 54.205668], [37.618920, 54.205670]],
+            "lane_number": 2,
+            "lane_direction": 1,
+            "count_mode": "line",
+            "kind": "pedestrian",
+            "target_classes": ["person", "bicycle"],
+            "virtual_line": {"geometry": [[365, 177], [540, 185]], "direction": "rlbt", "margin_px": 3.0}
+        })).unwrap();
+        let zone = request.to_zone(|| [130, 70, 0]).unwrap();
+        let exported = ZoneCreateRequest::from_zone(&zone).unwrap();
+        assert_eq!(exported.color_rgb, Some([130, 70, 0]));
+        assert_eq!(exported.target_classes, Some(vec!["bicycle".to_string(), "person".to_string()]));
+        // Virtual line inherits color of the zone when it is not provided
+        assert_eq!(exported.virtual_line.as_ref().unwrap().color_rgb, Some([130, 70, 0]));
+
+        // Importing the exported zone reproduces it
+        let imported = exported.to_zone(|| [0, 0, 0]).unwrap();
+        assert_ne!(imported.get_id(), zone.get_id());
+        assert_eq!(imported.get_pixel_coordinates(), zone.get_pixel_coordinates());
+        assert_eq!(imported.get_spatial_coordinates(), zone.get_spatial_coordinates());
+        assert_eq!(imported.get_crs(), zone.get_crs());
+        assert_eq!(imported.get_count_mode(), zone.get_count_mode());
+        assert_eq!(imported.get_kind(), zone.get_kind());
+        let (vl_original, vl_imported) = (zone.get_virtual_line().unwrap(), imported.get_virtual_line().unwrap());
+        assert_eq!(vl_imported.line, vl_original.line);
+        assert_eq!(vl_imported.direction, vl_original.direction);
+        assert_eq!(vl_imported.margin_px, vl_original.margin_px);
+        assert_eq!(
+            serde_json::to_value(ZoneCreateRequest::from_zone(&imported).unwrap()).unwrap(),
+            serde_json::to_value(&exported).unwrap()
+        );
+    }
+    #[test]
+    fn test_rejected_update_keeps_zone() {
+        let ds = DataStorage::new_with_id("test".to_string(), false);
+        let request: ZoneCreateRequest = serde_json::from_value(serde_json::json!({
+            "pixel_points": [[10, 10], [60, 10], [60, 60], [10, 60]],
+            "spatial_points": [[37.618908, 54.205646], [37.618915, 54.205645], [37.618927, 54.205668], [37.618920, 54.205670]],
+            "lane_number": 1,
+            "lane_direction": 1
+        })).unwrap();
+        let zone = request.to_zone(|| [130, 70, 0]).unwrap();
+        let zone_id = zone.get_id();
+        let old_coordinates = zone.get_pixel_coordinates();
+        ds.insert_zone(zone).unwrap();
+        let old_grid = ds.zone_grid.read().unwrap().get_occupied_cells();
+        assert_eq!(ds.zone_grid.read().unwrap().get_candidate_zones(30.0, 30.0), &[zone_id.clone()]);
+
+        // New geometry is valid, but count mode is not: nothing should be applied
+        let update: ZoneUpdateRequest = serde_json::from_value(serde_json::json!({
+            "zone_id": zone_id,
+            "pixel_points": [[500, 500], [560, 500], [560, 560], [500, 560]],
+            "count_mode": "unknown"
+        })).unwrap();
+        let err = apply_zone_update(&ds, &update).unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        let zones = ds.zones.read().unwrap();
+        assert_eq!(zones.get(&zone_id).unwrap().lock().unwrap().get_pixel_coordinates(), old_coordinates);
+        drop(zones);
+        assert_eq!(ds.zone_grid.read().unwrap().get_occupied_cells(), old_grid);
+        assert_eq!(ds.zone_grid.read().unwrap().get_candidate_zones(30.0, 30.0), &[zone_id.clone()]);
+        assert!(ds.zone_grid.read().unwrap().get_candidate_zones(530.0, 530.0).is_empty());
+
+        // Same for CRS provided without spatial points
+        let update: ZoneUpdateRequest = serde_json::from_value(serde_json::json!({
+            "zone_id": zone_id,
+            "pixel_points": [[500, 500], [560, 500], [560, 560], [500, 560]],
+            "crs": "wgs84"
+        })).unwrap();
+        assert_eq!(apply_zone_update(&ds, &update).unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert_eq!(ds.zone_grid.read().unwrap().get_occupied_cells(), old_grid);
+
+        // Valid update moves both the zone and its cells in the index
+        let update: ZoneUpdateRequest = serde_json::from_value(serde_json::json!({
+            "zone_id": zone_id,
+            "pixel_points": [[500, 500], [560, 500], [560, 560], [500, 560]],
+            "count_mode": "line"
+        })).unwrap();
+        apply_zone_update(&ds, &update).unwrap();
+        assert!(ds.zone_grid.read().unwrap().get_candidate_zones(30.0, 30.0).is_empty());
+        assert_eq!(ds.zone_grid.read().unwrap().get_candidate_zones(530.0, 530.0), &[zone_id.clone()]);
+    }
+}
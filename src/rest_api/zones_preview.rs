@@ -0,0 +1,79 @@
+use actix_web::{HttpResponse, web, Error, http::StatusCode};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use opencv::{
+    prelude::*,
+    core::Point2f,
+    core::Vector,
+    imgcodecs::imencode,
+};
+use crate::lib::zones::Zone;
+use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
+
+/// The body of the request to preview the zone
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ZonePreviewRequest {
+    /// 4 points represinting zone for the image coordinates
+    #[schema(example = json!([[230, 200], [550, 235], [512, 40], [359, 69]]))]
+    pub pixel_points: [[u16; 2]; 4],
+    /// Color of the zone
+    #[schema(example = json!([130, 130, 0]))]
+    pub color_rgb: Option<[i16; 3]>,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Zones",
+    path = "/api/zones/preview",
+    request_body = ZonePreviewRequest,
+    responses(
+        (status = 200, description = "JPEG image of the latest frame with the candidate zone drawn", content_type = "image/jpeg"),
+        (status = 500, description = "Internal error", body = ErrorResponse),
+        (status = 503, description = "No frame available yet", body = ErrorResponse)
+    )
+)]
+pub async fn zone_preview(data: web::Data<APIStorage>, _preview: web::Json<ZonePreviewRequest>) -> Result<HttpResponse, Error> {
+    let ds_guard = data.data_storage.read().expect("DataStorage is poisoned [RWLock]");
+    let latest_frame = ds_guard.latest_frame.lock().expect("Latest frame is poisoned [Mutex]");
+    let mut frame = match latest_frame.as_ref().map(|frame| frame.try_clone()) {
+        Some(Ok(frame)) => frame,
+        Some(Err(err)) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: format!("Can't copy the latest frame due the error: {}", err)
+            }));
+        },
+        None => {
+            return Ok(HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE).json(ErrorResponse {
+                error_text: "No frame available yet".to_string()
+            }));
+        }
+    };
+    drop(latest_frame);
+    drop(ds_guard);
+
+    // Zone is not persisted: it is used for drawing only
+    let mut zone = Zone::default_from_cv(_preview.pixel_points.iter().map(|pt| Point2f::new(pt[0] as f32, pt[1] as f32)).collect());
+    if let Some(rgb) = _preview.color_rgb {
+        zone.set_color(rgb);
+    }
+    zone.draw_geom(&mut frame);
+    zone.draw_skeleton(&mut frame);
+
+    let mut buffer = Vector::<u8>::new();
+    let params = Vector::<i32>::new();
+    match imencode(".jpg", &frame, &mut buffer, &params) {
+        Ok(true) => {},
+        Ok(false) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: "Image has not been encoded".to_string()
+            }));
+        },
+        Err(err) => {
+            return Ok(HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(ErrorResponse {
+                error_text: format!("Can't encode image due the error: {}", err)
+            }));
+        }
+    };
+    return Ok(HttpResponse::Ok().content_type("image/jpeg").body(buffer.to_vec()));
+}
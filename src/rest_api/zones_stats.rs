@@ -1,10 +1,13 @@
-use actix_web::{web, Error, HttpResponse};
+use actix_web::{web, Error, HttpResponse, http::StatusCode};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use utoipa::ToSchema;
 
+use crate::lib::zones::Zone;
 use crate::rest_api::APIStorage;
+use crate::rest_api::ErrorResponse;
 use std::collections::HashMap;
+use std::fmt::Write;
 
 /// Information about aggregated road traffic flow parameters for the equipment
 #[derive(Debug, Serialize, ToSchema)]
@@ -12,6 +15,12 @@ pub struct AllZonesStats {
     /// Equipment identifier. Should match software configuration
     #[schema(example = "1e23985f-1fa3-45d0-a365-2d8525a23ddd")]
     pub equipment_id: String,
+    /// Name of the site (see 'equipment_info.location_name' in configuration). Null when it is not configured
+    #[schema(example = "Main St. / 5th Ave.")]
+    pub location_name: Option<String>,
+    /// Position of the site [lon, lat]. Null when it is not configured
+    #[schema(example = json!([-3.70588, 40.39306]))]
+    pub location_wgs84: Option<[f32; 2]>,
     /// Set of data with summary information about road traffic parameters for each detection zone
     pub data: Vec<ZoneStats>,
 }
@@ -19,6 +28,9 @@ pub struct AllZonesStats {
 /// Summary information for each detection zone
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ZoneStats {
+    /// Kind of the zone: 'vehicle' (road lane) or 'pedestrian' (crosswalk). Pedestrian zones report pedestrian flow and walking speed
+    #[schema(example = "vehicle")]
+    pub kind: String,
     /// Corresponding road lane number
     #[schema(example = 2)]
     pub lane_number: u16,
@@ -52,7 +64,25 @@ pub struct VehicleTypeParameters {
     // that sum_intensity does not take into account whether vehicles have estimated speed, when
     // defined_sum_intensity does. Could be less or equal to sum_intensity.
     #[schema(example = 12)]
-    pub estimated_defined_sum_intensity: u32
+    pub estimated_defined_sum_intensity: u32,
+    /// Average confidence [0; 1] of the defined speeds. Depends on pixels per meter of the zone and on track length used for estimation: low values mean that speed should be weighted down or discarded
+    #[schema(example = 0.8)]
+    pub avg_speed_confidence: f32,
+    /// Number of vehicles faster than the speed limit of the class (see 'speed_limits' of the zone). Always zero when there is no limit for the class
+    #[schema(example = 3)]
+    pub over_speed_count: u32,
+    /// Number of vehicles registered in the zone polygon whether they have crossed the virtual line or not
+    #[schema(example = 23)]
+    pub present_intensity: u32,
+    /// Number of vehicles which have crossed the virtual line. Always zero when zone has no virtual line
+    #[schema(example = 19)]
+    pub crossed_intensity: u32,
+    /// Average bounding box area (square pixels) of the counted vehicles. Depends on camera perspective, so compare it between classes of the same zone only (e.g. to tell trucks from cars). Value "-1" indicates that it is undefined
+    #[schema(example = 5400.0)]
+    pub avg_bbox_area: f32,
+    /// Average headway between consecutive vehicles of this class only (seconds). Helps to analyze e.g. truck platooning. Value "-1" for pedestrian zones
+    #[schema(example = 12.5)]
+    pub avg_headway: f32,
 }
 
 /// Road traffic parameters for specific vehicle type
@@ -69,11 +99,106 @@ pub struct TrafficFlowInfo {
     // defined_sum_intensity does. Could be less or equal to sum_intensity.
     #[schema(example = 13)]
     pub defined_sum_intensity: u32,
-    /// Average headway. Headway - number of seconds between arrival of leading vehicle and following vehicle
+    /// Average headway. Headway - number of seconds between arrival of leading vehicle and following vehicle. Value "-1" for pedestrian zones
     #[schema(example = 2.5)]
     pub avg_headway: f32,
+    /// Average density (vehicles per kilometer) based on occupancy. Value "-1" indicates that it can't be estimated
+    #[schema(example = 18.4)]
+    pub avg_density: f32,
+    /// Average number of vehicles in the zone at once. Value "-1" indicates that it has not been measured
+    #[schema(example = 1.7)]
+    pub avg_occupancy: f32,
+    /// Congestion level based on density. Possible values: 'undefined', 'free', 'moderate', 'congested', 'jammed'
+    #[schema(example = "moderate")]
+    pub congestion_level: String,
+    /// Number of vehicles (with defined speed) in each speed bin. Bin "i" covers [i * bin_width; (i + 1) * bin_width) km/h, the last bin also includes higher speeds
+    #[schema(example = json!([0, 0, 1, 4, 7, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0]))]
+    pub speed_histogram: Vec<u32>,
+    /// Width of the speed histogram bin (km/h)
+    #[schema(example = 10.0)]
+    pub speed_histogram_bin_width: f32,
+    /// Median speed (km/h) of the vehicles with defined speed. Value "-1" indicates that there are no such vehicles
+    #[schema(example = 41.5)]
+    pub speed_p50: f32,
+    /// 85th percentile speed (km/h): 85% of the vehicles drive at or below this speed. Value "-1" indicates that there are no such vehicles
+    #[schema(example = 52.0)]
+    pub speed_p85: f32,
+    /// 95th percentile speed (km/h). Value "-1" indicates that there are no such vehicles
+    #[schema(example = 58.3)]
+    pub speed_p95: f32,
+    /// Number of vehicles with speed below 'min_moving_speed' (stopped or parked). They are counted in sum_intensity, but not in avg_speed
+    #[schema(example = 2)]
+    pub stationary_count: u32,
+    /// Number of vehicles with harsh braking (acceleration below -3.5 m/s^2). Rough estimate: it is sensitive to detection noise
+    #[schema(example = 1)]
+    pub harsh_braking_events: u32,
+    /// Number of vehicles with hard acceleration (above 3.0 m/s^2). Rough estimate: it is sensitive to detection noise
+    #[schema(example = 0)]
+    pub hard_acceleration_events: u32,
+    /// Number of vehicles faster than the speed limits of their classes
+    #[schema(example = 3)]
+    pub over_speed_count: u32,
+    /// Number of vehicles registered in the zone polygon whether they have crossed the virtual line or not
+    #[schema(example = 18)]
+    pub present_intensity: u32,
+    /// Number of vehicles which have crossed the virtual line. Always zero when zone has no virtual line
+    #[schema(example = 15)]
+    pub crossed_intensity: u32,
+    /// Dominant heading (circular mean, degrees clockwise [0; 360)) of the counted vehicles. It is geographic bearing (0 is north) when zone has spatial calibration, otherwise 0 is the top of the frame. Value "-1" indicates that it is undefined
+    #[schema(example = 172.4)]
+    pub dominant_heading: f32,
 }
 
+impl From<&Zone> for ZoneStats {
+    fn from(zone: &Zone) -> Self {
+        let mut stats = ZoneStats {
+            kind: zone.get_kind().to_string(),
+            lane_number: zone.road_lane_num,
+            lane_direction: zone.road_lane_direction,
+            period_start: zone.statistics.period_start,
+            period_end: zone.statistics.period_end,
+            statistics: HashMap::new(),
+            traffic_flow_parameters: TrafficFlowInfo{
+                avg_speed: zone.statistics.traffic_flow_parameters.avg_speed,
+                sum_intensity: zone.statistics.traffic_flow_parameters.sum_intensity,
+                defined_sum_intensity: zone.statistics.traffic_flow_parameters.defined_sum_intensity,
+                avg_headway: zone.statistics.traffic_flow_parameters.avg_headway,
+                avg_density: zone.statistics.traffic_flow_parameters.avg_density,
+                avg_occupancy: zone.statistics.traffic_flow_parameters.avg_occupancy,
+                congestion_level: zone.statistics.traffic_flow_parameters.congestion_level.to_string(),
+                speed_histogram: zone.statistics.traffic_flow_parameters.speed_histogram.clone(),
+                speed_histogram_bin_width: zone.statistics.traffic_flow_parameters.speed_histogram_bin_width,
+                speed_p50: zone.statistics.traffic_flow_parameters.speed_p50,
+                speed_p85: zone.statistics.traffic_flow_parameters.speed_p85,
+                speed_p95: zone.statistics.traffic_flow_parameters.speed_p95,
+                stationary_count: zone.statistics.traffic_flow_parameters.stationary_count,
+                harsh_braking_events: zone.statistics.traffic_flow_parameters.harsh_braking_events,
+                hard_acceleration_events: zone.statistics.traffic_flow_parameters.hard_acceleration_events,
+                over_speed_count: zone.statistics.traffic_flow_parameters.over_speed_count,
+                present_intensity: zone.statistics.traffic_flow_parameters.present_intensity,
+                crossed_intensity: zone.statistics.traffic_flow_parameters.crossed_intensity,
+                dominant_heading: zone.statistics.traffic_flow_parameters.dominant_heading,
+            }
+        };
+        for (vehicle_type, statistics) in zone.statistics.vehicles_data.iter() {
+            stats.statistics.insert(
+                vehicle_type.to_string(),
+                VehicleTypeParameters {
+                    estimated_avg_speed: statistics.avg_speed,
+                    estimated_sum_intensity: statistics.sum_intensity,
+                    estimated_defined_sum_intensity: statistics.defined_sum_intensity,
+                    avg_speed_confidence: statistics.avg_speed_confidence,
+                    over_speed_count: statistics.over_speed_count,
+                    present_intensity: statistics.present_intensity,
+                    crossed_intensity: statistics.crossed_intensity,
+                    avg_bbox_area: statistics.avg_bbox_area,
+                    avg_headway: statistics.avg_headway,
+                },
+            );
+        }
+        stats
+    }
+}
 
 #[utoipa::path(
     get,
@@ -94,33 +219,14 @@ pub async fn all_zones_stats(data: web::Data<APIStorage>) -> Result<HttpResponse
         .expect("Spatial data is poisoned [RWLock]");
     let mut ans: AllZonesStats = AllZonesStats {
         equipment_id: ds_guard.id.clone(),
+        location_name: ds_guard.location_name.clone(),
+        location_wgs84: ds_guard.location_wgs84,
         data: vec![],
     };
     for (_, zone_guarded) in zones.iter() {
         let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
-        let mut stats = ZoneStats {
-            lane_number: zone.road_lane_num,
-            lane_direction: zone.road_lane_direction,
-            period_start: zone.statistics.period_start,
-            period_end: zone.statistics.period_end,
-            statistics: HashMap::new(),
-            traffic_flow_parameters: TrafficFlowInfo{
-                avg_speed: zone.statistics.traffic_flow_parameters.avg_speed,
-                sum_intensity: zone.statistics.traffic_flow_parameters.sum_intensity,
-                defined_sum_intensity: zone.statistics.traffic_flow_parameters.defined_sum_intensity,
-                avg_headway: zone.statistics.traffic_flow_parameters.avg_headway,
-            }
-        };
-        for (vehicle_type, statistics) in zone.statistics.vehicles_data.iter() {
-            stats.statistics.insert(
-                vehicle_type.to_string(),
-                VehicleTypeParameters {
-                    estimated_avg_speed: statistics.avg_speed,
-                    estimated_sum_intensity: statistics.sum_intensity,
-                    estimated_defined_sum_intensity: statistics.defined_sum_intensity
-                },
-            );
-        }
+        let stats = ZoneStats::from(&*zone);
+        drop(zone);
         ans.data.push(stats);
     }
     drop(zones);
@@ -128,6 +234,80 @@ pub async fn all_zones_stats(data: web::Data<APIStorage>) -> Result<HttpResponse
     return Ok(HttpResponse::Ok().json(ans));
 }
 
+// Columns of the CSV export. Per-class values come from VehicleTypeParameters, per-zone ones from ZoneStats and TrafficFlowInfo
+const STATS_CSV_HEADER: &str = "zone_id,lane_number,lane_direction,period_start,period_end,class,count,defined_count,avg_speed,avg_speed_confidence,over_speed_count,avg_headway,avg_occupancy";
+
+// Quotes the field if it contains delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    value.to_string()
+}
+
+// One row per zone and vehicle class. Rows are sorted by zone identifier and class
+pub fn zones_stats_csv(zones_stats: &[(String, ZoneStats)]) -> String {
+    let mut sorted = zones_stats.iter().collect::<Vec<&(String, ZoneStats)>>();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut csv = String::from(STATS_CSV_HEADER);
+    csv.push('\n');
+    for (zone_id, stats) in sorted {
+        let mut classes = stats.statistics.keys().collect::<Vec<&String>>();
+        classes.sort();
+        for class in classes {
+            let class_stats = &stats.statistics[class];
+            let _ = writeln!(csv, "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                csv_field(zone_id),
+                stats.lane_number,
+                stats.lane_direction,
+                stats.period_start.to_rfc3339(),
+                stats.period_end.to_rfc3339(),
+                csv_field(class),
+                class_stats.estimated_sum_intensity,
+                class_stats.estimated_defined_sum_intensity,
+                class_stats.estimated_avg_speed,
+                class_stats.avg_speed_confidence,
+                class_stats.over_speed_count,
+                stats.traffic_flow_parameters.avg_headway,
+                stats.traffic_flow_parameters.avg_occupancy,
+            );
+        }
+    }
+    csv
+}
+
+#[utoipa::path(
+    get,
+    tag = "Statistics",
+    path = "/api/zones/stats.csv",
+    responses(
+        (status = 200, description = "Aggregated statistics as CSV: one row per zone and vehicle class", body = String, content_type = "text/csv")
+    )
+)]
+pub async fn all_zones_stats_csv(data: web::Data<APIStorage>) -> Result<HttpResponse, Error> {
+    let ds_guard = data
+        .data_storage
+        .read()
+        .expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard
+        .zones
+        .read()
+        .expect("Spatial data is poisoned [RWLock]");
+    let mut zones_stats = vec![];
+    for (zone_id, zone_guarded) in zones.iter() {
+        let zone = zone_guarded.lock().expect("Zone is poisoned [Mutex]");
+        zones_stats.push((zone_id.clone(), ZoneStats::from(&*zone)));
+        drop(zone);
+    }
+    let filename = format!("stats_{}.csv", ds_guard.id);
+    drop(zones);
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .body(zones_stats_csv(&zones_stats)));
+}
+
 /// Information about occupancy in real-time for each detection zone
 #[derive(Debug, Serialize, ToSchema)]
 pub struct AllZonesRealtimeStatistics {
@@ -200,3 +380,133 @@ pub async fn all_zones_occupancy(data: web::Data<APIStorage>) -> Result<HttpResp
     return Ok(HttpResponse::Ok().json(ans));
 }
 
+
+/// Number of registered vehicles for each hour of the current day
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ZoneHourlyTotals {
+    /// Zone identifier
+    #[schema(example = "dir_0_lane_1")]
+    pub zone_id: String,
+    /// Timezone used to determine hour of the aggregation period: 'local' (system timezone) or 'utc'
+    #[schema(example = "local")]
+    pub timezone: String,
+    /// Current day. Empty if there were no aggregation periods yet
+    #[schema(example = "2023-01-02")]
+    pub day: Option<String>,
+    /// Total number of vehicles for each hour (index is the hour of the day). Hour is taken from the end of aggregation period
+    #[schema(example = json!([0, 0, 0, 0, 0, 2, 14, 63, 98, 71, 45, 40, 52, 48, 44, 50, 67, 102, 88, 54, 31, 17, 9, 3]))]
+    pub totals: [u32; 24],
+}
+
+#[utoipa::path(
+    get,
+    tag = "Statistics",
+    path = "/api/zones/{zone_id}/hourly",
+    params(
+        ("zone_id" = String, Path, description = "Zone identifier")
+    ),
+    responses(
+        (status = 200, description = "Hourly totals of the zone for the current day", body = ZoneHourlyTotals),
+        (status = 404, description = "No such zone", body = ErrorResponse)
+    )
+)]
+pub async fn zone_hourly_totals(data: web::Data<APIStorage>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let zone_id = path.into_inner();
+    let ds_guard = data
+        .data_storage
+        .read()
+        .expect("DataStorage is poisoned [RWLock]");
+    let zones = ds_guard
+        .zones
+        .read()
+        .expect("Spatial data is poisoned [RWLock]");
+    if !zones.contains_key(&zone_id) {
+        return Ok(HttpResponse::build(StatusCode::NOT_FOUND).json(ErrorResponse {
+            error_text: format!("No such zone. Requested ID: {}", zone_id)
+        }));
+    }
+    drop(zones);
+    let ans = match ds_guard.hourly_totals.get(&zone_id) {
+        Some(hourly) => ZoneHourlyTotals {
+            zone_id: zone_id,
+            timezone: ds_guard.hourly_timezone.to_string(),
+            day: hourly.day.map(|day| day.format("%Y-%m-%d").to_string()),
+            totals: hourly.totals,
+        },
+        None => ZoneHourlyTotals {
+            zone_id: zone_id,
+            timezone: ds_guard.hourly_timezone.to_string(),
+            day: None,
+            totals: [0; 24],
+        }
+    };
+    drop(ds_guard);
+    return Ok(HttpResponse::Ok().json(ans));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    fn zone_stats(classes: &[(&str, u32, f32)]) -> ZoneStats {
+        let mut statistics = HashMap::new();
+        for (class, count, avg_speed) in classes.iter() {
+            statistics.insert(class.to_string(), VehicleTypeParameters {
+                estimated_avg_speed: *avg_speed,
+                estimated_sum_intensity: *count,
+                estimated_defined_sum_intensity: *count,
+                avg_speed_confidence: 0.5,
+                over_speed_count: 0,
+                present_intensity: *count,
+                crossed_intensity: 0,
+                avg_bbox_area: -1.0,
+                avg_headway: 0.0,
+            });
+        }
+        ZoneStats {
+            kind: "vehicle".to_string(),
+            lane_number: 1,
+            lane_direction: 0,
+            period_start: Utc.with_ymd_and_hms(2023, 1, 2, 15, 0, 0).unwrap(),
+            period_end: Utc.with_ymd_and_hms(2023, 1, 2, 15, 5, 0).unwrap(),
+            statistics: statistics,
+            traffic_flow_parameters: TrafficFlowInfo {
+                avg_speed: -1.0,
+                sum_intensity: 0,
+                defined_sum_intensity: 0,
+                avg_headway: 2.5,
+                avg_density: -1.0,
+                avg_occupancy: 1.5,
+                congestion_level: "undefined".to_string(),
+                speed_histogram: vec![],
+                speed_histogram_bin_width: 10.0,
+                speed_p50: -1.0,
+                speed_p85: -1.0,
+                speed_p95: -1.0,
+                stationary_count: 0,
+                harsh_braking_events: 0,
+                hard_acceleration_events: 0,
+                over_speed_count: 0,
+                present_intensity: 0,
+                crossed_intensity: 0,
+                dominant_heading: -1.0,
+            },
+        }
+    }
+    #[test]
+    fn test_zones_stats_csv() {
+        let zones_stats = vec![
+            ("dir_0_lane_2".to_string(), zone_stats(&[("car", 4, 42.5)])),
+            ("dir_0_lane_1".to_string(), zone_stats(&[("truck", 1, 30.0), ("bus", 2, -1.0)])),
+            ("lane, \"north\"".to_string(), zone_stats(&[])),
+        ];
+        let csv = zones_stats_csv(&zones_stats);
+        let lines = csv.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], STATS_CSV_HEADER);
+        assert_eq!(lines[1], "dir_0_lane_1,1,0,2023-01-02T15:00:00+00:00,2023-01-02T15:05:00+00:00,bus,2,2,-1,0.5,0,2.5,1.5");
+        assert_eq!(lines[2], "dir_0_lane_1,1,0,2023-01-02T15:00:00+00:00,2023-01-02T15:05:00+00:00,truck,1,1,30,0.5,0,2.5,1.5");
+        assert_eq!(lines[3], "dir_0_lane_2,1,0,2023-01-02T15:00:00+00:00,2023-01-02T15:05:00+00:00,car,4,4,42.5,0.5,0,2.5,1.5");
+        assert_eq!(csv_field("lane, \"north\""), "\"lane, \"\"north\"\"\"");
+    }
+}
@@ -1,4 +1,6 @@
 use std::fs;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use serde::{ Deserialize, Serialize };
@@ -7,6 +9,13 @@ use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
 use od_opencv::model_format::{ModelFormat, ModelVersion};
+use crate::lib::dataset::LabelFormat;
+
+// Number of configuration backups to keep when 'max_config_backups' is not set
+const DEFAULT_MAX_CONFIG_BACKUPS: usize = 10;
+
+// Placeholder for the secrets when configuration is exposed (e.g. via REST API)
+pub const REDACTED_SECRET: &str = "***";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppSettings {
@@ -20,17 +29,119 @@ pub struct AppSettings {
     pub worker: WorkerSettings,
     pub rest_api: RestAPISettings,
     pub redis_publisher: RedisPublisherSettings,
+    pub schedule: Option<ScheduleSettings>,
+    pub sqlite: Option<SqliteSettings>,
+    pub incidents: Option<IncidentsSettings>,
+    pub dataset: Option<DatasetSettings>,
+    pub od: Option<ODSettings>,
+    pub events: Option<EventsSettings>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventsSettings {
+    // Save track of the object (both smoothed and raw detection centroids) next to images of the virtual line crossing (default is false)
+    pub include_raw_track: Option<bool>,
+    pub image_dump: Option<EventImageDumpSettings>,
+}
+
+// Frames (raw and annotated) saved on virtual line crossings and incidents. File names contain event identifier
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventImageDumpSettings {
+    pub enable: bool,
+    pub directory: String,
+    // The oldest files are removed when there are more files than this in the directory (default is 1000)
+    pub max_files: Option<usize>,
+}
+
+// Origin-destination matrix: each virtual line crossing is attributed to the zone crossed by the object previously
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ODSettings {
+    // Previous crossing older than this is considered to be stale, so origin is 'external'. No limit by default
+    pub max_transition_seconds: Option<f32>,
+}
+
+// Samples for the dataset (frame + annotations in YOLO format) captured on demand via REST API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DatasetSettings {
+    pub enable: bool,
+    // Images are saved into '<directory>/images'. Annotations location depends on format
+    pub directory: String,
+    // Possible values: 'yolo' (default), 'coco', 'voc'
+    pub label_format: Option<String>,
+}
+
+impl DatasetSettings {
+    pub fn get_label_format(&self) -> Result<LabelFormat, Box<dyn Error>> {
+        parse_label_format(&self.label_format)
+    }
+}
+
+// Object moving slower than 'stopped_speed' (km/h) inside of a zone for longer than 'stopped_duration_seconds' is reported as stopped vehicle
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IncidentsSettings {
+    pub enable: bool,
+    pub stopped_speed: f32,
+    pub stopped_duration_seconds: f32,
+    // Number of the latest incidents to be kept in memory
+    pub max_events: Option<usize>,
+}
+
+// Durable history of statistics: one row per zone and vehicle class for each period
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SqliteSettings {
+    pub enable: bool,
+    pub path: String,
+}
+
+// Time windows when statistics should be accumulated. Objects are still tracked outside of them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleSettings {
+    // 'local' (default) or 'utc'
+    pub timezone: Option<String>,
+    pub windows: Vec<ScheduleWindowSettings>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleWindowSettings {
+    // Weekdays (e.g. "mon", "tue"). Every day when omitted
+    pub days: Option<Vec<String>>,
+    // "HH:MM" or "HH:MM:SS". End could be less than start for windows spanning midnight
+    pub start: String,
+    pub end: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InputSettings {
     pub video_src: String,
     pub typ: String,
+    // Drop frame instead of waiting when detection thread is busy
+    pub drop_on_backpressure: Option<bool>,
+    // FPS to be used when the video source reports zero or implausible one
+    pub assumed_fps: Option<f32>,
+    // Maximum number of frames per second to be processed. Frames are picked evenly, timestamps still follow source FPS
+    pub target_fps: Option<f32>,
+    // Start finite input (video file or folder of images) over when it ends instead of exiting (default is false)
+    #[serde(rename = "loop")]
+    pub loop_input: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DebugSettings {
-    pub enable: bool
+    pub enable: bool,
+    // Mark centroid of each object by its side relative to the zone's virtual line (default is false)
+    pub draw_line_side: Option<bool>,
+    // Draw cells of the zones spatial index and shade the ones which contain zones. Helps to pick the cell size of the index (default is false)
+    pub draw_zone_grid: Option<bool>,
+    pub frame_dump: Option<FrameDumpSettings>,
+}
+
+// Per-frame JSONL dump of tracked objects. Heavy, so it is for debugging only
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FrameDumpSettings {
+    pub enable: bool,
+    pub path: String,
+    // File is rotated when it exceeds this size. Zero means no rotation
+    pub max_size_mb: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -39,6 +150,25 @@ pub struct OutputSettings {
     pub width: i32,
     pub height: i32,
     pub window_name: String,
+    // Number of the last track points to be drawn (tracking itself keeps 'max_points_in_track' points)
+    pub track_draw_points: Option<usize>,
+    // Draw short integer identifiers of objects instead of UUIDs (default is true)
+    pub draw_short_ids: Option<bool>,
+    // Draw scale bar for each zone (length is derived from the zone's pixels per meter)
+    pub draw_scale_bar: Option<bool>,
+    // Draw arrow pointing to the north for each zone (derived from the zone's spatial mapping)
+    pub draw_north_arrow: Option<bool>,
+    // Draw arrow along the zone's skeleton in the configured travel direction (default is true)
+    pub draw_direction_arrow: Option<bool>,
+    // Draw bbox of each object colored by class with class name and speed label (default is false)
+    pub draw_boxes: Option<bool>,
+    // Fill zones with translucent color, opacity depends on current density (default is false)
+    pub shade_by_occupancy: Option<bool>,
+    // Draw arrows of the last period OD matrix between zones' centroids, thickness depends on the number of transitions (default is false)
+    pub draw_od_flows: Option<bool>,
+    // Font of the text drawn over the frame. By default it is scaled relatively to the frame height (0.5 and 2 for 720p)
+    pub font_scale: Option<f64>,
+    pub font_thickness: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -49,52 +179,146 @@ pub struct DetectionSettings {
     pub network_cfg: Option<String>,
     pub conf_threshold: f32,
     pub nms_threshold: f32,
+    // Boxes of different classes suppress each other too (default is false: model's own per-class NMS only)
+    pub nms_class_agnostic: Option<bool>,
     pub net_width: i32,
     pub net_height: i32,
+    // Pad frame to the network aspect ratio before inference (for models trained with letterbox preprocessing)
+    pub letterbox: Option<bool>,
+    // Additional regions [x, y, width, height] of the frame to run inference on (e.g. for distant small objects)
+    pub roi_crops: Option<Vec<[i32; 4]>>,
+    // Could be omitted when 'net_classes_file' is provided
+    #[serde(default)]
     pub net_classes: Vec<String>,
+    // Path to the newline-delimited labels file (e.g. coco.names)
+    pub net_classes_file: Option<String>,
     pub target_classes: Option<Vec<String>>,
+    // Switch to the next model when active one returns no detections for this number of consecutive frames
+    pub fallback_empty_frames: Option<usize>,
+    // Number of threads OpenCV uses for CPU inference. Zero (default) keeps OpenCV's own choice
+    pub num_threads: Option<u32>,
+    // Image for the inference self-test at start (see /api/model/selftest). Blank frame of the network size is used when omitted
+    pub selftest_image: Option<String>,
+    // Fallback models. The model defined by the attributes above is the primary one
+    // Note: tables (and arrays of tables) should go after plain values, otherwise TOML serialization fails
+    pub models: Option<Vec<DetectionModelSettings>>,
+    // Ignore virtual line crossings by objects whose bounding box touches the frame border
+    pub edge_filter: Option<EdgeFilterSettings>,
 }
 
-impl DetectionSettings {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EdgeFilterSettings {
+    pub enable: bool,
+    // Distance (pixels) to the frame border. Default is 5
+    pub edge_margin_pixels: Option<f32>,
+}
+
+// Fallback neural network. Each model has its own input size
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetectionModelSettings {
+    pub network_ver: Option<i32>,
+    pub network_format: Option<String>,
+    pub network_weights: String,
+    pub network_cfg: Option<String>,
+    pub net_width: i32,
+    pub net_height: i32,
+}
+
+impl DetectionModelSettings {
     pub fn get_nn_format(&self) -> Result<ModelFormat,  Box<dyn Error>> {
-        match self.network_format.clone() {
-            Some(mf) => {
-                match mf.to_lowercase().as_str() {
-                    "darknet" => { Ok(ModelFormat::Darknet) },
-                    "onnx" => { Ok(ModelFormat::ONNX) },
-                    _ => { 
-                        return Err(format!("Can't prepare neural network due the unhandled format: {}", mf).into());
-                    }
-                }
-            },
-            None => { Ok(ModelFormat::Darknet) }
-        }
+        parse_nn_format(&self.network_format)
     }
     pub fn get_nn_version(&self) -> Result<ModelVersion,  Box<dyn Error>> {
-        match self.network_ver.clone() {
-            Some(mv) => {
-                match mv {
-                    3 => { Ok(ModelVersion::V3) },
-                    4 => { Ok(ModelVersion::V4) },
-                    7 => { Ok(ModelVersion::V7) },
-                    8 => { Ok(ModelVersion::V8) },
-                    _ => { 
-                        return Err(format!("Can't prepare neural network due the unhandled version: {}", mv).into());
-                    }
+        parse_nn_version(&self.network_ver)
+    }
+}
+
+impl DetectionSettings {
+    // Primary model followed by the fallback ones
+    pub fn get_models(&self) -> Vec<DetectionModelSettings> {
+        let mut models = vec![DetectionModelSettings {
+            network_ver: self.network_ver,
+            network_format: self.network_format.clone(),
+            network_weights: self.network_weights.clone(),
+            network_cfg: self.network_cfg.clone(),
+            net_width: self.net_width,
+            net_height: self.net_height,
+        }];
+        models.extend(self.models.clone().unwrap_or_default());
+        models
+    }
+}
+
+fn parse_nn_format(network_format: &Option<String>) -> Result<ModelFormat,  Box<dyn Error>> {
+    match network_format.clone() {
+        Some(mf) => {
+            match mf.to_lowercase().as_str() {
+                "darknet" => { Ok(ModelFormat::Darknet) },
+                "onnx" => { Ok(ModelFormat::ONNX) },
+                _ => { 
+                    return Err(format!("Can't prepare neural network due the unhandled format: {}", mf).into());
                 }
-            },
-            None => { Ok(ModelVersion::V3) }
-        }
+            }
+        },
+        None => { Ok(ModelFormat::Darknet) }
+    }
+}
+
+fn parse_label_format(label_format: &Option<String>) -> Result<LabelFormat, Box<dyn Error>> {
+    match label_format {
+        Some(lf) => {
+            match lf.to_lowercase().as_str() {
+                "yolo" => { Ok(LabelFormat::Yolo) },
+                "coco" => { Ok(LabelFormat::Coco) },
+                "voc" => { Ok(LabelFormat::Voc) },
+                _ => {
+                    return Err(format!("Unhandled dataset label format: '{}'. Possible values: 'yolo', 'coco', 'voc'", lf).into());
+                }
+            }
+        },
+        None => { Ok(LabelFormat::Yolo) }
+    }
+}
+
+fn parse_nn_version(network_ver: &Option<i32>) -> Result<ModelVersion,  Box<dyn Error>> {
+    match network_ver.clone() {
+        Some(mv) => {
+            match mv {
+                3 => { Ok(ModelVersion::V3) },
+                4 => { Ok(ModelVersion::V4) },
+                7 => { Ok(ModelVersion::V7) },
+                8 => { Ok(ModelVersion::V8) },
+                _ => { 
+                    return Err(format!("Can't prepare neural network due the unhandled version: {}", mv).into());
+                }
+            }
+        },
+        None => { Ok(ModelVersion::V3) }
     }
 }
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TrackingSettings {
     pub max_points_in_track: usize,
+    // Estimate speed from Kalman-corrected centroid kept by the tracker instead of the raw detection centroid
+    pub speed_from_kalman: Option<bool>,
+    // Objects with lower speed (km/h) are considered to be stationary: they are excluded from average speed, but still counted
+    pub min_moving_speed: Option<f32>,
+    // 'frame' (default) or 'processing_wallclock'. See TimeBase
+    pub time_base: Option<String>,
+    // Maximum number of objects to be tracked at once. Excess objects are evicted. Zero (default) means no limit
+    pub max_objects: Option<usize>,
+    // 'skeleton' (default) or 'ground_plane'. See SpeedMethod
+    pub speed_method: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EquipmentInfo {
     pub id: String,
+    // Human-readable name of the site. It is included in the exported statistics, events and OD matrix
+    pub location_name: Option<String>,
+    // Site position [lon, lat]
+    pub location_wgs84: Option<[f32; 2]>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -102,14 +326,78 @@ pub struct RoadLanesSettings {
     pub lane_number: u16,
     pub lane_direction: u8,
     pub geometry: Vec<[i32; 2]>,
-    pub geometry_wgs84: Vec<[f32; 2]>,
+    // Could be omitted when only counting is needed: zone is not calibrated then and speed is not estimated
+    pub geometry_wgs84: Option<Vec<[f32; 2]>>,
+    // Spatial coordinates in local metric CRS. Used instead of 'geometry_wgs84' when spatial.crs = "local_meters"
+    pub geometry_local_meters: Option<Vec<[f32; 2]>>,
     pub color_rgb: [i16; 3],
-    pub virtual_line: Option<VirtualLineSettings>
+    // 'polygon' (default) or 'line'
+    pub count_mode: Option<String>,
+    // 'vehicle' (default) or 'pedestrian'. Pedestrian zone (crosswalk) counts 'person' class only unless 'target_classes' is set
+    pub kind: Option<String>,
+    // Overlapping zones: object is counted in the zone with the highest priority (default is 0, negative values are allowed)
+    pub priority: Option<i32>,
+    // Classes allowlist for the zone (e.g. ["bus"] for the bus lane). Overrides global 'target_classes'
+    pub target_classes: Option<Vec<String>>,
+    pub virtual_line: Option<VirtualLineSettings>,
+    pub congestion: Option<CongestionSettings>,
+    pub spatial: Option<ZoneSpatialSettings>,
+    pub speed_hist: Option<SpeedHistogramSettings>,
+    pub speed_lines: Option<SpeedLinesSettings>,
+    pub crossing_cooldown: Option<CrossingCooldownSettings>,
+    // Speed limits (km/h) per class, e.g. { car = 60.0, truck = 40.0 }. Faster vehicles are counted in 'over_speed_count'
+    pub speed_limits: Option<HashMap<String, f32>>,
+}
+
+// Crossings of the virtual line within radius (pixels) and time window (seconds) after accepted one are considered to be duplicates
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrossingCooldownSettings {
+    pub radius_pixels: f32,
+    pub window_seconds: f32,
+}
+
+// Two lines placed known real distance apart. Speed is estimated as distance divided by time between crossings of the both lines
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpeedLinesSettings {
+    pub geometry_a: [[i32; 2]; 2],
+    pub geometry_b: [[i32; 2]; 2],
+    pub known_distance_meters: f32,
+    pub color_rgb: Option<[i16; 3]>,
+}
+
+// Bins of the speed histogram (km/h). Speeds above max_speed are accumulated in the last bin
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpeedHistogramSettings {
+    pub bin_width: Option<f32>,
+    pub max_speed: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ZoneSpatialSettings {
+    // 'wgs84' (default) or 'local_meters'
+    pub crs: Option<String>,
+    // Ground sample distance (meters per pixel). When set, it is used for speed estimation instead of the spatial coordinates
+    // Assumes negligible perspective distortion (e.g. nadir-ish camera)
+    pub gsd_meters_per_pixel: Option<f32>,
+}
+
+// Density thresholds (vehicles per kilometer) for congestion levels
+// Density is estimated from occupancy and zone length, so correct spatial calibration is required
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CongestionSettings {
+    pub moderate: Option<f32>,
+    pub congested: Option<f32>,
+    pub jammed: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VirtualLineSettings {
-    pub geometry: [[i32; 2]; 2],
+    // Pixels or, when 'normalized' is set, coordinates in [0; 1] relative to the frame size
+    pub geometry: [[f32; 2]; 2],
+    // Default is false (absolute pixels)
+    pub normalized: Option<bool>,
+    // Deadband (pixels) around the line against jittering objects. Default is 0 (disabled)
+    pub margin_px: Option<f32>,
     pub color_rgb: [i16; 3],
     // 'lrtb' stands for "left->right, top->bottom"
     // 'rlbt' stands for "right->left, bottom->top"
@@ -119,6 +407,14 @@ pub struct VirtualLineSettings {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WorkerSettings {
     pub reset_data_milliseconds: i64,
+    // Timezone for the hourly totals: 'local' (system timezone, default) or 'utc'
+    pub hourly_timezone: Option<String>,
+    // Minimum number of vehicles with defined speed in the period to report average speed. Otherwise it is reported as -1 (undefined)
+    pub min_samples_for_speed: Option<u32>,
+    // Keep registrations of the still tracked objects for the next period, so vehicle dwelling across period boundary is counted once
+    pub dedup_across_periods: Option<bool>,
+    // Seconds after start when objects are tracked, but not registered in zones (tracker stabilization). Default is 0
+    pub warmup_seconds: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -127,7 +423,16 @@ pub struct RestAPISettings {
     pub host: String,
     pub back_end_port: i32,
     pub api_scope: String,
+    // Optional key which should be provided via 'X-API-Key' header for the mutation endpoints
+    pub api_key: Option<String>,
+    // Require API key for the read-only endpoints (zones, statistics, MJPEG live stream) too. Live stream accepts it via 'api_key' query parameter as well
+    pub protect_read_only: Option<bool>,
+    // Number of the most recent configuration backups (created on each save) to keep. Default is 10
+    pub max_config_backups: Option<usize>,
+    // Number of decimal places of the exported spatial coordinates (GeoJSON, KML, tracked objects). Default is 7 (about 1 cm)
+    pub coordinate_precision: Option<u32>,
     pub mjpeg_streaming: Option<MJPEGStreamingSettings>,
+    pub tls: Option<TLSSettings>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -138,16 +443,40 @@ pub struct RedisPublisherSettings {
     pub password: String,
     pub db_index: i32,
     pub channel_name: String,
+    // Minimum interval between publishes (in milliseconds). Snapshots in between are dropped
+    pub min_publish_interval_ms: Option<u64>,
+    // Store the latest snapshot at 'traffic:{equipment_id}:latest' key in addition to publishing it
+    pub set_latest_key: Option<bool>,
+    // TTL of the latest snapshot key (in seconds). Zero or absent value means no expiration
+    pub latest_key_ttl_seconds: Option<u64>,
+    // 'json' (default) or 'protobuf' (see 'proto/statistics.proto')
+    pub format: Option<String>,
+    // Number of extra connection attempts (with exponential backoff) on startup. Default is 0
+    pub connect_retries: Option<u32>,
+    // Restore connection when it has been lost. Default is true
+    pub reconnect: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MJPEGStreamingSettings {
     pub enable: bool,
+    // JPEG quality in [1; 100] for clients which do not ask for specific one via '?quality=' (default is 95)
+    pub quality: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TLSSettings {
+    pub enable: bool,
+    // PEM-encoded certificate chain
+    pub cert_path: String,
+    // PEM-encoded private key (PKCS#8 or RSA)
+    pub key_path: String,
 }
 
 use crate::lib::zones::Zone;
-use crate::lib::zones::{VirtualLineDirection, VirtualLine};
+use crate::lib::zones::{VirtualLineDirection, VirtualLine, CountMode, ZoneKind, CongestionThresholds, SpeedHistogramBins, SpeedLines, CrossingCooldown};
 use crate::lib::spatial::epsg::lonlat_to_meters;
+use crate::lib::spatial::SpatialCRS;
 use opencv::core::Point2f;
 use opencv::core::Scalar;
 use std::convert::From;
@@ -159,18 +488,38 @@ impl From<&RoadLanesSettings> for Zone {
             .map(|pt| Point2f::new(pt[0] as f32, pt[1] as f32))
             .collect();
 
-        let geom_epsg4326 = setting.geometry_wgs84
-            .iter()
-            .map(|pt| Point2f::new(pt[0], pt[1]))
-            .collect();
+        let crs = match setting.spatial.as_ref().and_then(|spatial| spatial.crs.as_ref()) {
+            Some(crs) => match SpatialCRS::from_str(crs) {
+                Ok(result) => result,
+                Err(_) => {
+                    panic!("Can't prepare zone 'dir_{}_lane_{}' due the unknown CRS: {}", setting.lane_direction, setting.lane_number, crs);
+                }
+            },
+            None => SpatialCRS::default()
+        };
 
-        let geom_epsg3857 = setting.geometry_wgs84
-            .iter()
-            .map(|pt| {
-                let lonlat = lonlat_to_meters(pt[0], pt[1]);
-                Point2f::new(lonlat.0, lonlat.1)
-            })
-            .collect();
+        // Local metric coordinates bypass lon/lat projection and are applied after zone creation
+        let (geom_epsg4326, geom_epsg3857) = match crs {
+            SpatialCRS::WGS84 => {
+                let geometry_wgs84 = setting.geometry_wgs84.clone().unwrap_or_default();
+                if !geometry_wgs84.is_empty() && geometry_wgs84.len() != 4 {
+                    panic!("Can't prepare zone 'dir_{}_lane_{}' due the 'geometry_wgs84' should have 4 points, but got {}", setting.lane_direction, setting.lane_number, geometry_wgs84.len());
+                }
+                let geom_epsg4326 = geometry_wgs84
+                    .iter()
+                    .map(|pt| Point2f::new(pt[0], pt[1]))
+                    .collect();
+                let geom_epsg3857 = geometry_wgs84
+                    .iter()
+                    .map(|pt| {
+                        let lonlat = lonlat_to_meters(pt[0], pt[1]);
+                        Point2f::new(lonlat.0, lonlat.1)
+                    })
+                    .collect();
+                (geom_epsg4326, geom_epsg3857)
+            },
+            SpatialCRS::LocalMeters => (vec![], vec![])
+        };
 
         let virtual_line = match &setting.virtual_line {
             Some(vl) => {
@@ -178,10 +527,21 @@ impl From<&RoadLanesSettings> for Zone {
                     None
                 } else {
                     let dir = VirtualLineDirection::from_str(&vl.direction).unwrap_or_default();
-                    let a = Point2f::new(vl.geometry[0][0] as f32, vl.geometry[0][1] as f32);
-                    let b = Point2f::new(vl.geometry[1][0] as f32, vl.geometry[1][1] as f32);
-                    let mut line = VirtualLine::new_from_cv(a, b, dir);
+                    let mut line = if vl.normalized.unwrap_or(false) {
+                        if vl.geometry.iter().flatten().any(|v| *v < 0.0 || *v > 1.0) {
+                            panic!("Can't prepare zone 'dir_{}_lane_{}' due the normalized virtual line is out of [0; 1] range: {:?}", setting.lane_direction, setting.lane_number, vl.geometry);
+                        }
+                        // Pixel coordinates are resolved when frame size is known
+                        VirtualLine::new_normalized(vl.geometry, dir)
+                    } else {
+                        let a = Point2f::new(vl.geometry[0][0], vl.geometry[0][1]);
+                        let b = Point2f::new(vl.geometry[1][0], vl.geometry[1][1]);
+                        VirtualLine::new_from_cv(a, b, dir)
+                    };
                     line.set_color_rgb(vl.color_rgb[0], vl.color_rgb[1], vl.color_rgb[2]);
+                    if let Some(margin_px) = vl.margin_px {
+                        line.set_margin_px(margin_px);
+                    }
                     Some(line)
                 }
             },
@@ -190,7 +550,7 @@ impl From<&RoadLanesSettings> for Zone {
             }
         };
 
-        Zone::new(
+        let mut zone = Zone::new(
             format!("dir_{}_lane_{}", setting.lane_direction, setting.lane_number),
             geom,
             geom_epsg4326,
@@ -199,7 +559,95 @@ impl From<&RoadLanesSettings> for Zone {
             setting.lane_number,
             setting.lane_direction,
             virtual_line
-        )
+        );
+        if crs == SpatialCRS::LocalMeters {
+            zone.set_crs(crs);
+            match &setting.geometry_local_meters {
+                Some(geom_local) if !geom_local.is_empty() => {
+                    zone.update_spatial_map_cv(geom_local.iter().map(|pt| Point2f::new(pt[0], pt[1])).collect());
+                },
+                _ => {
+                    println!("[WARNING]: Zone '{}' has 'local_meters' CRS, but there is no 'geometry_local_meters'. Spatial calibration is skipped", zone.get_id());
+                }
+            }
+        }
+        if let Some(gsd) = setting.spatial.as_ref().and_then(|spatial| spatial.gsd_meters_per_pixel) {
+            if !(gsd > 0.0) {
+                panic!("Can't prepare zone '{}' due the non-positive GSD: {}", zone.get_id(), gsd);
+            }
+            zone.set_gsd(gsd);
+        }
+        if crs == SpatialCRS::WGS84 && zone.get_spatial_coordinates_epsg4326().is_empty() && zone.get_gsd().is_none() {
+            println!("Zone '{}' has no 'geometry_wgs84'. It is used for counting only: speed is not estimated", zone.get_id());
+        }
+        if let Some(count_mode) = &setting.count_mode {
+            let mode = CountMode::from_str(count_mode).unwrap_or_default();
+            if mode == CountMode::Line && setting.virtual_line.is_none() {
+                println!("[WARNING]: Zone '{}' has 'line' count mode, but there is no virtual line. Nothing will be counted", zone.get_id());
+            }
+            zone.set_count_mode(mode);
+        }
+        if let Some(congestion) = &setting.congestion {
+            let defaults = CongestionThresholds::default();
+            zone.set_congestion_thresholds(CongestionThresholds {
+                moderate: congestion.moderate.unwrap_or(defaults.moderate),
+                congested: congestion.congested.unwrap_or(defaults.congested),
+                jammed: congestion.jammed.unwrap_or(defaults.jammed),
+            });
+        }
+        if let Some(speed_hist) = &setting.speed_hist {
+            let defaults = SpeedHistogramBins::default();
+            let bins = SpeedHistogramBins {
+                bin_width: speed_hist.bin_width.unwrap_or(defaults.bin_width),
+                max_speed: speed_hist.max_speed.unwrap_or(defaults.max_speed),
+            };
+            if bins.bin_width <= 0.0 || bins.max_speed <= 0.0 {
+                panic!("Can't prepare zone '{}' due the bad speed histogram bins: bin_width and max_speed should be positive", zone.get_id());
+            }
+            zone.set_speed_histogram_bins(bins);
+        }
+        if let Some(speed_lines) = &setting.speed_lines {
+            if speed_lines.known_distance_meters <= 0.0 {
+                panic!("Can't prepare zone '{}' due the bad speed lines: known_distance_meters should be positive", zone.get_id());
+            }
+            let color = speed_lines.color_rgb.unwrap_or(setting.color_rgb);
+            let mut line_a = VirtualLine::new_from(speed_lines.geometry_a, VirtualLineDirection::default());
+            line_a.set_color_rgb(color[0], color[1], color[2]);
+            let mut line_b = VirtualLine::new_from(speed_lines.geometry_b, VirtualLineDirection::default());
+            line_b.set_color_rgb(color[0], color[1], color[2]);
+            zone.set_speed_lines(SpeedLines::new(line_a, line_b, speed_lines.known_distance_meters));
+        }
+        if let Some(priority) = setting.priority {
+            zone.set_priority(priority);
+        }
+        if let Some(kind) = &setting.kind {
+            let kind = match ZoneKind::from_str(kind) {
+                Ok(kind) => kind,
+                Err(_) => {
+                    panic!("Can't prepare zone '{}' due the unknown kind: {}. Possible values: vehicle, pedestrian", zone.get_id(), kind);
+                }
+            };
+            zone.set_kind(kind);
+        }
+        if let Some(target_classes) = &setting.target_classes {
+            zone.set_allowed_classes(Some(target_classes.iter().cloned().collect()));
+        }
+        if let Some(cooldown) = &setting.crossing_cooldown {
+            if cooldown.radius_pixels <= 0.0 || cooldown.window_seconds <= 0.0 {
+                panic!("Can't prepare zone '{}' due the bad crossing cooldown: radius_pixels and window_seconds should be positive", zone.get_id());
+            }
+            if setting.virtual_line.is_none() {
+                println!("[WARNING]: Zone '{}' has crossing cooldown, but there is no virtual line. It will be ignored", zone.get_id());
+            }
+            zone.set_crossing_cooldown(CrossingCooldown::new(cooldown.radius_pixels, cooldown.window_seconds));
+        }
+        if let Some(speed_limits) = &setting.speed_limits {
+            if let Some((classname, limit)) = speed_limits.iter().find(|(_, limit)| !(**limit > 0.0)) {
+                panic!("Can't prepare zone '{}' due the non-positive speed limit for class '{}': {}", zone.get_id(), classname, limit);
+            }
+            zone.set_speed_limits(speed_limits.clone());
+        }
+        zone
     }
 }
 
@@ -212,10 +660,33 @@ impl AppSettings {
                 panic!("Can't parse TOML configuration file due the error: {:?}", err);
             }
         };
+        if let Some(classes_file) = &app_settings.detection.net_classes_file {
+            let classes = match load_classes_file(classes_file) {
+                Ok(result) => result,
+                Err(err) => {
+                    panic!("Can't read classes file '{}' due the error: {}", classes_file, err);
+                }
+            };
+            if !app_settings.detection.net_classes.is_empty() && app_settings.detection.net_classes != classes {
+                panic!("Both 'net_classes' and 'net_classes_file' are provided, but they are different. Keep only one of them");
+            }
+            app_settings.detection.net_classes = classes;
+        }
+        if app_settings.detection.net_classes.is_empty() {
+            panic!("Neither 'net_classes' nor 'net_classes_file' has been provided");
+        }
+        if let Some(dataset) = &app_settings.dataset {
+            if let Err(err) = dataset.get_label_format() {
+                panic!("Can't prepare dataset settings due the error: {}", err);
+            }
+        }
         match app_settings.debug {
             None => {
                 app_settings.debug = Some(DebugSettings{
                     enable: false,
+                    draw_line_side: None,
+                    draw_zone_grid: None,
+                    frame_dump: None,
                 });
             },
             _ => {  }
@@ -226,6 +697,7 @@ impl AppSettings {
         fs::copy(filename, filename.to_owned() + &format!(".{}.bak", Utc::now().format("%Y-%m-%dT%H-%M-%S-%f")))?;
         let docs = toml::to_string(self)?;
         fs::write(filename, docs)?;
+        prune_backups(filename, self.rest_api.max_config_backups.unwrap_or(DEFAULT_MAX_CONFIG_BACKUPS))?;
         Ok(())
     }
     pub fn get_copy_no_roads(&self) -> AppSettings {
@@ -240,8 +712,72 @@ impl AppSettings {
             worker: self.worker.clone(),
             rest_api: self.rest_api.clone(),
             redis_publisher: self.redis_publisher.clone(),
+            schedule: self.schedule.clone(),
+            sqlite: self.sqlite.clone(),
+            incidents: self.incidents.clone(),
+            dataset: self.dataset.clone(),
+            od: self.od.clone(),
+            events: self.events.clone(),
+        }
+    }
+    // Replaces passwords and keys with placeholder. Empty secrets are kept as is, so it is still visible whether they are set
+    pub fn redact_secrets(&mut self) {
+        if !self.redis_publisher.password.is_empty() {
+            self.redis_publisher.password = REDACTED_SECRET.to_string();
         }
+        if self.rest_api.api_key.is_some() {
+            self.rest_api.api_key = Some(REDACTED_SECRET.to_string());
+        }
+    }
+}
+
+// Removes the oldest backups ('<filename>.<timestamp>.bak') of the configuration file keeping only 'keep' most recent ones
+// Timestamp format is sortable, so backups are ordered by name. Returns number of removed files
+fn prune_backups(filename: &str, keep: usize) -> Result<usize, Box<dyn Error>> {
+    let path = Path::new(filename);
+    let prefix = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => format!("{}.", name),
+        None => return Ok(0),
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut backups: Vec<PathBuf> = vec![];
+    for entry in fs::read_dir(&dir)? {
+        let entry_path = entry?.path();
+        let is_backup = match entry_path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.starts_with(&prefix) && name.ends_with(".bak"),
+            None => false,
+        };
+        if is_backup {
+            backups.push(entry_path);
+        }
+    }
+    if backups.len() <= keep {
+        return Ok(0);
+    }
+    backups.sort();
+    let to_remove = backups.len() - keep;
+    for backup in backups.iter().take(to_remove) {
+        fs::remove_file(backup)?;
+    }
+    Ok(to_remove)
+}
+
+// Reads newline-delimited class names. Empty lines are ignored
+fn load_classes_file(filename: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(filename)?;
+    let classes: Vec<String> = contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    if classes.is_empty() {
+        return Err("file contains no class names".into());
     }
+    Ok(classes)
 }
 
 impl fmt::Display for AppSettings {
@@ -257,3 +793,112 @@ impl fmt::Display for AppSettings {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::tracker::SpatialInfo;
+    #[test]
+    fn test_prune_backups() {
+        let dir = std::env::temp_dir().join(format!("prune_backups_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("conf.toml");
+        fs::write(&config, "").unwrap();
+        // Unrelated files should not be touched
+        fs::write(dir.join("other.toml.2023-01-01T00-00-00-000000000.bak"), "").unwrap();
+        for second in 0..5 {
+            fs::write(dir.join(format!("conf.toml.2023-01-01T00-00-0{}-000000000.bak", second)), "").unwrap();
+        }
+        let removed = prune_backups(config.to_str().unwrap(), 2).unwrap();
+        assert_eq!(removed, 3);
+        let mut left: Vec<String> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        left.sort();
+        assert_eq!(left, vec![
+            "conf.toml".to_string(),
+            "conf.toml.2023-01-01T00-00-03-000000000.bak".to_string(),
+            "conf.toml.2023-01-01T00-00-04-000000000.bak".to_string(),
+            "other.toml.2023-01-01T00-00-00-000000000.bak".to_string(),
+        ]);
+        assert_eq!(prune_backups(config.to_str().unwrap(), 2).unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_redact_secrets() {
+        let mut settings = toml::from_str::<AppSettings>(&fs::read_to_string("data/conf.toml").unwrap()).unwrap();
+        settings.redis_publisher.password = "redis-secret".to_string();
+        settings.rest_api.api_key = Some("api-secret".to_string());
+        settings.redact_secrets();
+        let docs = toml::to_string(&settings).unwrap();
+        assert!(!docs.contains("redis-secret"));
+        assert!(!docs.contains("api-secret"));
+        assert_eq!(settings.redis_publisher.password, REDACTED_SECRET);
+        assert_eq!(settings.rest_api.api_key.as_deref(), Some(REDACTED_SECRET));
+        // Not set secrets stay empty
+        settings.redis_publisher.password = String::new();
+        settings.rest_api.api_key = None;
+        settings.redact_secrets();
+        assert!(settings.redis_publisher.password.is_empty());
+        assert!(settings.rest_api.api_key.is_none());
+    }
+    #[test]
+    fn test_detection_models_roundtrip() {
+        let mut settings = toml::from_str::<AppSettings>(&fs::read_to_string("data/conf.toml").unwrap()).unwrap();
+        settings.detection.num_threads = Some(4);
+        settings.detection.selftest_image = Some("./data/selftest.jpg".to_string());
+        settings.detection.models = Some(vec![DetectionModelSettings {
+            network_ver: Some(8),
+            network_format: Some("onnx".to_string()),
+            network_weights: "./data/yolov8n_night.onnx".to_string(),
+            network_cfg: None,
+            net_width: 640,
+            net_height: 640,
+        }]);
+        let docs = toml::to_string(&settings).unwrap();
+        let parsed = toml::from_str::<AppSettings>(&docs).unwrap();
+        assert_eq!(parsed.detection.num_threads, Some(4));
+        assert_eq!(parsed.detection.selftest_image.as_deref(), Some("./data/selftest.jpg"));
+        let models = parsed.detection.models.unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].network_weights, "./data/yolov8n_night.onnx");
+        assert_eq!(models[0].net_width, 640);
+    }
+    #[test]
+    fn test_events_roundtrip() {
+        let mut settings = toml::from_str::<AppSettings>(&fs::read_to_string("data/conf.toml").unwrap()).unwrap();
+        settings.events = Some(EventsSettings {
+            include_raw_track: Some(true),
+            image_dump: Some(EventImageDumpSettings {
+                enable: true,
+                directory: "./events".to_string(),
+                max_files: Some(10),
+            }),
+        });
+        let docs = toml::to_string(&settings).unwrap();
+        let events = toml::from_str::<AppSettings>(&docs).unwrap().events.unwrap();
+        assert_eq!(events.include_raw_track, Some(true));
+        let image_dump = events.image_dump.unwrap();
+        assert_eq!(image_dump.directory, "./events");
+        assert_eq!(image_dump.max_files, Some(10));
+    }
+    #[test]
+    fn test_zone_without_wgs84() {
+        let setting = toml::from_str::<RoadLanesSettings>(r#"
+            lane_number = 0
+            lane_direction = 0
+            geometry = [[204, 542], [398, 558], [506, 325], [402, 318]]
+            color_rgb = [255, 0, 0]
+        "#).unwrap();
+        assert!(setting.geometry_wgs84.is_none());
+        let zone = Zone::from(&setting);
+        assert!(zone.get_spatial_coordinates_epsg4326().is_empty());
+        assert!(zone.get_skeleton_ppm() < 0.0);
+        // Zone is still usable for counting
+        assert!(zone.contains_point(300.0, 450.0));
+        // Speed is undefined for uncalibrated zone
+        let mut spatial_info = SpatialInfo::new(0.0, 300.0, 450.0, 300.0, 450.0);
+        spatial_info.update_avg(1.0, 310.0, 420.0, 310.0, 420.0, zone.get_skeleton_ppm());
+        assert_eq!(spatial_info.speed, -1.0);
+        assert_eq!(spatial_info.speed_confidence, 0.0);
+    }
+
+}
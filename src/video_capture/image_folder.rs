@@ -0,0 +1,99 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use opencv::{
+    core::Mat,
+    imgcodecs::imread,
+    imgcodecs::IMREAD_COLOR,
+    prelude::MatTraitConst,
+};
+
+// Extensions (case-insensitive) of the files which are considered to be images
+pub const SUPPORTED_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "tif", "tiff", "webp"];
+
+// Lists images of the directory (non-recursive) sorted by file name
+pub fn list_images(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let supported = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| SUPPORTED_IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            .unwrap_or(false);
+        if supported {
+            files.push(path);
+        }
+    }
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+// Reads images of the directory one by one as if they were frames of the video
+pub struct ImageFolderCapture {
+    files: Vec<PathBuf>,
+    next_idx: usize,
+}
+
+impl ImageFolderCapture {
+    pub fn new(directory: &str) -> io::Result<Self> {
+        Ok(ImageFolderCapture {
+            files: list_images(Path::new(directory))?,
+            next_idx: 0,
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    // Size (width, height) of the first image. Images are expected to be the same size
+    pub fn probe_size(&self) -> opencv::Result<Option<(f32, f32)>> {
+        let path = match self.files.first() {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let image = imread(&path.to_string_lossy(), IMREAD_COLOR)?;
+        if image.empty() {
+            return Ok(None);
+        }
+        Ok(Some((image.cols() as f32, image.rows() as f32)))
+    }
+    // Returns false when there are no images left. Unreadable image gives an empty frame
+    pub fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        let path = match self.files.get(self.next_idx) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        self.next_idx += 1;
+        *frame = imread(&path.to_string_lossy(), IMREAD_COLOR)?;
+        Ok(true)
+    }
+    // Starts reading from the first image again
+    pub fn rewind(&mut self) {
+        self.next_idx = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_list_images() {
+        let directory = std::env::temp_dir().join(format!("rust_road_traffic_images_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for name in &["frame_0002.png", "frame_0001.JPG", "notes.txt", "frame_0003.jpeg"] {
+            fs::write(directory.join(name), b"").unwrap();
+        }
+        fs::create_dir_all(directory.join("nested.png")).unwrap();
+        let names = list_images(&directory).unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(names, vec!["frame_0001.JPG", "frame_0002.png", "frame_0003.jpeg"]);
+    }
+}
@@ -1,4 +1,6 @@
 mod frame;
+mod image_folder;
+mod throttle;
 mod video_capture;
 
-pub use self::{frame::*, video_capture::*};
\ No newline at end of file
+pub use self::{frame::*, image_folder::*, throttle::*, video_capture::*};
\ No newline at end of file
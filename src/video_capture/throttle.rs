@@ -0,0 +1,110 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Tolerance for comparison of content times (frame times are accumulated as fractions of the second)
+const TIME_EPSILON: f32 = 1e-4;
+
+// Video sources are decimated: only every N-th frame (by the counter within the second) is sent to the detection thread
+pub const SKIP_EVERY_N_FRAME: i32 = 2;
+
+// Checks if the frame should be skipped by the decimation. Folder of images is processed entirely,
+// since it is used for offline processing and benchmarking where every image matters
+pub fn skip_frame(frames_counter: f32, images_input: bool) -> bool {
+    !images_input && frames_counter as i32 % SKIP_EVERY_N_FRAME != 0
+}
+
+// Caps processing rate to the target FPS. Frames are admitted with even spacing in the video (content) time,
+// so timestamps of the processed frames are still derived from the source FPS and speed estimation is not affected
+// For sources which are read faster than real time (video files, folders of images) it also sleeps between frames
+#[derive(Debug)]
+pub struct FpsThrottle {
+    interval: f32,
+    next_content_time: Option<f32>,
+    last_sent: Option<Instant>,
+}
+
+impl FpsThrottle {
+    // Returns None when throttling is not needed (target FPS is not lower than source FPS)
+    pub fn new(target_fps: f32, source_fps: f32) -> Option<Self> {
+        if !target_fps.is_finite() || target_fps <= 0.0 || target_fps >= source_fps {
+            return None;
+        }
+        Some(FpsThrottle {
+            interval: 1.0 / target_fps,
+            next_content_time: None,
+            last_sent: None,
+        })
+    }
+    // Checks if the frame with the given content time (seconds) should be processed
+    pub fn admit(&mut self, content_time: f32) -> bool {
+        match self.next_content_time {
+            Some(next_content_time) if content_time + TIME_EPSILON < next_content_time => false,
+            Some(next_content_time) => {
+                // Keep the grid even. Jump forward when source has a gap bigger than interval
+                let mut next = next_content_time + self.interval;
+                if next <= content_time {
+                    next = content_time + self.interval;
+                }
+                self.next_content_time = Some(next);
+                true
+            },
+            None => {
+                self.next_content_time = Some(content_time + self.interval);
+                true
+            }
+        }
+    }
+    // Sleeps until target interval has passed since the previous admitted frame (wall clock)
+    pub fn wait(&mut self) {
+        if let Some(last_sent) = self.last_sent {
+            let interval = Duration::from_secs_f32(self.interval);
+            let elapsed = last_sent.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        self.last_sent = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_fps_throttle_admit() {
+        assert!(FpsThrottle::new(30.0, 25.0).is_none());
+        assert!(FpsThrottle::new(0.0, 25.0).is_none());
+        // 25 FPS source capped to 5 FPS: every 5th frame
+        let mut throttle = FpsThrottle::new(5.0, 25.0).unwrap();
+        let admitted = (0..25).filter(|i| throttle.admit(*i as f32 / 25.0)).collect::<Vec<i32>>();
+        assert_eq!(admitted, vec![0, 5, 10, 15, 20]);
+        // Gap in the source does not produce burst of frames
+        assert!(throttle.admit(10.0));
+        assert!(!throttle.admit(10.1));
+        assert!(throttle.admit(10.2));
+    }
+    // Mimics counters of the capture loop
+    fn count_sent(frames: usize, fps: f32, images_input: bool) -> usize {
+        let mut frames_counter: f32 = 0.0;
+        let mut sent = 0;
+        for _ in 0..frames {
+            frames_counter += 1.0;
+            if frames_counter >= fps {
+                frames_counter = 0.0;
+            }
+            if skip_frame(frames_counter, images_input) {
+                continue;
+            }
+            sent += 1;
+        }
+        sent
+    }
+    #[test]
+    fn test_skip_frame() {
+        // N images produce N sent frames
+        assert_eq!(count_sent(37, 25.0, true), 37);
+        assert_eq!(count_sent(1, 25.0, true), 1);
+        // Video is decimated
+        assert_eq!(count_sent(50, 25.0, false), 26);
+    }
+}
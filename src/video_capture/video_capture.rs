@@ -1,9 +1,41 @@
 use opencv::{
+    core::Mat,
     videoio::VideoCapture,
     videoio::CAP_ANY,
+    videoio::CAP_GSTREAMER,
+    videoio::CAP_PROP_FRAME_COUNT,
+    videoio::CAP_PROP_POS_FRAMES,
+    prelude::VideoCaptureTrait,
+    prelude::VideoCaptureTraitConst,
 };
 
+use crate::video_capture::ImageFolderCapture;
+
+// FPS which is used when probed one is unknown (some streams report zero) and there is no 'assumed_fps' in configuration
+pub const DEFAULT_ASSUMED_FPS: f32 = 25.0;
+// Probed FPS above this value is considered to be garbage
+pub const MAX_PLAUSIBLE_FPS: f32 = 120.0;
+
 pub fn get_video_capture(video_src: &str, typ: String) -> VideoCapture {
+    if typ == "gstreamer" {
+        // video_src is the whole GStreamer pipeline ending with appsink (e.g. for hardware decoding)
+        let video_capture = match VideoCapture::from_file(video_src, CAP_GSTREAMER) {
+            Ok(result) => {result},
+            Err(err) => {
+                panic!("Can't init GStreamer pipeline '{}' due the error: {:?}", video_src, err);
+            }
+        };
+        match video_capture.is_opened() {
+            Ok(true) => {},
+            Ok(false) => {
+                panic!("Can't open GStreamer pipeline '{}'. Make sure that OpenCV is built with GStreamer support and pipeline ends with appsink", video_src);
+            },
+            Err(err) => {
+                panic!("Can't check GStreamer pipeline '{}' due the error: {:?}", video_src, err);
+            }
+        };
+        return video_capture;
+    }
     if typ == "rtsp" {
         let video_capture = match VideoCapture::from_file(video_src, CAP_ANY) {
             Ok(result) => {result},
@@ -26,4 +58,94 @@ pub fn get_video_capture(video_src: &str, typ: String) -> VideoCapture {
         }
     };
     return video_capture;
-}
\ No newline at end of file
+}
+// Source of frames: either video stream (file, RTSP, camera, GStreamer) or folder of images ("images" type)
+pub enum FrameSource {
+    Video(VideoCapture),
+    Images(ImageFolderCapture),
+}
+
+impl FrameSource {
+    pub fn is_opened(&self) -> opencv::Result<bool> {
+        match self {
+            FrameSource::Video(capture) => capture.is_opened(),
+            FrameSource::Images(images) => Ok(!images.is_empty()),
+        }
+    }
+    // Returns false when source has been exhausted (folder of images only). Video streams signal problems via empty frames
+    pub fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        match self {
+            FrameSource::Video(capture) => {
+                capture.read(frame)?;
+                Ok(true)
+            },
+            FrameSource::Images(images) => images.read(frame),
+        }
+    }
+    // Seeks to the first frame. Returns false when source can't be seeked (live streams report no frame count)
+    pub fn rewind(&mut self) -> opencv::Result<bool> {
+        match self {
+            FrameSource::Video(capture) => {
+                if capture.get(CAP_PROP_FRAME_COUNT)? <= 0.0 {
+                    return Ok(false);
+                }
+                capture.set(CAP_PROP_POS_FRAMES, 0.0)
+            },
+            FrameSource::Images(images) => {
+                images.rewind();
+                Ok(true)
+            },
+        }
+    }
+    pub fn release(&mut self) -> opencv::Result<()> {
+        match self {
+            FrameSource::Video(capture) => capture.release(),
+            FrameSource::Images(_) => Ok(()),
+        }
+    }
+}
+
+pub fn get_frame_source(video_src: &str, typ: String) -> FrameSource {
+    if typ == "images" {
+        let images = match ImageFolderCapture::new(video_src) {
+            Ok(result) => result,
+            Err(err) => {
+                panic!("Can't list images in directory '{}' due the error: {}", video_src, err);
+            }
+        };
+        println!("Found {} images in directory '{}'", images.len(), video_src);
+        return FrameSource::Images(images);
+    }
+    FrameSource::Video(get_video_capture(video_src, typ))
+}
+
+// Returns FPS to be used for time estimation and flag whether fallback has been applied
+//
+// probed_fps - FPS reported by the video source
+// assumed_fps - FPS to fall back to when the probed one is zero or implausible
+//
+pub fn resolve_fps(probed_fps: f32, assumed_fps: f32) -> (f32, bool) {
+    if probed_fps.is_finite() && probed_fps > 0.0 && probed_fps <= MAX_PLAUSIBLE_FPS {
+        return (probed_fps, false);
+    }
+    if assumed_fps.is_finite() && assumed_fps > 0.0 {
+        return (assumed_fps, true);
+    }
+    (DEFAULT_ASSUMED_FPS, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_resolve_fps() {
+        assert_eq!(resolve_fps(30.0, 25.0), (30.0, false));
+        assert_eq!(resolve_fps(MAX_PLAUSIBLE_FPS, 25.0), (MAX_PLAUSIBLE_FPS, false));
+        assert_eq!(resolve_fps(0.0, 15.0), (15.0, true));
+        assert_eq!(resolve_fps(90000.0, 15.0), (15.0, true));
+        assert_eq!(resolve_fps(f32::NAN, 15.0), (15.0, true));
+        assert_eq!(resolve_fps(-1.0, 15.0), (15.0, true));
+        // Bad assumed FPS should not break anything either
+        assert_eq!(resolve_fps(0.0, 0.0), (DEFAULT_ASSUMED_FPS, true));
+    }
+}